@@ -1,20 +1,38 @@
 //! Reading and interpreting Tytanic configuration.
 
+use std::collections::BTreeMap;
 use std::fs;
 use std::io;
 
+use camino::Utf8Path;
+use ecow::EcoVec;
 use serde::Deserialize;
 use serde::Serialize;
 use thiserror::Error;
 use tytanic_utils::result::ResultEx;
 use tytanic_utils::result::io_not_found;
 
+use crate::doc::ArtifactFormat;
+use crate::doc::OptimizeLevel;
+use crate::doc::PageFormat;
+use crate::doc::PagePattern;
+use crate::doc::compare::CompareMode;
+use crate::doc::compare::PageCompare;
+use crate::doc::compare::PrescaleMode;
+use crate::doc::process::Processor;
+use crate::test::Annotation;
+use crate::test::ParseAnnotationError;
+
 /// The key used to configure Tytanic in the manifest tool config.
 pub const MANIFEST_TOOL_KEY: &str = crate::TOOL_NAME;
 
 /// The directory name for in which the user config can be found.
 pub const CONFIG_SUB_DIRECTORY: &str = crate::TOOL_NAME;
 
+/// The file name of a directory config, which configures all unit tests in
+/// the directory it is placed in and its sub directories.
+pub const DIRECTORY_CONFIG_FILE: &str = ".config.toml";
+
 /// A system config, found in the user's `$XDG_CONFIG_HOME` or globally on the
 /// system.
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
@@ -42,7 +60,7 @@ impl SystemConfig {
 }
 
 /// A project config, read from a project's manifest.
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
 pub struct ProjectConfig {
@@ -52,6 +70,73 @@ pub struct ProjectConfig {
     #[serde(rename = "tests", default = "default_unit_tests_root")]
     pub unit_tests_root: String,
 
+    /// The image format in which persistent references are stored.
+    ///
+    /// Defaults to `"png"`.
+    #[serde(default)]
+    pub ref_format: PageFormat,
+
+    /// The file name pattern of persistent reference pages, without the
+    /// extension, the placeholder `{page}` is replaced by the 1-based page
+    /// number.
+    ///
+    /// Page files which don't match this pattern are ignored with a warning.
+    ///
+    /// Defaults to `"{page}"`.
+    #[serde(default)]
+    pub ref_pattern: PagePattern,
+
+    /// Whether to store a freshness hash of the test script and project
+    /// defaults alongside persistent references when they are updated.
+    ///
+    /// If the stored hash differs from the current one when a test passes
+    /// comparison, a warning is emitted that its references may be stale.
+    ///
+    /// Defaults to `false`.
+    #[serde(default)]
+    pub ref_meta: bool,
+
+    /// Whether persistent references are stored per platform.
+    ///
+    /// If enabled, references are updated in a subdirectory of the reference
+    /// directory named after the current platform, like `ref/linux`, and read
+    /// from it if it exists, falling back to the references in the reference
+    /// directory itself otherwise.
+    ///
+    /// Defaults to `false`.
+    #[serde(default)]
+    pub ref_variants: bool,
+
+    /// How thoroughly persistent reference pages stored as PNG are optimized
+    /// when they are written.
+    ///
+    /// Defaults to `"max"`.
+    #[serde(default)]
+    pub optimize_refs: OptimizeLevel,
+
+    /// Additional template entrypoints, relative to the template directory.
+    ///
+    /// Each entrypoint is compiled as its own template test with the id
+    /// `@template:<name>`, where `<name>` is the file stem of the entrypoint.
+    ///
+    /// Defaults to no additional entrypoints.
+    #[serde(default)]
+    pub template_entrypoints: Vec<String>,
+
+    /// The format in which test output is exported, either a PNG image per
+    /// page or a single PDF of the whole document.
+    ///
+    /// Defaults to `"png"`.
+    #[serde(default)]
+    pub export_format: ArtifactFormat,
+
+    /// Directories relative to the project root which are scanned for fonts
+    /// in addition to those passed on the command line.
+    ///
+    /// Defaults to no additional directories.
+    #[serde(default)]
+    pub font_paths: Vec<String>,
+
     /// The project wide defaults.
     #[serde(rename = "default", default)]
     pub defaults: ProjectDefaults,
@@ -61,16 +146,48 @@ impl Default for ProjectConfig {
     fn default() -> Self {
         Self {
             unit_tests_root: default_unit_tests_root(),
+            ref_format: PageFormat::default(),
+            ref_pattern: PagePattern::default(),
+            ref_meta: false,
+            ref_variants: false,
+            optimize_refs: OptimizeLevel::default(),
+            template_entrypoints: vec![],
+            export_format: ArtifactFormat::default(),
+            font_paths: vec![],
             defaults: ProjectDefaults::default(),
         }
     }
 }
 
+impl ProjectConfig {
+    /// Renders this config as a TOML document containing only the
+    /// `[tool.tytanic]` section of a manifest.
+    ///
+    /// All fields are rendered, including those which are set to their
+    /// default values.
+    pub fn to_manifest_toml(&self) -> Result<String, toml::ser::Error> {
+        #[derive(Serialize)]
+        struct Manifest<'a> {
+            tool: Tool<'a>,
+        }
+
+        #[derive(Serialize)]
+        struct Tool<'a> {
+            #[serde(rename = "tytanic")]
+            config: &'a ProjectConfig,
+        }
+
+        toml::to_string(&Manifest {
+            tool: Tool { config: self },
+        })
+    }
+}
+
 fn default_unit_tests_root() -> String {
     String::from("tests")
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
 pub struct ProjectDefaults {
@@ -84,6 +201,13 @@ pub struct ProjectDefaults {
     #[serde(default = "default_ppi")]
     pub ppi: f64,
 
+    /// Whether to crop the borders of uniform color around rendered pages
+    /// before they are compared, exported or stored as references.
+    ///
+    /// Defaults to `false`.
+    #[serde(default)]
+    pub crop: bool,
+
     /// The default maximum allowed delta per pixel.
     ///
     /// Defaults to `1`.
@@ -95,6 +219,98 @@ pub struct ProjectDefaults {
     /// Defaults to `0`.
     #[serde(default = "default_max_deviations")]
     pub max_deviations: usize,
+
+    /// The default maximum allowed pages which may fail comparison before
+    /// documents are considered different.
+    ///
+    /// Defaults to `0`.
+    #[serde(default)]
+    pub max_failing_pages: usize,
+
+    /// The default maximum translation in pixels by which tiles of a reference
+    /// may be shifted to find a better match before deviations are counted.
+    ///
+    /// Defaults to `0`, which disables alignment.
+    #[serde(default)]
+    pub compare_align_tolerance: u32,
+
+    /// The default factor by which pages are downscaled for a fast comparison
+    /// before or instead of the full resolution comparison.
+    ///
+    /// Defaults to `0`, which disables the downscaled comparison.
+    #[serde(default)]
+    pub compare_prescale: u32,
+
+    /// How the downscaled comparison is used.
+    ///
+    /// Defaults to `gate`.
+    #[serde(default)]
+    pub compare_prescale_mode: PrescaleMode,
+
+    /// The default factor by which output and reference pages are downscaled
+    /// before they are compared and diffed, see
+    /// [`render::page_downscale`][crate::doc::render::page_downscale].
+    ///
+    /// Defaults to `0`, which compares pages at full resolution.
+    #[serde(default)]
+    pub compare_downscale: u32,
+
+    /// Whether to ignore deviations which are likely anti-aliasing at the
+    /// edges of shapes.
+    ///
+    /// Defaults to `false`.
+    #[serde(default)]
+    pub compare_ignore_aa: bool,
+
+    /// The default maximum allowed channel difference between a deviating
+    /// pixel and any of the 8 neighbors of its reference pixel for the
+    /// deviation to be ignored.
+    ///
+    /// Defaults to `None`, which disables the neighbor comparison.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compare_aa_tolerance: Option<u8>,
+
+    /// Which comparison strategy is used.
+    ///
+    /// Defaults to `simple`.
+    #[serde(default)]
+    pub compare_mode: CompareMode,
+
+    /// The minimum structural similarity index a page must reach if the
+    /// `ssim` comparison mode is used.
+    ///
+    /// Defaults to `0.99`.
+    #[serde(default = "default_min_ssim")]
+    pub min_ssim: f64,
+
+    /// The number of times a failing test is run again before it is reported
+    /// as failed, a test which passes on a retry is reported as flaky.
+    ///
+    /// Defaults to `0`.
+    #[serde(default)]
+    pub retries: usize,
+
+    /// Comparison tolerances of individual pages, keyed by 1-based page number
+    /// or `default` for unlisted pages, these take precedence over the other
+    /// project defaults but not over the annotations of a test.
+    ///
+    /// Defaults to no per-page tolerances.
+    #[serde(default, skip_serializing_if = "PageCompare::is_empty")]
+    pub page_compare: PageCompare,
+
+    /// The key-value pairs exposed in `sys.inputs` for all tests, these are
+    /// overridden by the input annotations of a test.
+    ///
+    /// Defaults to `{}`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub inputs: BTreeMap<String, String>,
+
+    /// The processors applied to each rendered page before comparison and
+    /// export, in order.
+    ///
+    /// Defaults to `[]`.
+    #[serde(default)]
+    pub post_process: Vec<Processor>,
 }
 
 impl Default for ProjectDefaults {
@@ -102,8 +318,22 @@ impl Default for ProjectDefaults {
         Self {
             direction: default_direction(),
             ppi: default_ppi(),
+            crop: false,
             max_delta: default_max_delta(),
             max_deviations: default_max_deviations(),
+            max_failing_pages: 0,
+            compare_align_tolerance: 0,
+            compare_prescale: 0,
+            compare_prescale_mode: PrescaleMode::Gate,
+            compare_downscale: 0,
+            compare_ignore_aa: false,
+            compare_aa_tolerance: None,
+            compare_mode: CompareMode::Simple,
+            min_ssim: default_min_ssim(),
+            retries: 0,
+            page_compare: PageCompare::new(),
+            inputs: BTreeMap::new(),
+            post_process: vec![],
         }
     }
 }
@@ -124,8 +354,111 @@ fn default_max_deviations() -> usize {
     0
 }
 
+fn default_min_ssim() -> f64 {
+    0.99
+}
+
+/// A directory config, read from a [`DIRECTORY_CONFIG_FILE`] below the unit
+/// test root.
+///
+/// Each member is applied to the tests below its directory like the
+/// annotation of the same name, unless a test or the config of a deeper
+/// directory sets it too.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct DirectoryConfig {
+    /// The direction to use for diffing the documents.
+    pub dir: Option<Direction>,
+
+    /// The origin at which pages of different sizes are aligned in difference
+    /// documents.
+    pub diff_origin: Option<String>,
+
+    /// The pixel per inch to use for exporting the documents.
+    pub ppi: Option<f64>,
+
+    /// The maximum allowed per pixel delta to use for comparison.
+    pub max_delta: Option<u8>,
+
+    /// The maximum allowed amount of deviations to use for comparison.
+    pub max_deviations: Option<usize>,
+
+    /// The maximum allowed amount of pages which may fail comparison.
+    pub max_failing_pages: Option<usize>,
+
+    /// The number of times a failing test is run again.
+    pub retries: Option<usize>,
+
+    /// The maximum number of bytes the compilation of a test may allocate.
+    pub max_memory: Option<String>,
+
+    /// A fixed date returned for the current date.
+    pub date: Option<toml::value::Datetime>,
+
+    /// Additional key-value pairs to expose in `sys.inputs`.
+    #[serde(default)]
+    pub inputs: BTreeMap<String, String>,
+}
+
+impl DirectoryConfig {
+    /// Reads the directory config in the given directory, returns `None` if
+    /// it has none.
+    pub fn read(dir: &Utf8Path) -> Result<Option<Self>, Error> {
+        let Some(content) =
+            fs::read_to_string(dir.join(DIRECTORY_CONFIG_FILE)).ignore(io_not_found)?
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(toml::from_str(&content)?))
+    }
+
+    /// The annotations this config sets, values which are not checked by the
+    /// config's types are parsed like they would be in an annotation.
+    pub fn annotations(&self) -> Result<EcoVec<Annotation>, Error> {
+        let mut annotations = EcoVec::new();
+
+        if let Some(dir) = self.dir {
+            annotations.push(Annotation::Dir(dir));
+        }
+        if let Some(origin) = &self.diff_origin {
+            annotations.push(format!("[diff-origin: {origin}]").parse()?);
+        }
+        if let Some(ppi) = self.ppi {
+            annotations.push(Annotation::Ppi(ppi));
+        }
+        if let Some(max_delta) = self.max_delta {
+            annotations.push(Annotation::MaxDelta(max_delta));
+        }
+        if let Some(max_deviations) = self.max_deviations {
+            annotations.push(Annotation::MaxDeviations(max_deviations));
+        }
+        if let Some(max_failing_pages) = self.max_failing_pages {
+            annotations.push(Annotation::MaxFailingPages(max_failing_pages));
+        }
+        if let Some(retries) = self.retries {
+            annotations.push(Annotation::Retries(retries));
+        }
+        if let Some(max_memory) = &self.max_memory {
+            annotations.push(format!("[max-memory: {max_memory}]").parse()?);
+        }
+        if let Some(date) = &self.date {
+            annotations.push(format!("[date: {date}]").parse()?);
+        }
+        for (key, value) in &self.inputs {
+            annotations.push(Annotation::Input {
+                key: key.clone(),
+                value: value.clone(),
+            });
+        }
+
+        Ok(annotations)
+    }
+}
+
 /// The reading direction of a document.
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum Direction {
     /// The documents are generated left-to-right.
@@ -136,13 +469,17 @@ pub enum Direction {
     Rtl,
 }
 
-/// Returned by [`SystemConfig::collect_user`].
+/// Returned by [`SystemConfig::collect_user`] and [`DirectoryConfig::read`].
 #[derive(Debug, Error)]
 pub enum Error {
     /// The given key is not valid or the config.
     #[error("a toml parsing error occurred")]
     Toml(#[from] toml::de::Error),
 
+    /// A value could not be parsed like an annotation.
+    #[error("an invalid value was given")]
+    Annotation(#[from] ParseAnnotationError),
+
     /// An io error occurred.
     #[error("an io error occurred")]
     Io(#[from] io::Error),
@@ -151,6 +488,7 @@ pub enum Error {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::doc::compare::PageTolerance;
 
     // Verify that the `tool.tytanic.default` section in `typst.toml` is optional.
     #[test]
@@ -179,4 +517,89 @@ mod tests {
         assert_eq!(project_config.unit_tests_root, "test_dir");
         assert_eq!(project_config.defaults.ppi, ProjectDefaults::default().ppi);
     }
+
+    #[test]
+    fn config_manifest_toml_round_trip() {
+        let config = ProjectConfig {
+            unit_tests_root: String::from("test_dir"),
+            ref_format: PageFormat::Webp,
+            ref_pattern: PagePattern::new("page-{page}").unwrap(),
+            ref_meta: true,
+            ref_variants: true,
+            optimize_refs: OptimizeLevel::Fast,
+            template_entrypoints: vec![String::from("article.typ")],
+            export_format: ArtifactFormat::Pdf,
+            font_paths: vec![String::from("fonts")],
+            defaults: ProjectDefaults {
+                direction: Direction::Rtl,
+                ppi: 72.0,
+                crop: true,
+                max_delta: 5,
+                max_deviations: 10,
+                max_failing_pages: 1,
+                compare_align_tolerance: 1,
+                compare_prescale: 4,
+                compare_prescale_mode: PrescaleMode::Only,
+                compare_downscale: 2,
+                compare_ignore_aa: true,
+                compare_aa_tolerance: Some(8),
+                compare_mode: CompareMode::Ssim,
+                min_ssim: 0.95,
+                retries: 2,
+                page_compare: PageCompare::new().with_page(
+                    1,
+                    PageTolerance {
+                        max_delta: None,
+                        max_deviations: Some(500),
+                    },
+                ),
+                inputs: BTreeMap::from([(String::from("version"), String::from("1.0"))]),
+                post_process: vec![
+                    Processor::CropBorder { width: 2 },
+                    Processor::Quantize { levels: 16 },
+                ],
+            },
+        };
+
+        let rendered = config.to_manifest_toml().unwrap();
+        let table = toml::from_str::<toml::Table>(&rendered).unwrap();
+        let section = table["tool"][crate::TOOL_NAME].clone();
+
+        assert_eq!(ProjectConfig::deserialize(section).unwrap(), config);
+    }
+
+    #[test]
+    fn test_directory_config_annotations() {
+        let config = toml::from_str::<DirectoryConfig>(
+            r#"
+            dir = "rtl"
+            max-deviations = 10
+            max-memory = "64MiB"
+            date = 2024-01-01
+
+            [inputs]
+            version = "1.0"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.annotations().unwrap().as_slice(),
+            [
+                Annotation::Dir(Direction::Rtl),
+                Annotation::MaxDeviations(10),
+                Annotation::MaxMemory(64 << 20),
+                Annotation::Date(typst::foundations::Datetime::from_ymd(2024, 1, 1).unwrap()),
+                Annotation::Input {
+                    key: "version".into(),
+                    value: "1.0".into(),
+                },
+            ],
+        );
+
+        assert!(toml::from_str::<DirectoryConfig>("max-delt = 1").is_err());
+
+        let config = toml::from_str::<DirectoryConfig>(r#"diff-origin = "center""#).unwrap();
+        assert!(config.annotations().is_err());
+    }
 }
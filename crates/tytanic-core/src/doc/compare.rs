@@ -1,14 +1,49 @@
 //! Comparison of rendered pages.
 //!
-//! This currently only provides a single primitive comparison algorithm,
-//! [`Strategy::Simple`].
+//! This provides a primitive pixel difference comparison,
+//! [`Strategy::Simple`], a structural similarity comparison,
+//! [`Strategy::Ssim`], and a comparison of the text of pages, see [`text`].
 
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::iter;
+use std::path::Path;
+use std::path::PathBuf;
 
+use ecow::EcoString;
+use serde::Deserialize;
+use serde::Serialize;
 use thiserror::Error;
 use tiny_skia::Pixmap;
+use tiny_skia::PremultipliedColorU8;
+use typst_utils::Scalar;
+use typst_utils::hash128;
 use tytanic_utils::fmt::Term;
+use tytanic_utils::result::ResultEx;
+use tytanic_utils::result::io_not_found;
+
+use super::render;
+use super::render::CropOffset;
+
+/// The width and height of the tiles used for aligned comparisons, roughly the
+/// size of a glyph at the default resolution.
+pub const ALIGN_TILE_SIZE: u32 = 16;
+
+/// The maximum channel delta of a deviating pixel for it to be considered
+/// anti-aliasing, see [`is_antialiasing`].
+pub const AA_MAX_DELTA: u8 = 96;
+
+/// The minimum luminance contrast within the neighborhood of a deviating pixel
+/// in both pages for it to be considered anti-aliasing, see
+/// [`is_antialiasing`].
+pub const AA_MIN_CONTRAST: u8 = 128;
+
+/// The width and height of the windows over which the structural similarity
+/// of two pages is computed, see [`ssim`].
+pub const SSIM_WINDOW_SIZE: u32 = 8;
 
 /// A struct representing page size in pixels.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -26,8 +61,50 @@ impl Display for Size {
     }
 }
 
+/// A rectangular region of a page in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Region {
+    /// The horizontal offset of the region from the left edge of the page.
+    pub x: u32,
+
+    /// The vertical offset of the region from the top edge of the page.
+    pub y: u32,
+
+    /// The width of the region.
+    pub width: u32,
+
+    /// The height of the region.
+    pub height: u32,
+}
+
+impl Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}x{} at ({}, {})",
+            self.width, self.height, self.x, self.y
+        )
+    }
+}
+
+/// Where and how much two pages of equal size deviate, see [`page_detailed`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PageDiff {
+    /// The number of pixels for which any channel differed by more than the
+    /// max delta.
+    pub deviations: usize,
+
+    /// The smallest region containing all deviating pixels, `None` if no pixel
+    /// deviated.
+    pub region: Option<Region>,
+
+    /// The largest difference of any channel of any pixel, including those
+    /// which did not deviate.
+    pub largest_delta: u8,
+}
+
 /// The strategy to use for visual comparison.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Strategy {
     /// Use a simple pixel channel difference comparison, setting both fields
     /// to `0` makes an exact comparison.
@@ -40,7 +117,148 @@ pub enum Strategy {
         /// The maximum allowed amount of pixels that can differ per page in
         /// accordance to `max_delta` before two pages are considered different.
         max_deviation: usize,
+
+        /// The maximum allowed amount of pages that can differ before two
+        /// documents are considered different, this is not used when
+        /// comparing individual pages.
+        ///
+        /// Documents with different page counts are always considered
+        /// different.
+        max_failing_pages: usize,
+
+        /// The maximum translation in pixels by which a tile of the reference
+        /// may be shifted to find its best match before deviations are counted,
+        /// `0` disables alignment.
+        ///
+        /// See [`ALIGN_TILE_SIZE`] for the size of these tiles.
+        align_tolerance: u32,
+
+        /// An optional downscaled comparison to run before or instead of the
+        /// full resolution comparison.
+        prescale: Option<Prescale>,
+
+        /// Whether to ignore deviations which are likely anti-aliasing at the
+        /// edges of shapes, see [`is_antialiasing`].
+        ///
+        /// This is not applied to downscaled comparisons and disables the
+        /// downscaled gate.
+        ignore_aa: bool,
+
+        /// The maximum allowed difference between a channel of a deviating
+        /// output pixel and any of the 8 neighbors of its reference pixel for
+        /// the deviation to be ignored, see [`matches_neighbor`]. `None`
+        /// disables the neighbor comparison.
+        ///
+        /// This forgives anti-aliasing fringes and edges shifted by a single
+        /// pixel. It is not applied to downscaled comparisons and disables the
+        /// downscaled gate.
+        aa_tolerance: Option<u8>,
     },
+
+    /// Use the mean structural similarity index of the luminance of two
+    /// pages, this forgives small shifts and anti-aliasing differences which
+    /// don't change the perceived structure of a page.
+    Ssim {
+        /// The minimum structural similarity index a page must reach, `1.0`
+        /// is only reached by pages with identical luminance.
+        min_ssim: Scalar,
+
+        /// The maximum allowed amount of pages that can differ before two
+        /// documents are considered different, see
+        /// [`Strategy::Simple::max_failing_pages`].
+        max_failing_pages: usize,
+    },
+}
+
+impl Strategy {
+    /// The maximum allowed amount of pages that can differ before two
+    /// documents are considered different.
+    pub fn max_failing_pages(&self) -> usize {
+        match *self {
+            Strategy::Simple {
+                max_failing_pages, ..
+            } => max_failing_pages,
+            Strategy::Ssim {
+                max_failing_pages, ..
+            } => max_failing_pages,
+        }
+    }
+
+    /// Returns this strategy with the given maximum allowed amount of failing
+    /// pages.
+    pub fn with_max_failing_pages(self, max_failing_pages: usize) -> Self {
+        match self {
+            Strategy::Simple {
+                max_delta,
+                max_deviation,
+                max_failing_pages: _,
+                align_tolerance,
+                prescale,
+                ignore_aa,
+                aa_tolerance,
+            } => Strategy::Simple {
+                max_delta,
+                max_deviation,
+                max_failing_pages,
+                align_tolerance,
+                prescale,
+                ignore_aa,
+                aa_tolerance,
+            },
+            Strategy::Ssim { min_ssim, .. } => Strategy::Ssim {
+                min_ssim,
+                max_failing_pages,
+            },
+        }
+    }
+}
+
+/// Which comparison strategy is used.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CompareMode {
+    /// Use [`Strategy::Simple`].
+    #[default]
+    Simple,
+
+    /// Use [`Strategy::Ssim`].
+    Ssim,
+
+    /// Compare the normalized text of pages as it's written to PDF exports
+    /// instead of their pixels, see [`text`].
+    PdfText,
+}
+
+/// A downscaled comparison, each block of `factor` by `factor` pixels is
+/// compared by its average color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Prescale {
+    /// The factor by which to downscale pages, factors below `2` disable the
+    /// downscaled comparison.
+    pub factor: u32,
+
+    /// How the downscaled comparison is used.
+    pub mode: PrescaleMode,
+}
+
+/// How a downscaled comparison is used.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PrescaleMode {
+    /// Quickly reject pages which differ grossly and compare the remaining
+    /// pages at full resolution, this never changes the outcome of a
+    /// comparison.
+    ///
+    /// This is skipped if alignment, [`Strategy::Simple::ignore_aa`] or
+    /// [`Strategy::Simple::aa_tolerance`] is enabled.
+    #[default]
+    Gate,
+
+    /// Only compare the downscaled pages, deviations are counted in blocks
+    /// rather than pixels.
+    ///
+    /// This is faster, but may pass pages which differ at full resolution.
+    Only,
 }
 
 impl Default for Strategy {
@@ -48,27 +266,444 @@ impl Default for Strategy {
         Self::Simple {
             max_delta: 0,
             max_deviation: 0,
+            max_failing_pages: 0,
+            align_tolerance: 0,
+            prescale: None,
+            ignore_aa: false,
+            aa_tolerance: None,
+        }
+    }
+}
+
+/// Comparison tolerances which override those of a [`Strategy`] for a single
+/// page, see [`PageCompare`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct PageTolerance {
+    /// Overrides [`Strategy::Simple::max_delta`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_delta: Option<u8>,
+
+    /// Overrides [`Strategy::Simple::max_deviation`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_deviations: Option<usize>,
+}
+
+impl PageTolerance {
+    /// Overrides the tolerances of this one with those set in `other`.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            max_delta: other.max_delta.or(self.max_delta),
+            max_deviations: other.max_deviations.or(self.max_deviations),
+        }
+    }
+
+    /// Applies the overrides of this tolerance to the given strategy.
+    ///
+    /// Tolerances only configure [`Strategy::Simple`], other strategies are
+    /// returned unchanged.
+    pub fn apply(&self, strategy: Strategy) -> Strategy {
+        let Strategy::Simple {
+            max_delta,
+            max_deviation,
+            max_failing_pages,
+            align_tolerance,
+            prescale,
+            ignore_aa,
+            aa_tolerance,
+        } = strategy
+        else {
+            return strategy;
+        };
+
+        Strategy::Simple {
+            max_delta: self.max_delta.unwrap_or(max_delta),
+            max_deviation: self.max_deviations.unwrap_or(max_deviation),
+            max_failing_pages,
+            align_tolerance,
+            prescale,
+            ignore_aa,
+            aa_tolerance,
+        }
+    }
+}
+
+/// Per-page comparison tolerances, keyed by 1-based page number.
+///
+/// Pages which are not listed use the `default` tolerance if it is given, or
+/// the unchanged strategy otherwise. In a config these are written as a table
+/// whose keys are page numbers or `default`:
+///
+/// ```toml
+/// page-compare = { 1 = { max-deviations = 500 }, default = { max-deviations = 0 } }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(try_from = "BTreeMap<String, PageTolerance>")]
+#[serde(into = "BTreeMap<String, PageTolerance>")]
+pub struct PageCompare {
+    pages: BTreeMap<usize, PageTolerance>,
+    default: Option<PageTolerance>,
+}
+
+impl PageCompare {
+    /// Creates a new empty set of per-page tolerances, which leaves all
+    /// strategies unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether there are no per-page tolerances at all.
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty() && self.default.is_none()
+    }
+
+    /// Sets the tolerance of the page with the given 1-based page number.
+    pub fn with_page(mut self, page: usize, tolerance: PageTolerance) -> Self {
+        self.pages.insert(page, tolerance);
+        self
+    }
+
+    /// Overrides the tolerance of the page with the given 1-based page number
+    /// with those set in the given tolerance, tolerances which are not set
+    /// are kept.
+    ///
+    /// The default tolerance does not apply to a page once it has been
+    /// overridden, unset tolerances then fall back to the strategy.
+    pub fn override_page(mut self, page: usize, tolerance: PageTolerance) -> Self {
+        let entry = self.pages.entry(page).or_default();
+        *entry = entry.merge(tolerance);
+        self
+    }
+
+    /// Overrides the tolerances of all listed pages and the default tolerance
+    /// with those set in the given tolerance, tolerances which are not set
+    /// are kept.
+    ///
+    /// This is used to give the tolerances of a test precedence over the
+    /// per-page tolerances of a project.
+    pub fn override_all(mut self, tolerance: PageTolerance) -> Self {
+        for entry in self.pages.values_mut() {
+            *entry = entry.merge(tolerance);
+        }
+
+        if let Some(default) = &mut self.default {
+            *default = default.merge(tolerance);
+        }
+
+        self
+    }
+
+    /// Renumbers the tolerances of the given sorted 1-based page numbers to
+    /// their position among them, such that they apply to a document which
+    /// only consists of these pages. Tolerances of other pages are dropped.
+    pub fn select(&self, pages: &[usize]) -> Self {
+        Self {
+            pages: pages
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, page)| Some((idx + 1, *self.pages.get(page)?)))
+                .collect(),
+            default: self.default,
+        }
+    }
+
+    /// Sets the tolerance of all unlisted pages.
+    pub fn with_default(mut self, tolerance: PageTolerance) -> Self {
+        self.default = Some(tolerance);
+        self
+    }
+
+    /// The tolerance of the page with the given 1-based page number, falls
+    /// back to the default tolerance for unlisted pages.
+    pub fn page(&self, page: usize) -> Option<&PageTolerance> {
+        self.pages.get(&page).or(self.default.as_ref())
+    }
+
+    /// Resolves the strategy used for the page at the given 0-based index.
+    pub fn resolve(&self, idx: usize, strategy: Strategy) -> Strategy {
+        match self.page(idx + 1) {
+            Some(tolerance) => tolerance.apply(strategy),
+            None => strategy,
+        }
+    }
+}
+
+/// Returned when a [`PageCompare`] key is neither a page number nor
+/// `default`.
+#[derive(Debug, Clone, Error)]
+#[error("invalid page key {0:?}, expected a page number starting at 1 or `default`")]
+pub struct InvalidPageKey(pub String);
+
+impl TryFrom<BTreeMap<String, PageTolerance>> for PageCompare {
+    type Error = InvalidPageKey;
+
+    fn try_from(value: BTreeMap<String, PageTolerance>) -> Result<Self, Self::Error> {
+        let mut this = Self::new();
+
+        for (key, tolerance) in value {
+            if key == "default" {
+                this.default = Some(tolerance);
+                continue;
+            }
+
+            match key.parse() {
+                Ok(page) if page != 0 => {
+                    this.pages.insert(page, tolerance);
+                }
+                _ => return Err(InvalidPageKey(key)),
+            }
         }
+
+        Ok(this)
+    }
+}
+
+impl From<PageCompare> for BTreeMap<String, PageTolerance> {
+    fn from(value: PageCompare) -> Self {
+        value
+            .pages
+            .into_iter()
+            .map(|(page, tolerance)| (page.to_string(), tolerance))
+            .chain(value.default.map(|tolerance| ("default".into(), tolerance)))
+            .collect()
     }
 }
 
 /// Compares two pages individually using the given strategy.
 pub fn page(output: &Pixmap, reference: &Pixmap, strategy: Strategy) -> Result<(), PageError> {
     match strategy {
-        Strategy::Simple {
-            max_delta,
-            max_deviation,
-        } => page_simple(output, reference, max_delta, max_deviation),
+        Strategy::Simple { .. } => page_simple(output, reference, strategy),
+        Strategy::Ssim {
+            min_ssim,
+            max_failing_pages: _,
+        } => page_ssim(output, reference, min_ssim.get()),
     }
 }
 
-/// Compares two pages individually using [`Strategy::Simple`].
-fn page_simple(
+/// Compares the normalized text of two documents page by page, see
+/// [`page_text`].
+///
+/// Each page is given as its text, see
+/// [`Document::text`](super::Document::text). Up to `max_failing_pages` pages
+/// may differ.
+pub fn text(
+    outputs: &[String],
+    references: &[String],
+    max_failing_pages: usize,
+) -> Result<(), Error> {
+    let pages: Vec<_> = iter::zip(outputs, references)
+        .enumerate()
+        .filter_map(|(idx, (a, b))| page_text(a, b).err().map(|err| (idx, err)))
+        .collect();
+
+    if pages.len() > max_failing_pages || outputs.len() != references.len() {
+        return Err(Error {
+            output: outputs.len(),
+            reference: references.len(),
+            pages,
+            max_failing_pages,
+            details: vec![],
+        });
+    }
+
+    Ok(())
+}
+
+/// Compares the normalized text of two pages line by line, the first line
+/// which differs is returned.
+pub fn page_text(output: &str, reference: &str) -> Result<(), PageError> {
+    let mut outputs = output.lines();
+    let mut references = reference.lines();
+    let mut line = 0;
+
+    loop {
+        line += 1;
+        match (outputs.next(), references.next()) {
+            (None, None) => return Ok(()),
+            (a, b) if a == b => {}
+            (a, b) => {
+                return Err(PageError::Text {
+                    line,
+                    output: a.unwrap_or_default().into(),
+                    reference: b.unwrap_or_default().into(),
+                });
+            }
+        }
+    }
+}
+
+/// Compares two pages pixel by pixel and returns where and how much they
+/// deviate.
+///
+/// Unlike [`page`], this doesn't account for alignment, anti-aliasing or
+/// prescaling, every pixel for which any channel differs by more than
+/// `max_delta` is a deviation.
+pub fn page_detailed(
     output: &Pixmap,
     reference: &Pixmap,
     max_delta: u8,
-    max_deviation: usize,
-) -> Result<(), PageError> {
+) -> Result<PageDiff, PageError> {
+    if output.width() != reference.width() || output.height() != reference.height() {
+        return Err(PageError::Dimensions {
+            output: Size {
+                width: output.width(),
+                height: output.height(),
+            },
+            reference: Size {
+                width: reference.width(),
+                height: reference.height(),
+            },
+        });
+    }
+
+    let width = output.width();
+    let mut diff = PageDiff::default();
+    let mut min = (u32::MAX, u32::MAX);
+    let mut max = (0, 0);
+
+    for (idx, (a, b)) in
+        Iterator::zip(output.pixels().iter(), reference.pixels().iter()).enumerate()
+    {
+        let delta = channel_delta(*a, *b);
+        diff.largest_delta = Ord::max(diff.largest_delta, delta);
+
+        if delta > max_delta {
+            let (x, y) = (idx as u32 % width, idx as u32 / width);
+            min = (Ord::min(min.0, x), Ord::min(min.1, y));
+            max = (Ord::max(max.0, x), Ord::max(max.1, y));
+            diff.deviations += 1;
+        }
+    }
+
+    if diff.deviations != 0 {
+        diff.region = Some(Region {
+            x: min.0,
+            y: min.1,
+            width: max.0 - min.0 + 1,
+            height: max.1 - min.1 + 1,
+        });
+    }
+
+    Ok(diff)
+}
+
+/// Compares two pages individually using [`Strategy::Ssim`].
+fn page_ssim(output: &Pixmap, reference: &Pixmap, min_ssim: f64) -> Result<(), PageError> {
+    if output.width() != reference.width() || output.height() != reference.height() {
+        return Err(PageError::Dimensions {
+            output: Size {
+                width: output.width(),
+                height: output.height(),
+            },
+            reference: Size {
+                width: reference.width(),
+                height: reference.height(),
+            },
+        });
+    }
+
+    let score = ssim(output, reference);
+    if score < min_ssim {
+        return Err(PageError::Ssim { score, min_ssim });
+    }
+
+    Ok(())
+}
+
+/// Computes the mean structural similarity index of the luminance of two
+/// pages of equal size.
+///
+/// The index is computed over non-overlapping windows of
+/// [`SSIM_WINDOW_SIZE`] pixels, windows at the right and bottom edges may be
+/// smaller. Transparent pixels are treated as white. The result lies between
+/// `-1.0` and `1.0`, identical pages have an index of `1.0`.
+///
+/// # Panics
+/// Panics if the pages differ in size.
+pub fn ssim(output: &Pixmap, reference: &Pixmap) -> f64 {
+    assert_eq!(
+        (output.width(), output.height()),
+        (reference.width(), reference.height()),
+        "pages must have the same size",
+    );
+
+    // The stabilizing constants for a dynamic range of 255 as suggested by
+    // Wang et al.
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let (width, height) = (output.width(), output.height());
+    if width == 0 || height == 0 {
+        return 1.0;
+    }
+
+    let luma = |page: &Pixmap, x: u32, y: u32| {
+        let pixel = page.pixels()[(y * width + x) as usize];
+
+        // Pixels are premultiplied, adding the missing coverage composes
+        // them over white.
+        let bg = 255.0 - pixel.alpha() as f64;
+        0.299 * (pixel.red() as f64 + bg)
+            + 0.587 * (pixel.green() as f64 + bg)
+            + 0.114 * (pixel.blue() as f64 + bg)
+    };
+
+    let mut total = 0.0;
+    let mut windows = 0;
+
+    for wy in (0..height).step_by(SSIM_WINDOW_SIZE as usize) {
+        for wx in (0..width).step_by(SSIM_WINDOW_SIZE as usize) {
+            let (mut sum_a, mut sum_b) = (0.0, 0.0);
+            let (mut sum_aa, mut sum_bb, mut sum_ab) = (0.0, 0.0, 0.0);
+            let mut n = 0.0;
+
+            for y in wy..Ord::min(wy + SSIM_WINDOW_SIZE, height) {
+                for x in wx..Ord::min(wx + SSIM_WINDOW_SIZE, width) {
+                    let a = luma(output, x, y);
+                    let b = luma(reference, x, y);
+
+                    sum_a += a;
+                    sum_b += b;
+                    sum_aa += a * a;
+                    sum_bb += b * b;
+                    sum_ab += a * b;
+                    n += 1.0;
+                }
+            }
+
+            let (mean_a, mean_b) = (sum_a / n, sum_b / n);
+            let var_a = sum_aa / n - mean_a * mean_a;
+            let var_b = sum_bb / n - mean_b * mean_b;
+            let cov = sum_ab / n - mean_a * mean_b;
+
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * cov + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+
+    total / windows as f64
+}
+
+/// Compares two pages individually using [`Strategy::Simple`].
+///
+/// # Panics
+/// Panics if the strategy is not [`Strategy::Simple`].
+fn page_simple(output: &Pixmap, reference: &Pixmap, strategy: Strategy) -> Result<(), PageError> {
+    let Strategy::Simple {
+        max_delta,
+        max_deviation,
+        max_failing_pages: _,
+        align_tolerance,
+        prescale,
+        ignore_aa,
+        aa_tolerance,
+    } = strategy
+    else {
+        panic!("expected a simple strategy, got {strategy:?}");
+    };
+
     if output.width() != reference.width() || output.height() != reference.height() {
         return Err(PageError::Dimensions {
             output: Size {
@@ -82,22 +717,139 @@ fn page_simple(
         });
     }
 
-    let deviations = Iterator::zip(output.pixels().iter(), reference.pixels().iter())
-        .filter(|(a, b)| {
-            u8::abs_diff(a.red(), b.red()) > max_delta
-                || u8::abs_diff(a.green(), b.green()) > max_delta
-                || u8::abs_diff(a.blue(), b.blue()) > max_delta
-                || u8::abs_diff(a.alpha(), b.alpha()) > max_delta
-        })
-        .count();
+    if let Some(Prescale { factor, mode }) = prescale
+        && factor > 1
+        && (mode == PrescaleMode::Only
+            || (align_tolerance == 0 && !ignore_aa && aa_tolerance.is_none()))
+    {
+        // Each deviating block contains at least one deviating pixel, so this
+        // is a lower bound for the full resolution deviations.
+        let deviations = deviations_prescaled(output, reference, max_delta, factor);
+
+        if deviations > max_deviation {
+            return Err(PageError::SimpleDeviations {
+                deviations,
+                max_delta,
+                max_deviation,
+            });
+        }
+
+        if mode == PrescaleMode::Only {
+            return Ok(());
+        }
+    }
+
+    let deviations = if align_tolerance != 0 {
+        deviations_aligned(
+            output,
+            reference,
+            max_delta,
+            align_tolerance,
+            ignore_aa,
+            aa_tolerance,
+        )
+    } else if ignore_aa || aa_tolerance.is_some() {
+        let width = output.width();
+        Iterator::zip(output.pixels().iter(), reference.pixels().iter())
+            .enumerate()
+            .filter(|(idx, (a, b))| {
+                let (x, y) = (*idx as u32 % width, *idx as u32 / width);
+                deviates(**a, **b, max_delta)
+                    && !(ignore_aa && is_antialiasing(output, reference, x, y, x, y))
+                    && !aa_tolerance
+                        .is_some_and(|tolerance| matches_neighbor(**a, reference, x, y, tolerance))
+            })
+            .count()
+    } else {
+        page_detailed(output, reference, max_delta)?.deviations
+    };
 
     if deviations > max_deviation {
-        return Err(PageError::SimpleDeviations { deviations });
+        return Err(PageError::SimpleDeviations {
+            deviations,
+            max_delta,
+            max_deviation,
+        });
     }
 
     Ok(())
 }
 
+/// A cache of passed document comparisons.
+///
+/// Entries are keyed by the content of the compared pages and the strategy
+/// used to compare them, see [`CompareCache::key`]. Only passed comparisons
+/// are stored, each as an empty file named after its key within the cache
+/// directory.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CompareCache {
+    dir: PathBuf,
+}
+
+impl CompareCache {
+    /// Creates a new cache in the given directory, the directory is created
+    /// once the first entry is inserted.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The directory in which the cache entries are stored.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Computes the key of a comparison of the given output and reference
+    /// pages using the given strategy and per-page tolerances.
+    ///
+    /// The key includes the version of this crate such that changes to the
+    /// comparison algorithms invalidate old entries.
+    pub fn key(
+        outputs: &[Pixmap],
+        references: &[Pixmap],
+        strategy: Strategy,
+        page_compare: &PageCompare,
+    ) -> u128 {
+        fn pages(pages: &[Pixmap]) -> Vec<(u32, u32, &[u8])> {
+            pages
+                .iter()
+                .map(|page| (page.width(), page.height(), page.data()))
+                .collect()
+        }
+
+        hash128(&(
+            env!("CARGO_PKG_VERSION"),
+            strategy,
+            page_compare,
+            pages(outputs),
+            pages(references),
+        ))
+    }
+
+    /// Whether a passed comparison with the given key is cached.
+    pub fn contains(&self, key: u128) -> io::Result<bool> {
+        Ok(fs::metadata(self.entry(key))
+            .ignore(io_not_found)?
+            .is_some_and(|meta| meta.is_file()))
+    }
+
+    /// Records a passed comparison with the given key.
+    pub fn insert(&self, key: u128) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.entry(key), b"")
+    }
+
+    /// Removes all entries from the cache.
+    pub fn clear(&self) -> io::Result<()> {
+        fs::remove_dir_all(&self.dir).ignore(io_not_found)?;
+
+        Ok(())
+    }
+
+    fn entry(&self, key: u128) -> PathBuf {
+        self.dir.join(format!("{key:032x}"))
+    }
+}
+
 /// An error describing why a document comparison failed.
 #[derive(Debug, Clone, Error)]
 pub struct Error {
@@ -109,6 +861,32 @@ pub struct Error {
 
     /// The page failures if there are any with their indices.
     pub pages: Vec<(usize, PageError)>,
+
+    /// The number of pages which were allowed to fail.
+    pub max_failing_pages: usize,
+
+    /// The detailed deviations of the pages which failed because of their
+    /// content with their indices, see [`page_detailed`].
+    pub details: Vec<(usize, PageDiff)>,
+}
+
+impl Error {
+    /// Maps the page indices of a comparison of documents which only consist
+    /// of the given sorted 1-based page numbers back to the indices of these
+    /// pages in the whole document.
+    pub fn select(mut self, pages: &[usize]) -> Self {
+        let map = |idx: usize| pages.get(idx).map(|page| page - 1).unwrap_or(idx);
+
+        for (idx, _) in &mut self.pages {
+            *idx = map(*idx);
+        }
+
+        for (idx, _) in &mut self.details {
+            *idx = map(*idx);
+        }
+
+        self
+    }
 }
 
 impl Display for Error {
@@ -154,7 +932,7 @@ pub enum PageError {
 
     /// The pages differed according to [`Strategy::Simple`].
     #[error(
-        "content differed in at least {} {}",
+        "content differed in at least {} {} (max-delta {max_delta}, max-deviations {max_deviation})",
         deviations,
         Term::simple("pixel").with(*deviations)
     )]
@@ -162,38 +940,325 @@ pub enum PageError {
         /// The amount of visual deviations, i.e. the amount of pixels which did
         /// not match according to the visual strategy.
         deviations: usize,
+
+        /// The maximum allowed per pixel delta the page was compared with.
+        max_delta: u8,
+
+        /// The maximum allowed amount of deviations the page was compared
+        /// with.
+        max_deviation: usize,
     },
-}
 
-#[cfg(test)]
-mod tests {
-    use tiny_skia::PremultipliedColorU8;
+    /// The pages were cropped at different offsets, i.e. their content was at
+    /// different positions, see [`Document::crop`](super::Document::crop).
+    #[error(
+        "content position differed: out ({}, {}) != ref ({}, {})",
+        output.x,
+        output.y,
+        reference.x,
+        reference.y
+    )]
+    CropOffset {
+        /// The offset of the output page.
+        output: CropOffset,
 
-    use super::*;
+        /// The offset of the reference page.
+        reference: CropOffset,
+    },
 
-    fn images() -> [Pixmap; 2] {
-        let a = Pixmap::new(10, 1).unwrap();
-        let mut b = Pixmap::new(10, 1).unwrap();
+    /// The reference page was rendered at a different PPI than the output
+    /// page, the references must be updated.
+    #[error(
+        "reference rendered at different PPI: out {output} != ref {reference}, re-update needed"
+    )]
+    Ppi {
+        /// The PPI of the output page.
+        output: f64,
 
-        let red = PremultipliedColorU8::from_rgba(128, 0, 0, 128).unwrap();
-        b.pixels_mut()[0] = red;
-        b.pixels_mut()[1] = red;
-        b.pixels_mut()[2] = red;
-        b.pixels_mut()[3] = red;
+        /// The PPI of the reference page.
+        reference: f64,
+    },
 
-        [a, b]
-    }
+    /// The pages differed according to [`Strategy::Ssim`].
+    #[error("structural similarity {score:.4} was below the minimum of {min_ssim}")]
+    Ssim {
+        /// The mean structural similarity index of the pages.
+        score: f64,
 
-    #[test]
-    fn test_page_simple_below_max_delta() {
-        let [a, b] = images();
-        assert!(
-            page(
-                &a,
-                &b,
-                Strategy::Simple {
-                    max_delta: 128,
+        /// The minimum structural similarity index the page had to reach.
+        min_ssim: f64,
+    },
+
+    /// The text of the pages differed, see [`page_text`].
+    #[error("text differed on line {line}: out {output:?} != ref {reference:?}")]
+    Text {
+        /// The 1-based number of the first line which differed.
+        line: usize,
+
+        /// The output line, empty if the output page had fewer lines.
+        output: EcoString,
+
+        /// The reference line, empty if the reference page had fewer lines.
+        reference: EcoString,
+    },
+}
+
+/// Whether any channel of the two pixels differs by more than `max_delta`.
+pub(crate) fn deviates(a: PremultipliedColorU8, b: PremultipliedColorU8, max_delta: u8) -> bool {
+    u8::abs_diff(a.red(), b.red()) > max_delta
+        || u8::abs_diff(a.green(), b.green()) > max_delta
+        || u8::abs_diff(a.blue(), b.blue()) > max_delta
+        || u8::abs_diff(a.alpha(), b.alpha()) > max_delta
+}
+
+/// The largest difference of any channel of the two pixels.
+fn channel_delta(a: PremultipliedColorU8, b: PremultipliedColorU8) -> u8 {
+    [
+        u8::abs_diff(a.red(), b.red()),
+        u8::abs_diff(a.green(), b.green()),
+        u8::abs_diff(a.blue(), b.blue()),
+        u8::abs_diff(a.alpha(), b.alpha()),
+    ]
+    .into_iter()
+    .max()
+    .unwrap_or(0)
+}
+
+/// Whether the deviation between the output pixel at `(x, y)` and the
+/// reference pixel at `(rx, ry)` is likely anti-aliasing at the edge of a shape.
+///
+/// This is an approximate heuristic, a deviation is considered anti-aliasing
+/// if no channel differs by more than [`AA_MAX_DELTA`] and both pixels lie
+/// within a neighborhood whose luminance contrast is at least
+/// [`AA_MIN_CONTRAST`] in their respective page. Genuine changes at the edges
+/// of shapes may therefore be ignored too.
+pub fn is_antialiasing(
+    output: &Pixmap,
+    reference: &Pixmap,
+    x: u32,
+    y: u32,
+    rx: u32,
+    ry: u32,
+) -> bool {
+    let a = output.pixels()[(y * output.width() + x) as usize];
+    let b = reference.pixels()[(ry * reference.width() + rx) as usize];
+
+    !deviates(a, b, AA_MAX_DELTA)
+        && contrast(output, x, y) >= AA_MIN_CONTRAST
+        && contrast(reference, rx, ry) >= AA_MIN_CONTRAST
+}
+
+/// Whether the given output pixel matches any of the 8 neighbors of the
+/// reference pixel at `(x, y)`, i.e. no channel differs by more than
+/// `tolerance`.
+///
+/// Neighbors outside the reference are ignored.
+pub fn matches_neighbor(
+    pixel: PremultipliedColorU8,
+    reference: &Pixmap,
+    x: u32,
+    y: u32,
+    tolerance: u8,
+) -> bool {
+    let pixels = reference.pixels();
+
+    for ny in y.saturating_sub(1)..Ord::min(y + 2, reference.height()) {
+        for nx in x.saturating_sub(1)..Ord::min(x + 2, reference.width()) {
+            if (nx, ny) != (x, y)
+                && !deviates(
+                    pixel,
+                    pixels[(ny * reference.width() + nx) as usize],
+                    tolerance,
+                )
+            {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// The luminance contrast of the 3x3 neighborhood around the given pixel, i.e.
+/// the difference between its brightest and darkest pixel.
+fn contrast(page: &Pixmap, x: u32, y: u32) -> u8 {
+    let pixels = page.pixels();
+    let mut min = u8::MAX;
+    let mut max = u8::MIN;
+
+    for ny in y.saturating_sub(1)..Ord::min(y + 2, page.height()) {
+        for nx in x.saturating_sub(1)..Ord::min(x + 2, page.width()) {
+            let l = luminance(pixels[(ny * page.width() + nx) as usize]);
+            min = Ord::min(min, l);
+            max = Ord::max(max, l);
+        }
+    }
+
+    max - min
+}
+
+/// The luminance of a pixel as if composited onto a white background.
+fn luminance(p: PremultipliedColorU8) -> u8 {
+    let luma = (p.red() as u32 * 299 + p.green() as u32 * 587 + p.blue() as u32 * 114) / 1000;
+    Ord::min(luma + (255 - p.alpha() as u32), 255) as u8
+}
+
+/// Counts the deviating pixels of two pages of equal size after downscaling
+/// both by the given factor, see [`render::page_downscale`].
+///
+/// The rounded average colors of a block can only deviate if at least one of
+/// its pixels deviates, so this is a lower bound for the full resolution
+/// deviations.
+fn deviations_prescaled(output: &Pixmap, reference: &Pixmap, max_delta: u8, factor: u32) -> usize {
+    let output = render::page_downscale(output, factor);
+    let reference = render::page_downscale(reference, factor);
+
+    Iterator::zip(output.pixels().iter(), reference.pixels().iter())
+        .filter(|(a, b)| deviates(**a, **b, max_delta))
+        .count()
+}
+
+/// Counts the deviations of two pages of equal size, allowing each tile of
+/// [`ALIGN_TILE_SIZE`] to be matched against a reference shifted by up to
+/// `tolerance` pixels in each direction.
+///
+/// Pixels which would be compared against a pixel outside the reference are
+/// compared against the unshifted reference pixel instead.
+fn deviations_aligned(
+    output: &Pixmap,
+    reference: &Pixmap,
+    max_delta: u8,
+    tolerance: u32,
+    ignore_aa: bool,
+    aa_tolerance: Option<u8>,
+) -> usize {
+    let width = output.width();
+    let height = output.height();
+    let outputs = output.pixels();
+    let references = reference.pixels();
+    let tolerance = tolerance as i64;
+
+    let index = |x: u32, y: u32| (y * width + x) as usize;
+
+    let tile_deviations = |tx: u32, ty: u32, dx: i64, dy: i64| {
+        let mut deviations = 0;
+
+        for y in ty..Ord::min(ty + ALIGN_TILE_SIZE, height) {
+            for x in tx..Ord::min(tx + ALIGN_TILE_SIZE, width) {
+                let rx = x as i64 + dx;
+                let ry = y as i64 + dy;
+
+                let (rx, ry) =
+                    if (0..width as i64).contains(&rx) && (0..height as i64).contains(&ry) {
+                        (rx as u32, ry as u32)
+                    } else {
+                        (x, y)
+                    };
+
+                if deviates(outputs[index(x, y)], references[index(rx, ry)], max_delta)
+                    && !(ignore_aa && is_antialiasing(output, reference, x, y, rx, ry))
+                    && !aa_tolerance.is_some_and(|tolerance| {
+                        matches_neighbor(outputs[index(x, y)], reference, rx, ry, tolerance)
+                    })
+                {
+                    deviations += 1;
+                }
+            }
+        }
+
+        deviations
+    };
+
+    let mut deviations = 0;
+
+    for ty in (0..height).step_by(ALIGN_TILE_SIZE as usize) {
+        for tx in (0..width).step_by(ALIGN_TILE_SIZE as usize) {
+            let mut best = tile_deviations(tx, ty, 0, 0);
+
+            'search: for dy in -tolerance..=tolerance {
+                for dx in -tolerance..=tolerance {
+                    if best == 0 {
+                        break 'search;
+                    }
+
+                    if (dx, dy) != (0, 0) {
+                        best = Ord::min(best, tile_deviations(tx, ty, dx, dy));
+                    }
+                }
+            }
+
+            deviations += best;
+        }
+    }
+
+    deviations
+}
+
+#[cfg(test)]
+mod tests {
+    use tytanic_utils::fs::TempTestEnv;
+
+    use super::*;
+
+    fn images() -> [Pixmap; 2] {
+        let a = Pixmap::new(10, 1).unwrap();
+        let mut b = Pixmap::new(10, 1).unwrap();
+
+        let red = PremultipliedColorU8::from_rgba(128, 0, 0, 128).unwrap();
+        b.pixels_mut()[0] = red;
+        b.pixels_mut()[1] = red;
+        b.pixels_mut()[2] = red;
+        b.pixels_mut()[3] = red;
+
+        [a, b]
+    }
+
+    #[test]
+    fn test_page_detailed() {
+        let [a, b] = images();
+
+        assert_eq!(
+            page_detailed(&a, &b, 0).unwrap(),
+            PageDiff {
+                deviations: 4,
+                region: Some(Region {
+                    x: 0,
+                    y: 0,
+                    width: 4,
+                    height: 1,
+                }),
+                largest_delta: 128,
+            }
+        );
+        assert_eq!(
+            page_detailed(&a, &b, 128).unwrap(),
+            PageDiff {
+                deviations: 0,
+                region: None,
+                largest_delta: 128,
+            }
+        );
+        assert!(matches!(
+            page_detailed(&a, &Pixmap::new(5, 1).unwrap(), 0),
+            Err(PageError::Dimensions { .. })
+        ));
+    }
+
+    #[test]
+    fn test_page_simple_below_max_delta() {
+        let [a, b] = images();
+        assert!(
+            page(
+                &a,
+                &b,
+                Strategy::Simple {
+                    max_delta: 128,
                     max_deviation: 0,
+                    max_failing_pages: 0,
+                    align_tolerance: 0,
+                    prescale: None,
+                    ignore_aa: false,
+                    aa_tolerance: None,
                 },
             )
             .is_ok()
@@ -210,6 +1275,11 @@ mod tests {
                 Strategy::Simple {
                     max_delta: 0,
                     max_deviation: 5,
+                    max_failing_pages: 0,
+                    align_tolerance: 0,
+                    prescale: None,
+                    ignore_aa: false,
+                    aa_tolerance: None,
                 },
             )
             .is_ok()
@@ -226,9 +1296,594 @@ mod tests {
                 Strategy::Simple {
                     max_delta: 0,
                     max_deviation: 0,
+                    max_failing_pages: 0,
+                    align_tolerance: 0,
+                    prescale: None,
+                    ignore_aa: false,
+                    aa_tolerance: None,
+                },
+            ),
+            Err(PageError::SimpleDeviations { deviations: 4, .. })
+        ))
+    }
+
+    fn shifted_images() -> [Pixmap; 2] {
+        let mut a = Pixmap::new(40, 20).unwrap();
+        let mut b = Pixmap::new(40, 20).unwrap();
+
+        let black = PremultipliedColorU8::from_rgba(0, 0, 0, 255).unwrap();
+        for y in 2..18 {
+            for x in [3, 15, 16, 30] {
+                a.pixels_mut()[y * 40 + x] = black;
+                b.pixels_mut()[y * 40 + x + 1] = black;
+            }
+        }
+
+        [a, b]
+    }
+
+    #[test]
+    fn test_page_simple_shifted_without_alignment() {
+        let [a, b] = shifted_images();
+        assert!(matches!(
+            page(
+                &a,
+                &b,
+                Strategy::Simple {
+                    max_delta: 0,
+                    max_deviation: 0,
+                    max_failing_pages: 0,
+                    align_tolerance: 0,
+                    prescale: None,
+                    ignore_aa: false,
+                    aa_tolerance: None,
+                },
+            ),
+            Err(PageError::SimpleDeviations { deviations: 96, .. })
+        ))
+    }
+
+    #[test]
+    fn test_page_simple_shifted_with_alignment() {
+        let [a, b] = shifted_images();
+        assert!(
+            page(
+                &a,
+                &b,
+                Strategy::Simple {
+                    max_delta: 0,
+                    max_deviation: 0,
+                    max_failing_pages: 0,
+                    align_tolerance: 1,
+                    prescale: None,
+                    ignore_aa: false,
+                    aa_tolerance: None,
+                },
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_page_simple_shifted_with_aa_tolerance() {
+        let [a, b] = shifted_images();
+        assert!(
+            page(
+                &a,
+                &b,
+                Strategy::Simple {
+                    max_delta: 0,
+                    max_deviation: 0,
+                    max_failing_pages: 0,
+                    align_tolerance: 0,
+                    prescale: None,
+                    ignore_aa: false,
+                    aa_tolerance: Some(0),
+                },
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_page_simple_aa_tolerance_does_not_hide_changes() {
+        let [a, mut b] = shifted_images();
+        let red = PremultipliedColorU8::from_rgba(255, 0, 0, 255).unwrap();
+        b.pixels_mut()[10 * 40 + 30] = red;
+        b.pixels_mut()[10 * 40 + 31] = red;
+        b.pixels_mut()[9 * 40 + 31] = red;
+        b.pixels_mut()[11 * 40 + 31] = red;
+
+        assert!(matches!(
+            page(
+                &a,
+                &b,
+                Strategy::Simple {
+                    max_delta: 0,
+                    max_deviation: 0,
+                    max_failing_pages: 0,
+                    align_tolerance: 0,
+                    prescale: None,
+                    ignore_aa: false,
+                    aa_tolerance: Some(0),
+                },
+            ),
+            Err(PageError::SimpleDeviations { deviations: 1, .. })
+        ))
+    }
+
+    #[test]
+    fn test_page_simple_alignment_does_not_hide_changes() {
+        let [a, mut b] = shifted_images();
+        let black = PremultipliedColorU8::from_rgba(0, 0, 0, 255).unwrap();
+        b.pixels_mut()[10 * 40 + 8] = black;
+
+        assert!(matches!(
+            page(
+                &a,
+                &b,
+                Strategy::Simple {
+                    max_delta: 0,
+                    max_deviation: 0,
+                    max_failing_pages: 0,
+                    align_tolerance: 1,
+                    prescale: None,
+                    ignore_aa: false,
+                    aa_tolerance: None,
                 },
             ),
-            Err(PageError::SimpleDeviations { deviations: 4 })
+            Err(PageError::SimpleDeviations { deviations: 1, .. })
         ))
     }
+
+    fn large_images() -> [Pixmap; 2] {
+        let a = Pixmap::new(16, 16).unwrap();
+        let mut b = Pixmap::new(16, 16).unwrap();
+
+        // A gross difference in the top left corner.
+        let red = PremultipliedColorU8::from_rgba(255, 0, 0, 255).unwrap();
+        for y in 0..4 {
+            for x in 0..4 {
+                b.pixels_mut()[y * 16 + x] = red;
+            }
+        }
+
+        // A subtle difference which vanishes when averaged.
+        let faint = PremultipliedColorU8::from_rgba(2, 0, 0, 2).unwrap();
+        b.pixels_mut()[10 * 16 + 10] = faint;
+
+        [a, b]
+    }
+
+    fn prescaled(max_deviation: usize, mode: PrescaleMode) -> Strategy {
+        Strategy::Simple {
+            max_delta: 1,
+            max_deviation,
+            max_failing_pages: 0,
+            align_tolerance: 0,
+            prescale: Some(Prescale { factor: 4, mode }),
+            ignore_aa: false,
+            aa_tolerance: None,
+        }
+    }
+
+    #[test]
+    fn test_page_simple_prescale_gate_rejects() {
+        let [a, b] = large_images();
+        assert!(matches!(
+            page(&a, &b, prescaled(0, PrescaleMode::Gate)),
+            Err(PageError::SimpleDeviations { deviations: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_page_simple_prescale_gate_is_authoritative() {
+        let [a, b] = large_images();
+
+        // The gate passes with one deviating block, but full resolution finds
+        // 17 deviating pixels.
+        assert!(matches!(
+            page(&a, &b, prescaled(1, PrescaleMode::Gate)),
+            Err(PageError::SimpleDeviations { deviations: 17, .. })
+        ));
+    }
+
+    #[test]
+    fn test_page_simple_prescale_only() {
+        let [a, b] = large_images();
+
+        // The faint pixel is lost when downscaling.
+        assert!(page(&a, &b, prescaled(1, PrescaleMode::Only)).is_ok());
+        assert!(matches!(
+            page(&a, &b, prescaled(0, PrescaleMode::Only)),
+            Err(PageError::SimpleDeviations { deviations: 1, .. })
+        ));
+    }
+
+    fn aa_images() -> [Pixmap; 2] {
+        let mut a = Pixmap::new(10, 5).unwrap();
+        a.fill(tiny_skia::Color::WHITE);
+
+        let black = PremultipliedColorU8::from_rgba(0, 0, 0, 255).unwrap();
+        for y in 0..5 {
+            a.pixels_mut()[y * 10 + 5] = black;
+        }
+        let mut b = a.clone();
+
+        // A slightly darker pixel next to the line.
+        let grey = PremultipliedColorU8::from_rgba(200, 200, 200, 255).unwrap();
+        b.pixels_mut()[2 * 10 + 4] = grey;
+
+        // The same pixel away from any edge.
+        b.pixels_mut()[2 * 10 + 1] = grey;
+
+        [a, b]
+    }
+
+    fn ignore_aa(ignore_aa: bool, align_tolerance: u32) -> Strategy {
+        Strategy::Simple {
+            max_delta: 0,
+            max_deviation: 0,
+            max_failing_pages: 0,
+            align_tolerance,
+            prescale: None,
+            ignore_aa,
+            aa_tolerance: None,
+        }
+    }
+
+    #[test]
+    fn test_ssim_identical() {
+        let [a, _] = shifted_images();
+        assert_eq!(ssim(&a, &a), 1.0);
+    }
+
+    #[test]
+    fn test_page_ssim() {
+        let [a, b] = images();
+        let strategy = |min_ssim| Strategy::Ssim {
+            min_ssim: Scalar::new(min_ssim),
+            max_failing_pages: 0,
+        };
+
+        let score = ssim(&a, &b);
+        assert!(score < 1.0);
+
+        assert!(page(&a, &b, strategy(score)).is_ok());
+        assert!(matches!(
+            page(&a, &b, strategy(1.0)),
+            Err(PageError::Ssim { score: s, min_ssim }) if s == score && min_ssim == 1.0
+        ));
+    }
+
+    #[test]
+    fn test_page_ssim_dimensions() {
+        let a = Pixmap::new(10, 1).unwrap();
+        let b = Pixmap::new(10, 2).unwrap();
+        assert!(matches!(
+            page(
+                &a,
+                &b,
+                Strategy::Ssim {
+                    min_ssim: Scalar::new(0.0),
+                    max_failing_pages: 0,
+                },
+            ),
+            Err(PageError::Dimensions { .. })
+        ));
+    }
+
+    #[test]
+    fn test_is_antialiasing() {
+        let [a, b] = aa_images();
+        assert!(is_antialiasing(&a, &b, 4, 2, 4, 2));
+        assert!(!is_antialiasing(&a, &b, 1, 2, 1, 2));
+    }
+
+    #[test]
+    fn test_page_simple_ignore_aa() {
+        let [a, b] = aa_images();
+
+        assert!(matches!(
+            page(&a, &b, ignore_aa(false, 0)),
+            Err(PageError::SimpleDeviations { deviations: 2, .. })
+        ));
+        assert!(matches!(
+            page(&a, &b, ignore_aa(true, 0)),
+            Err(PageError::SimpleDeviations { deviations: 1, .. })
+        ));
+        assert!(matches!(
+            page(&a, &b, ignore_aa(true, 1)),
+            Err(PageError::SimpleDeviations { deviations: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_compare_cache_key() {
+        let [a, b] = images().map(|image| [image]);
+
+        let none = PageCompare::new();
+        let key = CompareCache::key(&a, &b, Strategy::default(), &none);
+
+        assert_eq!(key, CompareCache::key(&a, &b, Strategy::default(), &none));
+        assert_ne!(key, CompareCache::key(&b, &a, Strategy::default(), &none));
+        assert_ne!(key, CompareCache::key(&a, &b, ignore_aa(true, 0), &none));
+        assert_ne!(
+            key,
+            CompareCache::key(
+                &a,
+                &b,
+                Strategy::default(),
+                &PageCompare::new().with_default(PageTolerance {
+                    max_delta: None,
+                    max_deviations: Some(4),
+                }),
+            )
+        );
+    }
+
+    #[test]
+    fn test_page_compare_resolve() {
+        let page_compare = PageCompare::new()
+            .with_page(
+                1,
+                PageTolerance {
+                    max_delta: None,
+                    max_deviations: Some(500),
+                },
+            )
+            .with_default(PageTolerance {
+                max_delta: Some(0),
+                max_deviations: None,
+            });
+
+        let strategy = |max_delta, max_deviation| Strategy::Simple {
+            max_delta,
+            max_deviation,
+            max_failing_pages: 0,
+            align_tolerance: 0,
+            prescale: None,
+            ignore_aa: false,
+            aa_tolerance: None,
+        };
+
+        assert_eq!(page_compare.resolve(0, strategy(1, 2)), strategy(1, 500));
+        assert_eq!(page_compare.resolve(1, strategy(1, 2)), strategy(0, 2));
+        assert_eq!(
+            PageCompare::new().resolve(0, strategy(1, 2)),
+            strategy(1, 2)
+        );
+        let ssim = Strategy::Ssim {
+            min_ssim: Scalar::new(0.9),
+            max_failing_pages: 0,
+        };
+        assert_eq!(page_compare.resolve(0, ssim), ssim);
+    }
+
+    #[test]
+    fn test_page_compare_override_all() {
+        let strategy = |max_delta, max_deviation| Strategy::Simple {
+            max_delta,
+            max_deviation,
+            max_failing_pages: 0,
+            align_tolerance: 0,
+            prescale: None,
+            ignore_aa: false,
+            aa_tolerance: None,
+        };
+
+        let page_compare = PageCompare::new()
+            .with_page(
+                1,
+                PageTolerance {
+                    max_delta: Some(3),
+                    max_deviations: Some(500),
+                },
+            )
+            .with_default(PageTolerance {
+                max_delta: None,
+                max_deviations: Some(10),
+            })
+            .override_all(PageTolerance {
+                max_delta: None,
+                max_deviations: Some(20),
+            });
+
+        assert_eq!(page_compare.resolve(0, strategy(1, 0)), strategy(3, 20));
+        assert_eq!(page_compare.resolve(1, strategy(1, 0)), strategy(1, 20));
+    }
+
+    #[test]
+    fn test_page_compare_select() {
+        let tolerance = |max_deviations| PageTolerance {
+            max_delta: None,
+            max_deviations: Some(max_deviations),
+        };
+
+        let page_compare = PageCompare::new()
+            .with_page(1, tolerance(1))
+            .with_page(3, tolerance(3))
+            .with_page(4, tolerance(4))
+            .with_default(tolerance(0))
+            .select(&[3, 5]);
+
+        assert_eq!(page_compare.page(1), Some(&tolerance(3)));
+        assert_eq!(page_compare.page(2), Some(&tolerance(0)));
+        assert_eq!(page_compare.page(3), Some(&tolerance(0)));
+    }
+
+    #[test]
+    fn test_error_select() {
+        let error = Error {
+            output: 2,
+            reference: 2,
+            pages: vec![
+                (
+                    0,
+                    PageError::Text {
+                        line: 1,
+                        output: "a".into(),
+                        reference: "b".into(),
+                    },
+                ),
+                (
+                    1,
+                    PageError::Text {
+                        line: 1,
+                        output: "a".into(),
+                        reference: "b".into(),
+                    },
+                ),
+            ],
+            max_failing_pages: 0,
+            details: vec![],
+        }
+        .select(&[3, 5]);
+
+        assert_eq!(
+            error.pages.iter().map(|(idx, _)| *idx).collect::<Vec<_>>(),
+            [2, 4]
+        );
+    }
+
+    #[test]
+    fn test_page_compare_override_page() {
+        let page_compare = PageCompare::new()
+            .with_page(
+                1,
+                PageTolerance {
+                    max_delta: Some(1),
+                    max_deviations: Some(500),
+                },
+            )
+            .with_default(PageTolerance {
+                max_delta: Some(0),
+                max_deviations: None,
+            })
+            .override_page(
+                1,
+                PageTolerance {
+                    max_delta: None,
+                    max_deviations: Some(100),
+                },
+            )
+            .override_page(
+                3,
+                PageTolerance {
+                    max_delta: None,
+                    max_deviations: Some(100),
+                },
+            );
+
+        assert_eq!(
+            page_compare.page(1),
+            Some(&PageTolerance {
+                max_delta: Some(1),
+                max_deviations: Some(100),
+            })
+        );
+        assert_eq!(
+            page_compare.page(2),
+            Some(&PageTolerance {
+                max_delta: Some(0),
+                max_deviations: None,
+            })
+        );
+        assert_eq!(
+            page_compare.page(3),
+            Some(&PageTolerance {
+                max_delta: None,
+                max_deviations: Some(100),
+            })
+        );
+    }
+
+    #[test]
+    fn test_page_compare_deserialize() {
+        let page_compare: PageCompare = toml::from_str(
+            r#"
+            1 = { max-deviations = 500 }
+            default = { max-deviations = 0 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            page_compare,
+            PageCompare::new()
+                .with_page(
+                    1,
+                    PageTolerance {
+                        max_delta: None,
+                        max_deviations: Some(500),
+                    },
+                )
+                .with_default(PageTolerance {
+                    max_delta: None,
+                    max_deviations: Some(0),
+                })
+        );
+
+        assert!(toml::from_str::<PageCompare>("0 = { max-delta = 1 }").is_err());
+        assert!(toml::from_str::<PageCompare>("first = { max-delta = 1 }").is_err());
+    }
+
+    #[test]
+    fn test_compare_cache_insert() {
+        TempTestEnv::run_no_check(
+            |root| root,
+            |root| {
+                let cache = CompareCache::new(root.join("cache"));
+
+                assert!(!cache.contains(1).unwrap());
+                cache.insert(1).unwrap();
+                assert!(cache.contains(1).unwrap());
+                assert!(!cache.contains(2).unwrap());
+
+                cache.clear().unwrap();
+                assert!(!cache.contains(1).unwrap());
+            },
+        );
+    }
+
+    #[test]
+    fn test_page_text() {
+        page_text("Hello\nWorld", "Hello\nWorld").unwrap();
+
+        let PageError::Text {
+            line,
+            output,
+            reference,
+        } = page_text("Hello\nWorld", "Hello\nThere").unwrap_err()
+        else {
+            panic!("expected text error");
+        };
+        assert_eq!(
+            (line, output.as_str(), reference.as_str()),
+            (2, "World", "There")
+        );
+
+        let PageError::Text { line, output, .. } = page_text("Hello", "Hello\nWorld").unwrap_err()
+        else {
+            panic!("expected text error");
+        };
+        assert_eq!((line, output.as_str()), (2, ""));
+    }
+
+    #[test]
+    fn test_text() {
+        let outputs = ["a".to_owned(), "b".to_owned()];
+        let references = ["a".to_owned(), "c".to_owned()];
+
+        let err = text(&outputs, &references, 0).unwrap_err();
+        assert_eq!(
+            err.pages.iter().map(|(idx, _)| *idx).collect::<Vec<_>>(),
+            [1]
+        );
+
+        text(&outputs, &references, 1).unwrap();
+
+        let err = text(&outputs[..1], &references, 1).unwrap_err();
+        assert_eq!((err.output, err.reference), (1, 2));
+    }
 }
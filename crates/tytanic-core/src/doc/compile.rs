@@ -33,6 +33,11 @@ pub enum Warnings {
 pub struct Error(pub EcoVec<SourceDiagnostic>);
 
 /// Compiles a test using the given test world.
+///
+/// This is also the entry point for compiling tests with custom worlds, such
+/// worlds must have the test script as their main file and annotations
+/// affecting the world, like `input` or `data-root`, must be applied to them
+/// by the caller.
 pub fn compile(world: &dyn World, warnings: Warnings) -> Warned<Result<PagedDocument, Error>> {
     let Warned {
         output,
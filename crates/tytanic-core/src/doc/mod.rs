@@ -1,39 +1,262 @@
 //! On-disk management of reference and test documents.
 //!
-//! These documents are currently stored as individual pages in the PNG format.
+//! These documents are stored as individual pages, either in the PNG or the
+//! lossless WebP format, see [`PageFormat`].
 
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
 use std::fs;
 use std::io;
+use std::io::Cursor;
 use std::iter;
 use std::path::Path;
+use std::path::PathBuf;
+use std::time::Instant;
 
 use compile::Warnings;
+use ecow::EcoString;
 use ecow::EcoVec;
 use ecow::eco_vec;
+use image_webp::ColorType;
+use image_webp::WebPDecoder;
+use image_webp::WebPEncoder;
+use rayon::iter::IntoParallelIterator;
+use rayon::iter::ParallelIterator;
+use serde::Deserialize;
+use serde::Serialize;
 use thiserror::Error;
+use tiny_skia::ColorU8;
 use tiny_skia::Pixmap;
 use typst::World;
 use typst::diag::Warned;
+use typst::layout::Abs;
+use typst::layout::Frame;
+use typst::layout::FrameItem;
+use typst::layout::Point;
 use typst_layout::PagedDocument;
 use typst_render::RenderOptions;
+use tytanic_utils::fmt::Term;
+use tytanic_utils::sync::Semaphore;
+use tytanic_utils::sync::SemaphoreGuard;
 
+use self::compare::PageCompare;
+use self::compare::PageDiff;
 use self::compare::Strategy;
+use self::process::ProcessPage;
+use self::render::CropOffset;
+use self::render::DiffColors;
+use self::render::Focus;
+use self::render::Mask;
 use self::render::Origin;
 
 pub mod compare;
 pub mod compile;
+pub mod process;
 pub mod render;
 
 /// The extension used in the page storage, each page is stored separately with it.
+///
+/// This is the extension of [`PageFormat::Png`], the default page format.
 pub const PAGE_EXTENSION: &str = "png";
 
+/// The permits for writing pages to disk, see [`set_max_concurrent_writes`].
+static WRITE_PERMITS: Semaphore = Semaphore::unlimited();
+
+/// Sets the maximum number of pages which are written to disk concurrently,
+/// `0` removes the limit.
+///
+/// This bounds the number of files opened by [`Document`] at any given time,
+/// independent of how many threads are used to compile and render tests.
+pub fn set_max_concurrent_writes(max: usize) {
+    WRITE_PERMITS.set_limit(max);
+}
+
+/// Acquires a permit to write a single file to disk, the permit is released
+/// once the guard is dropped.
+///
+/// This is used internally when saving pages and should be used by callers
+/// which write their own artifacts next to documents.
+pub fn acquire_write_permit() -> SemaphoreGuard<'static> {
+    WRITE_PERMITS.acquire()
+}
+
+/// The image format in which pages are stored on disk.
+///
+/// Pages are only ever stored losslessly, such that loading a stored page
+/// yields exactly the pixels which were rendered.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PageFormat {
+    /// Pages are stored as PNG images.
+    #[default]
+    Png,
+
+    /// Pages are stored as lossless WebP images.
+    Webp,
+}
+
+impl PageFormat {
+    /// The file extension used for pages of this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            PageFormat::Png => PAGE_EXTENSION,
+            PageFormat::Webp => "webp",
+        }
+    }
+
+    /// Returns the format with the given file extension, if there is one.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        [PageFormat::Png, PageFormat::Webp]
+            .into_iter()
+            .find(|format| format.extension() == ext)
+    }
+}
+
+/// The format in which the output of a test is exported as an artifact.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArtifactFormat {
+    /// Each rendered page is exported as its own PNG image.
+    #[default]
+    Png,
+
+    /// The whole document is exported as a single PDF, this is produced from
+    /// the laid out document, not the rendered pages, such that text stays
+    /// selectable.
+    Pdf,
+}
+
+/// How thoroughly persistent reference pages stored as PNG are optimized
+/// before they are written to disk.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OptimizeLevel {
+    /// Pages are written as they are encoded.
+    None,
+
+    /// Pages are optimized with a fast preset, which yields most of the size
+    /// reduction in a fraction of the time.
+    Fast,
+
+    /// Pages are optimized with the maximum compression preset.
+    #[default]
+    Max,
+}
+
+impl OptimizeLevel {
+    /// The `oxipng` options for this level, `None` if pages should not be
+    /// optimized.
+    pub fn options(self) -> Option<oxipng::Options> {
+        match self {
+            OptimizeLevel::None => None,
+            OptimizeLevel::Fast => Some(oxipng::Options::from_preset(1)),
+            OptimizeLevel::Max => Some(oxipng::Options::max_compression()),
+        }
+    }
+}
+
+/// The pattern of the file stems of stored pages, the placeholder `{page}` is
+/// replaced by the 1-based page number.
+///
+/// The default pattern `{page}` stores pages as `1.png`, `2.png` and so on.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PagePattern {
+    prefix: EcoString,
+    suffix: EcoString,
+}
+
+impl PagePattern {
+    /// The placeholder for the page number in a pattern.
+    pub const PLACEHOLDER: &str = "{page}";
+
+    /// Parses a page pattern, it must contain exactly one placeholder and must
+    /// not contain path separators.
+    pub fn new(pattern: &str) -> Result<Self, ParsePagePatternError> {
+        if pattern.contains(['/', '\\']) {
+            return Err(ParsePagePatternError::PathSeparator(pattern.into()));
+        }
+
+        let Some((prefix, suffix)) = pattern.split_once(Self::PLACEHOLDER) else {
+            return Err(ParsePagePatternError::MissingPlaceholder(pattern.into()));
+        };
+
+        if suffix.contains(Self::PLACEHOLDER) {
+            return Err(ParsePagePatternError::DuplicatePlaceholder(pattern.into()));
+        }
+
+        Ok(Self {
+            prefix: prefix.into(),
+            suffix: suffix.into(),
+        })
+    }
+
+    /// Returns the file stem of the page with the given 1-based page number.
+    pub fn stem(&self, num: usize) -> String {
+        format!("{}{num}{}", self.prefix, self.suffix)
+    }
+
+    /// Returns the 1-based page number of the given file stem if it matches
+    /// this pattern.
+    pub fn page(&self, stem: &str) -> Option<usize> {
+        let num = stem
+            .strip_prefix(self.prefix.as_str())?
+            .strip_suffix(self.suffix.as_str())?;
+
+        if num.is_empty() || !num.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        num.parse().ok().filter(|&num| num != 0)
+    }
+}
+
+impl Display for PagePattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.prefix, Self::PLACEHOLDER, self.suffix)
+    }
+}
+
+impl TryFrom<String> for PagePattern {
+    type Error = ParsePagePatternError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::new(&value)
+    }
+}
+
+impl From<PagePattern> for String {
+    fn from(value: PagePattern) -> Self {
+        value.to_string()
+    }
+}
+
+/// Returned by [`PagePattern::new`].
+#[derive(Debug, Clone, Error)]
+pub enum ParsePagePatternError {
+    /// The pattern did not contain a placeholder.
+    #[error("page pattern {0:?} must contain the placeholder `{{page}}`")]
+    MissingPlaceholder(EcoString),
+
+    /// The pattern contained more than one placeholder.
+    #[error("page pattern {0:?} must contain the placeholder `{{page}}` only once")]
+    DuplicatePlaceholder(EcoString),
+
+    /// The pattern contained a path separator.
+    #[error("page pattern {0:?} must not contain path separators")]
+    PathSeparator(EcoString),
+}
+
 /// A document that was rendered from an in-memory compilation, or loaded from disk.
 #[derive(Debug, Clone)]
 pub struct Document {
     doc: Option<Box<PagedDocument>>,
     buffers: EcoVec<Pixmap>,
+    crops: EcoVec<CropOffset>,
 }
 
 impl Document {
@@ -42,6 +265,7 @@ impl Document {
         Self {
             doc: None,
             buffers: buffers.into_iter().collect(),
+            crops: eco_vec![],
         }
     }
 
@@ -72,6 +296,137 @@ impl Document {
         Self {
             doc: Some(doc),
             buffers,
+            crops: eco_vec![],
+        }
+    }
+
+    /// Creates a new rendered document from only the given sorted 1-based
+    /// page numbers of a compiled one.
+    ///
+    /// Returns an error if a page number exceeds the number of pages of the
+    /// document.
+    pub fn render_pages<D: Into<Box<PagedDocument>>>(
+        doc: D,
+        render_options: &RenderOptions,
+        pages: &[usize],
+    ) -> Result<Self, PageSelectionError> {
+        let doc = doc.into();
+        let total = doc.pages().len();
+
+        let buffers = pages
+            .iter()
+            .map(|&page| {
+                doc.pages()
+                    .get(page.wrapping_sub(1))
+                    .map(|page| typst_render::render(page, render_options))
+                    .ok_or(PageSelectionError { page, total })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            doc: Some(doc),
+            buffers,
+            crops: eco_vec![],
+        })
+    }
+
+    /// Applies the given processor to each page of this document.
+    ///
+    /// See [`process`] for more info.
+    pub fn process<P: ProcessPage + ?Sized>(&mut self, processor: &P) {
+        self.buffers = self
+            .buffers
+            .iter()
+            .cloned()
+            .map(|page| processor.process_page(page))
+            .collect();
+    }
+
+    /// Crops each page of this document to the given focus rectangle.
+    ///
+    /// Returns an error if the rectangle doesn't lie within one of the pages,
+    /// the document is left unchanged in that case.
+    ///
+    /// See [`render::page_focus`] for more info.
+    pub fn focus(&mut self, focus: Focus, pixel_per_pt: f64) -> Result<(), FocusError> {
+        self.buffers = self
+            .buffers
+            .iter()
+            .enumerate()
+            .map(|(idx, page)| {
+                render::page_focus(page, focus, pixel_per_pt).ok_or(FocusError {
+                    focus,
+                    page: idx + 1,
+                    width: page.width() as f64 / pixel_per_pt,
+                    height: page.height() as f64 / pixel_per_pt,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(())
+    }
+
+    /// Returns a copy of this document with each page downscaled by the given
+    /// factor, crop offsets are scaled accordingly.
+    ///
+    /// The copy has no compiled document, see [`render::page_downscale`] for
+    /// more info. This downscales pages like the downscaled comparison of
+    /// [`Strategy::Simple::prescale`].
+    pub fn downscale(&self, factor: u32) -> Self {
+        Self {
+            doc: None,
+            buffers: self
+                .buffers
+                .iter()
+                .map(|page| render::page_downscale(page, factor))
+                .collect(),
+            crops: self
+                .crops
+                .iter()
+                .map(|crop| CropOffset {
+                    x: crop.x / factor.max(1),
+                    y: crop.y / factor.max(1),
+                })
+                .collect(),
+        }
+    }
+
+    /// Crops the borders of uniform color around each page of this document
+    /// and records the offset of each cropped page.
+    ///
+    /// See [`render::page_crop`] for more info.
+    pub fn crop(&mut self) {
+        let (buffers, crops): (Vec<_>, Vec<_>) = self.buffers.iter().map(render::page_crop).unzip();
+
+        self.buffers = buffers.into();
+        self.crops = crops.into();
+    }
+
+    /// Sets the offsets of the pages of this document within the pages they
+    /// were cropped from, used for documents whose pages were stored cropped.
+    ///
+    /// See [`Document::crop`] for more info.
+    pub fn with_crops<I: IntoIterator<Item = CropOffset>>(mut self, crops: I) -> Self {
+        self.crops = crops.into_iter().collect();
+        self
+    }
+
+    /// Clears the given masked regions on each page of this document, such
+    /// that they are excluded from comparison.
+    ///
+    /// See [`render::page_mask`] for more info.
+    pub fn mask(&mut self, masks: &[Mask], pixel_per_pt: f64) {
+        for page in self.buffers.make_mut() {
+            render::page_mask(page, masks, pixel_per_pt);
+        }
+    }
+
+    /// Highlights the given masked regions on each page of this document.
+    ///
+    /// See [`render::page_mask_highlight`] for more info.
+    pub fn highlight_masks(&mut self, masks: &[Mask], pixel_per_pt: f64) {
+        for page in self.buffers.make_mut() {
+            render::page_mask_highlight(page, masks, pixel_per_pt);
         }
     }
 
@@ -79,79 +434,70 @@ impl Document {
     /// document will have no inner document set because it was created only
     /// from pixel buffers.
     ///
-    /// Diff images are created pair-wise in order using [`render::page_diff`].
-    pub fn render_diff(base: &Self, change: &Self, origin: Origin) -> Self {
-        let buffers = iter::zip(&base.buffers, &change.buffers)
-            .map(|(base, change)| render::page_diff(base, change, origin))
+    /// Diff images are created pair-wise in order using [`render::page_diff`],
+    /// cropped pages are placed at their original offset first.
+    pub fn render_diff(base: &Self, change: &Self, origin: Origin, colors: DiffColors) -> Self {
+        let buffers = (0..Ord::min(base.buffers.len(), change.buffers.len()))
+            .map(|idx| {
+                render::page_diff(
+                    &base.uncropped_page(idx),
+                    &change.uncropped_page(idx),
+                    origin,
+                    colors,
+                )
+            })
+            .collect();
+
+        Self {
+            doc: None,
+            buffers,
+            crops: eco_vec![],
+        }
+    }
+
+    /// Creates a new rendered document marking which deviating pixels of the
+    /// given documents are classified as anti-aliasing, see
+    /// [`render::page_antialiasing`].
+    pub fn render_antialiasing(output: &Self, reference: &Self, max_delta: u8) -> Self {
+        let buffers = iter::zip(&output.buffers, &reference.buffers)
+            .map(|(output, reference)| render::page_antialiasing(output, reference, max_delta))
             .collect();
 
-        Self { doc: None, buffers }
+        Self {
+            doc: None,
+            buffers,
+            crops: eco_vec![],
+        }
     }
 
     /// Collects the reference document in the given directory.
     #[tracing::instrument(skip_all, fields(dir = ?dir.as_ref()))]
     pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self, LoadError> {
-        let mut buffers = BTreeMap::new();
-
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-
-            if !entry.file_type()?.is_file() {
-                tracing::trace!(entry = ?path, "ignoring non-file entry in reference directory");
-                continue;
-            }
-
-            if path.extension().is_none()
-                || path.extension().is_some_and(|ext| ext != PAGE_EXTENSION)
-            {
-                tracing::trace!(entry = ?path, "ignoring non-PNG entry in reference directory");
-                continue;
-            }
+        Self::load_matching(dir, &PagePattern::default())
+    }
 
-            let Some(page) = path
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .and_then(|s| s.parse().ok())
-                .filter(|&num| num != 0)
-            else {
-                tracing::trace!(
-                    entry = ?path,
-                    "ignoring non-numeric or invalid filename in reference directory",
-                );
-                continue;
-            };
+    /// Loads the pages whose file stems match the given pattern from the
+    /// given directory, see [`Document::load`].
+    ///
+    /// Page files which don't match the pattern are ignored with a warning.
+    #[tracing::instrument(skip_all, fields(dir = ?dir.as_ref(), %pattern))]
+    pub fn load_matching<P: AsRef<Path>>(dir: P, pattern: &PagePattern) -> Result<Self, LoadError> {
+        let mut buffers = BTreeMap::new();
 
-            buffers.insert(page, Pixmap::load_png(path)?);
+        for (page, path) in page_paths(dir.as_ref(), pattern)? {
+            buffers.insert(page, load_page(&path)?);
         }
 
         if buffers.is_empty() {
             return Ok(Self {
                 doc: None,
                 buffers: eco_vec![],
+                crops: eco_vec![],
             });
         }
 
-        // Check we got pages starting at 1.
-        match buffers.first_key_value() {
-            Some((min, _)) if *min != 1 => {
-                return Err(LoadError::MissingPages(buffers.into_keys().collect()));
-            }
-            Some(_) => {}
-            None => {
-                unreachable!("we checked the length");
-            }
-        }
-
-        // Check we got pages ending in the page count.
-        match buffers.last_key_value() {
-            Some((max, _)) if *max != buffers.len() => {
-                return Err(LoadError::MissingPages(buffers.into_keys().collect()));
-            }
-            Some(_) => {}
-            None => {
-                unreachable!("we checked the length");
-            }
+        if !is_gap_free(buffers.keys()) {
+            return Err(LoadError::MissingPages(buffers.into_keys().collect()));
         }
 
         Ok(Self {
@@ -159,22 +505,146 @@ impl Document {
             // NOTE(tinger): the pages are ordered by key and must not have any
             // page keys missing
             buffers: buffers.into_values().collect(),
+            crops: eco_vec![],
         })
     }
 
-    /// Saves a single page within the given directory with the given 1-based page
-    /// number.
+    /// Attempts to decode every page matching the given pattern in the given
+    /// directory, returns the paths of the pages which failed to decode along
+    /// with their errors.
     ///
-    /// # Panics
-    /// Panics if `num == 0`.
+    /// Unlike [`Document::load_matching`] this does not stop at the first
+    /// page which fails to decode and does not check for missing pages.
+    #[tracing::instrument(skip_all, fields(dir = ?dir.as_ref(), %pattern))]
+    pub fn verify_matching<P: AsRef<Path>>(
+        dir: P,
+        pattern: &PagePattern,
+    ) -> io::Result<Vec<(PathBuf, LoadError)>> {
+        let mut failures = vec![];
+
+        for path in page_paths(dir.as_ref(), pattern)?.into_values() {
+            if let Err(err) = load_page(&path) {
+                failures.push((path, err));
+            }
+        }
+
+        Ok(failures)
+    }
+
+    /// Checks that the given directory contains a loadable document without
+    /// decoding its pages, returns the number of pages.
+    ///
+    /// A document is considered loadable if the directory exists, contains at
+    /// least one page, has no missing pages and no empty page files.
+    #[tracing::instrument(skip_all, fields(dir = ?dir.as_ref()))]
+    pub fn check<P: AsRef<Path>>(dir: P) -> Result<usize, CheckError> {
+        Self::check_matching(dir, &PagePattern::default())
+    }
+
+    /// Checks the pages whose file stems match the given pattern in the given
+    /// directory, see [`Document::check`].
+    #[tracing::instrument(skip_all, fields(dir = ?dir.as_ref(), %pattern))]
+    pub fn check_matching<P: AsRef<Path>>(
+        dir: P,
+        pattern: &PagePattern,
+    ) -> Result<usize, CheckError> {
+        let dir = dir.as_ref();
+
+        if !dir.try_exists()? {
+            return Err(CheckError::Missing);
+        }
+
+        let pages = page_paths(dir, pattern)?;
+
+        if pages.is_empty() {
+            return Err(CheckError::Empty);
+        }
+
+        if !is_gap_free(pages.keys()) {
+            return Err(CheckError::MissingPages(pages.into_keys().collect()));
+        }
+
+        let mut empty = BTreeSet::new();
+        for (&page, path) in &pages {
+            if fs::metadata(path)?.len() == 0 {
+                empty.insert(page);
+            }
+        }
+
+        if !empty.is_empty() {
+            return Err(CheckError::EmptyPages(empty));
+        }
+
+        Ok(pages.len())
+    }
+
+    /// Saves each page within the given directory in the PNG format using the
+    /// default page pattern, see [`Document::save_as`] for other formats.
     #[tracing::instrument(skip_all, fields(dir = ?dir.as_ref()))]
     pub fn save<P: AsRef<Path>>(
         &self,
         dir: P,
         optimize_options: Option<&oxipng::Options>,
+    ) -> Result<(), SaveError> {
+        self.save_as(
+            dir,
+            PageFormat::Png,
+            &PagePattern::default(),
+            optimize_options,
+        )
+    }
+
+    /// Saves each page within the given directory in the given format, the
+    /// file stems are created from the given pattern and the 1-based page
+    /// number.
+    ///
+    /// The optimization options are only used for PNG pages.
+    #[tracing::instrument(skip_all, fields(dir = ?dir.as_ref(), ?format, %pattern))]
+    pub fn save_as<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        format: PageFormat,
+        pattern: &PagePattern,
+        optimize_options: Option<&oxipng::Options>,
     ) -> Result<(), SaveError> {
         tracing::trace!(?optimize_options, "using optimize options");
 
+        let pages = self
+            .buffers
+            .iter()
+            .enumerate()
+            .map(|(idx, page)| {
+                let path = dir
+                    .as_ref()
+                    .join(pattern.stem(idx + 1))
+                    .with_extension(format.extension());
+
+                (path, page)
+            })
+            .collect();
+
+        save_pages(pages, format, optimize_options)
+    }
+
+    /// Saves only those pages within the given directory which differ from
+    /// the pages already stored there, see [`Document::save_as`].
+    ///
+    /// A page is unchanged if it is already stored in the given format and
+    /// decodes to exactly the same pixels. Pages stored in another format are
+    /// replaced and surplus pages are removed.
+    #[tracing::instrument(skip_all, fields(dir = ?dir.as_ref(), ?format, %pattern))]
+    pub fn save_changed<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        format: PageFormat,
+        pattern: &PagePattern,
+        optimize_options: Option<&oxipng::Options>,
+    ) -> Result<SaveSummary, SaveError> {
+        let dir = dir.as_ref();
+        let mut existing = page_paths(dir, pattern)?;
+        let mut summary = SaveSummary::default();
+        let mut changed = vec![];
+
         for (num, page) in self
             .buffers
             .iter()
@@ -182,23 +652,104 @@ impl Document {
             .map(|(idx, page)| (idx + 1, page))
         {
             let path = dir
-                .as_ref()
-                .join(num.to_string())
-                .with_extension(PAGE_EXTENSION);
-
-            if let Some(options) = optimize_options {
-                let buffer = page.encode_png()?;
-                let optimized = oxipng::optimize_from_memory(&buffer, options)?;
-                fs::write(path, optimized)?;
-            } else {
-                page.save_png(path)?;
+                .join(pattern.stem(num))
+                .with_extension(format.extension());
+
+            match existing.remove(&num) {
+                Some(old) if old == path => {
+                    let stored = match format {
+                        PageFormat::Png => Pixmap::load_png(&path).ok(),
+                        PageFormat::Webp => fs::read(&path)
+                            .ok()
+                            .and_then(|buffer| decode_webp(&buffer).ok()),
+                    };
+
+                    if stored.as_ref() == Some(page) {
+                        tracing::trace!(num, "page unchanged");
+                        summary.unchanged += 1;
+                        continue;
+                    }
+                }
+                Some(old) => fs::remove_file(old)?,
+                None => {}
             }
+
+            changed.push((path, page));
+            summary.updated += 1;
         }
 
-        Ok(())
+        for surplus in existing.into_values() {
+            tracing::trace!(?surplus, "removing surplus page");
+            fs::remove_file(surplus)?;
+        }
+
+        save_pages(changed, format, optimize_options)?;
+
+        Ok(summary)
+    }
+
+    /// Saves only the given 1-based pages within the given directory, see
+    /// [`Document::save_as`].
+    ///
+    /// Page numbers which are not in this document are ignored, all other
+    /// pages stored in the directory are left untouched. A given page which is
+    /// stored in another format is replaced.
+    #[tracing::instrument(skip_all, fields(dir = ?dir.as_ref(), ?pages, ?format, %pattern))]
+    pub fn save_pages<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        pages: &BTreeSet<usize>,
+        format: PageFormat,
+        pattern: &PagePattern,
+        optimize_options: Option<&oxipng::Options>,
+    ) -> Result<SaveSummary, SaveError> {
+        let dir = dir.as_ref();
+        let existing = page_paths(dir, pattern)?;
+        let mut summary = SaveSummary::default();
+        let mut changed = vec![];
+
+        for (num, page) in self
+            .buffers
+            .iter()
+            .enumerate()
+            .map(|(idx, page)| (idx + 1, page))
+        {
+            if !pages.contains(&num) {
+                summary.unchanged += 1;
+                continue;
+            }
+
+            let path = dir
+                .join(pattern.stem(num))
+                .with_extension(format.extension());
+
+            if let Some(old) = existing.get(&num)
+                && *old != path
+            {
+                fs::remove_file(old)?;
+            }
+
+            changed.push((path, page));
+            summary.updated += 1;
+        }
+
+        save_pages(changed, format, optimize_options)?;
+
+        Ok(summary)
     }
 }
 
+/// The number of pages written or left untouched by
+/// [`Document::save_changed`] or [`Document::save_pages`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SaveSummary {
+    /// The number of pages which were written.
+    pub updated: usize,
+
+    /// The number of pages which were already stored and left untouched.
+    pub unchanged: usize,
+}
+
 impl Document {
     /// The inner document if this was created from an in-memory compilation.
     pub fn doc(&self) -> Option<&PagedDocument> {
@@ -209,6 +760,40 @@ impl Document {
     pub fn buffers(&self) -> &[Pixmap] {
         &self.buffers
     }
+
+    /// The offsets of the pages of this document within the pages they were
+    /// cropped from, this is empty if the pages were not cropped.
+    pub fn crops(&self) -> &[CropOffset] {
+        &self.crops
+    }
+
+    /// The normalized text of each page, `None` if this document was not
+    /// created from an in-memory compilation, see [`compare::text`].
+    ///
+    /// This is the text which is written to the content streams of a PDF
+    /// export. Text on the same baseline is joined into one line with runs of
+    /// whitespace collapsed, lines are ordered from top to bottom.
+    pub fn text(&self) -> Option<Vec<String>> {
+        let doc = self.doc.as_deref()?;
+        Some(
+            doc.pages()
+                .iter()
+                .map(|page| page_text(&page.frame))
+                .collect(),
+        )
+    }
+
+    /// The page at the given index placed at its offset within the page it
+    /// was cropped from, see [`render::page_uncrop`].
+    fn uncropped_page(&self, idx: usize) -> Cow<'_, Pixmap> {
+        let page = &self.buffers[idx];
+        match self.crops.get(idx) {
+            Some(offset) if *offset != CropOffset::default() => {
+                Cow::Owned(render::page_uncrop(page, *offset))
+            }
+            _ => Cow::Borrowed(page),
+        }
+    }
 }
 
 impl Document {
@@ -219,30 +804,291 @@ impl Document {
         outputs: &Self,
         references: &Self,
         strategy: Strategy,
+    ) -> Result<(), compare::Error> {
+        Self::compare_pages(outputs, references, strategy, &PageCompare::new())
+    }
+
+    /// Compares the pages of two documents pixel by pixel and returns where and
+    /// how much each pair of pages deviates, see [`compare::page_detailed`].
+    ///
+    /// Only pages which exist in both documents are compared.
+    pub fn compare_detailed(
+        outputs: &Self,
+        references: &Self,
+        max_delta: u8,
+    ) -> Vec<Result<PageDiff, compare::PageError>> {
+        iter::zip(&outputs.buffers, &references.buffers)
+            .map(|(a, b)| compare::page_detailed(a, b, max_delta))
+            .collect()
+    }
+
+    /// Compares two documents using the given strategy, with the tolerances
+    /// of individual pages overridden by the given per-page tolerances.
+    ///
+    /// The maximum allowed failing pages applies to the whole document, a page
+    /// fails if it fails comparison using its own tolerances. If both
+    /// documents were cropped, a page also fails if it was cropped at a
+    /// different offset.
+    pub fn compare_pages(
+        outputs: &Self,
+        references: &Self,
+        strategy: Strategy,
+        page_compare: &PageCompare,
     ) -> Result<(), compare::Error> {
         let output_len = outputs.buffers.len();
         let reference_len = references.buffers.len();
 
         let mut page_errors = Vec::with_capacity(Ord::min(output_len, reference_len));
+        let mut details = vec![];
 
         for (idx, (a, b)) in iter::zip(&outputs.buffers, &references.buffers).enumerate() {
-            if let Err(err) = compare::page(a, b, strategy) {
+            if let (Some(&output), Some(&reference)) =
+                (outputs.crops.get(idx), references.crops.get(idx))
+                && output != reference
+            {
+                page_errors.push((idx, compare::PageError::CropOffset { output, reference }));
+                continue;
+            }
+
+            if let Err(err) = compare::page(a, b, page_compare.resolve(idx, strategy)) {
+                let max_delta = match err {
+                    compare::PageError::SimpleDeviations { max_delta, .. } => Some(max_delta),
+                    compare::PageError::Ssim { .. } => Some(0),
+                    _ => None,
+                };
+
+                if let Some(max_delta) = max_delta
+                    && let Ok(diff) = compare::page_detailed(a, b, max_delta)
+                {
+                    details.push((idx, diff));
+                }
+
                 page_errors.push((idx, err));
             }
         }
 
-        if !page_errors.is_empty() || output_len != reference_len {
+        let max_failing_pages = strategy.max_failing_pages();
+
+        if page_errors.len() > max_failing_pages || output_len != reference_len {
             page_errors.shrink_to_fit();
             return Err(compare::Error {
                 output: output_len,
                 reference: reference_len,
                 pages: page_errors,
+                max_failing_pages,
+                details,
             });
         }
 
         Ok(())
     }
 }
+
+/// Extracts the normalized text of a page, see [`Document::text`].
+fn page_text(frame: &Frame) -> String {
+    fn collect(frame: &Frame, offset: Point, runs: &mut Vec<(Abs, Abs, EcoString)>) {
+        for (pos, item) in frame.items() {
+            let pos = offset + *pos;
+            match item {
+                FrameItem::Group(group) => collect(&group.frame, pos, runs),
+                FrameItem::Text(text) => runs.push((pos.y, pos.x, text.text.clone())),
+                _ => {}
+            }
+        }
+    }
+
+    let mut runs = vec![];
+    collect(frame, Point::zero(), &mut runs);
+    runs.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+
+    // NOTE(tinger): Runs are compared on their baseline rounded to a tenth of
+    // a point, this is well below any line spacing but hides the float noise
+    // of layout.
+    let mut lines: Vec<(i64, String)> = vec![];
+    for (y, _, text) in runs {
+        let y = (y.to_pt() * 10.0).round() as i64;
+        match lines.last_mut() {
+            Some((last, line)) if *last == y => {
+                line.push(' ');
+                line.push_str(&text);
+            }
+            _ => lines.push((y, text.to_string())),
+        }
+    }
+
+    lines
+        .into_iter()
+        .map(|(_, line)| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Collects the paths of all pages in the given directory by their 1-based
+/// page number, other entries are ignored.
+///
+/// Page files which don't match the given pattern are ignored with a warning.
+fn page_paths(dir: &Path, pattern: &PagePattern) -> io::Result<BTreeMap<usize, PathBuf>> {
+    let mut pages = BTreeMap::new();
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if !entry.file_type()?.is_file() {
+            tracing::trace!(entry = ?path, "ignoring non-file entry in reference directory");
+            continue;
+        }
+
+        if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(PageFormat::from_extension)
+            .is_none()
+        {
+            tracing::trace!(entry = ?path, "ignoring non-page entry in reference directory");
+            continue;
+        }
+
+        let Some(page) = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| pattern.page(s))
+        else {
+            tracing::warn!(
+                entry = ?path,
+                %pattern,
+                "ignoring page file which does not match the page pattern",
+            );
+            continue;
+        };
+
+        if let Some(other) = pages.insert(page, path) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!(
+                    "page {page} is stored more than once, found {}",
+                    other.display()
+                ),
+            ));
+        }
+    }
+
+    Ok(pages)
+}
+
+/// Saves the given pages at their paths in the given format.
+///
+/// Pages are encoded and optimized on the global thread pool, only writing
+/// them to disk is bounded by the write permits.
+fn save_pages(
+    pages: Vec<(PathBuf, &Pixmap)>,
+    format: PageFormat,
+    optimize_options: Option<&oxipng::Options>,
+) -> Result<(), SaveError> {
+    pages
+        .into_par_iter()
+        .try_for_each(|(path, page)| save_page(&path, page, format, optimize_options))
+}
+
+/// Saves a single page at the given path in the given format.
+fn save_page(
+    path: &Path,
+    page: &Pixmap,
+    format: PageFormat,
+    optimize_options: Option<&oxipng::Options>,
+) -> Result<(), SaveError> {
+    let buffer = match format {
+        PageFormat::Webp => encode_webp(page)?,
+        PageFormat::Png => page.encode_png()?,
+    };
+
+    let buffer = match optimize_options {
+        Some(options) if format == PageFormat::Png => {
+            let start = Instant::now();
+            let optimized = oxipng::optimize_from_memory(&buffer, options)?;
+            tracing::info!(
+                ?path,
+                before = buffer.len(),
+                after = optimized.len(),
+                duration = ?start.elapsed(),
+                "optimized page",
+            );
+            optimized
+        }
+        _ => buffer,
+    };
+
+    let _permit = acquire_write_permit();
+    fs::write(path, buffer)?;
+
+    Ok(())
+}
+
+/// Whether the given ordered page numbers start at 1 and have no gaps.
+fn is_gap_free<'a, I>(pages: I) -> bool
+where
+    I: IntoIterator<Item = &'a usize>,
+{
+    pages
+        .into_iter()
+        .zip(1..)
+        .all(|(&page, expected)| page == expected)
+}
+
+/// Encodes a page as a lossless WebP image.
+fn encode_webp(page: &Pixmap) -> Result<Vec<u8>, image_webp::EncodingError> {
+    let data = page
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect::<Vec<_>>();
+
+    let mut buffer = vec![];
+    WebPEncoder::new(&mut buffer).encode(&data, page.width(), page.height(), ColorType::Rgba8)?;
+
+    Ok(buffer)
+}
+
+/// Decodes a page from a lossless WebP image.
+fn load_page(path: &Path) -> Result<Pixmap, LoadError> {
+    let format = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(PageFormat::from_extension);
+
+    Ok(match format {
+        Some(PageFormat::Webp) => decode_webp(&fs::read(path)?)?,
+        _ => Pixmap::load_png(path)?,
+    })
+}
+
+fn decode_webp(buffer: &[u8]) -> Result<Pixmap, LoadError> {
+    let mut decoder = WebPDecoder::new(Cursor::new(buffer))?;
+
+    if decoder.is_lossy() {
+        return Err(LoadError::LossyPage);
+    }
+
+    let (width, height) = decoder.dimensions();
+    let has_alpha = decoder.has_alpha();
+
+    let mut data = vec![0; decoder.output_buffer_size().ok_or(LoadError::InvalidPage)?];
+    decoder.read_image(&mut data)?;
+
+    let mut page = Pixmap::new(width, height).ok_or(LoadError::InvalidPage)?;
+    let channels = if has_alpha { 4 } else { 3 };
+    for (pixel, color) in iter::zip(page.pixels_mut(), data.chunks_exact(channels)) {
+        let alpha = if has_alpha { color[3] } else { u8::MAX };
+        *pixel = ColorU8::from_rgba(color[0], color[1], color[2], alpha).premultiply();
+    }
+
+    Ok(page)
+}
+
 /// Returned by [`Document::load`].
 #[derive(Debug, Error)]
 pub enum LoadError {
@@ -255,11 +1101,85 @@ pub enum LoadError {
     #[error("a page could not be decoded")]
     Page(#[from] png::DecodingError),
 
+    /// A WebP page could not be decoded.
+    #[error("a page could not be decoded")]
+    WebpPage(#[from] image_webp::DecodingError),
+
+    /// A WebP page was stored lossily.
+    #[error("a page was stored lossily")]
+    LossyPage,
+
+    /// A page had invalid dimensions.
+    #[error("a page had invalid dimensions")]
+    InvalidPage,
+
+    /// An IO error occurred.
+    #[error("an io error occurred")]
+    Io(#[from] io::Error),
+}
+
+/// Returned by [`Document::check`].
+#[derive(Debug, Error)]
+pub enum CheckError {
+    /// The directory does not exist.
+    #[error("the document directory does not exist")]
+    Missing,
+
+    /// The directory contains no pages.
+    #[error("the document contains no pages")]
+    Empty,
+
+    /// One or more pages were missing, contains the physical page numbers which
+    /// were found.
+    #[error("one or more pages were missing, found: {0:?}")]
+    MissingPages(BTreeSet<usize>),
+
+    /// One or more page files were empty, contains their physical page numbers.
+    #[error("one or more pages were empty: {0:?}")]
+    EmptyPages(BTreeSet<usize>),
+
     /// An IO error occurred.
     #[error("an io error occurred")]
     Io(#[from] io::Error),
 }
 
+/// Returned by [`Document::focus`].
+#[derive(Debug, Error)]
+#[error(
+    "the focus rectangle ({}pt, {}pt, {}pt, {}pt) does not lie within page {page} ({width}pt x {height}pt)",
+    focus.x,
+    focus.y,
+    focus.width,
+    focus.height
+)]
+pub struct FocusError {
+    /// The focus rectangle.
+    pub focus: Focus,
+
+    /// The 1-based number of the page the rectangle did not lie within.
+    pub page: usize,
+
+    /// The width of the page in pt.
+    pub width: f64,
+
+    /// The height of the page in pt.
+    pub height: f64,
+}
+
+/// Returned by [`Document::render_pages`].
+#[derive(Debug, Error)]
+#[error(
+    "page {page} was selected, but the document only has {total} {}",
+    Term::simple("page").with(*total)
+)]
+pub struct PageSelectionError {
+    /// The 1-based number of the selected page.
+    pub page: usize,
+
+    /// The number of pages of the document.
+    pub total: usize,
+}
+
 /// Returned by [`Document::save`].
 #[derive(Debug, Error)]
 pub enum SaveError {
@@ -271,6 +1191,10 @@ pub enum SaveError {
     #[error("a page could not be encoded")]
     Page(#[from] png::EncodingError),
 
+    /// A WebP page could not be encoded.
+    #[error("a page could not be encoded")]
+    WebpPage(#[from] image_webp::EncodingError),
+
     /// An IO error occurred.
     #[error("an io error occurred")]
     Io(#[from] io::Error),
@@ -283,11 +1207,118 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_document_compare_max_failing_pages() {
+        let mut changed = Pixmap::new(10, 10).unwrap();
+        changed.fill(tiny_skia::Color::BLACK);
+        let page = Pixmap::new(10, 10).unwrap();
+
+        let output = Document::new([page.clone(), changed.clone(), changed]);
+        let reference = Document::new([page.clone(), page.clone(), page]);
+
+        let strategy = |max_failing_pages| Strategy::Simple {
+            max_delta: 0,
+            max_deviation: 0,
+            max_failing_pages,
+            align_tolerance: 0,
+            prescale: None,
+            ignore_aa: false,
+            aa_tolerance: None,
+        };
+
+        let err = Document::compare(&output, &reference, strategy(1)).unwrap_err();
+        assert_eq!(err.pages.len(), 2);
+        assert_eq!(err.max_failing_pages, 1);
+        assert_eq!(
+            err.details.iter().map(|(idx, _)| *idx).collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert_eq!(err.details[0].1.deviations, 100);
+
+        Document::compare(&output, &reference, strategy(2)).unwrap();
+
+        let shorter = Document::new(output.buffers()[..2].to_vec());
+        let err = Document::compare(&shorter, &reference, strategy(2)).unwrap_err();
+        assert_eq!((err.output, err.reference), (2, 3));
+    }
+
+    #[test]
+    fn test_document_compare_pages() {
+        let mut changed = Pixmap::new(10, 10).unwrap();
+        changed.fill(tiny_skia::Color::BLACK);
+        let page = Pixmap::new(10, 10).unwrap();
+
+        let output = Document::new([changed.clone(), changed]);
+        let reference = Document::new([page.clone(), page]);
+
+        let cover = PageCompare::new().with_page(
+            1,
+            compare::PageTolerance {
+                max_delta: None,
+                max_deviations: Some(100),
+            },
+        );
+
+        let err =
+            Document::compare_pages(&output, &reference, Strategy::default(), &cover).unwrap_err();
+        assert_eq!(
+            err.pages.iter().map(|(idx, _)| *idx).collect::<Vec<_>>(),
+            [1]
+        );
+
+        let all = cover.with_default(compare::PageTolerance {
+            max_delta: Some(255),
+            max_deviations: None,
+        });
+        Document::compare_pages(&output, &reference, Strategy::default(), &all).unwrap();
+    }
+
+    #[test]
+    fn test_document_crop() {
+        let page = |x: f32| {
+            let mut page = Pixmap::new(10, 10).unwrap();
+            page.fill(tiny_skia::Color::WHITE);
+            page.fill_rect(
+                tiny_skia::Rect::from_xywh(x, x, 2.0, 2.0).unwrap(),
+                &tiny_skia::Paint::default(),
+                tiny_skia::Transform::identity(),
+                None,
+            );
+            page
+        };
+
+        let mut output = Document::new([page(2.0)]);
+        let mut reference = Document::new([page(3.0)]);
+        output.crop();
+        reference.crop();
+
+        assert_eq!(output.buffers()[0].width(), 2);
+        assert_eq!(output.crops(), [CropOffset { x: 2, y: 2 }]);
+
+        let err = Document::compare(&output, &reference, Strategy::default()).unwrap_err();
+        assert!(matches!(
+            err.pages[..],
+            [(0, compare::PageError::CropOffset { .. })]
+        ));
+
+        let diff =
+            Document::render_diff(&reference, &output, Origin::TopLeft, DiffColors::default());
+        assert_eq!(
+            (diff.buffers()[0].width(), diff.buffers()[0].height()),
+            (5, 5)
+        );
+
+        let mut same = Document::new([page(2.0)]);
+        same.crop();
+        Document::compare(&output, &same, Strategy::default()).unwrap();
+    }
+
     #[test]
     fn test_document_save() {
         let doc = Document {
             doc: None,
             buffers: eco_vec![Pixmap::new(10, 10).unwrap(); 3],
+            crops: eco_vec![],
         };
 
         TempTestEnv::run(
@@ -303,6 +1334,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_document_save_optimized() {
+        let mut page = Pixmap::new(10, 10).unwrap();
+        page.fill(tiny_skia::Color::BLACK);
+        let doc = Document::new([page.clone(), Pixmap::new(10, 10).unwrap()]);
+
+        TempTestEnv::run_no_check(
+            |root| root,
+            |root| {
+                let options = OptimizeLevel::Fast.options();
+                doc.save(root, options.as_ref()).unwrap();
+
+                let saved = Document::load(root).unwrap();
+                assert_eq!(saved.buffers, doc.buffers);
+            },
+        );
+    }
+
+    #[test]
+    fn test_optimize_level_options() {
+        assert!(OptimizeLevel::None.options().is_none());
+        assert!(OptimizeLevel::Fast.options().is_some());
+        assert!(OptimizeLevel::Max.options().is_some());
+    }
+
     #[test]
     fn test_document_load() {
         let buffers = eco_vec![Pixmap::new(10, 10).unwrap(); 3];
@@ -322,4 +1378,200 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn test_page_pattern() {
+        let pattern = PagePattern::new("page-{page}").unwrap();
+        assert_eq!(pattern.stem(3), "page-3");
+        assert_eq!(pattern.page("page-3"), Some(3));
+        assert_eq!(pattern.page("page-003"), Some(3));
+        assert_eq!(pattern.page("page-0"), None);
+        assert_eq!(pattern.page("page-+3"), None);
+        assert_eq!(pattern.page("page-"), None);
+        assert_eq!(pattern.page("3"), None);
+        assert_eq!(pattern.to_string(), "page-{page}");
+
+        assert_eq!(PagePattern::default().page("3"), Some(3));
+        assert_eq!(PagePattern::default().to_string(), "{page}");
+
+        assert!(matches!(
+            PagePattern::new("page"),
+            Err(ParsePagePatternError::MissingPlaceholder(_))
+        ));
+        assert!(matches!(
+            PagePattern::new("{page}-{page}"),
+            Err(ParsePagePatternError::DuplicatePlaceholder(_))
+        ));
+        assert!(matches!(
+            PagePattern::new("pages/{page}"),
+            Err(ParsePagePatternError::PathSeparator(_))
+        ));
+    }
+
+    #[test]
+    fn test_document_load_matching() {
+        let buffers = eco_vec![Pixmap::new(10, 10).unwrap(); 2];
+
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("page-1.png", buffers[0].encode_png().unwrap())
+                    .setup_file("page-2.png", buffers[1].encode_png().unwrap())
+            },
+            |root| {
+                let pattern = PagePattern::new("page-{page}").unwrap();
+                let doc = Document::load_matching(root, &pattern).unwrap();
+                assert_eq!(doc.buffers.len(), 2);
+                assert_eq!(Document::check_matching(root, &pattern).unwrap(), 2);
+
+                assert!(matches!(Document::check(root), Err(CheckError::Empty)));
+            },
+        );
+    }
+
+    #[test]
+    fn test_document_check() {
+        let page = Pixmap::new(10, 10).unwrap().encode_png().unwrap();
+
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("ok/1.png", page.clone())
+                    .setup_file("ok/2.png", page.clone())
+                    .setup_dir("empty")
+                    .setup_file("gap/1.png", page.clone())
+                    .setup_file("gap/3.png", page.clone())
+                    .setup_file("empty-page/1.png", page.clone())
+                    .setup_file_empty("empty-page/2.png")
+            },
+            |root| {
+                assert_eq!(Document::check(root.join("ok")).unwrap(), 2);
+                assert!(matches!(
+                    Document::check(root.join("missing")),
+                    Err(CheckError::Missing)
+                ));
+                assert!(matches!(
+                    Document::check(root.join("empty")),
+                    Err(CheckError::Empty)
+                ));
+                assert!(matches!(
+                    Document::check(root.join("gap")),
+                    Err(CheckError::MissingPages(found)) if found == BTreeSet::from([1, 3])
+                ));
+                assert!(matches!(
+                    Document::check(root.join("empty-page")),
+                    Err(CheckError::EmptyPages(empty)) if empty == BTreeSet::from([2])
+                ));
+            },
+        );
+    }
+
+    #[test]
+    fn test_document_save_changed() {
+        let mut changed = Pixmap::new(10, 10).unwrap();
+        changed.fill(tiny_skia::Color::BLACK);
+        let page = Pixmap::new(10, 10).unwrap();
+
+        let doc = Document::new([page.clone(), changed.clone(), changed.clone()]);
+
+        TempTestEnv::run(
+            |root| {
+                root.setup_file("1.png", page.encode_png().unwrap())
+                    .setup_file("2.png", page.encode_png().unwrap())
+                    .setup_file("3.webp", encode_webp(&changed).unwrap())
+                    .setup_file("4.png", page.encode_png().unwrap())
+            },
+            |root| {
+                let summary = doc
+                    .save_changed(root, PageFormat::Png, &PagePattern::default(), None)
+                    .unwrap();
+                assert_eq!(
+                    summary,
+                    SaveSummary {
+                        updated: 2,
+                        unchanged: 1,
+                    }
+                );
+            },
+            |root| {
+                root.expect_file_content("1.png", page.encode_png().unwrap())
+                    .expect_file_content("2.png", changed.encode_png().unwrap())
+                    .expect_file_content("3.png", changed.encode_png().unwrap())
+            },
+        );
+    }
+
+    #[test]
+    fn test_document_save_pages() {
+        let mut changed = Pixmap::new(10, 10).unwrap();
+        changed.fill(tiny_skia::Color::BLACK);
+        let page = Pixmap::new(10, 10).unwrap();
+
+        let doc = Document::new([changed.clone(), changed.clone(), changed.clone()]);
+
+        TempTestEnv::run(
+            |root| {
+                root.setup_file("1.png", page.encode_png().unwrap())
+                    .setup_file("2.png", page.encode_png().unwrap())
+                    .setup_file("3.webp", encode_webp(&page).unwrap())
+            },
+            |root| {
+                let summary = doc
+                    .save_pages(
+                        root,
+                        &BTreeSet::from([2, 3, 4]),
+                        PageFormat::Png,
+                        &PagePattern::default(),
+                        None,
+                    )
+                    .unwrap();
+                assert_eq!(
+                    summary,
+                    SaveSummary {
+                        updated: 2,
+                        unchanged: 1,
+                    }
+                );
+            },
+            |root| {
+                root.expect_file_content("1.png", page.encode_png().unwrap())
+                    .expect_file_content("2.png", changed.encode_png().unwrap())
+                    .expect_file_content("3.png", changed.encode_png().unwrap())
+            },
+        );
+    }
+
+    #[test]
+    fn test_document_save_load_webp() {
+        let mut page = Pixmap::new(4, 2).unwrap();
+        page.fill(tiny_skia::Color::from_rgba8(10, 20, 30, 128));
+        page.pixels_mut()[0] = ColorU8::from_rgba(255, 0, 0, 255).premultiply();
+
+        let doc = Document::new([page.clone(), page]);
+
+        TempTestEnv::run(
+            |root| root,
+            |root| {
+                doc.save_as(root, PageFormat::Webp, &PagePattern::default(), None)
+                    .unwrap();
+
+                let loaded = Document::load(root).unwrap();
+                assert_eq!(loaded.buffers, doc.buffers);
+            },
+            |root| root.expect_file("1.webp").expect_file("2.webp"),
+        );
+    }
+
+    #[test]
+    fn test_document_load_duplicate_page() {
+        let page = Pixmap::new(1, 1).unwrap();
+
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("1.png", page.encode_png().unwrap())
+                    .setup_file("1.webp", encode_webp(&page).unwrap())
+            },
+            |root| {
+                assert!(matches!(Document::load(root), Err(LoadError::Io(_))));
+            },
+        );
+    }
 }
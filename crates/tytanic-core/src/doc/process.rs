@@ -0,0 +1,147 @@
+//! Post-processing of rendered document pages.
+//!
+//! Processors are applied to each page after it was rendered, but before it is
+//! compared, exported or used to create a difference document. Because
+//! persistent references are created from rendered output, they are processed
+//! at update time and never processed again when loaded.
+
+use serde::Deserialize;
+use serde::Serialize;
+use tiny_skia::IntRect;
+use tiny_skia::Pixmap;
+
+/// A post-processing step applied to rendered pages.
+///
+/// This is implemented for closures taking and returning a [`Pixmap`].
+pub trait ProcessPage {
+    /// Processes a single rendered page.
+    fn process_page(&self, page: Pixmap) -> Pixmap;
+}
+
+impl<F> ProcessPage for F
+where
+    F: Fn(Pixmap) -> Pixmap,
+{
+    fn process_page(&self, page: Pixmap) -> Pixmap {
+        self(page)
+    }
+}
+
+impl<P: ProcessPage> ProcessPage for [P] {
+    fn process_page(&self, page: Pixmap) -> Pixmap {
+        self.iter().fold(page, |page, p| p.process_page(page))
+    }
+}
+
+/// A built-in page processor which can be selected by configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Processor {
+    /// Reduces each color channel to the given number of evenly spaced levels.
+    ///
+    /// Levels below 2 leave the page unchanged.
+    Quantize {
+        /// The number of levels per channel.
+        levels: u8,
+    },
+
+    /// Removes a border of the given width in pixels from each side of a page.
+    ///
+    /// If the page is too small it is left unchanged.
+    CropBorder {
+        /// The width of the border in pixels.
+        width: u32,
+    },
+}
+
+impl ProcessPage for Processor {
+    fn process_page(&self, page: Pixmap) -> Pixmap {
+        match *self {
+            Processor::Quantize { levels } => quantize(page, levels),
+            Processor::CropBorder { width } => crop_border(page, width),
+        }
+    }
+}
+
+/// Reduces each color channel to the given number of evenly spaced levels.
+pub fn quantize(mut page: Pixmap, levels: u8) -> Pixmap {
+    if levels < 2 {
+        return page;
+    }
+
+    let step = 255.0 / (levels - 1) as f32;
+    let quantize = |c: u8| ((c as f32 / step).round() * step).round() as u8;
+
+    for pixel in page.pixels_mut() {
+        let color = pixel.demultiply();
+        *pixel = tiny_skia::ColorU8::from_rgba(
+            quantize(color.red()),
+            quantize(color.green()),
+            quantize(color.blue()),
+            quantize(color.alpha()),
+        )
+        .premultiply();
+    }
+
+    page
+}
+
+/// Removes a border of the given width in pixels from each side of a page.
+pub fn crop_border(page: Pixmap, width: u32) -> Pixmap {
+    if width == 0 {
+        return page;
+    }
+
+    let Some(rect) = page
+        .width()
+        .checked_sub(width * 2)
+        .zip(page.height().checked_sub(width * 2))
+        .and_then(|(w, h)| IntRect::from_xywh(width as i32, width as i32, w, h))
+    else {
+        return page;
+    };
+
+    page.clone_rect(rect).unwrap_or(page)
+}
+
+#[cfg(test)]
+mod tests {
+    use tiny_skia::ColorU8;
+
+    use super::*;
+
+    #[test]
+    fn test_quantize() {
+        let mut page = Pixmap::new(1, 1).unwrap();
+        page.fill(tiny_skia::Color::from_rgba8(100, 200, 20, 255));
+
+        let page = quantize(page, 2);
+        assert_eq!(
+            page.pixel(0, 0).unwrap().demultiply(),
+            ColorU8::from_rgba(0, 255, 0, 255),
+        );
+    }
+
+    #[test]
+    fn test_crop_border() {
+        let page = Pixmap::new(10, 6).unwrap();
+
+        let cropped = crop_border(page.clone(), 2);
+        assert_eq!((cropped.width(), cropped.height()), (6, 2));
+
+        let too_large = crop_border(page, 3);
+        assert_eq!((too_large.width(), too_large.height()), (10, 6));
+    }
+
+    #[test]
+    fn test_processor_chain() {
+        let page = Pixmap::new(10, 10).unwrap();
+
+        let chain = [
+            Processor::CropBorder { width: 1 },
+            Processor::CropBorder { width: 2 },
+        ];
+        let page = chain[..].process_page(page);
+        assert_eq!((page.width(), page.height()), (4, 4));
+    }
+}
@@ -2,12 +2,21 @@
 
 use std::cmp::Ordering;
 
+use serde::Deserialize;
+use serde::Serialize;
 use tiny_skia::BlendMode;
+use tiny_skia::Color;
 use tiny_skia::FilterQuality;
+use tiny_skia::IntRect;
 use tiny_skia::Pixmap;
 use tiny_skia::PixmapPaint;
+use tiny_skia::PremultipliedColorU8;
+use tiny_skia::Rect;
 use tiny_skia::Transform;
 
+use super::compare;
+use crate::config::Direction;
+
 /// The origin of a documents page, this is used for comparisons of pages with
 /// different dimensions.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
@@ -18,7 +27,7 @@ pub enum Origin {
     TopLeft,
 
     /// The origin of pages on the top right corner, this is used in
-    /// left-to-right read documents.
+    /// right-to-left read documents.
     TopRight,
 
     /// The origin of pages on the bottom left corner, this is included for
@@ -52,6 +61,16 @@ impl Origin {
     }
 }
 
+impl From<Direction> for Origin {
+    /// Aligns pages at the top edge at which a reader starts reading.
+    fn from(value: Direction) -> Self {
+        match value {
+            Direction::Ltr => Self::TopLeft,
+            Direction::Rtl => Self::TopRight,
+        }
+    }
+}
+
 /// The factor used to convert pixel per pt to pixel per inch.
 pub const PPP_TO_PPI_FACTOR: f64 = 72.0;
 
@@ -72,13 +91,287 @@ pub fn ppi_to_ppp(pixel_per_inch: f64) -> f64 {
     pixel_per_inch / PPP_TO_PPI_FACTOR
 }
 
+/// A rectangle on a page in pt, pages can be cropped to it to compare only a
+/// part of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Focus {
+    /// The horizontal offset of the rectangle from the left edge of the page.
+    pub x: f64,
+
+    /// The vertical offset of the rectangle from the top edge of the page.
+    pub y: f64,
+
+    /// The width of the rectangle.
+    pub width: f64,
+
+    /// The height of the rectangle.
+    pub height: f64,
+}
+
+impl Focus {
+    /// The smallest pixel rectangle covering this rectangle at the given pixel
+    /// per pt ratio, returns `None` if it is empty or not fully contained in a
+    /// page of the given dimensions.
+    pub fn to_pixel_rect(self, pixel_per_pt: f64, width: u32, height: u32) -> Option<IntRect> {
+        let left = (self.x * pixel_per_pt).floor();
+        let top = (self.y * pixel_per_pt).floor();
+        let right = ((self.x + self.width) * pixel_per_pt).ceil();
+        let bottom = ((self.y + self.height) * pixel_per_pt).ceil();
+
+        if left < 0.0 || top < 0.0 || right > width as f64 || bottom > height as f64 {
+            return None;
+        }
+
+        IntRect::from_ltrb(left as i32, top as i32, right as i32, bottom as i32)
+    }
+}
+
+/// Crops a page to the given focus rectangle, returns `None` if it does not
+/// lie within the page.
+pub fn page_focus(page: &Pixmap, focus: Focus, pixel_per_pt: f64) -> Option<Pixmap> {
+    let rect = focus.to_pixel_rect(pixel_per_pt, page.width(), page.height())?;
+    page.clone_rect(rect)
+}
+
+/// The offset in pixels of a cropped page within the page it was cropped
+/// from, see [`page_crop`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct CropOffset {
+    /// The number of columns removed from the left edge.
+    pub x: u32,
+
+    /// The number of rows removed from the top edge.
+    pub y: u32,
+}
+
+/// Crops the borders of uniform color around a page, the color of the top
+/// left pixel is used as the background color.
+///
+/// Returns the cropped page and its offset within the original page, pages
+/// of a single color are returned unchanged.
+pub fn page_crop(page: &Pixmap) -> (Pixmap, CropOffset) {
+    let width = page.width() as usize;
+    let pixels = page.pixels();
+    let background = pixels[0];
+
+    let is_background_row = |y: usize| {
+        pixels[y * width..(y + 1) * width]
+            .iter()
+            .all(|pixel| *pixel == background)
+    };
+
+    let Some(top) = (0..page.height() as usize).find(|&y| !is_background_row(y)) else {
+        return (page.clone(), CropOffset::default());
+    };
+
+    let bottom = (top..page.height() as usize)
+        .rev()
+        .find(|&y| !is_background_row(y))
+        .unwrap_or(top)
+        + 1;
+
+    let is_background_column =
+        |x: usize| (top..bottom).all(|y| pixels[y * width + x] == background);
+
+    let left = (0..width).find(|&x| !is_background_column(x)).unwrap_or(0);
+    let right = (left..width)
+        .rev()
+        .find(|&x| !is_background_column(x))
+        .unwrap_or(left)
+        + 1;
+
+    let rect = IntRect::from_ltrb(left as i32, top as i32, right as i32, bottom as i32)
+        .expect("content is not empty");
+
+    (
+        page.clone_rect(rect).expect("content lies within the page"),
+        CropOffset {
+            x: left as u32,
+            y: top as u32,
+        },
+    )
+}
+
+/// Pads a cropped page with transparent pixels on its top and left edges,
+/// such that its content is placed at the given offset again, see
+/// [`page_crop`].
+pub fn page_uncrop(page: &Pixmap, offset: CropOffset) -> Pixmap {
+    if offset == CropOffset::default() {
+        return page.clone();
+    }
+
+    let mut padded = Pixmap::new(page.width() + offset.x, page.height() + offset.y)
+        .expect("must be larger than zero");
+
+    padded.draw_pixmap(
+        offset.x as i32,
+        offset.y as i32,
+        page.as_ref(),
+        &PixmapPaint {
+            opacity: 1.0,
+            blend_mode: BlendMode::Source,
+            quality: FilterQuality::Nearest,
+        },
+        Transform::identity(),
+        None,
+    );
+
+    padded
+}
+
+/// Downscales a page by the given factor using a box filter, each block of
+/// `factor` by `factor` pixels becomes a single pixel of its average color.
+///
+/// Blocks at the right and bottom edges may be smaller if the page size is
+/// not a multiple of the factor, factors below `2` return the page unchanged.
+pub fn page_downscale(page: &Pixmap, factor: u32) -> Pixmap {
+    if factor < 2 {
+        return page.clone();
+    }
+
+    let width = page.width();
+    let height = page.height();
+    let pixels = page.pixels();
+
+    let mut scaled = Pixmap::new(width.div_ceil(factor), height.div_ceil(factor))
+        .expect("must be larger than zero");
+    let scaled_width = scaled.width();
+
+    for (idx, pixel) in scaled.pixels_mut().iter_mut().enumerate() {
+        let bx = idx as u32 % scaled_width * factor;
+        let by = idx as u32 / scaled_width * factor;
+
+        let mut sum = [0u32; 4];
+        let mut len = 0;
+        for y in by..Ord::min(by + factor, height) {
+            for x in bx..Ord::min(bx + factor, width) {
+                let p = pixels[(y * width + x) as usize];
+                for (sum, c) in sum
+                    .iter_mut()
+                    .zip([p.red(), p.green(), p.blue(), p.alpha()])
+                {
+                    *sum += c as u32;
+                }
+                len += 1;
+            }
+        }
+
+        // NOTE(tinger): The channels of each pixel are at most its alpha, so
+        // this holds for their rounded averages too.
+        let [r, g, b, a] = sum.map(|sum| ((sum + len / 2) / len) as u8);
+        *pixel = PremultipliedColorU8::from_rgba(r, g, b, a).expect("channels are premultiplied");
+    }
+
+    scaled
+}
+
+/// The color with which masked regions are highlighted in difference pages.
+pub const MASK_HIGHLIGHT_COLOR: (u8, u8, u8, u8) = (0, 128, 255, 96);
+
+/// A rectangle on a page in pt which is excluded from comparison, used to
+/// ignore dynamic content like timestamps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mask {
+    /// The horizontal offset of the rectangle from the left edge of the page.
+    pub x: f64,
+
+    /// The vertical offset of the rectangle from the top edge of the page.
+    pub y: f64,
+
+    /// The width of the rectangle.
+    pub width: f64,
+
+    /// The height of the rectangle.
+    pub height: f64,
+}
+
+impl Mask {
+    /// The smallest pixel rectangle covering this rectangle at the given pixel
+    /// per pt ratio, clamped to a page of the given dimensions, returns `None`
+    /// if nothing of it lies within the page.
+    pub fn to_pixel_rect(self, pixel_per_pt: f64, width: u32, height: u32) -> Option<IntRect> {
+        let clamp_x = |value: f64| value.clamp(0.0, width as f64);
+        let clamp_y = |value: f64| value.clamp(0.0, height as f64);
+
+        let left = clamp_x((self.x * pixel_per_pt).floor());
+        let top = clamp_y((self.y * pixel_per_pt).floor());
+        let right = clamp_x(((self.x + self.width) * pixel_per_pt).ceil());
+        let bottom = clamp_y(((self.y + self.height) * pixel_per_pt).ceil());
+
+        IntRect::from_ltrb(left as i32, top as i32, right as i32, bottom as i32)
+    }
+
+    /// This mask relative to the top left corner of the given focus
+    /// rectangle, i.e. on a page which was cropped to it.
+    pub fn relative_to(self, focus: Focus) -> Self {
+        Self {
+            x: self.x - focus.x,
+            y: self.y - focus.y,
+            ..self
+        }
+    }
+}
+
+/// Clears the given masked regions of a page, such that they are fully
+/// transparent and never deviate between pages. Masks are clamped to the page.
+pub fn page_mask(page: &mut Pixmap, masks: &[Mask], pixel_per_pt: f64) {
+    let width = page.width();
+    let height = page.height();
+
+    for mask in masks {
+        let Some(rect) = mask.to_pixel_rect(pixel_per_pt, width, height) else {
+            continue;
+        };
+
+        let pixels = page.pixels_mut();
+        for y in rect.top()..rect.bottom() {
+            let row = y as usize * width as usize;
+            pixels[row + rect.left() as usize..row + rect.right() as usize]
+                .fill(PremultipliedColorU8::TRANSPARENT);
+        }
+    }
+}
+
+/// Highlights the given masked regions on a difference page, such that it is
+/// visible which regions were excluded from comparison.
+pub fn page_mask_highlight(page: &mut Pixmap, masks: &[Mask], pixel_per_pt: f64) {
+    let (r, g, b, a) = MASK_HIGHLIGHT_COLOR;
+
+    let mut paint = tiny_skia::Paint::default();
+    paint.set_color_rgba8(r, g, b, a);
+
+    for mask in masks {
+        let Some(rect) = mask
+            .to_pixel_rect(pixel_per_pt, page.width(), page.height())
+            .map(|rect| rect.to_rect())
+        else {
+            continue;
+        };
+
+        page.fill_rect(rect, &paint, Transform::identity(), None);
+    }
+}
+
+/// The colors used for rendering difference pages using [`page_diff`], the
+/// default colors leave the result of the difference filter unchanged.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DiffColors {
+    /// The opaque color placed behind the difference, such that transparent
+    /// regions are distinguishable from identical ones.
+    pub background: Option<(u8, u8, u8)>,
+
+    /// The opaque color with which all differing pixels are replaced.
+    pub highlight: Option<(u8, u8, u8)>,
+}
+
 /// Render the visual diff of two pages. If the pages do not have matching
 /// dimensions, then the origin is used to align them, regions without overlap
 /// will simply be colored black.
 ///
 /// The difference is created by `change` on top of `base` using a difference
-/// filter.
-pub fn page_diff(base: &Pixmap, change: &Pixmap, origin: Origin) -> Pixmap {
+/// filter, differing pixels and the background are then colored according to
+/// the given colors.
+pub fn page_diff(base: &Pixmap, change: &Pixmap, origin: Origin, colors: DiffColors) -> Pixmap {
     fn aligned_offset((a, b): (u32, u32), end: bool) -> (i32, i32) {
         match Ord::cmp(&a, &b) {
             Ordering::Less if end => (u32::abs_diff(a, b) as i32, 0),
@@ -94,7 +387,7 @@ pub fn page_diff(base: &Pixmap, change: &Pixmap, origin: Origin) -> Pixmap {
     .expect("must be larger than zero");
 
     let (base_x, change_x) = aligned_offset((base.width(), change.width()), origin.is_right());
-    let (base_y, change_y) = aligned_offset((base.height(), change.height()), origin.is_right());
+    let (base_y, change_y) = aligned_offset((base.height(), change.height()), origin.is_bottom());
 
     diff.draw_pixmap(
         base_x,
@@ -122,13 +415,315 @@ pub fn page_diff(base: &Pixmap, change: &Pixmap, origin: Origin) -> Pixmap {
         None,
     );
 
+    if let Some((r, g, b)) = colors.highlight {
+        let highlight = PremultipliedColorU8::from_rgba(r, g, b, 255).expect("is opaque");
+
+        // The pixel of a page at a position of the diff, regions without
+        // overlap are treated as transparent.
+        let pixel = |page: &Pixmap, (x, y): (u32, u32), (dx, dy): (i32, i32)| {
+            x.checked_sub(dx as u32)
+                .zip(y.checked_sub(dy as u32))
+                .and_then(|(x, y)| page.pixel(x, y))
+                .unwrap_or(PremultipliedColorU8::TRANSPARENT)
+        };
+
+        let width = diff.width();
+        for (idx, px) in diff.pixels_mut().iter_mut().enumerate() {
+            let pos = (idx as u32 % width, idx as u32 / width);
+
+            if pixel(base, pos, (base_x, base_y)) != pixel(change, pos, (change_x, change_y)) {
+                *px = highlight;
+            }
+        }
+    }
+
+    if let Some((r, g, b)) = colors.background {
+        let mut background =
+            Pixmap::new(diff.width(), diff.height()).expect("must be larger than zero");
+        background.fill(Color::from_rgba8(r, g, b, 255));
+
+        background.draw_pixmap(
+            0,
+            0,
+            diff.as_ref(),
+            &PixmapPaint {
+                opacity: 1.0,
+                blend_mode: BlendMode::SourceOver,
+                quality: FilterQuality::Nearest,
+            },
+            Transform::identity(),
+            None,
+        );
+
+        diff = background;
+    }
+
     diff
 }
 
+/// Renders which deviating pixels of two pages are classified as
+/// anti-aliasing by [`compare::is_antialiasing`].
+///
+/// Pixels classified as anti-aliasing are marked cyan, all other deviating
+/// pixels are marked red, matching pixels are taken from the output. If the
+/// pages differ in size the output is returned unchanged.
+pub fn page_antialiasing(output: &Pixmap, reference: &Pixmap, max_delta: u8) -> Pixmap {
+    let mut page = output.clone();
+
+    if output.width() != reference.width() || output.height() != reference.height() {
+        return page;
+    }
+
+    let cyan = PremultipliedColorU8::from_rgba(0, 255, 255, 255).expect("is opaque");
+    let red = PremultipliedColorU8::from_rgba(255, 0, 0, 255).expect("is opaque");
+
+    let width = page.width();
+
+    for (idx, pixel) in page.pixels_mut().iter_mut().enumerate() {
+        let (x, y) = (idx as u32 % width, idx as u32 / width);
+
+        if !compare::deviates(*pixel, reference.pixels()[idx], max_delta) {
+            continue;
+        }
+
+        *pixel = if compare::is_antialiasing(output, reference, x, y, x, y) {
+            cyan
+        } else {
+            red
+        };
+    }
+
+    page
+}
+
+/// Options for rendering a page overview using [`page_overview`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OverviewOptions {
+    /// The maximum width and height of a single thumbnail in pixels.
+    pub thumbnail_size: u32,
+
+    /// The number of thumbnails per row, `0` places all thumbnails in a single
+    /// row.
+    pub columns: usize,
+}
+
+impl Default for OverviewOptions {
+    fn default() -> Self {
+        Self {
+            thumbnail_size: 128,
+            columns: 0,
+        }
+    }
+}
+
+/// The width of the colored border around each thumbnail in an overview.
+const OVERVIEW_BORDER: u32 = 2;
+
+/// Render an overview of the given pages, each page is downscaled to a
+/// thumbnail and framed by a green or red border depending on whether it
+/// passed.
+///
+/// Returns `None` if there are no pages.
+pub fn page_overview<'a, I>(pages: I, options: OverviewOptions) -> Option<Pixmap>
+where
+    I: IntoIterator<Item = (&'a Pixmap, bool)>,
+{
+    let pages: Vec<_> = pages.into_iter().collect();
+    if pages.is_empty() {
+        return None;
+    }
+
+    let size = options.thumbnail_size.max(1);
+    let cell = size + 2 * OVERVIEW_BORDER;
+    let columns = match options.columns {
+        0 => pages.len(),
+        columns => Ord::min(columns, pages.len()),
+    };
+    let rows = pages.len().div_ceil(columns);
+
+    let mut overview = Pixmap::new(cell * columns as u32, cell * rows as u32)?;
+
+    for (idx, (page, passed)) in pages.into_iter().enumerate() {
+        let x = (idx % columns) as u32 * cell;
+        let y = (idx / columns) as u32 * cell;
+
+        let border = if passed {
+            Color::from_rgba8(0, 200, 0, 255)
+        } else {
+            Color::from_rgba8(220, 0, 0, 255)
+        };
+
+        overview.fill_rect(
+            Rect::from_xywh(x as f32, y as f32, cell as f32, cell as f32)?,
+            &tiny_skia::Paint {
+                shader: tiny_skia::Shader::SolidColor(border),
+                ..Default::default()
+            },
+            Transform::identity(),
+            None,
+        );
+
+        overview.fill_rect(
+            Rect::from_xywh(
+                (x + OVERVIEW_BORDER) as f32,
+                (y + OVERVIEW_BORDER) as f32,
+                size as f32,
+                size as f32,
+            )?,
+            &tiny_skia::Paint {
+                shader: tiny_skia::Shader::SolidColor(Color::WHITE),
+                ..Default::default()
+            },
+            Transform::identity(),
+            None,
+        );
+
+        let scale = f32::min(
+            size as f32 / page.width() as f32,
+            size as f32 / page.height() as f32,
+        );
+        let offset_x = (size as f32 - page.width() as f32 * scale) / 2.0;
+        let offset_y = (size as f32 - page.height() as f32 * scale) / 2.0;
+
+        overview.draw_pixmap(
+            0,
+            0,
+            page.as_ref(),
+            &PixmapPaint {
+                opacity: 1.0,
+                blend_mode: BlendMode::SourceOver,
+                quality: FilterQuality::Bicubic,
+            },
+            Transform::from_scale(scale, scale).post_translate(
+                (x + OVERVIEW_BORDER) as f32 + offset_x,
+                (y + OVERVIEW_BORDER) as f32 + offset_y,
+            ),
+            None,
+        );
+    }
+
+    Some(overview)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_page_focus() {
+        let page = Pixmap::new(20, 10).unwrap();
+
+        let focus = Focus {
+            x: 2.0,
+            y: 1.0,
+            width: 4.5,
+            height: 3.0,
+        };
+        let cropped = page_focus(&page, focus, 2.0).unwrap();
+        assert_eq!((cropped.width(), cropped.height()), (9, 6));
+
+        let outside = Focus { x: 8.0, ..focus };
+        assert!(page_focus(&page, outside, 2.0).is_none());
+
+        let empty = Focus {
+            width: 0.0,
+            ..focus
+        };
+        assert!(page_focus(&page, empty, 2.0).is_none());
+    }
+
+    #[test]
+    fn test_page_downscale() {
+        let mut page = Pixmap::new(5, 3).unwrap();
+        page.fill(Color::WHITE);
+        page.fill_rect(
+            Rect::from_xywh(0.0, 0.0, 1.0, 1.0).unwrap(),
+            &tiny_skia::Paint::default(),
+            Transform::identity(),
+            None,
+        );
+
+        let scaled = page_downscale(&page, 2);
+        assert_eq!((scaled.width(), scaled.height()), (3, 2));
+
+        // One black and three white pixels.
+        let pixel = scaled.pixel(0, 0).unwrap();
+        assert_eq!((pixel.red(), pixel.alpha()), (191, 255));
+
+        // Edge blocks are averaged over the pixels they contain.
+        assert_eq!(scaled.pixel(2, 1).unwrap(), page.pixel(4, 2).unwrap());
+
+        let unchanged = page_downscale(&page, 1);
+        assert_eq!(unchanged, page);
+    }
+
+    #[test]
+    fn test_page_crop() {
+        let mut page = Pixmap::new(20, 10).unwrap();
+        page.fill(Color::WHITE);
+        page.fill_rect(
+            Rect::from_xywh(3.0, 2.0, 5.0, 4.0).unwrap(),
+            &tiny_skia::Paint::default(),
+            Transform::identity(),
+            None,
+        );
+
+        let (cropped, offset) = page_crop(&page);
+        assert_eq!((cropped.width(), cropped.height()), (5, 4));
+        assert_eq!(offset, CropOffset { x: 3, y: 2 });
+
+        let uncropped = page_uncrop(&cropped, offset);
+        assert_eq!((uncropped.width(), uncropped.height()), (8, 6));
+        assert_eq!(uncropped.pixel(3, 2), page.pixel(3, 2));
+        assert_eq!(uncropped.pixel(0, 0).unwrap().alpha(), 0);
+
+        let mut blank = Pixmap::new(20, 10).unwrap();
+        blank.fill(Color::WHITE);
+        let (cropped, offset) = page_crop(&blank);
+        assert_eq!((cropped.width(), cropped.height()), (20, 10));
+        assert_eq!(offset, CropOffset::default());
+    }
+
+    #[test]
+    fn test_page_mask() {
+        let mut page = Pixmap::new(20, 10).unwrap();
+        page.fill(Color::from_rgba8(255, 0, 0, 255));
+
+        let masks = [
+            Mask {
+                x: 1.0,
+                y: 1.0,
+                width: 2.0,
+                height: 1.0,
+            },
+            // Clamped to the page.
+            Mask {
+                x: 8.0,
+                y: -2.0,
+                width: 10.0,
+                height: 4.0,
+            },
+            // Outside of the page.
+            Mask {
+                x: 20.0,
+                y: 0.0,
+                width: 5.0,
+                height: 5.0,
+            },
+        ];
+        page_mask(&mut page, &masks, 2.0);
+
+        let is_masked =
+            |x: u32, y: u32| ((2..6).contains(&x) && (2..4).contains(&y)) || (x >= 16 && y < 4);
+
+        for y in 0..10 {
+            for x in 0..20 {
+                let pixel = page.pixel(x, y).unwrap();
+                assert_eq!(pixel.alpha() == 0, is_masked(x, y), "pixel ({x}, {y})");
+            }
+        }
+    }
+
     #[test]
     fn test_page_diff_top_left() {
         let mut base = Pixmap::new(10, 10).unwrap();
@@ -165,7 +760,7 @@ mod tests {
         }
 
         assert_eq!(
-            page_diff(&base, &change, Origin::TopLeft).data(),
+            page_diff(&base, &change, Origin::TopLeft, DiffColors::default()).data(),
             diff.data()
         );
     }
@@ -208,8 +803,146 @@ mod tests {
         }
 
         assert_eq!(
-            page_diff(&base, &change, Origin::BottomRight).data(),
+            page_diff(&base, &change, Origin::BottomRight, DiffColors::default()).data(),
             diff.data()
         );
     }
+
+    #[test]
+    fn test_page_diff_origin_placement() {
+        let mut base = Pixmap::new(10, 10).unwrap();
+        let mut change = Pixmap::new(4, 4).unwrap();
+
+        base.fill(tiny_skia::Color::from_rgba8(255, 255, 255, 255));
+        change.fill(tiny_skia::Color::from_rgba8(255, 0, 0, 255));
+
+        let pixel = |diff: &Pixmap, x: u32, y: u32| {
+            let px = diff.pixel(x, y).unwrap();
+            [px.red(), px.green(), px.blue(), px.alpha()]
+        };
+
+        // The smaller page must be placed in the corner of the origin, the
+        // opposite corner only contains the base.
+        for (origin, (x, y), (opposite_x, opposite_y)) in [
+            (Origin::TopLeft, (0, 0), (9, 9)),
+            (Origin::TopRight, (9, 0), (0, 9)),
+            (Origin::BottomLeft, (0, 9), (9, 0)),
+            (Origin::BottomRight, (9, 9), (0, 0)),
+        ] {
+            let diff = page_diff(&base, &change, origin, DiffColors::default());
+
+            assert_eq!(pixel(&diff, x, y), [0, 255, 255, 255], "{origin:?}");
+            assert_eq!(
+                pixel(&diff, opposite_x, opposite_y),
+                [255, 255, 255, 255],
+                "{origin:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_page_diff_direction() {
+        let pixel = |diff: &Pixmap, x: u32, y: u32| {
+            let px = diff.pixel(x, y).unwrap();
+            [px.red(), px.green(), px.blue(), px.alpha()]
+        };
+
+        // Both a narrower and a wider change must be aligned at the start edge
+        // of the direction, the other edge only contains the larger page.
+        for (width, start, end) in [
+            (6, [0, 255, 255, 255], [255, 255, 255, 255]),
+            (14, [0, 255, 255, 255], [255, 0, 0, 255]),
+        ] {
+            let mut base = Pixmap::new(10, 10).unwrap();
+            let mut change = Pixmap::new(width, 10).unwrap();
+
+            base.fill(tiny_skia::Color::from_rgba8(255, 255, 255, 255));
+            change.fill(tiny_skia::Color::from_rgba8(255, 0, 0, 255));
+
+            let diff = page_diff(&base, &change, Direction::Ltr.into(), DiffColors::default());
+            let last = diff.width() - 1;
+            assert_eq!(pixel(&diff, 0, 5), start, "ltr {width}");
+            assert_eq!(pixel(&diff, last, 5), end, "ltr {width}");
+
+            let diff = page_diff(&base, &change, Direction::Rtl.into(), DiffColors::default());
+            assert_eq!(pixel(&diff, last, 5), start, "rtl {width}");
+            assert_eq!(pixel(&diff, 0, 5), end, "rtl {width}");
+        }
+    }
+
+    #[test]
+    fn test_page_diff_colors() {
+        let mut base = Pixmap::new(4, 4).unwrap();
+
+        // Only the top left pixel differs, the bottom row is transparent.
+        base.fill_rect(
+            Rect::from_xywh(0.0, 0.0, 4.0, 3.0).unwrap(),
+            &tiny_skia::Paint {
+                shader: tiny_skia::Shader::SolidColor(Color::WHITE),
+                ..Default::default()
+            },
+            Transform::identity(),
+            None,
+        );
+        let mut change = base.clone();
+        change.pixels_mut()[0] = PremultipliedColorU8::from_rgba(0, 0, 0, 255).unwrap();
+
+        let pixel = |diff: &Pixmap, x: u32, y: u32| {
+            let px = diff.pixel(x, y).unwrap();
+            [px.red(), px.green(), px.blue(), px.alpha()]
+        };
+
+        let diff = page_diff(&base, &change, Origin::TopLeft, DiffColors::default());
+        assert_eq!(pixel(&diff, 0, 0), [255, 255, 255, 255]);
+        assert_eq!(pixel(&diff, 1, 1), [0, 0, 0, 255]);
+        assert_eq!(pixel(&diff, 1, 3), [0, 0, 0, 0]);
+
+        let colors = DiffColors {
+            background: Some((0, 0, 255)),
+            highlight: Some((255, 0, 255)),
+        };
+        let diff = page_diff(&base, &change, Origin::TopLeft, colors);
+        assert_eq!(pixel(&diff, 0, 0), [255, 0, 255, 255]);
+        assert_eq!(pixel(&diff, 1, 1), [0, 0, 0, 255]);
+        assert_eq!(pixel(&diff, 1, 3), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_page_overview_layout() {
+        let page = Pixmap::new(20, 10).unwrap();
+        let pages = [(&page, true), (&page, false), (&page, true)];
+
+        let options = OverviewOptions {
+            thumbnail_size: 10,
+            columns: 0,
+        };
+        let overview = page_overview(pages, options).unwrap();
+        assert_eq!((overview.width(), overview.height()), (14 * 3, 14));
+
+        let options = OverviewOptions {
+            thumbnail_size: 10,
+            columns: 2,
+        };
+        let overview = page_overview(pages, options).unwrap();
+        assert_eq!((overview.width(), overview.height()), (14 * 2, 14 * 2));
+
+        assert!(page_overview([], options).is_none());
+    }
+
+    #[test]
+    fn test_page_overview_border() {
+        let page = Pixmap::new(10, 10).unwrap();
+        let options = OverviewOptions {
+            thumbnail_size: 10,
+            columns: 0,
+        };
+
+        let overview = page_overview([(&page, true), (&page, false)], options).unwrap();
+
+        let pass = overview.pixel(0, 0).unwrap().demultiply();
+        assert_eq!((pass.red(), pass.green(), pass.blue()), (0, 200, 0));
+
+        let fail = overview.pixel(14, 0).unwrap().demultiply();
+        assert_eq!((fail.red(), fail.green(), fail.blue()), (220, 0, 0));
+    }
 }
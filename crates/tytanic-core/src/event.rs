@@ -0,0 +1,179 @@
+//! Lightweight events emitted during a test run.
+//!
+//! Events carry only identifiers, durations and the kind of a result, they
+//! never contain rendered documents, which makes them cheap to send to another
+//! thread, e.g. to show the progress of a test run in a graphical interface.
+
+use chrono::TimeDelta;
+use uuid::Uuid;
+
+use crate::test::Id;
+use crate::test::Stage;
+
+/// An event emitted during a test run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunEvent {
+    /// A test was started.
+    TestStarted {
+        /// The id of the test.
+        id: Id,
+    },
+
+    /// A stage of a test finished.
+    StageFinished {
+        /// The id of the test.
+        id: Id,
+
+        /// The stage which finished.
+        stage: RunStage,
+
+        /// Whether the stage finished successfully.
+        passed: bool,
+
+        /// How long the stage took.
+        duration: TimeDelta,
+    },
+
+    /// A test finished.
+    TestFinished {
+        /// The id of the test.
+        id: Id,
+
+        /// The kind of the test result.
+        kind: ResultKind,
+
+        /// How long the test took.
+        duration: TimeDelta,
+    },
+
+    /// The test suite finished.
+    SuiteFinished {
+        /// The id of the test run.
+        run_id: Uuid,
+
+        /// The number of tests which passed.
+        passed: usize,
+
+        /// The number of tests which failed.
+        failed: usize,
+
+        /// The number of tests which were skipped or not run because the run
+        /// was aborted.
+        skipped: usize,
+
+        /// How long the test run took.
+        duration: TimeDelta,
+    },
+}
+
+/// A stage of a single test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RunStage {
+    /// The temporary directories of the test were prepared.
+    Prepare,
+
+    /// The test or reference document was compiled.
+    Compilation {
+        /// Whether this was the compilation of the reference.
+        reference: bool,
+    },
+
+    /// The test document was compared to its reference.
+    Comparison,
+
+    /// The persistent reference of the test was updated.
+    Update,
+}
+
+impl RunStage {
+    /// A short name of this stage, e.g. for use in logs.
+    pub fn name(self) -> &'static str {
+        match self {
+            RunStage::Prepare => "prepare",
+            RunStage::Compilation { reference: false } => "compilation",
+            RunStage::Compilation { reference: true } => "reference-compilation",
+            RunStage::Comparison => "comparison",
+            RunStage::Update => "update",
+        }
+    }
+}
+
+/// The kind of a test result, this is a [`Stage`] without its payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResultKind {
+    /// See [`Stage::Skipped`].
+    Skipped,
+
+    /// See [`Stage::Cancelled`].
+    Cancelled,
+
+    /// See [`Stage::Filtered`].
+    Filtered,
+
+    /// See [`Stage::FailedCompilation`].
+    FailedCompilation {
+        /// Whether this was a compilation failure of the reference.
+        reference: bool,
+    },
+
+    /// See [`Stage::FailedComparison`].
+    FailedComparison,
+
+    /// See [`Stage::PassedCompilation`].
+    PassedCompilation,
+
+    /// See [`Stage::PassedComparison`].
+    PassedComparison,
+
+    /// See [`Stage::Updated`].
+    Updated,
+}
+
+impl ResultKind {
+    /// Whether this is a failure.
+    pub fn is_fail(self) -> bool {
+        matches!(
+            self,
+            ResultKind::FailedCompilation { .. } | ResultKind::FailedComparison
+        )
+    }
+}
+
+impl From<&Stage> for ResultKind {
+    fn from(stage: &Stage) -> Self {
+        match stage {
+            Stage::Skipped => ResultKind::Skipped,
+            Stage::Cancelled => ResultKind::Cancelled,
+            Stage::Filtered => ResultKind::Filtered,
+            Stage::FailedCompilation { reference, .. } => ResultKind::FailedCompilation {
+                reference: *reference,
+            },
+            Stage::FailedComparison(_) => ResultKind::FailedComparison,
+            Stage::PassedCompilation => ResultKind::PassedCompilation,
+            Stage::PassedComparison => ResultKind::PassedComparison,
+            Stage::Updated { .. } => ResultKind::Updated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doc::SaveSummary;
+
+    #[test]
+    fn test_result_kind_from_stage() {
+        assert_eq!(ResultKind::from(&Stage::Skipped), ResultKind::Skipped);
+        assert_eq!(ResultKind::from(&Stage::Cancelled), ResultKind::Cancelled);
+        assert_eq!(
+            ResultKind::from(&Stage::Updated {
+                optimized: true,
+                pages: SaveSummary::default(),
+                dry_run: false,
+            }),
+            ResultKind::Updated
+        );
+        assert!(!ResultKind::from(&Stage::PassedComparison).is_fail());
+        assert!(ResultKind::FailedComparison.is_fail());
+    }
+}
@@ -8,6 +8,7 @@
 use std::convert::Infallible;
 
 use crate::project::Project;
+use crate::test::Id;
 use crate::test::Test;
 
 /// A filter from which a [`FilterState`] can be created.
@@ -62,6 +63,20 @@ pub trait Filter {
     ///
     /// [`Suite`]: crate::suite::Suite
     fn state(&self) -> Self::State<'_>;
+
+    /// Whether the test with the given id or any test below it could be
+    /// matched by this filter.
+    ///
+    /// This is a hint used by [`Suite::collect_pruned`] to skip directories
+    /// during collection, it must only return `false` if no such test could
+    /// ever be matched. The default implementation returns `true`.
+    ///
+    /// [`Suite::collect_pruned`]: crate::suite::Suite::collect_pruned
+    fn may_match(&self, id: &Id) -> bool {
+        let _id = id;
+
+        true
+    }
 }
 
 /// A type which allows storing state during filter application.
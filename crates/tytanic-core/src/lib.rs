@@ -2,6 +2,7 @@
 
 pub mod config;
 pub mod doc;
+pub mod event;
 pub mod filter;
 pub mod library;
 pub mod project;
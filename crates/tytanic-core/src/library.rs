@@ -11,35 +11,98 @@
 //! })
 //! ```
 //!
+//! ## `catch-each`
+//! Like `catch`, but calls each closure of an array in order and returns an
+//! array with the panic message of each closure, or `none` if it didn't panic.
+//! ```typst
+//! #let (msg, ok) = catch-each((() => panic(), () => {}))
+//! #assert.eq(ok, none)
+//! ```
+//!
+//! ## `catch-all`
+//! Like `catch`, but returns an array with a dictionary for each diagnostic,
+//! containing its `message` and `hints`, or `none` if it didn't panic.
+//! ```typst
+//! #let errors = catch-all(() => eval("#} #}", mode: "markup"))
+//! #assert.eq(errors.len(), 2)
+//! ```
+//!
 //! ## `assert-panic`
 //! Provides an assertion that tests if a given closure panicked, panicking if
 //! it did not. Takes an optional `message` similar to other `assert` functions.
 //! ```typst
 //! #assert-panic(() => {}, message: "Did not panic")
 //! ```
+//!
+//! ## `assert-no-panic`
+//! Provides an assertion that tests if a given closure returned successfully,
+//! panicking with its panic message if it did not. Takes an optional `message`
+//! similar to other `assert` functions.
+//! ```typst
+//! #assert-no-panic(() => {})
+//! ```
+//!
+//! ## `expect-message`
+//! Provides an assertion that tests if a given closure panicked with the given
+//! `message`, panicking if it did not panic or panicked with another message.
+//! Messages starting with [`REGEX_PREFIX`] are matched as a regular
+//! expression instead.
+//! ```typst
+//! #expect-message(() => panic("a"), message: "panicked with: \"a\"")
+//! #expect-message(() => panic("a"), message: "regex:^panicked")
+//! ```
+//!
+//! ## `assert-approx-eq`
+//! Provides an assertion that tests if two numbers, lengths, ratios or angles
+//! are equal within a `tolerance`, which defaults to
+//! [`DEFAULT_APPROX_TOLERANCE`]. The tolerance applies to the value in points
+//! for lengths, as a fraction for ratios and in degrees for angles. Takes an
+//! optional `message` similar to other `assert` functions.
+//! ```typst
+//! #assert-approx-eq(0.1 + 0.2, 0.3)
+//! #assert-approx-eq(10pt / 3, 3.33pt, tolerance: 0.01)
+//! ```
 
 use ecow::EcoString;
+use ecow::eco_vec;
 use typst::Library;
 use typst::LibraryBuilder;
 use typst::LibraryExt;
 use typst::comemo::Tracked;
+use typst::diag::SourceDiagnostic;
 use typst::diag::SourceResult;
 use typst::diag::bail;
 use typst::engine::Engine;
+use typst::foundations::Array;
 use typst::foundations::Context;
+use typst::foundations::Dict;
 use typst::foundations::Func;
 use typst::foundations::Module;
+use typst::foundations::Regex;
 use typst::foundations::Repr;
 use typst::foundations::Scope;
 use typst::foundations::Str;
 use typst::foundations::Value;
 use typst::foundations::func;
+use typst::syntax::Span;
+
+/// The default tolerance of `assert-approx-eq`.
+pub const DEFAULT_APPROX_TOLERANCE: f64 = 1e-6;
+
+/// The prefix of `expect-message` messages which are matched as a regular
+/// expression.
+pub const REGEX_PREFIX: &str = "regex:";
 
 /// Defines prelude items for the given scope, this is a subset of
 /// [`define_test_module`].
 pub fn define_prelude(scope: &mut Scope) {
     scope.define_func::<catch>();
+    scope.define_func::<catch_each>();
+    scope.define_func::<catch_all>();
     scope.define_func::<assert_panic>();
+    scope.define_func::<assert_no_panic>();
+    scope.define_func::<expect_message>();
+    scope.define_func::<assert_approx_eq>();
 }
 
 /// Defines test module items for the given scope.
@@ -76,6 +139,46 @@ pub fn augmented_library(builder: impl FnOnce(LibraryBuilder) -> LibraryBuilder)
 
 #[func]
 fn catch(engine: &mut Engine, context: Tracked<Context>, func: Func) -> Value {
+    catch_one(engine, context, &func)
+}
+
+#[func]
+fn catch_each(engine: &mut Engine, context: Tracked<Context>, funcs: Vec<Func>) -> Array {
+    funcs
+        .iter()
+        .map(|func| catch_one(engine, context, func))
+        .collect()
+}
+
+#[func]
+fn catch_all(engine: &mut Engine, context: Tracked<Context>, func: Func) -> Value {
+    func.call::<[Value; 0]>(engine, context, [])
+        .map(|_| Value::None)
+        .unwrap_or_else(|errors| Value::Array(errors.iter().map(diagnostic_dict).collect()))
+}
+
+/// Converts a diagnostic into a dictionary containing its message and hints.
+fn diagnostic_dict(diagnostic: &SourceDiagnostic) -> Value {
+    let mut dict = Dict::new();
+    dict.insert(
+        "message".into(),
+        Value::Str(Str::from(diagnostic.message.clone())),
+    );
+    dict.insert(
+        "hints".into(),
+        Value::Array(
+            diagnostic
+                .hints
+                .iter()
+                .map(|hint| Value::Str(Str::from(hint.v.clone())))
+                .collect(),
+        ),
+    );
+    Value::Dict(dict)
+}
+
+/// Calls the given function, returning its first panic message or `none`.
+fn catch_one(engine: &mut Engine, context: Tracked<Context>, func: &Func) -> Value {
     func.call::<[Value; 0]>(engine, context, [])
         .map(|_| Value::None)
         .unwrap_or_else(|errors| {
@@ -115,6 +218,136 @@ fn assert_panic(
     Ok(())
 }
 
+#[func]
+fn assert_no_panic(
+    engine: &mut Engine,
+    context: Tracked<Context>,
+    func: Func,
+    #[named] message: Option<EcoString>,
+) -> SourceResult<()> {
+    let span = func.span();
+    if let Err(errors) = func.call::<[Value; 0]>(engine, context, []) {
+        match message {
+            Some(message) => bail!(span, "{}", message),
+            None => bail!(
+                span,
+                "Expected no panic, closure panicked with: {}",
+                errors
+                    .first()
+                    .expect("should contain at least one diagnostic")
+                    .message,
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+#[func]
+fn expect_message(
+    engine: &mut Engine,
+    context: Tracked<Context>,
+    func: Func,
+    #[named] message: Option<EcoString>,
+) -> SourceResult<()> {
+    let span = func.span();
+    let Some(expected) = message else {
+        bail!(span, "missing argument: message");
+    };
+
+    let found = match func.call::<[Value; 0]>(engine, context, []) {
+        Ok(_) => bail!(
+            span,
+            "Expected panic with message {}, closure returned successfully",
+            expected.repr(),
+        ),
+        Err(errors) => errors
+            .first()
+            .expect("should contain at least one diagnostic")
+            .message
+            .clone(),
+    };
+
+    let matches = match expected.strip_prefix(REGEX_PREFIX) {
+        Some(pattern) => Regex::new(pattern)
+            .map_err(|err| eco_vec![SourceDiagnostic::error(span, err)])?
+            .is_match(&found),
+        None => found == expected,
+    };
+
+    if !matches {
+        bail!(
+            span,
+            "panic message did not match\n  expected: {}\n  found:    {}",
+            expected.repr(),
+            found.repr(),
+        );
+    }
+
+    Ok(())
+}
+
+#[func]
+fn assert_approx_eq(
+    span: Span,
+    left: Value,
+    right: Value,
+    #[named] tolerance: Option<f64>,
+    #[named] message: Option<EcoString>,
+) -> SourceResult<()> {
+    let tolerance = tolerance.unwrap_or(DEFAULT_APPROX_TOLERANCE);
+    if tolerance.is_nan() || tolerance < 0.0 {
+        bail!(
+            span,
+            "tolerance must be a non-negative number, found {}",
+            tolerance.repr()
+        );
+    }
+
+    let within = |a: f64, b: f64| (a - b).abs() <= tolerance;
+
+    let equal = match (&left, &right) {
+        (Value::Int(_) | Value::Float(_), Value::Int(_) | Value::Float(_)) => {
+            within(as_float(&left), as_float(&right))
+        }
+        (Value::Length(a), Value::Length(b)) => {
+            within(a.abs.to_pt(), b.abs.to_pt()) && within(a.em.get(), b.em.get())
+        }
+        (Value::Ratio(a), Value::Ratio(b)) => within(a.get(), b.get()),
+        (Value::Angle(a), Value::Angle(b)) => within(a.to_deg(), b.to_deg()),
+        _ => bail!(
+            span,
+            "cannot approximately compare {} and {}",
+            left.ty(),
+            right.ty(),
+        ),
+    };
+
+    if !equal {
+        match message {
+            Some(message) => bail!(span, "{}", message),
+            None => bail!(
+                span,
+                "approximate equality assertion failed: value {} was not within {} of {}",
+                left.repr(),
+                tolerance.repr(),
+                right.repr(),
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts an integer or float value to a float.
+fn as_float(value: &Value) -> f64 {
+    match *value {
+        Value::Int(int) => int as f64,
+        Value::Float(float) => float,
+        _ => unreachable!("value must be a number"),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use typst::syntax::Source;
@@ -145,6 +378,53 @@ mod tests {
         compile::compile(&world, Warnings::Emit).output.unwrap();
     }
 
+    #[test]
+    fn test_catch_each() {
+        let mut files = VirtualFileProvider::new();
+        let library = LazyHash::new(augmented_default_library());
+
+        let source = Source::detached(
+            r#"
+            #let errors = catch-each((
+                () => panic("first"),
+                () => {},
+                () => panic("third"),
+            ))
+            #assert.eq(errors, ("panicked with: first", none, "panicked with: third"))
+        "#,
+        );
+
+        let world = test_utils::virtual_world(source, &mut files, &library);
+
+        compile::compile(&world, Warnings::Emit).output.unwrap();
+    }
+
+    #[test]
+    fn test_catch_all() {
+        let mut files = VirtualFileProvider::new();
+        let library = LazyHash::new(augmented_default_library());
+
+        let source = Source::detached(
+            r##"
+            #assert.eq(catch-all(() => {}), none)
+
+            #let errors = catch-all(() => panic("first"))
+            #assert.eq(errors.len(), 1)
+            #assert.eq(errors.first().message, "panicked with: first")
+            #assert.eq(errors.first().hints, ())
+
+            #let errors = catch-all(() => eval("#} #}", mode: "markup"))
+            #assert.eq(errors.len(), 2)
+            #assert(errors.all(error => type(error.message) == str))
+            #assert(errors.all(error => type(error.hints) == array))
+        "##,
+        );
+
+        let world = test_utils::virtual_world(source, &mut files, &library);
+
+        compile::compile(&world, Warnings::Emit).output.unwrap();
+    }
+
     #[test]
     fn test_assert_panic() {
         let mut files = VirtualFileProvider::new();
@@ -162,4 +442,152 @@ mod tests {
 
         compile::compile(&world, Warnings::Emit).output.unwrap();
     }
+
+    #[test]
+    fn test_assert_no_panic() {
+        let mut files = VirtualFileProvider::new();
+        let library = LazyHash::new(augmented_default_library());
+
+        let source = Source::detached(
+            r#"
+            #assert-no-panic(() => {})
+            #assert-no-panic(() => panic("oops"))
+        "#,
+        );
+
+        let world = test_utils::virtual_world(source, &mut files, &library);
+
+        let errors = compile::compile(&world, Warnings::Emit).output.unwrap_err();
+        assert_eq!(errors.0.len(), 1);
+        assert_eq!(
+            errors.0[0].message,
+            "Expected no panic, closure panicked with: panicked with: oops"
+        );
+    }
+
+    #[test]
+    fn test_expect_message() {
+        let mut files = VirtualFileProvider::new();
+        let library = LazyHash::new(augmented_default_library());
+
+        let source = Source::detached(
+            r#"
+            #expect-message(() => panic("oops"), message: "panicked with: oops")
+            #expect-message(() => panic("oops"), message: "regex:^panicked with: o+ps")
+        "#,
+        );
+
+        let world = test_utils::virtual_world(source, &mut files, &library);
+
+        compile::compile(&world, Warnings::Emit).output.unwrap();
+    }
+
+    #[test]
+    fn test_expect_message_mismatch() {
+        let mut files = VirtualFileProvider::new();
+        let library = LazyHash::new(augmented_default_library());
+
+        let source = Source::detached(
+            r#"
+            #expect-message(() => panic("oops"), message: "panicked")
+        "#,
+        );
+
+        let world = test_utils::virtual_world(source, &mut files, &library);
+
+        let errors = compile::compile(&world, Warnings::Emit).output.unwrap_err();
+        assert!(
+            errors.0[0]
+                .message
+                .starts_with("panic message did not match")
+        );
+        assert!(
+            errors.0[0]
+                .message
+                .contains("found:    \"panicked with: oops\"")
+        );
+    }
+
+    #[test]
+    fn test_expect_message_no_panic() {
+        let mut files = VirtualFileProvider::new();
+        let library = LazyHash::new(augmented_default_library());
+
+        let source = Source::detached(
+            r#"
+            #expect-message(() => {}, message: "regex:.*")
+        "#,
+        );
+
+        let world = test_utils::virtual_world(source, &mut files, &library);
+
+        let errors = compile::compile(&world, Warnings::Emit).output.unwrap_err();
+        assert!(
+            errors.0[0]
+                .message
+                .ends_with("closure returned successfully")
+        );
+    }
+
+    #[test]
+    fn test_assert_approx_eq() {
+        let mut files = VirtualFileProvider::new();
+        let library = LazyHash::new(augmented_default_library());
+
+        let source = Source::detached(
+            r#"
+            #assert-approx-eq(0.1 + 0.2, 0.3)
+            #assert-approx-eq(1, 1.0)
+            #assert-approx-eq(10pt / 3, 3.33pt, tolerance: 0.01)
+            #assert-approx-eq(1em + 1pt, 1em + 1.0000001pt)
+            #assert-approx-eq(100% / 3, 33.3%, tolerance: 0.001)
+            #assert-approx-eq(90deg, calc.pi / 2 * 1rad)
+        "#,
+        );
+
+        let world = test_utils::virtual_world(source, &mut files, &library);
+
+        compile::compile(&world, Warnings::Emit).output.unwrap();
+    }
+
+    #[test]
+    fn test_assert_approx_eq_fails() {
+        let mut files = VirtualFileProvider::new();
+        let library = LazyHash::new(augmented_default_library());
+
+        let source = Source::detached(
+            r#"
+            #assert-approx-eq(10pt / 3, 3.33pt)
+        "#,
+        );
+
+        let world = test_utils::virtual_world(source, &mut files, &library);
+
+        let errors = compile::compile(&world, Warnings::Emit).output.unwrap_err();
+        assert!(
+            errors.0[0]
+                .message
+                .starts_with("approximate equality assertion failed")
+        );
+    }
+
+    #[test]
+    fn test_assert_approx_eq_type_mismatch() {
+        let mut files = VirtualFileProvider::new();
+        let library = LazyHash::new(augmented_default_library());
+
+        let source = Source::detached(
+            r#"
+            #assert-approx-eq(1pt, 1deg)
+        "#,
+        );
+
+        let world = test_utils::virtual_world(source, &mut files, &library);
+
+        let errors = compile::compile(&world, Warnings::Emit).output.unwrap_err();
+        assert_eq!(
+            errors.0[0].message,
+            "cannot approximately compare length and angle"
+        );
+    }
 }
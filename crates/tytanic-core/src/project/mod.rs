@@ -9,6 +9,7 @@ use camino::Utf8Component;
 use camino::Utf8Path;
 use camino::Utf8PathBuf;
 use ecow::EcoString;
+use ecow::eco_format;
 use serde::Deserialize;
 use thiserror::Error;
 use typst::syntax::FileId;
@@ -23,6 +24,7 @@ use tytanic_utils::result::io_not_found;
 use crate::TOOL_NAME;
 use crate::config::ProjectConfig;
 use crate::test::Id;
+use crate::world_builder::lock::LOCKFILE_NAME;
 
 pub mod vcs;
 
@@ -33,6 +35,10 @@ pub use vcs::Vcs;
 /// automatically.
 pub const MANIFEST_FILE: &str = "typst.toml";
 
+/// The name of the reference variant directory of the current platform, see
+/// [`Project::unit_test_ref_variant_dir`].
+pub const REFERENCE_PLATFORM: &str = std::env::consts::OS;
+
 /// Represents a "shallow" unloaded project, it contains the base paths required
 /// to load a project.
 #[derive(Debug, Clone)]
@@ -156,7 +162,7 @@ impl ShallowProject {
             .transpose()?;
 
         if let Some(config) = &config {
-            validate_config(&self.root, config)?;
+            validate_config(&self.root, manifest, config)?;
         }
 
         Ok(config)
@@ -190,6 +196,11 @@ impl ShallowProject {
         self.root.join(MANIFEST_FILE)
     }
 
+    /// Returns the path to the package lockfile (`tytanic.lock`).
+    pub fn lockfile(&self) -> Utf8PathBuf {
+        self.root.join(LOCKFILE_NAME)
+    }
+
     /// Returns the path to the VCS root.
     ///
     /// The VCS root is used for properly handling non-persistent storage of
@@ -307,6 +318,30 @@ impl Project {
         self.root().join(&self.config.unit_tests_root)
     }
 
+    /// Returns the path to the cache directory within the test root.
+    ///
+    /// This directory is hidden and therefore never collected as a test.
+    pub fn cache_root(&self) -> Utf8PathBuf {
+        let mut dir = self.unit_tests_root();
+        dir.push(".cache");
+        dir
+    }
+
+    /// Returns the path to the file in which the outcome of the last run is
+    /// stored, see [`LastRun`][crate::suite::LastRun].
+    pub fn last_run_file(&self) -> Utf8PathBuf {
+        let mut file = self.cache_root();
+        file.push("last-run.toml");
+        file
+    }
+
+    /// Returns the path to the directory of the comparison cache.
+    pub fn compare_cache_dir(&self) -> Utf8PathBuf {
+        let mut dir = self.cache_root();
+        dir.push("compare");
+        dir
+    }
+
     /// Returns the root path of the template directory.
     pub fn template_root(&self) -> Option<Utf8PathBuf> {
         self.manifest
@@ -364,6 +399,44 @@ impl Project {
         dir
     }
 
+    /// Create a path to the reference variant directory of the current
+    /// platform for the given identifier, see [`REFERENCE_PLATFORM`].
+    pub fn unit_test_ref_variant_dir(&self, id: &Id) -> Utf8PathBuf {
+        let mut dir = self.unit_test_ref_dir(id);
+        dir.push(REFERENCE_PLATFORM);
+        dir
+    }
+
+    /// Returns the directory from which the persistent references of the given
+    /// identifier are read.
+    ///
+    /// If reference variants are enabled and the current platform has its own
+    /// references, this is its variant directory, otherwise it is the
+    /// reference directory.
+    pub fn unit_test_persistent_ref_dir(&self, id: &Id) -> Utf8PathBuf {
+        if self.config().ref_variants {
+            let variant = self.unit_test_ref_variant_dir(id);
+            if variant.is_dir() {
+                return variant;
+            }
+        }
+
+        self.unit_test_ref_dir(id)
+    }
+
+    /// Returns the directory into which the persistent references of the given
+    /// identifier are written.
+    ///
+    /// If reference variants are enabled, this is the variant directory of the
+    /// current platform, otherwise it is the reference directory.
+    pub fn unit_test_persistent_ref_target_dir(&self, id: &Id) -> Utf8PathBuf {
+        if self.config().ref_variants {
+            self.unit_test_ref_variant_dir(id)
+        } else {
+            self.unit_test_ref_dir(id)
+        }
+    }
+
     /// Create a path to the output directory for the given identifier.
     pub fn unit_test_out_dir(&self, id: &Id) -> Utf8PathBuf {
         let mut dir = self.unit_test_dir(id);
@@ -494,9 +567,21 @@ fn validate_manifest(root: &Utf8Path, manifest: &PackageManifest) -> Result<(),
     Ok(())
 }
 
-fn validate_config(root: &Utf8Path, config: &ProjectConfig) -> Result<(), ValidationError> {
+fn validate_config(
+    root: &Utf8Path,
+    manifest: &PackageManifest,
+    config: &ProjectConfig,
+) -> Result<(), ValidationError> {
     let ProjectConfig {
         unit_tests_root,
+        ref_format: _,
+        ref_pattern: _,
+        ref_meta: _,
+        ref_variants: _,
+        optimize_refs: _,
+        template_entrypoints,
+        export_format: _,
+        font_paths,
         defaults: _,
     } = config;
 
@@ -525,6 +610,72 @@ fn validate_config(root: &Utf8Path, config: &ProjectConfig) -> Result<(), Valida
         }
     }
 
+    for (idx, font_path) in font_paths.iter().enumerate() {
+        let key = eco_format!("font-paths[{idx}]");
+
+        if !is_trivial_path(font_path.as_str()) {
+            error.errors.insert(
+                key,
+                ValidationErrorCause::NonTrivialPath {
+                    field: font_path.into(),
+                },
+            );
+        } else {
+            let path = root.join(font_path);
+
+            if !path.exists() {
+                error.errors.insert(
+                    key,
+                    ValidationErrorCause::DoesNotExist {
+                        field: font_path.into(),
+                        resolved: path,
+                    },
+                );
+            }
+        }
+    }
+
+    for (idx, entrypoint) in template_entrypoints.iter().enumerate() {
+        let key = eco_format!("template-entrypoints[{idx}]");
+
+        let Some(template) = &manifest.template else {
+            error
+                .errors
+                .insert(key, ValidationErrorCause::MissingTemplate);
+            continue;
+        };
+
+        let path = Utf8Path::new(entrypoint);
+        if !is_trivial_path(path) {
+            error.errors.insert(
+                key,
+                ValidationErrorCause::NonTrivialPath {
+                    field: entrypoint.into(),
+                },
+            );
+        } else if !path.file_stem().is_some_and(Id::is_component_valid) {
+            error.errors.insert(
+                key,
+                ValidationErrorCause::InvalidEntrypointName {
+                    field: entrypoint.into(),
+                },
+            );
+        } else {
+            let mut resolved = root.join(template.path.as_str());
+            resolved.push(path);
+
+            if !resolved.exists() {
+                error.errors.insert(
+                    key,
+                    ValidationErrorCause::DoesNotExist {
+                        field: entrypoint.into(),
+                        resolved,
+                    },
+                );
+            }
+        }
+    }
+
     if !error.errors.is_empty() {
         return Err(error);
     }
@@ -584,6 +735,18 @@ pub enum ValidationErrorCause {
         /// The field as it was resolved.
         resolved: Utf8PathBuf,
     },
+
+    /// A template entrypoint was configured, but the manifest has no template.
+    #[error("the manifest has no template section")]
+    MissingTemplate,
+
+    /// The file stem of a template entrypoint was not a valid test id
+    /// component.
+    #[error("the entrypoint name was not a valid test id component: {field:?}")]
+    InvalidEntrypointName {
+        /// The field as it was set in the config.
+        field: EcoString,
+    },
 }
 
 /// Returned by [`ShallowProject::parse_config`].
@@ -690,13 +853,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_unit_test_ref_variants() {
+        TempTestEnv::run_no_check(
+            |root| root.setup_dir("tests/a/ref"),
+            |root| {
+                let id = Id::new("a").unwrap();
+                let ref_dir = root.join("tests/a/ref");
+                let variant_dir = ref_dir.join(REFERENCE_PLATFORM);
+
+                let project = Project::new(root);
+                assert_eq!(project.unit_test_persistent_ref_dir(&id), ref_dir);
+                assert_eq!(project.unit_test_persistent_ref_target_dir(&id), ref_dir);
+
+                let project = Project::new(root).with_config(ProjectConfig {
+                    ref_variants: true,
+                    ..Default::default()
+                });
+                assert_eq!(project.unit_test_persistent_ref_dir(&id), ref_dir);
+                assert_eq!(
+                    project.unit_test_persistent_ref_target_dir(&id),
+                    variant_dir
+                );
+
+                std::fs::create_dir(&variant_dir).unwrap();
+                assert_eq!(project.unit_test_persistent_ref_dir(&id), variant_dir);
+            },
+        );
+    }
+
     #[test]
     fn test_validation_default() {
         TempTestEnv::run_no_check(
             |root| root.setup_dir("tests"),
             |root| {
+                let manifest = PackageManifestBuilder::new().build();
                 let config = ProjectConfig::default();
-                validate_config(root, &config).unwrap();
+                validate_config(root, &manifest, &config).unwrap();
             },
         );
     }
@@ -721,7 +914,7 @@ mod tests {
                 };
 
                 validate_manifest(root, &manifest).unwrap();
-                validate_config(root, &config).unwrap();
+                validate_config(root, &manifest, &config).unwrap();
             },
         );
     }
@@ -745,8 +938,8 @@ mod tests {
                     ..Default::default()
                 };
 
+                let config = validate_config(root, &manifest, &config).unwrap_err();
                 let manifest = validate_manifest(root, &manifest).unwrap_err();
-                let config = validate_config(root, &config).unwrap_err();
 
                 assert_eq!(manifest.errors.len(), 2);
                 assert_eq!(config.errors.len(), 1);
@@ -786,8 +979,8 @@ mod tests {
                     ..Default::default()
                 };
 
+                let config = validate_config(root, &manifest, &config).unwrap_err();
                 let manifest = validate_manifest(root, &manifest).unwrap_err();
-                let config = validate_config(root, &config).unwrap_err();
 
                 assert_eq!(manifest.errors.len(), 2);
                 assert_eq!(config.errors.len(), 1);
@@ -816,4 +1009,95 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn test_validation_template_entrypoints() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_dir("tests")
+                    .setup_file_empty("foo/bar.typ")
+                    .setup_file_empty("foo/article.typ")
+            },
+            |root| {
+                let config = ProjectConfig {
+                    template_entrypoints: vec![
+                        "article.typ".into(),
+                        "../article.typ".into(),
+                        "1st.typ".into(),
+                        "thesis.typ".into(),
+                    ],
+                    ..Default::default()
+                };
+
+                let without_template = PackageManifestBuilder::new().build();
+                let errors = validate_config(root, &without_template, &config).unwrap_err();
+                assert_eq!(errors.errors.len(), 4);
+                assert_eq!(
+                    errors.errors.get("template-entrypoints[0]").unwrap(),
+                    &ValidationErrorCause::MissingTemplate
+                );
+
+                let manifest = PackageManifestBuilder::new()
+                    .template(
+                        TemplateInfoBuilder::new()
+                            .path("foo")
+                            .entrypoint("bar.typ")
+                            .build(),
+                    )
+                    .build();
+
+                let errors = validate_config(root, &manifest, &config).unwrap_err();
+                assert_eq!(errors.errors.len(), 3);
+                assert_eq!(
+                    errors.errors.get("template-entrypoints[1]").unwrap(),
+                    &ValidationErrorCause::NonTrivialPath {
+                        field: "../article.typ".into()
+                    }
+                );
+                assert_eq!(
+                    errors.errors.get("template-entrypoints[2]").unwrap(),
+                    &ValidationErrorCause::InvalidEntrypointName {
+                        field: "1st.typ".into()
+                    }
+                );
+                assert_eq!(
+                    errors.errors.get("template-entrypoints[3]").unwrap(),
+                    &ValidationErrorCause::DoesNotExist {
+                        field: "thesis.typ".into(),
+                        resolved: root.join("foo/thesis.typ")
+                    }
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_validation_font_paths() {
+        TempTestEnv::run_no_check(
+            |root| root.setup_dir("tests").setup_dir("fonts"),
+            |root| {
+                let manifest = PackageManifestBuilder::new().build();
+                let config = ProjectConfig {
+                    font_paths: vec!["fonts".into(), "../fonts".into(), "missing".into()],
+                    ..Default::default()
+                };
+
+                let errors = validate_config(root, &manifest, &config).unwrap_err();
+                assert_eq!(errors.errors.len(), 2);
+                assert_eq!(
+                    errors.errors.get("font-paths[1]").unwrap(),
+                    &ValidationErrorCause::NonTrivialPath {
+                        field: "../fonts".into()
+                    }
+                );
+                assert_eq!(
+                    errors.errors.get("font-paths[2]").unwrap(),
+                    &ValidationErrorCause::DoesNotExist {
+                        field: "missing".into(),
+                        resolved: root.join("missing")
+                    }
+                );
+            },
+        );
+    }
 }
@@ -3,6 +3,7 @@
 //! VCS support is used to ensure temporary artifacts and test output is not
 //! persisted by the VCS. When Tytanic is used within a Git repository for
 //! example, the temporary artifacts should most likely not be tracked by Git.
+//! It is also used to find the files which changed since a base revision.
 //!
 //! Tytanic only supports VCS which support `.gitignore`-like ignore files. This
 //! means that mercurial is not supported, because its `.hgignore` is only read
@@ -27,9 +28,11 @@
 //! # Ok::<_, Box<dyn std::error::Error>>(())
 //! ```
 
+use std::collections::BTreeSet;
 use std::fmt::Display;
 use std::fs;
 use std::io;
+use std::process::Command;
 
 use camino::Utf8Path;
 use camino::Utf8PathBuf;
@@ -214,6 +217,108 @@ impl Vcs {
 
         inner(self, dir.as_ref())
     }
+
+    /// Whether the given directory was ignored using
+    /// [`Vcs::ignore_directory`].
+    ///
+    /// This only checks for the generated ignore file within the directory,
+    /// it does not check whether the directory is ignored by other means.
+    ///
+    /// # Panics
+    /// Panics if it isn't an absolute path.
+    pub fn is_directory_ignored<P>(&self, dir: P) -> io::Result<bool>
+    where
+        P: AsRef<Utf8Path>,
+    {
+        fn inner(vcs: &Vcs, dir: &Utf8Path) -> io::Result<bool> {
+            assert!(dir.is_absolute());
+
+            let name = vcs.kind().ignore_filename();
+            dir.join(name).try_exists()
+        }
+
+        inner(self, dir.as_ref())
+    }
+}
+
+impl Vcs {
+    /// Queries the VCS for the files which differ between the given base
+    /// revision and the working copy, the returned paths are absolute.
+    ///
+    /// If no base is given, the parent of the working copy is used, i.e.
+    /// `HEAD` for git, `.` for Sapling and `@-` for Jujutsu. For git, untracked
+    /// files which are not ignored are included as well.
+    ///
+    /// # Errors
+    /// Returns an error if the base is not a valid revision, see
+    /// [`validate_base`], if the VCS has no root set, if the VCS executable
+    /// could not be run or if it exited unsuccessfully.
+    pub fn changed_files(
+        &self,
+        base: Option<&str>,
+    ) -> Result<BTreeSet<Utf8PathBuf>, ChangedFilesError> {
+        if let Some(base) = base {
+            validate_base(base)?;
+        }
+
+        let root = self.root().ok_or(ChangedFilesError::NoRoot)?;
+
+        let mut changed = BTreeSet::new();
+        let mut collect = |program: &str, args: &[&str]| -> Result<(), ChangedFilesError> {
+            let output = Command::new(program)
+                .args(args)
+                .current_dir(root)
+                .output()?;
+
+            if !output.status.success() {
+                return Err(ChangedFilesError::Command {
+                    program: program.into(),
+                    stderr: String::from_utf8_lossy(&output.stderr).trim().into(),
+                });
+            }
+
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            changed.extend(
+                stdout
+                    .split(['\0', '\n'])
+                    .filter(|line| !line.is_empty())
+                    .map(|line| root.join(line)),
+            );
+
+            Ok(())
+        };
+
+        match self.kind {
+            Kind::Git => {
+                let base = base.unwrap_or("HEAD");
+                collect("git", &["diff", "--name-only", "-z", base, "--"])?;
+                collect("git", &["ls-files", "--others", "--exclude-standard", "-z"])?;
+            }
+            Kind::Sapling => {
+                let base = base.unwrap_or(".");
+                collect("sl", &["status", "--rev", base, "-mar", "--no-status"])?;
+            }
+            Kind::Jujutsu => {
+                let base = base.unwrap_or("@-");
+                collect("jj", &["diff", "--name-only", "--from", base, "--to", "@"])?;
+            }
+        }
+
+        Ok(changed)
+    }
+}
+
+/// Ensures that the given base revision for [`Vcs::changed_files`] can't be
+/// mistaken for an option by the VCS executable.
+///
+/// # Errors
+/// Returns an error if the base starts with a `-`.
+pub fn validate_base(base: &str) -> Result<(), ChangedFilesError> {
+    if base.starts_with('-') {
+        return Err(ChangedFilesError::InvalidBase(base.into()));
+    }
+
+    Ok(())
 }
 
 /// Returned by [`Vcs::ignore_directory`].
@@ -224,6 +329,32 @@ pub enum IgnoreDirectoryError {
     Io(#[from] io::Error),
 }
 
+/// Returned by [`Vcs::changed_files`].
+#[derive(Debug, Error)]
+pub enum ChangedFilesError {
+    /// The base revision starts with a `-`.
+    #[error("the base revision {0:?} must not start with a `-`")]
+    InvalidBase(String),
+
+    /// The VCS has no root to query.
+    #[error("the VCS has no root")]
+    NoRoot,
+
+    /// The VCS executable exited unsuccessfully.
+    #[error("{program} exited unsuccessfully: {stderr}")]
+    Command {
+        /// The VCS executable which was run.
+        program: String,
+
+        /// The trimmed error output of the executable.
+        stderr: String,
+    },
+
+    /// An IO error occurred.
+    #[error("an IO error occurred")]
+    Io(#[from] io::Error),
+}
+
 #[cfg(test)]
 mod tests {
     use tytanic_utils::fs::TempTestEnv;
@@ -263,6 +394,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_changed_files_no_root() {
+        let vcs = Vcs::new_rootless(Kind::Git);
+        assert!(matches!(
+            vcs.changed_files(None),
+            Err(ChangedFilesError::NoRoot)
+        ));
+    }
+
+    #[test]
+    fn test_changed_files_dash_base() {
+        TempTestEnv::run_no_check(
+            |root| root.setup_dir(".git"),
+            |root| {
+                let vcs = Vcs::new(root, Kind::Git);
+                assert!(matches!(
+                    vcs.changed_files(Some("--output=/tmp/x")),
+                    Err(ChangedFilesError::InvalidBase(base)) if base == "--output=/tmp/x"
+                ));
+            },
+        );
+    }
+
     #[test]
     fn test_git_ignore_create() {
         TempTestEnv::run(
@@ -3,14 +3,20 @@
 //! necessary for managing, filtering, and running tests.
 
 use camino::Utf8Path;
+use camino::Utf8PathBuf;
 use chrono::DateTime;
 use chrono::TimeDelta;
 use chrono::Utc;
+use ecow::EcoString;
+use ecow::EcoVec;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::collections::btree_map;
+use std::fs;
 use std::io;
 
+use serde::Deserialize;
+use serde::Serialize;
 use thiserror::Error;
 use tytanic_utils::result::ResultEx;
 use tytanic_utils::result::io_not_found;
@@ -18,10 +24,15 @@ use uuid::Uuid;
 
 use crate::Project;
 use crate::TemplateTest;
+use crate::config;
+use crate::config::DIRECTORY_CONFIG_FILE;
+use crate::config::DirectoryConfig;
 use crate::filter::Filter;
 use crate::filter::FilterState;
+use crate::test::Annotation;
 use crate::test::Id;
 use crate::test::ParseIdError;
+use crate::test::Stage;
 use crate::test::Test;
 use crate::test::TestResult;
 use crate::test::UnitTest;
@@ -32,6 +43,8 @@ use crate::test::unit::LoadError;
 pub struct Suite {
     tests: BTreeMap<Id, Test>,
     nested: BTreeMap<Id, Test>,
+    invalid: BTreeSet<Utf8PathBuf>,
+    skipped: BTreeSet<Utf8PathBuf>,
 }
 
 impl Suite {
@@ -40,6 +53,8 @@ impl Suite {
         Self {
             tests: BTreeMap::new(),
             nested: BTreeMap::new(),
+            invalid: BTreeSet::new(),
+            skipped: BTreeSet::new(),
         }
     }
 
@@ -51,16 +66,34 @@ impl Suite {
         Self {
             tests: tests.into_iter().map(|t| (t.id().clone(), t)).collect(),
             nested: BTreeMap::new(),
+            invalid: BTreeSet::new(),
+            skipped: BTreeSet::new(),
         }
     }
 
     /// Recursively collects entries in the given directory.
     #[tracing::instrument(skip_all)]
     pub fn collect(project: &Project) -> Result<Self, Error> {
+        Self::collect_inner(project, &|_| true)
+    }
+
+    /// Recursively collects entries in the given directory like
+    /// [`Suite::collect`], but skips directories for which
+    /// [`Filter::may_match`] returns `false`.
+    ///
+    /// Tests below skipped directories are not loaded at all, they are
+    /// therefore neither matched nor filtered once the filter is applied
+    /// using [`Suite::filter`]. Template tests are always collected.
+    #[tracing::instrument(skip_all)]
+    pub fn collect_pruned<F: Filter>(project: &Project, filter: &F) -> Result<Self, Error> {
+        Self::collect_inner(project, &|id| filter.may_match(id))
+    }
+
+    fn collect_inner(project: &Project, prune: &dyn Fn(&Id) -> bool) -> Result<Self, Error> {
         let mut this = Self::new();
 
-        if let Some(test) = TemplateTest::load(project) {
-            tracing::debug!("found template test");
+        for test in TemplateTest::load(project) {
+            tracing::debug!(id = %test.id(), "found template test");
             this.tests.insert(test.id().clone(), Test::Template(test));
         }
 
@@ -80,7 +113,7 @@ impl Suite {
                     .strip_prefix(project.unit_tests_root())
                     .expect("entry must be in full");
 
-                this.collect_dir(project, rel)?;
+                this.collect_dir(project, rel, false, &[], prune)?;
             }
         }
 
@@ -111,7 +144,19 @@ impl Suite {
     }
 
     /// Recursively collect tests in the given directory.
-    fn collect_dir(&mut self, project: &Project, dir: &Utf8Path) -> Result<(), Error> {
+    ///
+    /// Directories without a test and sub directories are recorded as skipped
+    /// unless they are inside a test, like reference directories. The
+    /// annotations of the directory configs of all parent directories are
+    /// given as `inherited`, those of deeper directories take precedence.
+    fn collect_dir(
+        &mut self,
+        project: &Project,
+        dir: &Utf8Path,
+        in_test: bool,
+        inherited: &[Annotation],
+        prune: &dyn Fn(&Id) -> bool,
+    ) -> Result<(), Error> {
         let abs = project.unit_tests_root().join(dir);
 
         if dir.file_name().is_some_and(|p| p.starts_with('.')) {
@@ -123,30 +168,71 @@ impl Suite {
             Ok(id) => id,
             Err(err) => {
                 tracing::error!(?dir, ?err, "ignoring test with invalid id");
+                self.invalid.insert(dir.to_owned());
                 return Ok(());
             }
         };
 
-        tracing::trace!(?dir, "checking for test");
-        if let Some(test) = UnitTest::load(project, id.clone())? {
-            tracing::debug!(id = %test.id(), "collected test");
-            self.tests.insert(id, Test::Unit(test));
+        if !prune(&id) {
+            tracing::trace!(?dir, "skipping directory which can't match the filter");
+            return Ok(());
         }
 
+        let inherited = match DirectoryConfig::read(&abs)
+            .and_then(|config| config.map(|config| config.annotations()).transpose())
+        {
+            Ok(Some(annotations)) => {
+                tracing::debug!(?dir, "found directory config");
+                let mut merged = inherited
+                    .iter()
+                    .filter(|annot| !annotations.iter().any(|own| own.conflicts_with(annot)))
+                    .cloned()
+                    .collect::<EcoVec<_>>();
+                merged.extend(annotations);
+                merged
+            }
+            Ok(None) => EcoVec::from(inherited),
+            Err(error) => {
+                return Err(Error::DirectoryConfig {
+                    path: dir.join(DIRECTORY_CONFIG_FILE),
+                    error,
+                });
+            }
+        };
+
+        tracing::trace!(?dir, "checking for test");
+        let is_test = match UnitTest::load(project, id.clone())? {
+            Some(mut test) => {
+                test.inherit_annotations(&inherited);
+                tracing::debug!(id = %test.id(), "collected test");
+                self.tests.insert(id, Test::Unit(test));
+                true
+            }
+            None => false,
+        };
+
         tracing::trace!(?dir, "collecting sub directories");
+        let mut has_sub_dirs = false;
         for entry in abs.read_dir_utf8()? {
             let entry = entry?;
 
             if entry.metadata()?.is_dir() {
+                has_sub_dirs = true;
+
                 let abs = entry.path();
                 let rel = abs
                     .strip_prefix(project.unit_tests_root())
                     .expect("entry must be in full");
 
-                self.collect_dir(project, rel)?;
+                self.collect_dir(project, rel, in_test || is_test, &inherited, prune)?;
             }
         }
 
+        if !is_test && !has_sub_dirs && !in_test {
+            tracing::debug!(?dir, "skipping directory without test");
+            self.skipped.insert(dir.to_owned());
+        }
+
         Ok(())
     }
 }
@@ -164,7 +250,7 @@ impl Suite {
         UnitTests { iter: self.tests() }
     }
 
-    /// The template test, if it exists.
+    /// The template test of the manifest entrypoint, if it exists.
     pub fn template_test(&self) -> Option<&TemplateTest> {
         self.tests.get(&Id::template()).map(|test| {
             test.as_template_test()
@@ -172,6 +258,12 @@ impl Suite {
         })
     }
 
+    /// The template tests in this suite, this includes the template test of
+    /// the manifest entrypoint and those of additional template entrypoints.
+    pub fn template_tests(&self) -> TemplateTests<'_> {
+        TemplateTests { iter: self.tests() }
+    }
+
     /// The nested tests, those which contain other tests and need to be
     /// migrated.
     ///
@@ -180,6 +272,22 @@ impl Suite {
         &self.nested
     }
 
+    /// The directories which were ignored during collection because their
+    /// path is not a valid test identifier, relative to the unit test root.
+    pub fn invalid(&self) -> &BTreeSet<Utf8PathBuf> {
+        &self.invalid
+    }
+
+    /// The directories which were skipped during collection because they
+    /// contain neither a test nor any sub directories, relative to the unit
+    /// test root.
+    ///
+    /// Directories inside of tests, like reference directories, are not
+    /// included.
+    pub fn skipped(&self) -> &BTreeSet<Utf8PathBuf> {
+        &self.skipped
+    }
+
     /// Returns the test with the given id.
     pub fn get(&self, id: &Id) -> Option<&Test> {
         self.tests.get(id)
@@ -285,6 +393,26 @@ impl<'s> Iterator for UnitTests<'s> {
     }
 }
 
+/// Returned by [`Suite::template_tests`].
+#[derive(Debug)]
+pub struct TemplateTests<'s> {
+    iter: Tests<'s>,
+}
+
+impl<'s> Iterator for TemplateTests<'s> {
+    type Item = &'s TemplateTest;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for test in self.iter.by_ref() {
+            if let Test::Template(test) = test {
+                return Some(test);
+            }
+        }
+
+        None
+    }
+}
+
 /// A suite of tests with a filter applied to it.
 #[derive(Debug, Clone)]
 pub struct FilteredSuite<F> {
@@ -327,6 +455,17 @@ pub enum Error {
     #[error("an error occurred while collecting a test")]
     Test(#[from] LoadError),
 
+    /// A directory config is invalid.
+    #[error("the directory config {path} is invalid")]
+    DirectoryConfig {
+        /// The path of the config relative to the unit test root.
+        path: Utf8PathBuf,
+
+        /// The reason it is invalid.
+        #[source]
+        error: config::Error,
+    },
+
     /// An IO error occurred.
     #[error("an io error occurred")]
     Io(#[from] io::Error),
@@ -341,9 +480,15 @@ pub struct SuiteResult {
     total: usize,
     filtered: usize,
     passed: usize,
+    flaky: usize,
     failed: usize,
+    cancelled: usize,
+    updated: usize,
+    warned: usize,
+    warnings: usize,
     timestamp: DateTime<Utc>,
     duration: TimeDelta,
+    metadata: BTreeMap<EcoString, EcoString>,
     results: BTreeMap<Id, TestResult>,
 }
 
@@ -352,14 +497,26 @@ impl SuiteResult {
     /// all test set to canceled, these results can be overridden while running
     /// the suite.
     pub fn new<F>(suite: &FilteredSuite<F>) -> Self {
+        Self::with_id(suite, Uuid::new_v4())
+    }
+
+    /// Create a fresh result for a suite like [`SuiteResult::new`], but with
+    /// the given run id instead of a random one.
+    pub fn with_id<F>(suite: &FilteredSuite<F>, id: Uuid) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id,
             total: suite.inner().len(),
             filtered: suite.filtered().len(),
             passed: 0,
+            flaky: 0,
             failed: 0,
+            cancelled: 0,
+            updated: 0,
+            warned: 0,
+            warnings: 0,
             timestamp: Utc::now(),
             duration: TimeDelta::zero(),
+            metadata: BTreeMap::new(),
             results: suite
                 .matched()
                 .tests()
@@ -402,10 +559,16 @@ impl SuiteResult {
         self.filtered
     }
 
-    /// The number of tests in the suite which were _not_ run due to
-    /// cancellation.
+    /// The number of tests in the suite which were _not_ run for reasons
+    /// other than cancellation, like aborting after the first failure.
     pub fn skipped(&self) -> usize {
-        self.expected() - self.run()
+        self.expected() - self.run() - self.cancelled
+    }
+
+    /// The number of tests in the suite which were _not_ run because the run
+    /// was cancelled, see [`SuiteResult::set_cancelled`].
+    pub fn cancelled(&self) -> usize {
+        self.cancelled
     }
 
     /// The number of tests in the suite which passed.
@@ -413,11 +576,34 @@ impl SuiteResult {
         self.passed
     }
 
+    /// The number of tests in the suite which passed only after being run
+    /// again, these are included in [`SuiteResult::passed`].
+    pub fn flaky(&self) -> usize {
+        self.flaky
+    }
+
     /// The number of tests in the suite which failed.
     pub fn failed(&self) -> usize {
         self.failed
     }
 
+    /// The number of tests in the suite whose references were updated, these
+    /// are included in [`SuiteResult::passed`].
+    pub fn updated(&self) -> usize {
+        self.updated
+    }
+
+    /// The number of tests in the suite which emitted compiler warnings.
+    pub fn warned(&self) -> usize {
+        self.warned
+    }
+
+    /// The total number of compiler warnings emitted by the tests in the
+    /// suite, promoted warnings are counted as errors instead.
+    pub fn warnings(&self) -> usize {
+        self.warnings
+    }
+
     /// The timestamp at which the suite run started.
     pub fn timestamp(&self) -> DateTime<Utc> {
         self.timestamp
@@ -428,6 +614,12 @@ impl SuiteResult {
         self.duration
     }
 
+    /// The user supplied metadata of this run, such as the branch or CI job it
+    /// ran for.
+    pub fn metadata(&self) -> &BTreeMap<EcoString, EcoString> {
+        &self.metadata
+    }
+
     /// The individual test results.
     ///
     /// This contains results for all tests in the a suite, not just those added
@@ -443,6 +635,11 @@ impl SuiteResult {
 }
 
 impl SuiteResult {
+    /// Sets the metadata of this run, replacing any previous metadata.
+    pub fn set_metadata(&mut self, metadata: BTreeMap<EcoString, EcoString>) {
+        self.metadata = metadata;
+    }
+
     /// Sets the timestamp to [`Utc::now`].
     ///
     /// See [`SuiteResult::end`].
@@ -456,6 +653,17 @@ impl SuiteResult {
         self.duration = Utc::now().signed_duration_since(self.timestamp);
     }
 
+    /// Marks all tests which were not started as cancelled, this should be
+    /// called once the run was cancelled and all started tests finished.
+    pub fn set_cancelled(&mut self) {
+        for result in self.results.values_mut() {
+            if result.is_skipped() {
+                result.set_cancelled();
+                self.cancelled += 1;
+            }
+        }
+    }
+
     /// Add a test result.
     ///
     /// - This should only add results for each test once, otherwise the test
@@ -467,8 +675,19 @@ impl SuiteResult {
         debug_assert!(self.results.contains_key(&id));
         debug_assert!(result.is_pass() || result.is_fail());
 
+        if !result.warnings().is_empty() {
+            self.warned += 1;
+            self.warnings += result.warnings().len();
+        }
+
         if result.is_pass() {
             self.passed += 1;
+            if matches!(result.stage(), Stage::Updated { .. }) {
+                self.updated += 1;
+            }
+            if result.is_flaky() {
+                self.flaky += 1;
+            }
         } else {
             self.failed += 1;
         }
@@ -477,12 +696,95 @@ impl SuiteResult {
     }
 }
 
+/// The outcome of each test when it was last run, this is stored in the cache
+/// of a project after each run, see [`Project::last_run_file`].
+///
+/// Tests which were not run keep the status they had before, such that a run
+/// of only some tests doesn't discard the history of all others.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LastRun {
+    tests: BTreeMap<String, LastStatus>,
+}
+
+/// The outcome of a test when it was last run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LastStatus {
+    /// The test passed, this includes flaky and updated tests.
+    Passed,
+
+    /// The test failed.
+    Failed,
+}
+
+impl LastRun {
+    /// Reads the last run of the given project, returns `None` if no run was
+    /// recorded yet.
+    pub fn read(project: &Project) -> Result<Option<Self>, LastRunError> {
+        let Some(content) = fs::read_to_string(project.last_run_file()).ignore(io_not_found)?
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(toml::from_str(&content)?))
+    }
+
+    /// Writes this run to the cache of the given project.
+    pub fn write(&self, project: &Project) -> Result<(), LastRunError> {
+        fs::create_dir_all(project.cache_root())?;
+        fs::write(project.last_run_file(), toml::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    /// Records the outcome of all tests which passed or failed in the given
+    /// result.
+    pub fn record(&mut self, result: &SuiteResult) {
+        for (id, result) in result.results() {
+            let status = if result.is_pass() {
+                LastStatus::Passed
+            } else if result.is_fail() {
+                LastStatus::Failed
+            } else {
+                continue;
+            };
+
+            self.tests.insert(id.as_str().into(), status);
+        }
+    }
+
+    /// The status of the given test when it was last run, returns `None` if
+    /// it was never run.
+    pub fn status(&self, id: &Id) -> Option<LastStatus> {
+        self.tests.get(id.as_str()).copied()
+    }
+}
+
+/// Returned by [`LastRun::read`] and [`LastRun::write`].
+#[derive(Debug, Error)]
+pub enum LastRunError {
+    /// The stored run couldn't be parsed.
+    #[error("couldn't parse the last run")]
+    Parse(#[from] toml::de::Error),
+
+    /// The run couldn't be serialized.
+    #[error("couldn't serialize the last run")]
+    Serialize(#[from] toml::ser::Error),
+
+    /// An IO error occurred.
+    #[error("an io error occurred")]
+    Io(#[from] io::Error),
+}
+
 #[cfg(test)]
 mod tests {
     use ecow::eco_vec;
+    use typst::diag::SourceDiagnostic;
+    use typst::syntax::Span;
     use tytanic_utils::fs::TempTestEnv;
 
     use super::*;
+    use crate::filter::AllFilter;
     use crate::test::Annotation;
     use crate::test::unit::Kind;
 
@@ -519,7 +821,11 @@ mod tests {
                     ("compare/ephemeral", Kind::Ephemeral, eco_vec![]),
                     ("compare/ephemeral-store", Kind::Ephemeral, eco_vec![]),
                     ("compare/persistent", Kind::Persistent, eco_vec![]),
-                    ("ignored", Kind::CompileOnly, eco_vec![Annotation::Skip]),
+                    (
+                        "ignored",
+                        Kind::CompileOnly,
+                        eco_vec![Annotation::Skip(None)],
+                    ),
                 ];
 
                 for (key, kind, annotations) in tests {
@@ -529,6 +835,15 @@ mod tests {
                     assert_eq!(test.annotations(), &annotations[..]);
                     assert_eq!(test.kind(), kind);
                 }
+
+                assert_eq!(
+                    suite.invalid().iter().collect::<Vec<_>>(),
+                    [Utf8Path::new("ignored!")],
+                );
+                assert_eq!(
+                    suite.skipped().iter().collect::<Vec<_>>(),
+                    [Utf8Path::new("not-a-test")],
+                );
             },
         );
     }
@@ -554,4 +869,187 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn test_collect_directory_config() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("tests/foo/.config.toml", "max-delta = 2\nretries = 1")
+                    .setup_file("tests/foo/bar/.config.toml", "max-delta = 3")
+                    .setup_file("tests/foo/bar/baz/test.typ", "Hello World")
+                    .setup_file("tests/foo/bar/baz/ref.typ", "Hello World")
+                    .setup_file("tests/foo/qux/test.typ", "/// [retries: 3]\nHello World")
+                    .setup_file("tests/foo/qux/ref.typ", "Hello World")
+            },
+            |root| {
+                let project = Project::new(root);
+                let suite = Suite::collect(&project).unwrap();
+
+                let Test::Unit(test) = &suite.tests["foo/bar/baz"] else {
+                    panic!("not testing template here");
+                };
+                assert!(test.own_annotations().is_empty());
+                assert_eq!(
+                    test.inherited_annotations(),
+                    [Annotation::Retries(1), Annotation::MaxDelta(3)],
+                );
+
+                let Test::Unit(test) = &suite.tests["foo/qux"] else {
+                    panic!("not testing template here");
+                };
+                assert_eq!(test.own_annotations(), [Annotation::Retries(3)]);
+                assert_eq!(test.inherited_annotations(), [Annotation::MaxDelta(2)]);
+            },
+        );
+    }
+
+    #[test]
+    fn test_collect_invalid_directory_config() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("tests/foo/.config.toml", "max-delt = 2")
+                    .setup_file("tests/foo/bar/test.typ", "Hello World")
+            },
+            |root| {
+                let project = Project::new(root);
+                let err = Suite::collect(&project).unwrap_err();
+
+                assert!(matches!(
+                    err,
+                    Error::DirectoryConfig { path, .. } if path == "foo/.config.toml"
+                ));
+            },
+        );
+    }
+
+    struct PrefixFilter(&'static str);
+
+    impl Filter for PrefixFilter {
+        type State<'a>
+            = AllFilter
+        where
+            Self: 'a;
+
+        fn state(&self) -> Self::State<'_> {
+            AllFilter
+        }
+
+        fn may_match(&self, id: &Id) -> bool {
+            id.as_str().starts_with(self.0) || self.0.starts_with(id.as_str())
+        }
+    }
+
+    #[test]
+    fn test_collect_pruned() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("tests/foo/bar/test.typ", "Hello World")
+                    .setup_file("tests/foo/baz/test.typ", "Hello World")
+                    .setup_file("tests/qux/test.typ", "Hello World")
+            },
+            |root| {
+                let project = Project::new(root);
+                let suite = Suite::collect_pruned(&project, &PrefixFilter("foo/bar")).unwrap();
+
+                assert!(suite.tests.contains_key("foo/bar"));
+                assert!(!suite.tests.contains_key("foo/baz"));
+                assert!(!suite.tests.contains_key("qux"));
+            },
+        );
+    }
+
+    #[test]
+    fn test_result_cancelled() {
+        TempTestEnv::run_no_check(
+            |root| root,
+            |root| {
+                let project = Project::new(root);
+                let suite = Suite::from_tests([
+                    Test::Unit(UnitTest::new(Id::new("foo").unwrap(), Kind::CompileOnly)),
+                    Test::Unit(UnitTest::new(Id::new("bar").unwrap(), Kind::CompileOnly)),
+                ]);
+                let suite = suite.filter(&project, AllFilter).unwrap();
+
+                let mut result = SuiteResult::new(&suite);
+                let mut passed = TestResult::skipped();
+                passed.set_passed_compilation();
+                result.set_test_result(Id::new("foo").unwrap(), passed);
+                result.set_cancelled();
+
+                assert_eq!(result.run(), 1);
+                assert_eq!(result.cancelled(), 1);
+                assert_eq!(result.skipped(), 0);
+                assert!(result.results()["bar"].is_cancelled());
+            },
+        );
+    }
+
+    #[test]
+    fn test_result_warnings() {
+        TempTestEnv::run_no_check(
+            |root| root,
+            |root| {
+                let project = Project::new(root);
+                let suite = Suite::from_tests([
+                    Test::Unit(UnitTest::new(Id::new("foo").unwrap(), Kind::CompileOnly)),
+                    Test::Unit(UnitTest::new(Id::new("bar").unwrap(), Kind::CompileOnly)),
+                ]);
+                let suite = suite.filter(&project, AllFilter).unwrap();
+
+                let mut result = SuiteResult::new(&suite);
+                let mut warned = TestResult::skipped();
+                warned.set_passed_compilation();
+                warned.set_warnings(eco_vec![
+                    SourceDiagnostic::warning(Span::detached(), "foo"),
+                    SourceDiagnostic::warning(Span::detached(), "bar"),
+                ]);
+                result.set_test_result(Id::new("foo").unwrap(), warned);
+
+                let mut passed = TestResult::skipped();
+                passed.set_passed_compilation();
+                result.set_test_result(Id::new("bar").unwrap(), passed);
+
+                assert_eq!(result.warned(), 1);
+                assert_eq!(result.warnings(), 2);
+            },
+        );
+    }
+
+    #[test]
+    fn test_last_run_record() {
+        TempTestEnv::run_no_check(
+            |root| root,
+            |root| {
+                let project = Project::new(root);
+                let suite = Suite::from_tests([
+                    Test::Unit(UnitTest::new(Id::new("foo").unwrap(), Kind::CompileOnly)),
+                    Test::Unit(UnitTest::new(Id::new("bar").unwrap(), Kind::CompileOnly)),
+                ]);
+                let suite = suite.filter(&project, AllFilter).unwrap();
+
+                let mut last_run = LastRun::default();
+                last_run.tests.insert("bar".into(), LastStatus::Failed);
+
+                let mut result = SuiteResult::new(&suite);
+                let mut passed = TestResult::skipped();
+                passed.set_passed_compilation();
+                result.set_test_result(Id::new("foo").unwrap(), passed);
+                last_run.record(&result);
+
+                assert_eq!(
+                    last_run.status(&Id::new("foo").unwrap()),
+                    Some(LastStatus::Passed)
+                );
+                assert_eq!(
+                    last_run.status(&Id::new("bar").unwrap()),
+                    Some(LastStatus::Failed)
+                );
+                assert_eq!(last_run.status(&Id::new("qux").unwrap()), None);
+
+                assert_eq!(LastRun::read(&project).unwrap(), None);
+                last_run.write(&project).unwrap();
+                assert_eq!(LastRun::read(&project).unwrap(), Some(last_run));
+            },
+        );
+    }
 }
@@ -19,13 +19,42 @@
 //! ...
 //! ```
 
+use std::collections::BTreeSet;
+use std::ops::Range;
 use std::str::FromStr;
 
 use ecow::EcoString;
 use ecow::EcoVec;
+use ecow::eco_format;
 use thiserror::Error;
+use typst::foundations::Datetime;
 
 use crate::config::Direction;
+use crate::doc::compare::PageTolerance;
+use crate::doc::render::Focus;
+use crate::doc::render::Mask;
+use crate::doc::render::Origin;
+use crate::test::unit::Kind;
+
+/// The identifiers of all annotations, see [`Annotation::name`].
+pub const ANNOTATIONS: &[&str] = &[
+    "skip",
+    "dir",
+    "diff-origin",
+    "ppi",
+    "max-delta",
+    "max-deviations",
+    "max-failing-pages",
+    "retries",
+    "max-memory",
+    "input",
+    "data-root",
+    "date",
+    "focus",
+    "mask",
+    "page",
+    "pages",
+];
 
 /// An error which may occur while parsing an annotation.
 #[derive(Debug, Error)]
@@ -49,6 +78,10 @@ pub enum ParseAnnotationError {
     #[error("the annotation {0} requires a key-value separator")]
     MissingInputSeparator(EcoString),
 
+    /// A page tolerance was missing its key-value separator.
+    #[error("the page tolerance {0:?} requires a key-value separator")]
+    MissingPageToleranceSeparator(EcoString),
+
     /// An error occurred while parsing the annotation.
     #[error("an error occurred while parsing the annotation")]
     Other(#[source] Box<dyn std::error::Error + Sync + Send + 'static>),
@@ -62,12 +95,17 @@ pub enum ParseAnnotationError {
 /// Each annotation is on its own line.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Annotation {
-    /// The skip annotation, this adds a test to the builtin `skip` test set.
-    Skip,
+    /// The skip annotation, this adds a test to the builtin `skip` test set,
+    /// optionally with a reason which is shown when the test is skipped.
+    Skip(Option<String>),
 
     /// The direction to use for diffing the documents.
     Dir(Direction),
 
+    /// The origin at which pages of different sizes are aligned in difference
+    /// documents, takes precedence over the direction.
+    DiffOrigin(Origin),
+
     /// The pixel per inch to use for exporting the documents.
     Ppi(f64),
 
@@ -77,30 +115,236 @@ pub enum Annotation {
     /// The maximum allowed amount of deviations to use for comparison.
     MaxDeviations(usize),
 
+    /// The maximum allowed amount of pages which may fail comparison.
+    MaxFailingPages(usize),
+
+    /// The number of times a failing test is run again before it is reported
+    /// as failed.
+    Retries(usize),
+
+    /// The maximum number of bytes the compilation of the test may allocate
+    /// at once.
+    MaxMemory(usize),
+
     /// A key-value pair to expose in `sys.inputs` for the code running the test.
     Input { key: String, value: String },
+
+    /// A directory relative to the test directory against which reads of data
+    /// files are resolved before the project root.
+    DataRoot(String),
+
+    /// A fixed date returned for the current date instead of the one given by
+    /// the compile options.
+    Date(Datetime),
+
+    /// A rectangle in pt to which pages are cropped before comparison.
+    Focus(Focus),
+
+    /// A rectangle in pt which is excluded from comparison, may be given
+    /// multiple times.
+    Mask(Mask),
+
+    /// Comparison tolerances which override those of the test for a single
+    /// page, may be given multiple times.
+    Page {
+        /// The 1-based page number.
+        page: usize,
+
+        /// The tolerances of the page.
+        tolerance: PageTolerance,
+    },
+
+    /// The sorted 1-based page numbers to which rendering and comparison are
+    /// restricted.
+    Pages(EcoVec<usize>),
+}
+
+/// The location of an annotation within a test script.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AnnotationSpan {
+    /// The 1-based line number of the annotation.
+    pub line: usize,
+
+    /// The byte range of the annotation within the test script, including its
+    /// delimiters but excluding the doc comment prefix.
+    pub range: Range<usize>,
 }
 
 impl Annotation {
     /// Collects all annotations found within a test's source code.
     pub fn collect(source: &str) -> Result<EcoVec<Self>, ParseAnnotationError> {
+        Self::collect_spanned(source)
+            .map(|annotations| annotations.into_iter().map(|(annot, _)| annot).collect())
+    }
+
+    /// Collects all annotations found within a test's source code together
+    /// with their location in it.
+    pub fn collect_spanned(
+        source: &str,
+    ) -> Result<EcoVec<(Self, AnnotationSpan)>, ParseAnnotationError> {
+        let mut offset = 0;
+        let lines = source.split_inclusive('\n').enumerate().map(|(idx, raw)| {
+            let start = offset;
+            offset += raw.len();
+
+            let line = raw.strip_suffix('\n').unwrap_or(raw);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            (idx + 1, start, line)
+        });
+
         // Skip regular comments and leading empty lines.
-        let lines = source.lines().skip_while(|line| {
+        let lines = lines.skip_while(|(_, _, line)| {
             line.strip_prefix("//")
                 .is_some_and(|rest| !rest.starts_with('/'))
                 || line.trim().is_empty()
         });
 
         // Then collect all consecutive doc comment lines.
-        let lines = lines.map_while(|line| line.strip_prefix("///").map(str::trim));
+        let lines = lines.map_while(|(num, start, line)| {
+            let rest = line.strip_prefix("///")?;
+            let content = rest.trim();
+            let start = start + "///".len() + (rest.len() - rest.trim_start().len());
+
+            Some((
+                content,
+                AnnotationSpan {
+                    line: num,
+                    range: start..start + content.len(),
+                },
+            ))
+        });
 
         // Ignore empty ones.
-        let lines = lines.filter(|line| !line.is_empty());
+        let lines = lines.filter(|(line, _)| !line.is_empty());
 
         // Take only those which start with an annotation delimiter.
-        let lines = lines.take_while(|line| line.starts_with('['));
+        let lines = lines.take_while(|(line, _)| line.starts_with('['));
+
+        lines
+            .map(|(line, span)| line.parse().map(|annot| (annot, span)))
+            .collect()
+    }
+}
+
+impl Annotation {
+    /// The identifier of this annotation as it is written in a test.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Annotation::Skip(_) => "skip",
+            Annotation::Dir(_) => "dir",
+            Annotation::DiffOrigin(_) => "diff-origin",
+            Annotation::Ppi(_) => "ppi",
+            Annotation::MaxDelta(_) => "max-delta",
+            Annotation::MaxDeviations(_) => "max-deviations",
+            Annotation::MaxFailingPages(_) => "max-failing-pages",
+            Annotation::Retries(_) => "retries",
+            Annotation::MaxMemory(_) => "max-memory",
+            Annotation::Input { .. } => "input",
+            Annotation::DataRoot(_) => "data-root",
+            Annotation::Date(_) => "date",
+            Annotation::Focus(_) => "focus",
+            Annotation::Mask(_) => "mask",
+            Annotation::Page { .. } => "page",
+            Annotation::Pages(_) => "pages",
+        }
+    }
 
-        lines.map(str::parse).collect()
+    /// The argument of this annotation in its canonical form, `None` for
+    /// annotations without one.
+    ///
+    /// Numbers are written without units, rectangles in pt and lists are
+    /// separated by `, `, e.g. `[pages: 3-4,1]` has the argument `1, 3, 4`.
+    pub fn arg(&self) -> Option<EcoString> {
+        let rect = |x, y, width, height| eco_format!("{x}pt, {y}pt, {width}pt, {height}pt");
+
+        Some(match self {
+            Annotation::Skip(reason) => reason.as_deref()?.into(),
+            Annotation::Dir(Direction::Ltr) => "ltr".into(),
+            Annotation::Dir(Direction::Rtl) => "rtl".into(),
+            Annotation::DiffOrigin(origin) => match origin {
+                Origin::TopLeft => "top-left".into(),
+                Origin::TopRight => "top-right".into(),
+                Origin::BottomLeft => "bottom-left".into(),
+                Origin::BottomRight => "bottom-right".into(),
+            },
+            Annotation::Ppi(ppi) => eco_format!("{ppi}"),
+            Annotation::MaxDelta(max_delta) => eco_format!("{max_delta}"),
+            Annotation::MaxDeviations(max_deviations) => eco_format!("{max_deviations}"),
+            Annotation::MaxFailingPages(max_failing_pages) => eco_format!("{max_failing_pages}"),
+            Annotation::Retries(retries) => eco_format!("{retries}"),
+            Annotation::MaxMemory(max_memory) => eco_format!("{max_memory}"),
+            Annotation::Input { key, value } => eco_format!("{key}={value}"),
+            Annotation::DataRoot(root) => root.as_str().into(),
+            Annotation::Date(date) => eco_format!(
+                "{:04}-{:02}-{:02}",
+                date.year()?,
+                date.month()?,
+                date.day()?
+            ),
+            Annotation::Focus(Focus {
+                x,
+                y,
+                width,
+                height,
+            }) => rect(x, y, width, height),
+            Annotation::Mask(Mask {
+                x,
+                y,
+                width,
+                height,
+            }) => rect(x, y, width, height),
+            Annotation::Page { page, tolerance } => {
+                let mut arg = eco_format!("{page}");
+                if let Some(max_delta) = tolerance.max_delta {
+                    arg.push_str(&eco_format!(", max-delta={max_delta}"));
+                }
+                if let Some(max_deviations) = tolerance.max_deviations {
+                    arg.push_str(&eco_format!(", max-deviations={max_deviations}"));
+                }
+                arg
+            }
+            Annotation::Pages(pages) => pages
+                .iter()
+                .map(|page| eco_format!("{page}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+                .into(),
+        })
+    }
+
+    /// Whether this annotation sets the same config as the given one, such
+    /// that only one of them can take effect. Inputs only conflict if they
+    /// have the same key.
+    pub fn conflicts_with(&self, other: &Annotation) -> bool {
+        match (self, other) {
+            (Annotation::Input { key: a, .. }, Annotation::Input { key: b, .. }) => a == b,
+            _ => self.name() == other.name(),
+        }
+    }
+
+    /// Whether this annotation has any effect on a test of the given kind.
+    ///
+    /// Annotations which configure comparisons or difference documents have
+    /// no effect on compile-only tests.
+    pub fn applies_to(&self, kind: Kind) -> bool {
+        match self {
+            Annotation::Skip(_)
+            | Annotation::Ppi(_)
+            | Annotation::Retries(_)
+            | Annotation::MaxMemory(_)
+            | Annotation::Input { .. }
+            | Annotation::DataRoot(_)
+            | Annotation::Date(_) => true,
+            Annotation::Dir(_)
+            | Annotation::DiffOrigin(_)
+            | Annotation::MaxDelta(_)
+            | Annotation::MaxDeviations(_)
+            | Annotation::MaxFailingPages(_)
+            | Annotation::Focus(_)
+            | Annotation::Mask(_)
+            | Annotation::Page { .. }
+            | Annotation::Pages(_) => !kind.is_compile_only(),
+        }
     }
 }
 
@@ -122,13 +366,9 @@ impl FromStr for Annotation {
         };
 
         match id.trim() {
-            "skip" => {
-                if arg.is_some() {
-                    Err(ParseAnnotationError::UnexpectedArg("skip"))
-                } else {
-                    Ok(Annotation::Skip)
-                }
-            }
+            "skip" => Ok(Annotation::Skip(
+                arg.filter(|arg| !arg.is_empty()).map(Into::into),
+            )),
             "dir" => match arg {
                 Some(arg) => match arg.trim() {
                     "ltr" => Ok(Annotation::Dir(Direction::Ltr)),
@@ -139,6 +379,22 @@ impl FromStr for Annotation {
                 },
                 None => Err(ParseAnnotationError::MissingArg("dir")),
             },
+            "diff-origin" => match arg {
+                Some(arg) => match arg.trim() {
+                    "top-left" => Ok(Annotation::DiffOrigin(Origin::TopLeft)),
+                    "top-right" => Ok(Annotation::DiffOrigin(Origin::TopRight)),
+                    "bottom-left" => Ok(Annotation::DiffOrigin(Origin::BottomLeft)),
+                    "bottom-right" => Ok(Annotation::DiffOrigin(Origin::BottomRight)),
+                    _ => Err(ParseAnnotationError::Other(
+                        format!(
+                            "invalid origin {arg:?}, expected one of top-left, top-right, \
+                            bottom-left or bottom-right"
+                        )
+                        .into(),
+                    )),
+                },
+                None => Err(ParseAnnotationError::MissingArg("diff-origin")),
+            },
             "ppi" => match arg {
                 Some(arg) => match arg.trim().parse() {
                     Ok(arg) => Ok(Annotation::Ppi(arg)),
@@ -160,6 +416,24 @@ impl FromStr for Annotation {
                 },
                 None => Err(ParseAnnotationError::MissingArg("max-deviations")),
             },
+            "max-failing-pages" => match arg {
+                Some(arg) => match arg.trim().parse() {
+                    Ok(arg) => Ok(Annotation::MaxFailingPages(arg)),
+                    Err(err) => Err(ParseAnnotationError::Other(err.into())),
+                },
+                None => Err(ParseAnnotationError::MissingArg("max-failing-pages")),
+            },
+            "retries" => match arg {
+                Some(arg) => match arg.trim().parse() {
+                    Ok(arg) => Ok(Annotation::Retries(arg)),
+                    Err(err) => Err(ParseAnnotationError::Other(err.into())),
+                },
+                None => Err(ParseAnnotationError::MissingArg("retries")),
+            },
+            "max-memory" => match arg {
+                Some(arg) => parse_bytes(arg).map(Annotation::MaxMemory),
+                None => Err(ParseAnnotationError::MissingArg("max-memory")),
+            },
             "input" => match arg {
                 Some(arg) => match arg.trim().split_once('=') {
                     Some((key, value)) => Ok(Annotation::Input {
@@ -170,28 +444,333 @@ impl FromStr for Annotation {
                 },
                 None => Err(ParseAnnotationError::MissingArg("input")),
             },
+            "data-root" => match arg {
+                Some(arg) if !arg.is_empty() => Ok(Annotation::DataRoot(arg.to_string())),
+                _ => Err(ParseAnnotationError::MissingArg("data-root")),
+            },
+            "date" => match arg {
+                Some(arg) => parse_date(arg).map(Annotation::Date),
+                None => Err(ParseAnnotationError::MissingArg("date")),
+            },
+            "focus" => match arg {
+                Some(arg) => parse_focus(arg).map(Annotation::Focus),
+                None => Err(ParseAnnotationError::MissingArg("focus")),
+            },
+            "mask" => match arg {
+                Some(arg) => parse_mask(arg).map(Annotation::Mask),
+                None => Err(ParseAnnotationError::MissingArg("mask")),
+            },
+            "page" => match arg {
+                Some(arg) => parse_page(arg),
+                None => Err(ParseAnnotationError::MissingArg("page")),
+            },
+            "pages" => match arg {
+                Some(arg) => parse_pages(arg).map(Annotation::Pages),
+                None => Err(ParseAnnotationError::MissingArg("pages")),
+            },
             _ => Err(ParseAnnotationError::Unknown(id.into())),
         }
     }
 }
 
+/// Parses a date of the form `YYYY-MM-DD`.
+fn parse_date(arg: &str) -> Result<Datetime, ParseAnnotationError> {
+    let invalid = || {
+        ParseAnnotationError::Other(
+            format!("invalid date {arg:?}, expected a date of the form YYYY-MM-DD").into(),
+        )
+    };
+
+    let mut parts = arg.split('-');
+    let (Some(year), Some(month), Some(day), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid());
+    };
+
+    let year = year.trim().parse().map_err(|_| invalid())?;
+    let month = month.trim().parse().map_err(|_| invalid())?;
+    let day = day.trim().parse().map_err(|_| invalid())?;
+
+    Datetime::from_ymd(year, month, day).ok_or_else(invalid)
+}
+
+/// Parses a number of bytes with an optional binary unit suffix, i.e. one of
+/// `B`, `KiB`, `MiB` or `GiB`.
+fn parse_bytes(arg: &str) -> Result<usize, ParseAnnotationError> {
+    let invalid = || {
+        ParseAnnotationError::Other(
+            format!("invalid size {arg:?}, expected a number of bytes like 64MiB").into(),
+        )
+    };
+
+    let arg = arg.trim();
+    let digits = arg.find(|c: char| !c.is_ascii_digit()).unwrap_or(arg.len());
+    let (value, unit) = arg.split_at(digits);
+
+    let value = value.parse::<usize>().map_err(|_| invalid())?;
+    let factor = match unit.trim() {
+        "" | "B" => 1,
+        "KiB" => 1 << 10,
+        "MiB" => 1 << 20,
+        "GiB" => 1 << 30,
+        _ => return Err(invalid()),
+    };
+
+    value.checked_mul(factor).ok_or_else(invalid)
+}
+
+/// Parses a focus rectangle of the form `x, y, width, height` in pt, each
+/// value may optionally have a `pt` suffix.
+fn parse_focus(arg: &str) -> Result<Focus, ParseAnnotationError> {
+    let [x, y, width, height] = parse_rect("focus", arg)?;
+
+    Ok(Focus {
+        x,
+        y,
+        width,
+        height,
+    })
+}
+
+/// Parses a mask rectangle of the same form as a focus rectangle, see
+/// [`parse_focus`].
+fn parse_mask(arg: &str) -> Result<Mask, ParseAnnotationError> {
+    let [x, y, width, height] = parse_rect("mask", arg)?;
+
+    Ok(Mask {
+        x,
+        y,
+        width,
+        height,
+    })
+}
+
+/// Parses per-page tolerances of the form `page, key=value, ...` where `page`
+/// is a 1-based page number and the keys are `max-delta` or
+/// `max-deviations`.
+fn parse_page(arg: &str) -> Result<Annotation, ParseAnnotationError> {
+    let mut parts = arg.split(',').map(str::trim);
+
+    let page = parts
+        .next()
+        .unwrap_or_default()
+        .parse::<usize>()
+        .map_err(|err| ParseAnnotationError::Other(err.into()))?;
+
+    if page == 0 {
+        return Err(ParseAnnotationError::Other(
+            format!("invalid page {arg:?}, page numbers start at 1").into(),
+        ));
+    }
+
+    let mut tolerance = PageTolerance::default();
+    for part in parts {
+        let Some((key, value)) = part.split_once('=') else {
+            return Err(ParseAnnotationError::MissingPageToleranceSeparator(
+                part.into(),
+            ));
+        };
+
+        let value = value.trim();
+        match key.trim() {
+            "max-delta" => {
+                tolerance.max_delta = Some(
+                    value
+                        .parse::<u8>()
+                        .map_err(|err| ParseAnnotationError::Other(err.into()))?,
+                );
+            }
+            "max-deviations" => {
+                tolerance.max_deviations = Some(
+                    value
+                        .parse::<usize>()
+                        .map_err(|err| ParseAnnotationError::Other(err.into()))?,
+                );
+            }
+            key => {
+                return Err(ParseAnnotationError::Other(
+                    format!(
+                        "invalid page tolerance {key:?}, expected one of max-delta or \
+                        max-deviations"
+                    )
+                    .into(),
+                ));
+            }
+        }
+    }
+
+    if tolerance == PageTolerance::default() {
+        return Err(ParseAnnotationError::Other(
+            format!("invalid page {arg:?}, expected at least one tolerance").into(),
+        ));
+    }
+
+    Ok(Annotation::Page { page, tolerance })
+}
+
+/// Parses a page selection of the form `1, 3-5`, where each part is either a
+/// 1-based page number or an inclusive range of them.
+fn parse_pages(arg: &str) -> Result<EcoVec<usize>, ParseAnnotationError> {
+    let parse = |num: &str| {
+        let num = num
+            .trim()
+            .parse::<usize>()
+            .map_err(|err| ParseAnnotationError::Other(err.into()))?;
+
+        if num == 0 {
+            return Err(ParseAnnotationError::Other(
+                format!("invalid pages {arg:?}, page numbers start at 1").into(),
+            ));
+        }
+
+        Ok(num)
+    };
+
+    let mut pages = BTreeSet::new();
+    for part in arg.split(',') {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+                if start > end {
+                    return Err(ParseAnnotationError::Other(
+                        format!("invalid pages {arg:?}, range {start}-{end} is empty").into(),
+                    ));
+                }
+
+                pages.extend(start..=end);
+            }
+            None => {
+                pages.insert(parse(part)?);
+            }
+        }
+    }
+
+    Ok(pages.into_iter().collect())
+}
+
+/// Parses a rectangle of the form `x, y, width, height` in pt for the
+/// annotation with the given name.
+fn parse_rect(name: &str, arg: &str) -> Result<[f64; 4], ParseAnnotationError> {
+    let values = arg
+        .split(',')
+        .map(|value| {
+            let value = value.trim();
+            value
+                .strip_suffix("pt")
+                .unwrap_or(value)
+                .trim()
+                .parse::<f64>()
+                .map_err(|err| ParseAnnotationError::Other(err.into()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let [x, y, width, height] = values[..] else {
+        return Err(ParseAnnotationError::Other(
+            format!("invalid {name} {arg:?}, expected four values: x, y, width, height").into(),
+        ));
+    };
+
+    if width <= 0.0 || height <= 0.0 {
+        return Err(ParseAnnotationError::Other(
+            format!("invalid {name} {arg:?}, width and height must be positive").into(),
+        ));
+    }
+
+    Ok([x, y, width, height])
+}
+
 #[cfg(test)]
 mod tests {
+    use ecow::eco_vec;
+
     use super::*;
 
+    #[test]
+    fn test_annotation_applies_to() {
+        let annotations = [
+            Annotation::Skip(None),
+            Annotation::Ppi(72.0),
+            Annotation::Retries(2),
+            Annotation::MaxMemory(1024),
+            Annotation::Input {
+                key: "foo".into(),
+                value: "bar".into(),
+            },
+            Annotation::DataRoot("data".into()),
+            Annotation::Date(Datetime::from_ymd(2024, 1, 1).unwrap()),
+        ];
+
+        for annot in annotations {
+            assert!(annot.applies_to(Kind::CompileOnly));
+            assert!(annot.applies_to(Kind::Ephemeral));
+            assert!(annot.applies_to(Kind::Persistent));
+        }
+
+        let annotations = [
+            Annotation::Dir(Direction::Rtl),
+            Annotation::DiffOrigin(Origin::TopLeft),
+            Annotation::MaxDelta(1),
+            Annotation::MaxDeviations(1),
+            Annotation::MaxFailingPages(1),
+            Annotation::Focus(Focus {
+                x: 0.0,
+                y: 0.0,
+                width: 10.0,
+                height: 10.0,
+            }),
+            Annotation::Mask(Mask {
+                x: 0.0,
+                y: 0.0,
+                width: 10.0,
+                height: 10.0,
+            }),
+            Annotation::Page {
+                page: 1,
+                tolerance: PageTolerance {
+                    max_delta: Some(1),
+                    max_deviations: None,
+                },
+            },
+            Annotation::Pages(eco_vec![1]),
+        ];
+
+        for annot in annotations {
+            assert!(!annot.applies_to(Kind::CompileOnly));
+            assert!(annot.applies_to(Kind::Ephemeral));
+            assert!(annot.applies_to(Kind::Persistent));
+        }
+    }
+
     #[test]
     fn test_annotation_from_str() {
-        assert_eq!(Annotation::from_str("[skip]").unwrap(), Annotation::Skip);
-        assert_eq!(Annotation::from_str("[ skip  ]").unwrap(), Annotation::Skip);
+        assert_eq!(
+            Annotation::from_str("[skip]").unwrap(),
+            Annotation::Skip(None)
+        );
+        assert_eq!(
+            Annotation::from_str("[ skip  ]").unwrap(),
+            Annotation::Skip(None)
+        );
+        assert_eq!(
+            Annotation::from_str("[skip: broken upstream: see #12]").unwrap(),
+            Annotation::Skip(Some("broken upstream: see #12".into()))
+        );
 
         assert!(Annotation::from_str("[ skip  ").is_err());
         assert!(Annotation::from_str("[unknown]").is_err());
     }
 
     #[test]
-    fn test_annotation_unexpected_arg() {
-        assert!(Annotation::from_str("[skip:]").is_err());
-        assert!(Annotation::from_str("[skip: 10]").is_err());
+    fn test_annotation_skip_empty_reason() {
+        assert_eq!(
+            Annotation::from_str("[skip:]").unwrap(),
+            Annotation::Skip(None)
+        );
+        assert_eq!(
+            Annotation::from_str("[skip: 10]").unwrap(),
+            Annotation::Skip(Some("10".into()))
+        );
     }
 
     #[test]
@@ -206,10 +785,32 @@ mod tests {
             Annotation::from_str("[max-deviations: 20]").unwrap(),
             Annotation::MaxDeviations(20)
         );
+        assert_eq!(
+            Annotation::from_str("[max-failing-pages: 1]").unwrap(),
+            Annotation::MaxFailingPages(1)
+        );
+        assert_eq!(
+            Annotation::from_str("[retries: 3]").unwrap(),
+            Annotation::Retries(3)
+        );
+        assert_eq!(
+            Annotation::from_str("[max-memory: 2048]").unwrap(),
+            Annotation::MaxMemory(2048)
+        );
+        assert_eq!(
+            Annotation::from_str("[max-memory: 64 MiB]").unwrap(),
+            Annotation::MaxMemory(64 << 20)
+        );
+        assert!(Annotation::from_str("[max-memory: 64MB]").is_err());
         assert_eq!(
             Annotation::from_str("[ppi: 42.5]").unwrap(),
             Annotation::Ppi(42.5)
         );
+        assert_eq!(
+            Annotation::from_str("[diff-origin: bottom-left]").unwrap(),
+            Annotation::DiffOrigin(Origin::BottomLeft)
+        );
+        assert!(Annotation::from_str("[diff-origin: center]").is_err());
     }
 
     #[test]
@@ -229,6 +830,166 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_annotation_data_root() {
+        assert_eq!(
+            Annotation::from_str("[data-root: fixtures/data]").unwrap(),
+            Annotation::DataRoot("fixtures/data".to_string()),
+        );
+
+        assert!(matches!(
+            Annotation::from_str("[data-root:]"),
+            Err(ParseAnnotationError::MissingArg("data-root"))
+        ));
+    }
+
+    #[test]
+    fn test_annotation_date() {
+        assert_eq!(
+            Annotation::from_str("[date: 2024-01-01]").unwrap(),
+            Annotation::Date(Datetime::from_ymd(2024, 1, 1).unwrap()),
+        );
+
+        assert!(matches!(
+            Annotation::from_str("[date]"),
+            Err(ParseAnnotationError::MissingArg("date"))
+        ));
+        assert!(matches!(
+            Annotation::from_str("[date: 2024-02-30]"),
+            Err(ParseAnnotationError::Other(_))
+        ));
+        assert!(matches!(
+            Annotation::from_str("[date: 2024-01]"),
+            Err(ParseAnnotationError::Other(_))
+        ));
+    }
+
+    #[test]
+    fn test_annotation_focus() {
+        assert_eq!(
+            Annotation::from_str("[focus: 10, 20.5pt, 100, 50 pt]").unwrap(),
+            Annotation::Focus(Focus {
+                x: 10.0,
+                y: 20.5,
+                width: 100.0,
+                height: 50.0,
+            }),
+        );
+
+        assert!(Annotation::from_str("[focus: 10, 20, 100]").is_err());
+        assert!(Annotation::from_str("[focus: 10, 20, 0, 50]").is_err());
+    }
+
+    #[test]
+    fn test_annotation_mask() {
+        let annotations =
+            Annotation::collect("/// [mask: 0, 0, 50pt, 10]\n/// [mask: 100, 20, 5, 5]\n").unwrap();
+
+        assert_eq!(
+            annotations[..],
+            [
+                Annotation::Mask(Mask {
+                    x: 0.0,
+                    y: 0.0,
+                    width: 50.0,
+                    height: 10.0,
+                }),
+                Annotation::Mask(Mask {
+                    x: 100.0,
+                    y: 20.0,
+                    width: 5.0,
+                    height: 5.0,
+                }),
+            ]
+        );
+
+        assert!(Annotation::from_str("[mask]").is_err());
+        assert!(Annotation::from_str("[mask: 10, 20, -1, 50]").is_err());
+    }
+
+    #[test]
+    fn test_annotation_page() {
+        assert_eq!(
+            Annotation::from_str("[page: 3, max-deviations=100]").unwrap(),
+            Annotation::Page {
+                page: 3,
+                tolerance: PageTolerance {
+                    max_delta: None,
+                    max_deviations: Some(100),
+                },
+            },
+        );
+        assert_eq!(
+            Annotation::from_str("[page: 1, max-delta = 2, max-deviations = 5]").unwrap(),
+            Annotation::Page {
+                page: 1,
+                tolerance: PageTolerance {
+                    max_delta: Some(2),
+                    max_deviations: Some(5),
+                },
+            },
+        );
+
+        assert!(Annotation::from_str("[page: 3]").is_err());
+        assert!(Annotation::from_str("[page: 0, max-delta=1]").is_err());
+        assert!(Annotation::from_str("[page: 1, max-delta=256]").is_err());
+        assert!(Annotation::from_str("[page: 1, min-ssim=0.9]").is_err());
+        assert!(matches!(
+            Annotation::from_str("[page: 1, max-delta]"),
+            Err(ParseAnnotationError::MissingPageToleranceSeparator(_))
+        ));
+    }
+
+    #[test]
+    fn test_annotation_pages() {
+        assert_eq!(
+            Annotation::from_str("[pages: 1]").unwrap(),
+            Annotation::Pages(eco_vec![1]),
+        );
+        assert_eq!(
+            Annotation::from_str("[pages: 5, 1,3-4 , 4]").unwrap(),
+            Annotation::Pages(eco_vec![1, 3, 4, 5]),
+        );
+
+        assert!(Annotation::from_str("[pages]").is_err());
+        assert!(Annotation::from_str("[pages: 0]").is_err());
+        assert!(Annotation::from_str("[pages: 1-0]").is_err());
+        assert!(Annotation::from_str("[pages: 4-2]").is_err());
+        assert!(Annotation::from_str("[pages: 1,]").is_err());
+    }
+
+    #[test]
+    fn test_annotation_names() {
+        for name in ANNOTATIONS {
+            let err = Annotation::from_str(&format!("[{name}]")).err();
+            assert!(!matches!(err, Some(ParseAnnotationError::Unknown(_))));
+        }
+    }
+
+    #[test]
+    fn test_annotation_canonical_arg() {
+        for (annot, arg) in [
+            ("[skip]", None),
+            ("[skip: flaky]", Some("flaky")),
+            ("[dir: rtl]", Some("rtl")),
+            ("[diff-origin: bottom-right]", Some("bottom-right")),
+            ("[ppi: 72.5]", Some("72.5")),
+            ("[max-delta: 2]", Some("2")),
+            ("[max-memory: 1KiB]", Some("1024")),
+            ("[input: key=value]", Some("key=value")),
+            ("[date: 2024-01-02]", Some("2024-01-02")),
+            ("[mask: 1, 2pt, 3, 4]", Some("1pt, 2pt, 3pt, 4pt")),
+            ("[page: 2, max-deviations = 5]", Some("2, max-deviations=5")),
+            ("[pages: 3-4,1]", Some("1, 3, 4")),
+        ] {
+            assert_eq!(
+                Annotation::from_str(annot).unwrap().arg().as_deref(),
+                arg,
+                "{annot}"
+            );
+        }
+    }
+
     #[test]
     fn test_annotation_multiple() {
         let source = r#"
@@ -263,23 +1024,57 @@ mod tests {
         #import \"/src/internal.typ\": foo \n\
         ...";
 
-        assert_eq!(Annotation::collect(source).unwrap(), [Annotation::Skip]);
+        assert_eq!(
+            Annotation::collect(source).unwrap(),
+            [Annotation::Skip(None)]
+        );
     }
 
     #[test]
     fn test_collect_issue_109() {
         assert_eq!(
             Annotation::collect("///[skip]").unwrap(),
-            [Annotation::Skip]
+            [Annotation::Skip(None)]
         );
         assert_eq!(Annotation::collect("///").unwrap(), []);
         assert_eq!(
             Annotation::collect("/// [skip]").unwrap(),
-            [Annotation::Skip]
+            [Annotation::Skip(None)]
         );
         assert_eq!(
             Annotation::collect("///[skip]\n///").unwrap(),
-            [Annotation::Skip]
+            [Annotation::Skip(None)]
+        );
+    }
+
+    #[test]
+    fn test_collect_spanned() {
+        let source =
+            "// SPDX-License-Identifier: MIT\r\n\n///  [skip]\n/// [max-delta: 5] \n#[skip]";
+        let annotations = Annotation::collect_spanned(source).unwrap();
+
+        assert_eq!(
+            annotations,
+            [
+                (
+                    Annotation::Skip(None),
+                    AnnotationSpan {
+                        line: 3,
+                        range: 39..45,
+                    }
+                ),
+                (
+                    Annotation::MaxDelta(5),
+                    AnnotationSpan {
+                        line: 4,
+                        range: 50..64,
+                    }
+                ),
+            ]
         );
+
+        for (_, span) in annotations {
+            assert!(source[span.range].starts_with('['));
+        }
     }
 }
@@ -13,6 +13,7 @@ use std::str::FromStr;
 use std::sync::LazyLock;
 
 use ecow::EcoString;
+use ecow::eco_format;
 use thiserror::Error;
 
 // NOTE(tinger): The inner static in `Id::template()` cannot access the
@@ -34,6 +35,10 @@ impl Id {
 
     /// The unique special template identifier.
     pub const TEMPLATE: &'static str = _TEMPLATE;
+
+    /// The separator between the template identifier and the name of an
+    /// additional template entrypoint.
+    pub const TEMPLATE_ENTRYPOINT_SEPARATOR: &'static str = ":";
 }
 
 impl Id {
@@ -44,11 +49,36 @@ impl Id {
         TEMPLATE.clone()
     }
 
+    /// Returns the template identifier for an additional template entrypoint
+    /// with the given name, i.e. `@template:<name>`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tytanic_core::test::Id;
+    /// let id = Id::template_entrypoint("article")?;
+    /// assert_eq!(id.as_str(), "@template:article");
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the name is not a valid id component.
+    pub fn template_entrypoint<S: AsRef<str>>(name: S) -> Result<Self, ParseIdError> {
+        let name = name.as_ref();
+        Self::validate_component(name)?;
+
+        Ok(Self(eco_format!(
+            "{}{}{name}",
+            Self::TEMPLATE,
+            Self::TEMPLATE_ENTRYPOINT_SEPARATOR
+        )))
+    }
+
     /// Turns this string into an id.
     ///
     /// All components must start at least one ASCII alphabetic letter and
     /// contain only ASCII alphanumeric characters, underscores, and minuses.
-    /// The only exception is the special template test identifier `@template`.
+    /// The only exception are the special template test identifiers
+    /// `@template` and `@template:<name>`.
     ///
     /// # Examples
     /// ```
@@ -128,6 +158,7 @@ impl Id {
     /// assert!( Id::is_valid("a/b"));
     /// assert!( Id::is_valid("a"));
     /// assert!( Id::is_valid("@template"));
+    /// assert!( Id::is_valid("@template:article"));
     /// assert!(!Id::is_valid("a//b"));  // empty component
     /// assert!(!Id::is_valid("a/"));    // empty component
     /// ```
@@ -136,8 +167,12 @@ impl Id {
     }
 
     fn validate<S: AsRef<str>>(string: S) -> Result<(), ParseIdError> {
-        if string.as_ref() == Self::TEMPLATE {
-            return Ok(());
+        if let Some(rest) = string.as_ref().strip_prefix(Self::TEMPLATE) {
+            return match rest.strip_prefix(Self::TEMPLATE_ENTRYPOINT_SEPARATOR) {
+                Some(name) => Self::validate_component(name),
+                None if rest.is_empty() => Ok(()),
+                None => Err(ParseIdError::InvalidFragment),
+            };
         }
 
         for fragment in string.as_ref().split(Self::SEPARATOR) {
@@ -185,6 +220,21 @@ impl Id {
 }
 
 impl Id {
+    /// Whether this is the identifier of a template test, i.e. either
+    /// `@template` or `@template:<name>`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tytanic_core::test::Id;
+    /// assert!( Id::template().is_template());
+    /// assert!( Id::template_entrypoint("article")?.is_template());
+    /// assert!(!Id::new("a/b")?.is_template());
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn is_template(&self) -> bool {
+        self.0.starts_with(Self::TEMPLATE)
+    }
+
     /// The full id as a `str`, this string is never empty.
     pub fn as_str(&self) -> &str {
         self.0.as_str()
@@ -463,7 +513,10 @@ impl DoubleEndedIterator for Components<'_> {
 #[derive(Debug, Error)]
 pub enum ParseIdError {
     /// An id contained an invalid fragment.
-    #[error("id contained an invalid fragment")]
+    #[error(
+        "id contained an invalid fragment, fragments must start with an ASCII \
+        letter and contain only ASCII letters, digits, `-` or `_`"
+    )]
     InvalidFragment,
 
     /// An id contained empty or no fragments.
@@ -527,4 +580,55 @@ mod tests {
         assert!(Id::new("1a").is_err());
         assert!(Id::new("").is_err());
     }
+
+    #[test]
+    fn test_str_edge_cases() {
+        assert!(Id::new("a-").is_ok());
+        assert!(Id::new("a_").is_ok());
+        assert!(Id::new("a--b__c").is_ok());
+        assert!(Id::new("A1/b2-/c_3").is_ok());
+
+        assert!(Id::new("-a").is_err());
+        assert!(Id::new("_a").is_err());
+        assert!(Id::new("a.b").is_err());
+        assert!(Id::new("a:b").is_err());
+        assert!(Id::new("a\\b").is_err());
+        assert!(Id::new("'a'").is_err());
+        assert!(Id::new("ä").is_err());
+        assert!(Id::new("@template/a").is_err());
+    }
+
+    #[test]
+    fn test_str_template_entrypoint() {
+        assert!(Id::new("@template:article").is_ok());
+        assert!(Id::new("@template:a-b_c").is_ok());
+
+        assert!(Id::new("@template:").is_err());
+        assert!(Id::new("@template:1a").is_err());
+        assert!(Id::new("@template:a/b").is_err());
+        assert!(Id::new("@templates").is_err());
+        assert!(Id::template_entrypoint("a b").is_err());
+
+        assert_eq!(
+            Id::template_entrypoint("article").unwrap(),
+            Id::new("@template:article").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_str_round_trip() {
+        let ids = ["a", "a-", "a_", "a--b__c", "A1/b2-/c_3"];
+
+        for id in ids {
+            let parsed = Id::new(id).unwrap();
+            assert_eq!(parsed.to_string(), id);
+            assert_eq!(parsed.to_string().parse::<Id>().unwrap(), parsed);
+            assert_eq!(Id::new_from_path(parsed.to_path()).unwrap(), parsed);
+        }
+
+        // The template id does not correspond to a path.
+        let template = Id::template();
+        assert_eq!(template.to_string().parse::<Id>().unwrap(), template);
+        assert!(Id::new_from_path(template.to_path()).is_err());
+    }
 }
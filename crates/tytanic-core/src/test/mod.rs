@@ -6,18 +6,23 @@ use chrono::DateTime;
 use chrono::TimeDelta;
 use chrono::Utc;
 use typst::diag::SourceDiagnostic;
+use typst::ecow::EcoString;
 use typst::ecow::EcoVec;
 use typst::ecow::eco_vec;
 
+use crate::doc::SaveSummary;
 use crate::doc::compare;
 use crate::doc::compile;
+use crate::event::RunStage;
 
 mod annotation;
 mod id;
 pub mod template;
 pub mod unit;
 
+pub use self::annotation::ANNOTATIONS;
 pub use self::annotation::Annotation;
+pub use self::annotation::AnnotationSpan;
 pub use self::annotation::ParseAnnotationError;
 pub use self::id::Id;
 pub use self::id::ParseIdError;
@@ -43,6 +48,14 @@ impl Test {
         }
     }
 
+    /// The annotations of this test, template tests have no annotations.
+    pub fn annotations(&self) -> &[Annotation] {
+        match self {
+            Test::Unit(test) => test.annotations(),
+            Test::Template(_) => &[],
+        }
+    }
+
     /// Returns the inner unit test, or `None` if this is a template test.
     pub fn as_unit_test(&self) -> Option<&UnitTest> {
         match self {
@@ -63,10 +76,13 @@ impl Test {
 /// The stage of a single test run.
 #[derive(Debug, Clone, Default)]
 pub enum Stage {
-    /// The test was canceled or not started in the first place.
+    /// The test was not started in the first place.
     #[default]
     Skipped,
 
+    /// The test was not started because the run was cancelled.
+    Cancelled,
+
     /// The test was filtered out by a [`Filter`].
     ///
     /// [`Filter`]: crate::filter::Filter
@@ -94,9 +110,89 @@ pub enum Stage {
     Updated {
         /// Whether the references were optimized.
         optimized: bool,
+
+        /// How many reference pages were written or left unchanged.
+        pages: SaveSummary,
+
+        /// Whether this was a dry run, the pages would have been written but
+        /// were left untouched.
+        dry_run: bool,
     },
 }
 
+/// The time spent in each stage of a single test run, see
+/// [`TestResult::timings`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageTimings {
+    /// The time spent preparing the temporary directories of the test.
+    pub prepare: TimeDelta,
+
+    /// The time spent compiling the test document.
+    pub compilation: TimeDelta,
+
+    /// The time spent compiling the reference document.
+    pub reference_compilation: TimeDelta,
+
+    /// The time spent comparing the test document to its reference.
+    pub comparison: TimeDelta,
+
+    /// The time spent writing, and possibly optimizing, the references.
+    pub update: TimeDelta,
+}
+
+impl StageTimings {
+    /// The time spent in the given stage.
+    pub fn get(&self, stage: RunStage) -> TimeDelta {
+        match stage {
+            RunStage::Prepare => self.prepare,
+            RunStage::Compilation { reference: false } => self.compilation,
+            RunStage::Compilation { reference: true } => self.reference_compilation,
+            RunStage::Comparison => self.comparison,
+            RunStage::Update => self.update,
+        }
+    }
+
+    /// Adds the given duration to the time spent in the given stage, a stage
+    /// may run more than once for a single test.
+    pub fn add(&mut self, stage: RunStage, duration: TimeDelta) {
+        let timing = match stage {
+            RunStage::Prepare => &mut self.prepare,
+            RunStage::Compilation { reference: false } => &mut self.compilation,
+            RunStage::Compilation { reference: true } => &mut self.reference_compilation,
+            RunStage::Comparison => &mut self.comparison,
+            RunStage::Update => &mut self.update,
+        };
+
+        *timing += duration;
+    }
+
+    /// The stages in the order in which they run, together with the time
+    /// spent in them.
+    pub fn iter(&self) -> impl Iterator<Item = (RunStage, TimeDelta)> + '_ {
+        [
+            RunStage::Prepare,
+            RunStage::Compilation { reference: false },
+            RunStage::Compilation { reference: true },
+            RunStage::Comparison,
+            RunStage::Update,
+        ]
+        .into_iter()
+        .map(|stage| (stage, self.get(stage)))
+    }
+}
+
+impl Default for StageTimings {
+    fn default() -> Self {
+        Self {
+            prepare: TimeDelta::zero(),
+            compilation: TimeDelta::zero(),
+            reference_compilation: TimeDelta::zero(),
+            comparison: TimeDelta::zero(),
+            update: TimeDelta::zero(),
+        }
+    }
+}
+
 /// The result of a single test run.
 #[derive(Debug, Clone)]
 pub struct TestResult {
@@ -104,6 +200,13 @@ pub struct TestResult {
     warnings: EcoVec<SourceDiagnostic>,
     timestamp: DateTime<Utc>,
     duration: TimeDelta,
+    timings: StageTimings,
+    input_hash: Option<u128>,
+    inputs: EcoVec<EcoString>,
+    stale_references: bool,
+    retries: usize,
+    deviations: Option<usize>,
+    peak_memory: Option<usize>,
 }
 
 impl TestResult {
@@ -118,6 +221,13 @@ impl TestResult {
             warnings: eco_vec![],
             timestamp: Utc::now(),
             duration: TimeDelta::zero(),
+            timings: StageTimings::default(),
+            input_hash: None,
+            inputs: eco_vec![],
+            stale_references: false,
+            retries: 0,
+            deviations: None,
+            peak_memory: None,
         }
     }
 
@@ -129,6 +239,13 @@ impl TestResult {
             warnings: eco_vec![],
             timestamp: Utc::now(),
             duration: TimeDelta::zero(),
+            timings: StageTimings::default(),
+            input_hash: None,
+            inputs: eco_vec![],
+            stale_references: false,
+            retries: 0,
+            deviations: None,
+            peak_memory: None,
         }
     }
 }
@@ -144,6 +261,56 @@ impl TestResult {
         &self.warnings
     }
 
+    /// A hash over all inputs of the test, if they were recorded.
+    ///
+    /// This includes the content of all files read during compilation, the
+    /// available fonts and the configuration used. If it is equal between two
+    /// runs, the outcome of the test is expected to be equal too.
+    pub fn input_hash(&self) -> Option<u128> {
+        self.input_hash
+    }
+
+    /// The files read while running the test, if inputs were recorded.
+    ///
+    /// Project files are given by their path from the project root with a
+    /// leading slash, package files are prefixed with their package spec.
+    pub fn inputs(&self) -> &[EcoString] {
+        &self.inputs
+    }
+
+    /// Whether the persistent references of this test may be stale, i.e.
+    /// whether the test or project defaults changed since the references were
+    /// last updated.
+    ///
+    /// This is only checked if the test passed comparison.
+    pub fn has_stale_references(&self) -> bool {
+        self.stale_references
+    }
+
+    /// How often the test was run again after failing, this is only non-zero
+    /// if retries were configured for it.
+    pub fn retries(&self) -> usize {
+        self.retries
+    }
+
+    /// The number of deviating pixels of a passed comparison, this is only
+    /// recorded if requested by the runner.
+    pub fn deviations(&self) -> Option<usize> {
+        self.deviations
+    }
+
+    /// The peak number of bytes allocated while compiling the test, this is
+    /// only measured for tests with a memory budget.
+    pub fn peak_memory(&self) -> Option<usize> {
+        self.peak_memory
+    }
+
+    /// Whether the test passed only after it was run again, see
+    /// [`TestResult::retries`].
+    pub fn is_flaky(&self) -> bool {
+        self.is_pass() && self.retries > 0
+    }
+
     /// The timestamp at which the suite run started.
     pub fn timestamp(&self) -> DateTime<Utc> {
         self.timestamp
@@ -154,11 +321,22 @@ impl TestResult {
         self.duration
     }
 
+    /// The time spent in each stage of the test, the stages which didn't run
+    /// are zero.
+    pub fn timings(&self) -> &StageTimings {
+        &self.timings
+    }
+
     /// Whether the test was not started.
     pub fn is_skipped(&self) -> bool {
         matches!(&self.stage, Stage::Skipped)
     }
 
+    /// Whether the test was not started because the run was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(&self.stage, Stage::Cancelled)
+    }
+
     /// Whether the test was filtered out.
     pub fn is_filtered(&self) -> bool {
         matches!(&self.stage, Stage::Filtered)
@@ -203,6 +381,11 @@ impl TestResult {
         self.duration = Utc::now().signed_duration_since(self.timestamp);
     }
 
+    /// Adds the given duration to the time spent in a stage of this test.
+    pub fn add_stage_duration(&mut self, stage: RunStage, duration: TimeDelta) {
+        self.timings.add(stage, duration);
+    }
+
     /// Sets the kind for this test to a compilation pass.
     pub fn set_passed_compilation(&mut self) {
         self.stage = Stage::PassedCompilation;
@@ -229,14 +412,66 @@ impl TestResult {
         self.stage = Stage::PassedComparison;
     }
 
+    /// Sets the kind for this test to a cancellation.
+    pub fn set_cancelled(&mut self) {
+        self.stage = Stage::Cancelled;
+    }
+
     /// Sets the kind for this test to a comparison failure.
     pub fn set_failed_comparison(&mut self, error: compare::Error) {
         self.stage = Stage::FailedComparison(error);
     }
 
     /// Sets the kind for this test to a test update.
-    pub fn set_updated(&mut self, optimized: bool) {
-        self.stage = Stage::Updated { optimized };
+    pub fn set_updated(&mut self, optimized: bool, pages: SaveSummary) {
+        self.stage = Stage::Updated {
+            optimized,
+            pages,
+            dry_run: false,
+        };
+    }
+
+    /// Sets the kind for this test to a test update which was only recorded
+    /// but not written because of a dry run.
+    pub fn set_would_update(&mut self, pages: SaveSummary) {
+        self.stage = Stage::Updated {
+            optimized: false,
+            pages,
+            dry_run: true,
+        };
+    }
+
+    /// Sets the input hash for this test.
+    pub fn set_input_hash(&mut self, hash: u128) {
+        self.input_hash = Some(hash);
+    }
+
+    /// Sets the files read while running this test.
+    pub fn set_inputs<I>(&mut self, inputs: I)
+    where
+        I: IntoIterator<Item = EcoString>,
+    {
+        self.inputs = inputs.into_iter().collect();
+    }
+
+    /// Marks the persistent references of this test as potentially stale.
+    pub fn set_stale_references(&mut self) {
+        self.stale_references = true;
+    }
+
+    /// Sets how often this test was run again after failing.
+    pub fn set_retries(&mut self, retries: usize) {
+        self.retries = retries;
+    }
+
+    /// Sets the number of deviating pixels of a passed comparison.
+    pub fn set_deviations(&mut self, deviations: usize) {
+        self.deviations = Some(deviations);
+    }
+
+    /// Sets the peak number of bytes allocated while compiling the test.
+    pub fn set_peak_memory(&mut self, bytes: usize) {
+        self.peak_memory = Some(bytes);
     }
 
     /// Sets the warnings for this test.
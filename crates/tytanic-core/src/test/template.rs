@@ -1,6 +1,9 @@
 use std::fs;
 use std::io;
 
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use ecow::EcoString;
 use typst::syntax::FileId;
 use typst::syntax::RootedPath;
 use typst::syntax::Source;
@@ -11,18 +14,60 @@ use super::Id;
 use crate::project::Project;
 
 /// A compile-only template test.
+///
+/// Each template entrypoint of a project is its own template test, the
+/// entrypoint from the manifest has the id `@template`, any additional
+/// entrypoints from the project config have the id `@template:<name>`, where
+/// `<name>` is the file stem of the entrypoint.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Test {
     id: Id,
+    entrypoint: EcoString,
 }
 
 impl Test {
-    pub fn load(project: &Project) -> Option<Self> {
-        if project.template_entrypoint().is_some() {
-            return Some(Self { id: Id::template() });
+    /// Loads all template tests of the given project.
+    ///
+    /// Additional entrypoints which are equal to the manifest entrypoint or
+    /// whose name is already used by another entrypoint are ignored.
+    pub fn load(project: &Project) -> Vec<Self> {
+        let Some(template) = project.manifest().and_then(|m| m.template.as_ref()) else {
+            return vec![];
+        };
+
+        let mut tests = vec![Self {
+            id: Id::template(),
+            entrypoint: template.entrypoint.clone(),
+        }];
+
+        for entrypoint in &project.config().template_entrypoints {
+            if entrypoint == template.entrypoint.as_str() {
+                continue;
+            }
+
+            let Some(id) = Utf8Path::new(entrypoint)
+                .file_stem()
+                .and_then(|stem| Id::template_entrypoint(stem).ok())
+            else {
+                tracing::warn!(
+                    ?entrypoint,
+                    "ignoring template entrypoint with invalid name"
+                );
+                continue;
+            };
+
+            if tests.iter().any(|test| test.id == id) {
+                tracing::warn!(?entrypoint, %id, "ignoring duplicate template entrypoint");
+                continue;
+            }
+
+            tests.push(Self {
+                id,
+                entrypoint: entrypoint.into(),
+            });
         }
 
-        None
+        tests
     }
 }
 
@@ -30,23 +75,99 @@ impl Test {
     pub fn id(&self) -> &Id {
         &self.id
     }
+
+    /// The path of the entrypoint of this test, relative to the template
+    /// directory.
+    pub fn entrypoint(&self) -> &str {
+        &self.entrypoint
+    }
 }
 
 impl Test {
+    /// Returns the path to the entrypoint script of this test.
+    pub fn entrypoint_file(&self, project: &Project) -> Utf8PathBuf {
+        let mut path = project
+            .template_root()
+            .expect("Existence of template test ensures existence of template root");
+        path.push(self.entrypoint.as_str());
+        path
+    }
+
+    /// Create a file id to the entrypoint script of this test.
+    ///
+    /// Note that this is rooted in the template directory.
+    pub fn entrypoint_id(&self) -> FileId {
+        FileId::new(RootedPath::new(
+            VirtualRoot::Project,
+            VirtualPath::new(self.entrypoint.as_str())
+                .expect("Project validation must never allow invalid template entrypoints"),
+        ))
+    }
+
     /// Loads the test script source of this test.
     #[tracing::instrument(skip(project))]
     pub fn load_source(&self, project: &Project) -> io::Result<Source> {
-        let test_script = project
-            .template_entrypoint()
-            .expect("Existence of template test ensures existence of entrypoint");
+        let test_script = self.entrypoint_file(project);
 
         Ok(Source::new(
             FileId::new(RootedPath::new(
                 VirtualRoot::Project,
                 VirtualPath::virtualize(project.root().as_std_path(), test_script.as_std_path())
-                    .expect("Project::root and Test::templat_entrypoint must never emit escaping or invalid paths"),
+                    .expect("Project::root and Test::entrypoint_file must never emit escaping or invalid paths"),
             )),
             fs::read_to_string(test_script)?,
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tytanic_utils::typst::PackageManifestBuilder;
+    use tytanic_utils::typst::TemplateInfoBuilder;
+
+    use super::*;
+    use crate::config::ProjectConfig;
+
+    #[test]
+    fn test_load() {
+        let project = Project::new("root")
+            .with_manifest(Some(
+                PackageManifestBuilder::new()
+                    .template(
+                        TemplateInfoBuilder::new()
+                            .path("template")
+                            .entrypoint("main.typ")
+                            .build(),
+                    )
+                    .build(),
+            ))
+            .with_config(ProjectConfig {
+                template_entrypoints: vec![
+                    "main.typ".into(),
+                    "article.typ".into(),
+                    "thesis/thesis.typ".into(),
+                    "other/article.typ".into(),
+                ],
+                ..Default::default()
+            });
+
+        let tests = Test::load(&project);
+        let ids: Vec<_> = tests.iter().map(|test| test.id().as_str()).collect();
+        assert_eq!(ids, ["@template", "@template:article", "@template:thesis"]);
+
+        assert_eq!(
+            tests[2].entrypoint_file(&project),
+            Utf8PathBuf::from_iter(["root", "template", "thesis", "thesis.typ"])
+        );
+    }
+
+    #[test]
+    fn test_load_without_template() {
+        let project = Project::new("root").with_config(ProjectConfig {
+            template_entrypoints: vec!["article.typ".into()],
+            ..Default::default()
+        });
+
+        assert!(Test::load(&project).is_empty());
+    }
+}
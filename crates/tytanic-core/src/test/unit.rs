@@ -1,27 +1,42 @@
 //! Test loading and on-disk manipulation.
 
+use std::collections::BTreeSet;
 use std::fmt::Debug;
 use std::fs;
 use std::fs::File;
 use std::io;
 use std::io::Write;
+use std::path::PathBuf;
 
 use ecow::EcoString;
 use ecow::EcoVec;
+use ecow::eco_format;
 use ecow::eco_vec;
+use serde::Deserialize;
+use serde::Serialize;
 use thiserror::Error;
 use typst::syntax::FileId;
+use typst::syntax::PathError;
 use typst::syntax::RootedPath;
 use typst::syntax::Source;
 use typst::syntax::VirtualPath;
 use typst::syntax::VirtualRoot;
+use typst_utils::hash128;
+use tytanic_utils::fmt::Term;
+use tytanic_utils::result::ResultEx;
+use tytanic_utils::result::io_not_found;
 
 use super::Annotation;
+use super::AnnotationSpan;
 use super::Id;
 use super::ParseAnnotationError;
+use crate::config::Direction;
+use crate::config::ProjectDefaults;
 use crate::doc;
 use crate::doc::Document;
 use crate::doc::SaveError;
+use crate::doc::SaveSummary;
+use crate::doc::render::CropOffset;
 use crate::project::Project;
 use crate::project::vcs;
 
@@ -37,6 +52,69 @@ pub const DEFAULT_TEST_INPUT: &str = include_str!("default-test.typ");
 /// The default test output as an encoded PNG.
 pub const DEFAULT_TEST_OUTPUT: &[u8] = include_bytes!("default-test.png");
 
+/// The file name of the reference metadata within the reference directory of
+/// a persistent test, see [`ReferenceMeta`].
+pub const REFERENCE_META_FILE: &str = ".meta.toml";
+
+/// The keys of the attributes of a unit test, see [`Test::attribute`].
+pub const ATTRIBUTES: &[&str] = &[
+    "kind",
+    "skip",
+    "dir",
+    "ppi",
+    "max-delta",
+    "max-deviations",
+    "max-failing-pages",
+];
+
+/// The file name of the crop offsets within the reference directory of a
+/// persistent test whose references were stored cropped, see
+/// [`ReferenceCrops`].
+pub const REFERENCE_CROPS_FILE: &str = ".crops.toml";
+
+/// The file name of the PPI within the reference directory of a persistent
+/// test, see [`ReferencePpi`].
+pub const REFERENCE_PPI_FILE: &str = ".ppi.toml";
+
+/// The file name of the page text within the reference directory of a
+/// persistent test which is compared by its text, see [`ReferenceText`].
+pub const REFERENCE_TEXT_FILE: &str = ".text.toml";
+
+/// The normalized text of persistent reference pages, see
+/// [`Document::text`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ReferenceText {
+    /// The text of each page in order.
+    pub pages: Vec<String>,
+}
+
+/// The PPI at which persistent references were rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ReferencePpi {
+    /// The pixel-per-inch ratio of the reference pages.
+    pub ppi: f64,
+}
+
+/// The offsets of cropped persistent reference pages, see
+/// [`Document::crop`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ReferenceCrops {
+    /// The offset of each page in order.
+    pub pages: Vec<CropOffset>,
+}
+
+/// Metadata stored alongside persistent references.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ReferenceMeta {
+    /// The freshness hash of the test at the time its references were last
+    /// updated as a hexadecimal string, see [`Test::reference_hash`].
+    pub hash: String,
+}
+
 /// References for a test.
 #[derive(Debug, Clone)]
 pub enum Reference {
@@ -112,6 +190,7 @@ pub struct Test {
     id: Id,
     kind: Kind,
     annotations: EcoVec<Annotation>,
+    annotation_spans: EcoVec<AnnotationSpan>,
 }
 
 impl Test {
@@ -121,6 +200,7 @@ impl Test {
             id,
             kind,
             annotations: eco_vec![],
+            annotation_spans: eco_vec![],
         }
     }
 
@@ -141,13 +221,38 @@ impl Test {
             Kind::CompileOnly
         };
 
-        let annotations = Annotation::collect(&fs::read_to_string(test_script)?)?;
+        let (annotations, annotation_spans) =
+            Annotation::collect_spanned(&fs::read_to_string(test_script)?)?
+                .into_iter()
+                .unzip();
 
-        Ok(Some(Test {
+        let test = Test {
             id,
             kind,
             annotations,
-        }))
+            annotation_spans,
+        };
+
+        test.data_root(project).map_err(LoadError::DataRoot)?;
+
+        Ok(Some(test))
+    }
+}
+
+impl Test {
+    /// Adds the given annotations of a directory config to this test, those
+    /// which conflict with its own or have no effect on its kind are ignored.
+    pub fn inherit_annotations(&mut self, annotations: &[Annotation]) {
+        for annot in annotations {
+            if annot.applies_to(self.kind)
+                && !self
+                    .own_annotations()
+                    .iter()
+                    .any(|own| own.conflicts_with(annot))
+            {
+                self.annotations.push(annot.clone());
+            }
+        }
     }
 }
 
@@ -162,14 +267,117 @@ impl Test {
         self.kind
     }
 
-    /// This test's annotations.
+    /// This test's annotations, including those inherited from directory
+    /// configs after its own.
     pub fn annotations(&self) -> &[Annotation] {
         &self.annotations
     }
 
+    /// The annotations written in this test's script.
+    pub fn own_annotations(&self) -> &[Annotation] {
+        &self.annotations[..self.annotation_spans.len()]
+    }
+
+    /// The annotations this test inherited from directory configs, see
+    /// [`Test::inherit_annotations`].
+    pub fn inherited_annotations(&self) -> &[Annotation] {
+        &self.annotations[self.annotation_spans.len()..]
+    }
+
+    /// This test's annotations together with their location in the test
+    /// script.
+    pub fn spanned_annotations(&self) -> impl Iterator<Item = (&Annotation, &AnnotationSpan)> {
+        self.annotations.iter().zip(&self.annotation_spans)
+    }
+
     /// Whether this test has a `skip` annotation.
     pub fn is_skip(&self) -> bool {
-        self.annotations.contains(&Annotation::Skip)
+        self.annotations
+            .iter()
+            .any(|annot| matches!(annot, Annotation::Skip(_)))
+    }
+
+    /// The reason given on this test's `skip` annotation, if it has one.
+    pub fn skip_reason(&self) -> Option<&str> {
+        self.annotations.iter().find_map(|annot| match annot {
+            Annotation::Skip(reason) => reason.as_deref(),
+            _ => None,
+        })
+    }
+
+    /// The data root of this test within the project, if it has a `data-root`
+    /// annotation.
+    ///
+    /// The annotation's path is resolved relative to the test directory, or
+    /// the project root if it starts with a slash. Returns an error if it
+    /// escapes the project root.
+    pub fn data_root(&self, project: &Project) -> Result<Option<VirtualPath>, PathError> {
+        let Some(path) = self.annotations.iter().find_map(|annot| match annot {
+            Annotation::DataRoot(path) => Some(path),
+            _ => None,
+        }) else {
+            return Ok(None);
+        };
+
+        let test_dir = VirtualPath::virtualize(
+            project.root().as_std_path(),
+            project.unit_test_dir(&self.id).as_std_path(),
+        )
+        .expect("Project and Test must never emit escaping or invalid paths");
+
+        test_dir.join(path).map(Some)
+    }
+
+    /// The annotations of this test which have no effect on its kind, these
+    /// are never inherited.
+    ///
+    /// See [`Annotation::applies_to`].
+    pub fn inapplicable_annotations(&self) -> impl Iterator<Item = &Annotation> {
+        self.own_annotations()
+            .iter()
+            .filter(|annot| !annot.applies_to(self.kind))
+    }
+
+    /// The value of the given attribute of this test, its applicable
+    /// annotations are resolved against the given project defaults. Returns
+    /// `None` if the key is not one of [`ATTRIBUTES`].
+    ///
+    /// Values are rendered as they are written in annotations or the manifest.
+    pub fn attribute(&self, defaults: &ProjectDefaults, key: &str) -> Option<EcoString> {
+        let mut dir = defaults.direction;
+        let mut ppi = defaults.ppi;
+        let mut max_delta = defaults.max_delta;
+        let mut max_deviations = defaults.max_deviations;
+        let mut max_failing_pages = defaults.max_failing_pages;
+
+        for annot in &self.annotations {
+            if !annot.applies_to(self.kind) {
+                continue;
+            }
+
+            match annot {
+                Annotation::Dir(set) => dir = *set,
+                Annotation::Ppi(set) => ppi = *set,
+                Annotation::MaxDelta(set) => max_delta = *set,
+                Annotation::MaxDeviations(set) => max_deviations = *set,
+                Annotation::MaxFailingPages(set) => max_failing_pages = *set,
+                _ => {}
+            }
+        }
+
+        Some(match key {
+            "kind" => self.kind.as_str().into(),
+            "skip" => eco_format!("{}", self.is_skip()),
+            "dir" => match dir {
+                Direction::Ltr => "ltr".into(),
+                Direction::Rtl => "rtl".into(),
+            },
+            "ppi" => eco_format!("{ppi}"),
+            "max-delta" => eco_format!("{max_delta}"),
+            "max-deviations" => eco_format!("{max_deviations}"),
+            "max-failing-pages" => eco_format!("{max_failing_pages}"),
+            _ => return None,
+        })
     }
 }
 
@@ -178,7 +386,7 @@ impl Test {
     /// reference and annotations are parsed from the test script.
     ///
     /// # Panics
-    /// Panics if the given id is equal to the unique template test id.
+    /// Panics if the given id is a template test id.
     #[tracing::instrument(skip(project, source, reference))]
     pub fn create(
         project: &Project,
@@ -186,7 +394,7 @@ impl Test {
         source: &str,
         reference: Option<Reference>,
     ) -> Result<Test, CreateError> {
-        assert_ne!(id, Id::template());
+        assert!(!id.is_template());
 
         let test_dir = project.unit_test_dir(&id);
         tytanic_utils::fs::create_dir(test_dir, true)?;
@@ -203,12 +411,14 @@ impl Test {
             .map(Reference::kind)
             .unwrap_or(Kind::CompileOnly);
 
-        let annotations = Annotation::collect(source)?;
+        let (annotations, annotation_spans) =
+            Annotation::collect_spanned(source)?.into_iter().unzip();
 
         let this = Self {
             id,
             kind,
             annotations,
+            annotation_spans,
         };
 
         match reference {
@@ -293,15 +503,102 @@ impl Test {
         // NOTE(tinger): if there are already more pages than we want to create,
         // the surplus pages would persist and make every comparison fail due to
         // a page count mismatch, so we clear them to be sure.
-        self.delete_reference_document(project)?;
-
-        let ref_dir = project.unit_test_ref_dir(&self.id);
+        let ref_dir = project.unit_test_persistent_ref_target_dir(&self.id);
+        tytanic_utils::fs::remove_dir(&ref_dir, true)?;
         tytanic_utils::fs::create_dir(&ref_dir, true)?;
-        reference.save(&ref_dir, optimize_options)?;
+        reference.save_as(
+            &ref_dir,
+            project.config().ref_format,
+            &project.config().ref_pattern,
+            optimize_options,
+        )?;
+        self.write_reference_crops(project, reference.crops())?;
 
         Ok(())
     }
 
+    /// Updates the persistent reference document of this test, writing only
+    /// those pages which differ from the stored references.
+    ///
+    /// See [`Document::save_changed`] for when a page is considered unchanged.
+    #[tracing::instrument(skip(project, reference, optimize_options))]
+    pub fn update_reference_document(
+        &self,
+        project: &Project,
+        reference: &Document,
+        optimize_options: Option<&oxipng::Options>,
+    ) -> Result<SaveSummary, SaveError> {
+        let ref_dir = project.unit_test_persistent_ref_target_dir(&self.id);
+        tytanic_utils::fs::create_dir(&ref_dir, true)?;
+
+        let summary = reference.save_changed(
+            &ref_dir,
+            project.config().ref_format,
+            &project.config().ref_pattern,
+            optimize_options,
+        )?;
+        self.write_reference_crops(project, reference.crops())?;
+
+        Ok(summary)
+    }
+
+    /// Updates only the given 1-based pages of the persistent reference
+    /// document of this test.
+    ///
+    /// The stored reference must be intact and have as many pages as the
+    /// given reference, otherwise the pages of both would no longer line up.
+    #[tracing::instrument(skip(project, reference, optimize_options))]
+    pub fn update_reference_pages(
+        &self,
+        project: &Project,
+        reference: &Document,
+        pages: &BTreeSet<usize>,
+        optimize_options: Option<&oxipng::Options>,
+    ) -> Result<SaveSummary, UpdatePagesError> {
+        // NOTE(tinger): The pages are written into the target directory, so
+        // they must line up with the pages already stored there.
+        let ref_dir = project.unit_test_persistent_ref_target_dir(&self.id);
+        let stored = Document::check_matching(&ref_dir, &project.config().ref_pattern)?;
+        if stored != reference.buffers().len() {
+            return Err(UpdatePagesError::PageCount {
+                stored,
+                output: reference.buffers().len(),
+            });
+        }
+
+        if let Some(&page) = pages.iter().find(|&&page| page == 0 || page > stored) {
+            return Err(UpdatePagesError::InvalidPage {
+                page,
+                count: stored,
+            });
+        }
+
+        let summary = reference.save_pages(
+            &ref_dir,
+            pages,
+            project.config().ref_format,
+            &project.config().ref_pattern,
+            optimize_options,
+        )?;
+
+        let mut crops = self
+            .read_reference_crops(project)
+            .map_err(doc::SaveError::from)?;
+        crops.resize(stored, CropOffset::default());
+        for &page in pages {
+            crops[page - 1] = reference.crops().get(page - 1).copied().unwrap_or_default();
+        }
+
+        if crops.iter().all(|crop| *crop == CropOffset::default()) {
+            crops.clear();
+        }
+
+        self.write_reference_crops(project, &crops)
+            .map_err(doc::SaveError::from)?;
+
+        Ok(summary)
+    }
+
     /// Deletes all directories and scripts of this test.
     #[tracing::instrument(skip(project))]
     pub fn delete(&self, project: &Project) -> io::Result<()> {
@@ -327,6 +624,29 @@ impl Test {
         Ok(())
     }
 
+    /// Whether this test has orphaned references, these are reference
+    /// directories of ephemeral tests which would be persisted by the VCS.
+    ///
+    /// This usually happens if a persistent test was turned into an ephemeral
+    /// test by hand, leaving the previous references behind. Without a VCS
+    /// references are never considered orphaned.
+    pub fn has_orphaned_references(&self, project: &Project) -> io::Result<bool> {
+        let Some(vcs) = project.vcs() else {
+            return Ok(false);
+        };
+
+        if !self.kind.is_ephemeral() {
+            return Ok(false);
+        }
+
+        let ref_dir = project.unit_test_ref_dir(&self.id);
+        if !ref_dir.try_exists()? {
+            return Ok(false);
+        }
+
+        Ok(!vcs.is_directory_ignored(&ref_dir)?)
+    }
+
     /// Deletes the test script of this test.
     #[tracing::instrument(skip(project))]
     pub fn delete_script(&self, project: &Project) -> io::Result<()> {
@@ -440,7 +760,204 @@ impl Test {
     /// Loads the persistent reference document of this test.
     #[tracing::instrument(skip(project))]
     pub fn load_reference_document(&self, project: &Project) -> Result<Document, doc::LoadError> {
-        Document::load(project.unit_test_ref_dir(&self.id))
+        let reference = Document::load_matching(
+            project.unit_test_persistent_ref_dir(&self.id),
+            &project.config().ref_pattern,
+        )?;
+
+        Ok(reference.with_crops(self.read_reference_crops(project)?))
+    }
+
+    /// Attempts to decode each page of the persistent reference document of
+    /// this test, returns the pages which failed to decode, see
+    /// [`Document::verify_matching`].
+    ///
+    /// This should only be called on persistent tests.
+    #[tracing::instrument(skip(project))]
+    pub fn verify_reference_document(
+        &self,
+        project: &Project,
+    ) -> io::Result<Vec<(PathBuf, doc::LoadError)>> {
+        Document::verify_matching(
+            project.unit_test_persistent_ref_dir(&self.id),
+            &project.config().ref_pattern,
+        )
+    }
+
+    /// Computes the freshness hash of this test's references.
+    ///
+    /// This is a hash of the test script and the project defaults, it does not
+    /// depend on paths or timestamps and is therefore stable across checkouts
+    /// and machines.
+    #[tracing::instrument(skip(project))]
+    pub fn reference_hash(&self, project: &Project) -> io::Result<u128> {
+        let source = std::fs::read_to_string(project.unit_test_script(&self.id))?;
+
+        // NOTE(tinger): The debug representation of the defaults is stable
+        // enough for this purpose, it only needs to be consistent for the same
+        // version of tytanic.
+        Ok(hash128(&(
+            source,
+            format!("{:?}", project.config().defaults),
+        )))
+    }
+
+    /// Writes the reference metadata of this test containing its current
+    /// freshness hash, see [`Test::reference_hash`].
+    #[tracing::instrument(skip(project))]
+    pub fn write_reference_meta(&self, project: &Project) -> io::Result<()> {
+        let meta = ReferenceMeta {
+            hash: format!("{:032x}", self.reference_hash(project)?),
+        };
+
+        let ref_dir = project.unit_test_persistent_ref_target_dir(&self.id);
+        tytanic_utils::fs::create_dir(&ref_dir, true)?;
+        std::fs::write(
+            ref_dir.join(REFERENCE_META_FILE),
+            toml::to_string(&meta).map_err(io::Error::other)?,
+        )
+    }
+
+    /// Writes the crop offsets of the persistent references of this test,
+    /// removes them if the references were not cropped.
+    pub fn write_reference_crops(&self, project: &Project, crops: &[CropOffset]) -> io::Result<()> {
+        let path = project
+            .unit_test_persistent_ref_target_dir(&self.id)
+            .join(REFERENCE_CROPS_FILE);
+
+        if crops.is_empty() {
+            std::fs::remove_file(path).ignore(io_not_found)?;
+            return Ok(());
+        }
+
+        let crops = ReferenceCrops {
+            pages: crops.to_vec(),
+        };
+
+        std::fs::write(path, toml::to_string(&crops).map_err(io::Error::other)?)
+    }
+
+    /// Reads the crop offsets of the persistent references of this test,
+    /// these are empty if the references were not cropped.
+    #[tracing::instrument(skip(project))]
+    pub fn read_reference_crops(&self, project: &Project) -> io::Result<Vec<CropOffset>> {
+        let path = project
+            .unit_test_persistent_ref_dir(&self.id)
+            .join(REFERENCE_CROPS_FILE);
+
+        let Some(content) = std::fs::read_to_string(path).ignore(io_not_found)? else {
+            return Ok(vec![]);
+        };
+
+        toml::from_str::<ReferenceCrops>(&content)
+            .map(|crops| crops.pages)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Writes the normalized text of the persistent references of this test.
+    pub fn write_reference_text(&self, project: &Project, pages: &[String]) -> io::Result<()> {
+        let path = project
+            .unit_test_persistent_ref_target_dir(&self.id)
+            .join(REFERENCE_TEXT_FILE);
+
+        let text = ReferenceText {
+            pages: pages.to_vec(),
+        };
+
+        std::fs::write(path, toml::to_string(&text).map_err(io::Error::other)?)
+    }
+
+    /// Reads the normalized text of the persistent references of this test,
+    /// if it was written.
+    #[tracing::instrument(skip(project))]
+    pub fn read_reference_text(&self, project: &Project) -> io::Result<Option<Vec<String>>> {
+        let path = project
+            .unit_test_persistent_ref_dir(&self.id)
+            .join(REFERENCE_TEXT_FILE);
+
+        let Some(content) = std::fs::read_to_string(path).ignore(io_not_found)? else {
+            return Ok(None);
+        };
+
+        toml::from_str::<ReferenceText>(&content)
+            .map(|text| Some(text.pages))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Writes the PPI at which the persistent references of this test were
+    /// rendered.
+    ///
+    /// The PPI is written even if it is the project default, such that a later
+    /// change of the default is detected.
+    pub fn write_reference_ppi(&self, project: &Project, ppi: f64) -> io::Result<()> {
+        let path = project
+            .unit_test_persistent_ref_target_dir(&self.id)
+            .join(REFERENCE_PPI_FILE);
+
+        let ppi = ReferencePpi { ppi };
+
+        std::fs::write(path, toml::to_string(&ppi).map_err(io::Error::other)?)
+    }
+
+    /// Reads the PPI at which the persistent references of this test were
+    /// rendered, this is the project default for references which were
+    /// stored before the PPI was recorded.
+    #[tracing::instrument(skip(project))]
+    pub fn read_reference_ppi(&self, project: &Project) -> io::Result<f64> {
+        let path = project
+            .unit_test_persistent_ref_dir(&self.id)
+            .join(REFERENCE_PPI_FILE);
+
+        let Some(content) = std::fs::read_to_string(path).ignore(io_not_found)? else {
+            return Ok(project.config().defaults.ppi);
+        };
+
+        toml::from_str::<ReferencePpi>(&content)
+            .map(|ppi| ppi.ppi)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Reads the reference metadata of this test, if it exists.
+    #[tracing::instrument(skip(project))]
+    pub fn read_reference_meta(&self, project: &Project) -> io::Result<Option<ReferenceMeta>> {
+        let path = project
+            .unit_test_persistent_ref_dir(&self.id)
+            .join(REFERENCE_META_FILE);
+
+        let Some(content) = std::fs::read_to_string(path).ignore(io_not_found)? else {
+            return Ok(None);
+        };
+
+        toml::from_str(&content)
+            .map(Some)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Whether the references of this test may be stale, i.e. whether the
+    /// stored freshness hash differs from the current one.
+    ///
+    /// Returns `None` if no reference metadata is stored.
+    #[tracing::instrument(skip(project))]
+    pub fn are_references_stale(&self, project: &Project) -> io::Result<Option<bool>> {
+        let Some(meta) = self.read_reference_meta(project)? else {
+            return Ok(None);
+        };
+
+        let hash = format!("{:032x}", self.reference_hash(project)?);
+
+        Ok(Some(meta.hash != hash))
+    }
+
+    /// Checks that the persistent reference document of this test can be
+    /// loaded without decoding it, returns the number of reference pages.
+    ///
+    /// See [`Document::check`].
+    #[tracing::instrument(skip(project))]
+    pub fn check_reference_document(&self, project: &Project) -> Result<usize, doc::CheckError> {
+        Document::check_matching(
+            project.unit_test_persistent_ref_dir(&self.id),
+            &project.config().ref_pattern,
+        )
     }
 }
 
@@ -464,6 +981,44 @@ pub enum CreateError {
     Io(#[from] io::Error),
 }
 
+/// Returned by [`Test::update_reference_pages`].
+#[derive(Debug, Error)]
+pub enum UpdatePagesError {
+    /// The stored reference document could not be checked.
+    #[error("the stored reference document is invalid")]
+    Check(#[from] doc::CheckError),
+
+    /// The page count of the output differs from the stored reference.
+    #[error(
+        "the output has {output} {}, but the stored reference has {stored}",
+        Term::simple("page").with(*output),
+    )]
+    PageCount {
+        /// The number of stored reference pages.
+        stored: usize,
+
+        /// The number of output pages.
+        output: usize,
+    },
+
+    /// A page was given which is not part of the document.
+    #[error(
+        "page {page} is not part of the document, it has {count} {}",
+        Term::simple("page").with(*count),
+    )]
+    InvalidPage {
+        /// The invalid 1-based page number.
+        page: usize,
+
+        /// The number of pages in the document.
+        count: usize,
+    },
+
+    /// An error occurred while saving the pages.
+    #[error("an error occurred while saving the pages")]
+    Save(#[from] doc::SaveError),
+}
+
 /// Returned by [`Test::load`].
 #[derive(Debug, Error)]
 pub enum LoadError {
@@ -471,6 +1026,10 @@ pub enum LoadError {
     #[error("an error occurred while parsing a test annotation")]
     Annotation(#[from] ParseAnnotationError),
 
+    /// The data root of the test is invalid.
+    #[error("the data root of the test is invalid")]
+    DataRoot(#[source] PathError),
+
     /// An IO error occurred.
     #[error("an io error occurred")]
     Io(#[from] io::Error),
@@ -681,6 +1240,94 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_has_orphaned_references() {
+        use crate::project::Vcs;
+        use crate::project::VcsKind;
+
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("tests/orphaned/test.typ", "Hello World")
+                    .setup_file("tests/orphaned/ref.typ", "Hello World")
+                    .setup_file_empty("tests/orphaned/ref/1.png")
+                    .setup_file("tests/ignored/test.typ", "Hello World")
+                    .setup_file("tests/ignored/ref.typ", "Hello World")
+                    .setup_file_empty("tests/ignored/ref/.gitignore")
+                    .setup_file("tests/persistent/test.typ", "Hello World")
+                    .setup_file_empty("tests/persistent/ref/1.png")
+            },
+            |root| {
+                let project = Project::new(root);
+                assert!(
+                    !test("orphaned", Kind::Ephemeral)
+                        .has_orphaned_references(&project)
+                        .unwrap()
+                );
+
+                let project = project.with_vcs(Some(Vcs::new(root.to_path_buf(), VcsKind::Git)));
+                assert!(
+                    test("orphaned", Kind::Ephemeral)
+                        .has_orphaned_references(&project)
+                        .unwrap()
+                );
+                assert!(
+                    !test("ignored", Kind::Ephemeral)
+                        .has_orphaned_references(&project)
+                        .unwrap()
+                );
+                assert!(
+                    !test("persistent", Kind::Persistent)
+                        .has_orphaned_references(&project)
+                        .unwrap()
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_update_reference_pages() {
+        let page = tiny_skia::Pixmap::new(10, 10).unwrap();
+        let png = page.encode_png().unwrap();
+
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("tests/persistent/test.typ", "Hello World")
+                    .setup_file("tests/persistent/ref/1.png", &png)
+                    .setup_file("tests/persistent/ref/2.png", &png)
+            },
+            |root| {
+                let project = Project::new(root);
+                let test = test("persistent", Kind::Persistent);
+
+                let doc = Document::new([page.clone(), page.clone(), page.clone()]);
+                assert!(matches!(
+                    test.update_reference_pages(&project, &doc, &BTreeSet::from([2]), None),
+                    Err(UpdatePagesError::PageCount {
+                        stored: 2,
+                        output: 3,
+                    }),
+                ));
+
+                let doc = Document::new([page.clone(), page.clone()]);
+                assert!(matches!(
+                    test.update_reference_pages(&project, &doc, &BTreeSet::from([3]), None),
+                    Err(UpdatePagesError::InvalidPage { page: 3, count: 2 }),
+                ));
+
+                let summary = test
+                    .update_reference_pages(&project, &doc, &BTreeSet::from([2]), None)
+                    .unwrap();
+                assert_eq!(
+                    summary,
+                    SaveSummary {
+                        updated: 1,
+                        unchanged: 1,
+                    }
+                );
+            },
+        );
+    }
+
     #[test]
     fn test_load_sources() {
         TempTestEnv::run_no_check(
@@ -717,4 +1364,121 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn test_data_root() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("tests/relative/test.typ", "/// [data-root: data]")
+                    .setup_file("tests/absolute/test.typ", "/// [data-root: /assets]")
+                    .setup_file("tests/escaping/test.typ", "/// [data-root: ../../..]")
+            },
+            |root| {
+                let project = Project::new(root);
+
+                let test = Test::load(&project, id("relative")).unwrap().unwrap();
+                assert_eq!(
+                    test.data_root(&project).unwrap(),
+                    Some(VirtualPath::new("tests/relative/data").unwrap()),
+                );
+
+                let test = Test::load(&project, id("absolute")).unwrap().unwrap();
+                assert_eq!(
+                    test.data_root(&project).unwrap(),
+                    Some(VirtualPath::new("assets").unwrap()),
+                );
+
+                assert!(matches!(
+                    Test::load(&project, id("escaping")),
+                    Err(LoadError::DataRoot(PathError::Escapes))
+                ));
+            },
+        );
+    }
+
+    #[test]
+    fn test_spanned_annotations() {
+        TempTestEnv::run_no_check(
+            |root| root.setup_file("tests/fancy/test.typ", "/// [skip]\n/// [ppi: 72]\nHello"),
+            |root| {
+                let project = Project::new(root);
+
+                let test = Test::load(&project, id("fancy")).unwrap().unwrap();
+                let spans: Vec<_> = test
+                    .spanned_annotations()
+                    .map(|(annot, span)| (annot.name(), span.line, span.range.clone()))
+                    .collect();
+
+                assert_eq!(spans, [("skip", 1, 4..10), ("ppi", 2, 15..24)]);
+            },
+        );
+    }
+
+    #[test]
+    fn test_skip_reason() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("tests/reason/test.typ", "/// [skip: flaky]\nHello")
+                    .setup_file("tests/no-reason/test.typ", "/// [skip]\nHello")
+            },
+            |root| {
+                let project = Project::new(root);
+
+                let test = Test::load(&project, id("reason")).unwrap().unwrap();
+                assert!(test.is_skip());
+                assert_eq!(test.skip_reason(), Some("flaky"));
+
+                let test = Test::load(&project, id("no-reason")).unwrap().unwrap();
+                assert!(test.is_skip());
+                assert_eq!(test.skip_reason(), None);
+            },
+        );
+    }
+
+    #[test]
+    fn test_attribute() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file(
+                    "tests/attrs/test.typ",
+                    "/// [ppi: 72]\n/// [dir: rtl]\nHello",
+                )
+                .setup_file("tests/attrs/ref.typ", "Hello")
+            },
+            |root| {
+                let project = Project::new(root);
+                let defaults = ProjectDefaults::default();
+
+                let test = Test::load(&project, id("attrs")).unwrap().unwrap();
+                assert_eq!(
+                    test.attribute(&defaults, "kind").as_deref(),
+                    Some("ephemeral")
+                );
+                assert_eq!(test.attribute(&defaults, "skip").as_deref(), Some("false"));
+                assert_eq!(test.attribute(&defaults, "dir").as_deref(), Some("rtl"));
+                assert_eq!(test.attribute(&defaults, "ppi").as_deref(), Some("72"));
+                assert_eq!(test.attribute(&defaults, "max-delta").as_deref(), Some("1"));
+                assert_eq!(test.attribute(&defaults, "warnings"), None);
+            },
+        );
+    }
+
+    #[test]
+    fn test_reference_meta() {
+        TempTestEnv::run_no_check(
+            |root| root.setup_file("tests/persistent/test.typ", "Hello World"),
+            |root| {
+                let project = Project::new(root);
+                let test = test("persistent", Kind::Persistent);
+
+                assert_eq!(test.are_references_stale(&project).unwrap(), None);
+
+                test.write_reference_meta(&project).unwrap();
+                assert_eq!(test.are_references_stale(&project).unwrap(), Some(false));
+
+                test.create_script(&project, "Hello\nWorld").unwrap();
+                assert_eq!(test.are_references_stale(&project).unwrap(), Some(true));
+            },
+        );
+    }
 }
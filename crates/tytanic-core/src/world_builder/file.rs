@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::path::Path;
@@ -5,17 +6,22 @@ use std::path::PathBuf;
 use std::sync::Mutex;
 use std::sync::MutexGuard;
 
+use camino::Utf8Path;
+use ecow::EcoString;
 use ecow::eco_format;
 use typst::diag::FileError;
 use typst::diag::FileResult;
 use typst::diag::PackageError;
 use typst::foundations::Bytes;
 use typst::syntax::FileId;
+use typst::syntax::RootedPath;
 use typst::syntax::Source;
+use typst::syntax::VirtualPath;
 use typst::syntax::VirtualRoot;
 use typst::syntax::package::PackageSpec;
 use typst_kit::files::FsRoot;
 use typst_kit::packages::SystemPackages;
+use typst_utils::hash128;
 
 use super::ProvideFile;
 
@@ -433,10 +439,147 @@ fn decode_utf8(buf: &[u8]) -> FileResult<&str> {
     )?)
 }
 
+/// Provides access to files from another provider, but resolves reads of data
+/// files in the project against a data root first.
+///
+/// This allows tests to read fixtures which live in a directory of their own
+/// as if they were at the project root. Only bytes are remapped, sources used
+/// for imports are always resolved against the project root. If a file doesn't
+/// exist within the data root, it is resolved as usual.
+#[derive(Debug)]
+pub struct DataRootFileProvider<P> {
+    inner: P,
+    data_root: VirtualPath,
+}
+
+impl<P> DataRootFileProvider<P> {
+    /// Creates a new file provider which resolves data files against the given
+    /// data root within the project before delegating to `inner`.
+    pub fn new(inner: P, data_root: VirtualPath) -> Self {
+        Self { inner, data_root }
+    }
+}
+
+impl<P> DataRootFileProvider<P> {
+    /// The inner file provider.
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// The data root against which data files are resolved first.
+    pub fn data_root(&self) -> &VirtualPath {
+        &self.data_root
+    }
+}
+
+impl<P: ProvideFile> ProvideFile for DataRootFileProvider<P> {
+    fn provide_source(&self, id: FileId) -> FileResult<Source> {
+        self.inner.provide_source(id)
+    }
+
+    fn provide_bytes(&self, id: FileId) -> FileResult<Bytes> {
+        if *id.root() == VirtualRoot::Project
+            && let Ok(vpath) = self.data_root.join(id.vpath().get_without_slash())
+            && let Ok(bytes) = self
+                .inner
+                .provide_bytes(FileId::new(RootedPath::new(VirtualRoot::Project, vpath)))
+        {
+            return Ok(bytes);
+        }
+
+        self.inner.provide_bytes(id)
+    }
+
+    fn reset_all(&self) {
+        self.inner.reset_all()
+    }
+}
+
+/// Provides access to files from another provider, but records the content
+/// hash of each file that was successfully provided.
+///
+/// Files are recorded by their rooted path rather than their [`FileId`], which
+/// is only unique within a process. Together with stable content hashes this
+/// makes the recorded inputs deterministic across compilations and machines.
+#[derive(Debug)]
+pub struct RecordingFileProvider<P> {
+    inner: P,
+    files: Mutex<BTreeMap<EcoString, u128>>,
+}
+
+impl<P> RecordingFileProvider<P> {
+    /// Creates a new file provider which records the files provided by
+    /// `inner`.
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            files: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl<P> RecordingFileProvider<P> {
+    /// The inner file provider.
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// The files recorded so far together with their content hashes, keyed by
+    /// their rooted path.
+    pub fn files(&self) -> BTreeMap<EcoString, u128> {
+        self.files.lock().unwrap().clone()
+    }
+
+    /// Consumes the provider and returns the recorded files.
+    pub fn into_files(self) -> BTreeMap<EcoString, u128> {
+        self.files.into_inner().unwrap()
+    }
+
+    fn record(&self, id: FileId, content: &[u8]) {
+        let key = match id.root() {
+            VirtualRoot::Project => EcoString::from(id.vpath().get_with_slash()),
+            VirtualRoot::Package(spec) => eco_format!("{spec}{}", id.vpath().get_with_slash()),
+        };
+
+        self.files.lock().unwrap().insert(key, hash128(content));
+    }
+}
+
+/// The key under which a [`RecordingFileProvider`] records the file at the
+/// given path within the project root, `None` if it is outside of the root.
+pub fn project_file_key(root: &Utf8Path, path: &Utf8Path) -> Option<EcoString> {
+    let path = path.strip_prefix(root).ok()?;
+
+    Some(eco_format!(
+        "/{}",
+        path.components()
+            .map(|component| component.as_str())
+            .collect::<Vec<_>>()
+            .join("/")
+    ))
+}
+
+impl<P: ProvideFile> ProvideFile for RecordingFileProvider<P> {
+    fn provide_source(&self, id: FileId) -> FileResult<Source> {
+        let source = self.inner.provide_source(id)?;
+        self.record(id, source.text().as_bytes());
+        Ok(source)
+    }
+
+    fn provide_bytes(&self, id: FileId) -> FileResult<Bytes> {
+        let bytes = self.inner.provide_bytes(id)?;
+        self.record(id, bytes.as_slice());
+        Ok(bytes)
+    }
+
+    fn reset_all(&self) {
+        self.inner.reset_all()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use typst::syntax::package::PackageVersion;
-    use typst::syntax::{RootedPath, VirtualPath, VirtualRoot};
     use tytanic_utils::fs::TempTestEnv;
 
     use super::*;
@@ -504,4 +647,94 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn test_data_root() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("data.csv", "root-data")
+                    .setup_file("other.csv", "root-other")
+                    .setup_file("tests/foo/data/data.csv", "test-data")
+                    .setup_file("tests/foo/data/lib.typ", "test-lib")
+                    .setup_file("lib.typ", "root-lib")
+            },
+            |root| {
+                let files = DataRootFileProvider::new(
+                    FilesystemFileProvider::new(root, None),
+                    VirtualPath::new("tests/foo/data").unwrap(),
+                );
+
+                let id = |path| {
+                    FileId::new(RootedPath::new(
+                        VirtualRoot::Project,
+                        VirtualPath::new(path).unwrap(),
+                    ))
+                };
+
+                // data.csv is remapped to the data root
+                assert_eq!(
+                    files.provide_bytes(id("data.csv")).unwrap().as_slice(),
+                    b"test-data",
+                );
+
+                // other.csv is not in the data root and falls back to the root
+                assert_eq!(
+                    files.provide_bytes(id("other.csv")).unwrap().as_slice(),
+                    b"root-other",
+                );
+
+                // sources are never remapped
+                assert_eq!(
+                    files.provide_source(id("lib.typ")).unwrap().text(),
+                    "root-lib",
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_recording() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("main.typ", "main")
+                    .setup_file("data.csv", "data")
+            },
+            |root| {
+                let files = RecordingFileProvider::new(FilesystemFileProvider::new(root, None));
+
+                let id = |path| {
+                    FileId::new(RootedPath::new(
+                        VirtualRoot::Project,
+                        VirtualPath::new(path).unwrap(),
+                    ))
+                };
+
+                files.provide_source(id("main.typ")).unwrap();
+                files.provide_bytes(id("data.csv")).unwrap();
+                files.provide_bytes(id("missing.csv")).unwrap_err();
+
+                assert_eq!(
+                    files.into_files(),
+                    BTreeMap::from([
+                        ("/data.csv".into(), hash128(b"data".as_slice())),
+                        ("/main.typ".into(), hash128(b"main".as_slice())),
+                    ]),
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_project_file_key() {
+        let root = Utf8Path::new("/project");
+
+        assert_eq!(
+            project_file_key(root, Utf8Path::new("/project/src/lib.typ")).as_deref(),
+            Some("/src/lib.typ"),
+        );
+        assert_eq!(
+            project_file_key(root, Utf8Path::new("/other/lib.typ")),
+            None,
+        );
+    }
 }
@@ -1,6 +1,11 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::sync::OnceLock;
+
 use typst::text::Font;
 use typst::text::FontBook;
 use typst::utils::LazyHash;
+use typst_kit::fonts::FontStore;
 
 use super::ProvideFont;
 
@@ -49,3 +54,130 @@ impl ProvideFont for VirtualFontProvider {
         self.font(index).cloned()
     }
 }
+
+/// Provides thread-safe access to fonts shared by many worlds.
+///
+/// Cloning this provider is cheap, all clones share the same font book and
+/// font slots. Each font is decoded at most once on first access, regardless of
+/// how many worlds or threads access it.
+#[derive(Clone)]
+pub struct SharedFontProvider {
+    store: Arc<FontStore>,
+}
+
+impl SharedFontProvider {
+    /// Creates a new shared font provider from the given font store.
+    pub fn new(store: FontStore) -> Self {
+        Self {
+            store: Arc::new(store),
+        }
+    }
+
+    /// The process-global shared font provider.
+    ///
+    /// The font store is created using `init` on the first call, subsequent
+    /// calls return the same provider and don't call `init`.
+    pub fn global<F>(init: F) -> &'static Self
+    where
+        F: FnOnce() -> FontStore,
+    {
+        static GLOBAL: OnceLock<SharedFontProvider> = OnceLock::new();
+
+        GLOBAL.get_or_init(|| Self::new(init()))
+    }
+}
+
+impl SharedFontProvider {
+    /// The font book storing the font metadata.
+    pub fn book(&self) -> &LazyHash<FontBook> {
+        self.store.book()
+    }
+
+    /// Access the font with the given index, decoding it if it wasn't accessed
+    /// before.
+    pub fn font(&self, index: usize) -> Option<Font> {
+        self.store.font(index)
+    }
+}
+
+impl From<FontStore> for SharedFontProvider {
+    fn from(value: FontStore) -> Self {
+        Self::new(value)
+    }
+}
+
+impl Debug for SharedFontProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedFontProvider")
+            .field("families", &self.book().families().count())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ProvideFont for SharedFontProvider {
+    fn provide_font_book(&self) -> &LazyHash<FontBook> {
+        self.book()
+    }
+
+    fn provide_font(&self, index: usize) -> Option<Font> {
+        self.font(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::thread;
+
+    use typst::foundations::Bytes;
+    use typst_kit::fonts::FontSource;
+
+    use super::*;
+
+    struct CountingSource {
+        font: Font,
+        loads: Arc<AtomicUsize>,
+    }
+
+    impl FontSource for CountingSource {
+        fn load(&self) -> Option<Font> {
+            self.loads.fetch_add(1, Ordering::SeqCst);
+            Some(self.font.clone())
+        }
+    }
+
+    #[test]
+    fn test_shared_font_provider_decodes_once() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let font = typst_assets::fonts()
+            .flat_map(|data| Font::iter(Bytes::new(data)))
+            .next()
+            .unwrap();
+
+        let mut store = FontStore::new();
+        store.push((
+            CountingSource {
+                font: font.clone(),
+                loads: Arc::clone(&loads),
+            },
+            font.info().clone(),
+        ));
+
+        let provider = SharedFontProvider::new(store);
+
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                let provider = provider.clone();
+                let font = font.clone();
+                scope.spawn(move || {
+                    assert_eq!(provider.provide_font(0), Some(font));
+                    assert_eq!(provider.provide_font(1), None);
+                });
+            }
+        });
+
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        assert_eq!(provider.provide_font_book().families().count(), 1);
+    }
+}
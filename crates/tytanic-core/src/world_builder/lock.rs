@@ -0,0 +1,414 @@
+//! Pinning the contents of packages used by tests.
+//!
+//! A lockfile records the content hash of every package file which was read
+//! while compiling the tests of a project. Compiling with a
+//! [`LockedFileProvider`] fails if a package file is not locked or its content
+//! changed since it was locked, this keeps references reproducible across
+//! machines even if a package is re-published or modified locally.
+
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+use ecow::EcoString;
+use ecow::eco_format;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+use typst::diag::FileError;
+use typst::diag::FileResult;
+use typst::diag::PackageError;
+use typst::foundations::Bytes;
+use typst::syntax::FileId;
+use typst::syntax::Source;
+use typst::syntax::VirtualRoot;
+use typst::syntax::package::PackageSpec;
+use typst_utils::hash128;
+use tytanic_utils::result::ResultEx;
+use tytanic_utils::result::io_not_found;
+
+use super::ProvideFile;
+
+/// The name of the lockfile in the project root.
+pub const LOCKFILE_NAME: &str = "tytanic.lock";
+
+/// The header written at the start of each lockfile.
+const LOCKFILE_HEADER: &str =
+    "# This file is generated by `tt util lock`, do not edit it manually.\n\n";
+
+/// The locked package files of a project.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Lockfile {
+    /// The content hashes of the locked files, keyed by their package spec
+    /// and path within the package.
+    #[serde(default)]
+    pub packages: BTreeMap<EcoString, BTreeMap<EcoString, EcoString>>,
+}
+
+impl Lockfile {
+    /// Reads the lockfile at the given path. Returns `None` if it doesn't
+    /// exist.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Option<Self>, LockfileError> {
+        let Some(content) = fs::read_to_string(path).ignore(io_not_found)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(toml::from_str(&content)?))
+    }
+
+    /// Writes the lockfile to the given path.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let content = toml::to_string(self).map_err(io::Error::other)?;
+        fs::write(path, format!("{LOCKFILE_HEADER}{content}"))
+    }
+}
+
+impl Lockfile {
+    /// Whether no package files are locked.
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+
+    /// Locks the given content of a file in a package.
+    pub fn lock(&mut self, spec: &PackageSpec, id: FileId, content: &[u8]) {
+        self.packages
+            .entry(eco_format!("{spec}"))
+            .or_default()
+            .insert(id.vpath().get_with_slash().into(), content_hash(content));
+    }
+
+    /// Verifies the given content of a file in a package against the locked
+    /// content hash.
+    pub fn verify(
+        &self,
+        spec: &PackageSpec,
+        id: FileId,
+        content: &[u8],
+    ) -> Result<(), VerifyError> {
+        let Some(files) = self.packages.get(eco_format!("{spec}").as_str()) else {
+            return Err(VerifyError::PackageNotLocked);
+        };
+
+        let Some(hash) = files.get(id.vpath().get_with_slash()) else {
+            return Err(VerifyError::FileNotLocked);
+        };
+
+        if *hash != content_hash(content) {
+            return Err(VerifyError::Changed);
+        }
+
+        Ok(())
+    }
+}
+
+/// The content hash stored for a file in the lockfile.
+fn content_hash(content: &[u8]) -> EcoString {
+    eco_format!("{:032x}", hash128(content))
+}
+
+/// Provides access to files from another provider, but verifies that package
+/// files match the given lockfile.
+///
+/// Packages which are exempt, such as packages resolved to local directories,
+/// are not verified.
+#[derive(Debug)]
+pub struct LockedFileProvider<P> {
+    inner: P,
+    lockfile: Lockfile,
+    exempt: HashSet<PackageSpec>,
+}
+
+impl<P> LockedFileProvider<P> {
+    /// Creates a new file provider which verifies the package files provided
+    /// by `inner` against the given lockfile.
+    pub fn new(inner: P, lockfile: Lockfile) -> Self {
+        Self::with_exempt(inner, lockfile, [])
+    }
+
+    /// Creates a new file provider which verifies the package files provided
+    /// by `inner` against the given lockfile, except for the exempt packages.
+    pub fn with_exempt<I>(inner: P, lockfile: Lockfile, exempt: I) -> Self
+    where
+        I: IntoIterator<Item = PackageSpec>,
+    {
+        Self {
+            inner,
+            lockfile,
+            exempt: HashSet::from_iter(exempt),
+        }
+    }
+}
+
+impl<P> LockedFileProvider<P> {
+    /// The inner file provider.
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// The lockfile against which package files are verified.
+    pub fn lockfile(&self) -> &Lockfile {
+        &self.lockfile
+    }
+
+    fn verify(&self, id: FileId, content: &[u8]) -> FileResult<()> {
+        let VirtualRoot::Package(spec) = id.root() else {
+            return Ok(());
+        };
+
+        if self.exempt.contains(spec) {
+            return Ok(());
+        }
+
+        self.lockfile.verify(spec, id, content).map_err(|err| {
+            tracing::error!(?spec, path = ?id.vpath(), %err, "failed to verify package file");
+            FileError::Package(PackageError::Other(Some(eco_format!(
+                "{err}: {spec}{}",
+                id.vpath().get_with_slash()
+            ))))
+        })
+    }
+}
+
+impl<P: ProvideFile> ProvideFile for LockedFileProvider<P> {
+    fn provide_source(&self, id: FileId) -> FileResult<Source> {
+        let source = self.inner.provide_source(id)?;
+        self.verify(id, source.text().as_bytes())?;
+        Ok(source)
+    }
+
+    fn provide_bytes(&self, id: FileId) -> FileResult<Bytes> {
+        let bytes = self.inner.provide_bytes(id)?;
+        self.verify(id, bytes.as_slice())?;
+        Ok(bytes)
+    }
+
+    fn reset_all(&self) {
+        self.inner.reset_all()
+    }
+}
+
+/// Provides access to files from another provider, but locks each package
+/// file that was successfully provided.
+///
+/// Packages which are exempt, such as packages resolved to local directories,
+/// are not locked.
+#[derive(Debug)]
+pub struct LockingFileProvider<P> {
+    inner: P,
+    lockfile: Mutex<Lockfile>,
+    exempt: HashSet<PackageSpec>,
+}
+
+impl<P> LockingFileProvider<P> {
+    /// Creates a new file provider which locks the package files provided by
+    /// `inner`.
+    pub fn new(inner: P) -> Self {
+        Self::with_exempt(inner, [])
+    }
+
+    /// Creates a new file provider which locks the package files provided by
+    /// `inner`, except for the exempt packages.
+    pub fn with_exempt<I>(inner: P, exempt: I) -> Self
+    where
+        I: IntoIterator<Item = PackageSpec>,
+    {
+        Self {
+            inner,
+            lockfile: Mutex::new(Lockfile::default()),
+            exempt: HashSet::from_iter(exempt),
+        }
+    }
+}
+
+impl<P> LockingFileProvider<P> {
+    /// The inner file provider.
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// Consumes the provider and returns the lockfile of all package files
+    /// provided so far.
+    pub fn into_lockfile(self) -> Lockfile {
+        self.lockfile.into_inner().unwrap()
+    }
+
+    fn lock(&self, id: FileId, content: &[u8]) {
+        if let VirtualRoot::Package(spec) = id.root()
+            && !self.exempt.contains(spec)
+        {
+            self.lockfile.lock().unwrap().lock(spec, id, content);
+        }
+    }
+}
+
+impl<P: ProvideFile> ProvideFile for LockingFileProvider<P> {
+    fn provide_source(&self, id: FileId) -> FileResult<Source> {
+        let source = self.inner.provide_source(id)?;
+        self.lock(id, source.text().as_bytes());
+        Ok(source)
+    }
+
+    fn provide_bytes(&self, id: FileId) -> FileResult<Bytes> {
+        let bytes = self.inner.provide_bytes(id)?;
+        self.lock(id, bytes.as_slice());
+        Ok(bytes)
+    }
+
+    fn reset_all(&self) {
+        self.inner.reset_all()
+    }
+}
+
+/// Returned by [`Lockfile::read`].
+#[derive(Debug, Error)]
+pub enum LockfileError {
+    /// An error occurred while parsing the lockfile.
+    #[error("an error occurred while parsing the lockfile")]
+    Parse(#[from] toml::de::Error),
+
+    /// An IO error occurred.
+    #[error("an io error occurred")]
+    Io(#[from] io::Error),
+}
+
+/// Returned by [`Lockfile::verify`].
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VerifyError {
+    /// The package of the file was not locked.
+    #[error("package is not locked")]
+    PackageNotLocked,
+
+    /// The package was locked, but the file was not.
+    #[error("package file is not locked")]
+    FileNotLocked,
+
+    /// The content of the file changed since it was locked.
+    #[error("package file changed since it was locked")]
+    Changed,
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::syntax::RootedPath;
+    use typst::syntax::VirtualPath;
+    use typst::syntax::package::PackageVersion;
+    use tytanic_utils::fs::TempTestEnv;
+
+    use super::*;
+    use crate::world_builder::file::VirtualFileProvider;
+    use crate::world_builder::file::VirtualFileSlot;
+
+    fn spec(name: &str) -> PackageSpec {
+        PackageSpec {
+            namespace: "preview".into(),
+            name: name.into(),
+            version: PackageVersion {
+                major: 0,
+                minor: 1,
+                patch: 0,
+            },
+        }
+    }
+
+    fn id(root: VirtualRoot, path: &str) -> FileId {
+        FileId::new(RootedPath::new(root, VirtualPath::new(path).unwrap()))
+    }
+
+    fn files(entries: &[(FileId, &'static str)]) -> VirtualFileProvider {
+        VirtualFileProvider::from_slots(
+            entries
+                .iter()
+                .map(|&(id, text)| (id, VirtualFileSlot::from_bytes(id, text)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_locking_and_verifying() {
+        let lib = id(VirtualRoot::Package(spec("foo")), "lib.typ");
+        let other = id(VirtualRoot::Package(spec("foo")), "other.typ");
+        let unlocked = id(VirtualRoot::Package(spec("bar")), "lib.typ");
+        let local = id(VirtualRoot::Package(spec("local")), "lib.typ");
+        let project = id(VirtualRoot::Project, "main.typ");
+
+        let locking = LockingFileProvider::with_exempt(
+            files(&[(lib, "foo"), (local, "local"), (project, "main")]),
+            [spec("local")],
+        );
+        locking.provide_bytes(lib).unwrap();
+        locking.provide_bytes(local).unwrap();
+        locking.provide_bytes(project).unwrap();
+
+        let lockfile = locking.into_lockfile();
+        assert_eq!(lockfile.packages.len(), 1);
+        assert_eq!(lockfile.packages["@preview/foo:0.1.0"].len(), 1);
+
+        let locked = LockedFileProvider::with_exempt(
+            files(&[
+                (lib, "foo"),
+                (other, "other"),
+                (unlocked, "bar"),
+                (local, "changed"),
+                (project, "changed"),
+            ]),
+            lockfile.clone(),
+            [spec("local")],
+        );
+        locked.provide_bytes(lib).unwrap();
+        locked.provide_bytes(local).unwrap();
+        locked.provide_bytes(project).unwrap();
+        locked.provide_bytes(other).unwrap_err();
+        locked.provide_bytes(unlocked).unwrap_err();
+
+        let changed = LockedFileProvider::new(files(&[(lib, "changed")]), lockfile);
+        changed.provide_bytes(lib).unwrap_err();
+    }
+
+    #[test]
+    fn test_verify() {
+        let lib = id(VirtualRoot::Package(spec("foo")), "lib.typ");
+        let other = id(VirtualRoot::Package(spec("foo")), "other.typ");
+
+        let mut lockfile = Lockfile::default();
+        lockfile.lock(&spec("foo"), lib, b"foo");
+
+        assert_eq!(lockfile.verify(&spec("foo"), lib, b"foo"), Ok(()));
+        assert_eq!(
+            lockfile.verify(&spec("foo"), lib, b"bar"),
+            Err(VerifyError::Changed)
+        );
+        assert_eq!(
+            lockfile.verify(&spec("foo"), other, b"foo"),
+            Err(VerifyError::FileNotLocked)
+        );
+        assert_eq!(
+            lockfile.verify(&spec("bar"), lib, b"foo"),
+            Err(VerifyError::PackageNotLocked)
+        );
+    }
+
+    #[test]
+    fn test_read_write_round_trip() {
+        TempTestEnv::run_no_check(
+            |root| root,
+            |root| {
+                let path = root.join(LOCKFILE_NAME);
+                assert_eq!(Lockfile::read(&path).unwrap(), None);
+
+                let mut lockfile = Lockfile::default();
+                lockfile.lock(
+                    &spec("foo"),
+                    id(VirtualRoot::Package(spec("foo")), "lib.typ"),
+                    b"foo",
+                );
+                lockfile.write(&path).unwrap();
+
+                assert_eq!(Lockfile::read(&path).unwrap(), Some(lockfile));
+            },
+        );
+    }
+}
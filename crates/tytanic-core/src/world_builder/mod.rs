@@ -16,6 +16,7 @@ use tytanic_utils::forward_trait;
 
 pub mod file;
 pub mod font;
+pub mod lock;
 
 /// A trait for providing access to files.
 pub trait ProvideFile: Send + Sync {
@@ -88,6 +89,7 @@ pub struct ComposedWorldBuilder<'w> {
     fonts: Option<&'w dyn ProvideFont>,
     library: Option<&'w LazyHash<Library>>,
     datetime: Option<&'w Time>,
+    date: Option<Datetime>,
 }
 
 impl ComposedWorldBuilder<'_> {
@@ -98,6 +100,7 @@ impl ComposedWorldBuilder<'_> {
             fonts: None,
             library: None,
             datetime: None,
+            date: None,
         }
     }
 }
@@ -135,6 +138,15 @@ impl<'w> ComposedWorldBuilder<'w> {
         }
     }
 
+    /// Configure a fixed date which is returned for the current date instead
+    /// of the one of the datetime provider.
+    pub fn fixed_date(self, value: Datetime) -> Self {
+        Self {
+            date: Some(value),
+            ..self
+        }
+    }
+
     /// Build the world with the configured providers.
     ///
     /// Panics if a provider is missing.
@@ -151,6 +163,7 @@ impl<'w> ComposedWorldBuilder<'w> {
             fonts: self.fonts?,
             library: self.library?,
             datetime: self.datetime?,
+            date: self.date,
             id,
         })
     }
@@ -169,6 +182,7 @@ pub struct ComposedWorld<'w> {
     fonts: &'w dyn ProvideFont,
     library: &'w LazyHash<Library>,
     datetime: &'w Time,
+    date: Option<Datetime>,
     id: FileId,
 }
 
@@ -205,6 +219,10 @@ impl World for ComposedWorld<'_> {
     }
 
     fn today(&self, offset: Option<Duration>) -> Option<Datetime> {
+        if let Some(date) = self.date {
+            return Some(date);
+        }
+
         self.datetime.today(offset)
     }
 }
@@ -57,6 +57,12 @@ impl Filter for ExactFilter {
             expected: self.expected.clone(),
         }
     }
+
+    fn may_match(&self, id: &Id) -> bool {
+        self.expected
+            .iter()
+            .any(|expected| expected.ancestors().any(|ancestor| ancestor == id.as_str()))
+    }
 }
 
 /// Created by [`ExactFilter::state`] for [`ExactFilter`].
@@ -243,4 +249,16 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn test_may_match() {
+        let filter = ExactFilter::new([Id::new("foo/bar").unwrap(), Id::new("qux").unwrap()]);
+
+        assert!(filter.may_match(&Id::new("foo").unwrap()));
+        assert!(filter.may_match(&Id::new("foo/bar").unwrap()));
+        assert!(filter.may_match(&Id::new("qux").unwrap()));
+        assert!(!filter.may_match(&Id::new("foo/baz").unwrap()));
+        assert!(!filter.may_match(&Id::new("foo/bar/baz").unwrap()));
+        assert!(!filter.may_match(&Id::new("quux").unwrap()));
+    }
 }
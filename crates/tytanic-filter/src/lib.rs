@@ -22,6 +22,7 @@ use thiserror::Error;
 use tytanic_core::filter::Filter;
 use tytanic_core::filter::FilterState;
 use tytanic_core::project::Project;
+use tytanic_core::test::Id;
 use tytanic_core::test::Test;
 
 use crate::exact::ExactFilter;
@@ -102,6 +103,14 @@ impl Filter for CombinedFilter {
             exact: self.exact.as_ref().map(Filter::state),
         }
     }
+
+    fn may_match(&self, id: &Id) -> bool {
+        self.exact.as_ref().is_some_and(|exact| exact.may_match(id))
+            || self
+                .test_set
+                .as_ref()
+                .is_some_and(|test_set| test_set.may_match(id))
+    }
 }
 
 /// Created by [`Filter::state`] for [`CombinedFilter`].
@@ -8,6 +8,8 @@ use crate::test_set::ast::Error;
 use crate::test_set::ast::Func;
 use crate::test_set::ast::Id;
 use crate::test_set::ast::Num;
+use crate::test_set::ast::PairExt;
+use crate::test_set::ast::PairsExt;
 use crate::test_set::ast::Pat;
 use crate::test_set::ast::Rule;
 use crate::test_set::ast::Str;
@@ -70,6 +72,19 @@ pub enum Expr {
         /// The right-hand side of this binary expression.
         rhs: Arc<Expr>,
     },
+
+    /// A let expression, binds a value to an identifier for the scope of its
+    /// body.
+    Let {
+        /// The identifier to bind the value to.
+        id: Id,
+
+        /// The expression whose value is bound.
+        value: Arc<Expr>,
+
+        /// The expression in which the binding is visible.
+        body: Arc<Expr>,
+    },
 }
 
 // TODO(tinger): Flatten intersection and union chains.
@@ -98,6 +113,15 @@ impl Eval for Expr {
                     InfixOp::SymDiff => Set::expr_sym_diff(lhs, rhs),
                 }))
             }
+            Self::Let { id, value, body } => {
+                // The binding is only visible in the body, the outer context is
+                // left untouched.
+                let value = value.eval(ctx)?;
+                let mut ctx = ctx.clone();
+                ctx.bind(id.clone(), value);
+
+                body.eval(&ctx)
+            }
         }
     }
 }
@@ -114,6 +138,7 @@ impl Expr {
                     }
                     Rule::num_inner => Expr::Atom(Atom::Num(Num::parse(primary)?)),
                     Rule::func => Expr::Func(Func::parse(primary, pratt)?),
+                    Rule::expr_let => Self::parse_let(primary, pratt)?,
                     Rule::expr => Self::parse(primary, pratt)?,
                     x => unreachable!("unhandled primary expression {x:?}"),
                 })
@@ -135,4 +160,63 @@ impl Expr {
             })
             .parse(pair.into_inner())
     }
+
+    fn parse_let(pair: Pair<'_, Rule>, pratt: &PrattParser<Rule>) -> Result<Expr, Error> {
+        pair.expect_rules(&[Rule::expr_let])?;
+        let mut pairs = pair.into_inner();
+
+        let _ = pairs.expect_pair(&[Rule::expr_let_kw])?;
+        let id = Id::parse(pairs.expect_pair(&[Rule::id])?)?;
+        let _ = pairs.expect_pair(&[Rule::expr_let_assign])?;
+        let value = Self::parse(pairs.expect_pair(&[Rule::expr])?, pratt)?;
+        let _ = pairs.expect_pair(&[Rule::expr_let_sep])?;
+        let body = Self::parse(pairs.expect_pair(&[Rule::expr])?, pratt)?;
+        pairs.expect_end()?;
+
+        Ok(Expr::Let {
+            id,
+            value: Arc::new(value),
+            body: Arc::new(body),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_set::ast;
+
+    #[test]
+    fn test_eval_let_binding() {
+        let ctx = Context::new();
+        let expr = ast::parse("let a = 1; let b = a; b").unwrap();
+
+        let num: Num = expr.eval(&ctx).unwrap().expect_type().unwrap();
+        assert_eq!(num, Num(1));
+    }
+
+    #[test]
+    fn test_eval_let_shadowing() {
+        let mut ctx = Context::new();
+        ctx.bind(Id("a".into()), Value::Num(Num(0)));
+        let expr = ast::parse("let a = 1; a").unwrap();
+
+        let num: Num = expr.eval(&ctx).unwrap().expect_type().unwrap();
+        assert_eq!(num, Num(1));
+
+        // The outer binding is not affected by the let expression.
+        let num: Num = ctx.resolve("a").unwrap().expect_type().unwrap();
+        assert_eq!(num, Num(0));
+    }
+
+    #[test]
+    fn test_eval_let_scope() {
+        let ctx = Context::new();
+        let expr = ast::parse("let b = (let a = 1; a); a").unwrap();
+
+        assert!(matches!(
+            expr.eval(&ctx),
+            Err(eval::Error::UnknownBinding { id }) if id == "a",
+        ));
+    }
 }
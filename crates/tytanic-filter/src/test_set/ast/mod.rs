@@ -409,4 +409,52 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_parse_let_expression() {
+        assert_eq!(
+            parse("let a = 0 and 1; a or 2").unwrap(),
+            Expr::Let {
+                id: Id("a".into()),
+                value: Arc::new(Expr::Infix {
+                    op: InfixOp::Inter,
+                    lhs: Arc::new(Expr::Atom(Atom::Num(Num(0)))),
+                    rhs: Arc::new(Expr::Atom(Atom::Num(Num(1)))),
+                }),
+                body: Arc::new(Expr::Infix {
+                    op: InfixOp::Union,
+                    lhs: Arc::new(Expr::Atom(Atom::Id(Id("a".into())))),
+                    rhs: Arc::new(Expr::Atom(Atom::Num(Num(2)))),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_let_expression_nested() {
+        assert_eq!(
+            parse("let a = 0; let b = (let c = a; c); b").unwrap(),
+            Expr::Let {
+                id: Id("a".into()),
+                value: Arc::new(Expr::Atom(Atom::Num(Num(0)))),
+                body: Arc::new(Expr::Let {
+                    id: Id("b".into()),
+                    value: Arc::new(Expr::Let {
+                        id: Id("c".into()),
+                        value: Arc::new(Expr::Atom(Atom::Id(Id("a".into())))),
+                        body: Arc::new(Expr::Atom(Atom::Id(Id("c".into())))),
+                    }),
+                    body: Arc::new(Expr::Atom(Atom::Id(Id("b".into())))),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_let_keyword_prefix_is_identifier() {
+        assert_eq!(
+            parse("letter").unwrap(),
+            Expr::Atom(Atom::Id(Id("letter".into())))
+        );
+    }
 }
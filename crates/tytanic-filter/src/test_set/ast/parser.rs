@@ -38,6 +38,10 @@ impl Rule {
             Rule::EOI => "EOI",
             Rule::main | Rule::expr | Rule::expr_term | Rule::expr_atom => "expression",
             Rule::expr_group => "expression group",
+            Rule::expr_let => "let expression",
+            Rule::expr_let_kw => "let keyword",
+            Rule::expr_let_assign => "equals sign",
+            Rule::expr_let_sep => "semicolon",
             Rule::prefix_op => "prefix op",
             Rule::prefix_op_excl => "symbol complement op",
             Rule::prefix_op_not => "literal complement op",
@@ -92,6 +96,10 @@ impl Rule {
             Rule::EOI => "<EOI>",
             Rule::main | Rule::expr | Rule::expr_term | Rule::expr_atom => "<expr>",
             Rule::expr_group => "(...)",
+            Rule::expr_let => "let <ident> = <expr>; <expr>",
+            Rule::expr_let_kw => "let",
+            Rule::expr_let_assign => "=",
+            Rule::expr_let_sep => ";",
             Rule::prefix_op => "<prefix op>",
             Rule::prefix_op_excl => "!",
             Rule::prefix_op_not => "not",
@@ -5,13 +5,30 @@
 //! [reference]: https://typst-community.github.io/tytanic/reference/test-sets/index.html
 //! [guide]: https://typst-community.github.io/tytanic/guides/test-sets.html
 
+use std::collections::BTreeSet;
+use std::slice;
+use std::sync::OnceLock;
+
+use ecow::EcoString;
+use ecow::eco_format;
+use ecow::eco_vec;
+use tytanic_core::project::Project;
+use tytanic_core::suite::LastRun;
+use tytanic_core::suite::LastStatus;
+use tytanic_core::test::ANNOTATIONS;
 use tytanic_core::test::Test;
+use tytanic_core::test::unit::ATTRIBUTES;
+use tytanic_utils::fmt::Separators;
 
 use crate::test_set::ast::Id;
+use crate::test_set::ast::Num;
+use crate::test_set::ast::Str;
 use crate::test_set::eval::Context;
 use crate::test_set::eval::Error;
 use crate::test_set::eval::Func;
+use crate::test_set::eval::Guard;
 use crate::test_set::eval::Set;
+use crate::test_set::eval::Type;
 use crate::test_set::eval::Value;
 
 /// Creates the default context used by Tytanic, this contains bindings for the
@@ -30,6 +47,14 @@ pub fn context() -> Context {
         ("compile-only", dsl::func_compile_only_ctor),
         ("ephemeral", dsl::func_ephemeral_ctor),
         ("persistent", dsl::func_persistent_ctor),
+        ("changed", dsl::func_changed_ctor),
+        ("failed", dsl::func_failed_ctor),
+        ("passed", dsl::func_passed_ctor),
+        ("new", dsl::func_new_ctor),
+        ("attr", dsl::func_attr_ctor),
+        ("annot", dsl::func_annot_ctor),
+        ("count", dsl::func_count_ctor),
+        ("at-most", dsl::func_at_most_ctor),
     ];
 
     for (id, func) in functions {
@@ -153,4 +178,276 @@ pub mod dsl {
                 .is_some_and(|unit| unit.kind().is_persistent()))
         })
     }
+
+    /// The constructor function for the test set returned by [`set_changed`].
+    pub fn func_changed_ctor(ctx: &Context, args: &[Value]) -> Result<Value, Error> {
+        let base = Func::expect_args_max::<Str, 1>("changed", ctx, args)?
+            .pop()
+            .map(Str::into_inner);
+
+        Ok(Value::Set(set_changed(base)))
+    }
+
+    /// Constructs the `changed()` test set. A test set which contains all unit
+    /// tests whose test or reference script differs from the given base
+    /// revision, or the parent of the working copy if there is none.
+    ///
+    /// The changed files are queried from the project's VCS once, if there is
+    /// no VCS, this set contains no tests.
+    pub fn set_changed(base: Option<EcoString>) -> Set {
+        let changed = OnceLock::new();
+
+        Set::new(move |project: &Project, _, test: &Test| {
+            let changed = changed.get_or_init(|| match project.vcs() {
+                Some(vcs) => vcs.changed_files(base.as_deref()),
+                None => {
+                    tracing::warn!("no VCS detected, changed() contains no tests");
+                    Ok(BTreeSet::new())
+                }
+            });
+
+            let changed = match changed {
+                Ok(changed) => changed,
+                Err(err) => return Err(Error::Custom(err.to_string().into())),
+            };
+
+            Ok(test.as_unit_test().is_some_and(|unit| {
+                changed.contains(&project.unit_test_script(unit.id()))
+                    || changed.contains(&project.unit_test_ref_script(unit.id()))
+            }))
+        })
+    }
+
+    /// The constructor function for the test set returned by [`set_failed`].
+    pub fn func_failed_ctor(ctx: &Context, args: &[Value]) -> Result<Value, Error> {
+        Func::expect_no_args("failed", ctx, args)?;
+        Ok(Value::Set(set_failed()))
+    }
+
+    /// Constructs the `failed()` test set. A test set which contains all tests
+    /// which failed when they were last run.
+    pub fn set_failed() -> Set {
+        set_last_status("failed", |status| status == Some(LastStatus::Failed))
+    }
+
+    /// The constructor function for the test set returned by [`set_passed`].
+    pub fn func_passed_ctor(ctx: &Context, args: &[Value]) -> Result<Value, Error> {
+        Func::expect_no_args("passed", ctx, args)?;
+        Ok(Value::Set(set_passed()))
+    }
+
+    /// Constructs the `passed()` test set. A test set which contains all tests
+    /// which passed when they were last run.
+    pub fn set_passed() -> Set {
+        set_last_status("passed", |status| status == Some(LastStatus::Passed))
+    }
+
+    /// The constructor function for the test set returned by [`set_new`].
+    pub fn func_new_ctor(ctx: &Context, args: &[Value]) -> Result<Value, Error> {
+        Func::expect_no_args("new", ctx, args)?;
+        Ok(Value::Set(set_new()))
+    }
+
+    /// Constructs the `new()` test set. A test set which contains all tests
+    /// which were never run.
+    pub fn set_new() -> Set {
+        set_last_status("new", |status| status.is_none())
+    }
+
+    /// A test set which contains all tests whose status in the last run
+    /// matches the given predicate, the status is `None` for tests which were
+    /// never run.
+    ///
+    /// The last run is read once, if no run was recorded yet, a warning is
+    /// emitted if this set contains no tests because of it.
+    fn set_last_status(func: &'static str, pred: fn(Option<LastStatus>) -> bool) -> Set {
+        let last_run = OnceLock::new();
+
+        Set::new(move |project: &Project, _, test: &Test| {
+            let last_run = last_run.get_or_init(|| {
+                let last_run = LastRun::read(project);
+                if let Ok(None) = last_run
+                    && !pred(None)
+                {
+                    tracing::warn!(
+                        "no previous run recorded, {func}() contains no tests, \
+                        run the tests first"
+                    );
+                }
+                last_run
+            });
+
+            match last_run {
+                Ok(last_run) => Ok(pred(
+                    last_run
+                        .as_ref()
+                        .and_then(|last_run| last_run.status(test.id())),
+                )),
+                Err(err) => Err(Error::Custom(err.to_string().into())),
+            }
+        })
+    }
+
+    /// The constructor function for the test set returned by [`set_attr`].
+    pub fn func_attr_ctor(ctx: &Context, args: &[Value]) -> Result<Value, Error> {
+        let [key, value] = args else {
+            return Err(Error::InvalidArgumentCount {
+                func: "attr".into(),
+                expected: 2,
+                is_min: false,
+                found: args.len(),
+            });
+        };
+
+        let [key] = Func::expect_args_exact::<Str, 1>("attr", ctx, slice::from_ref(key))?;
+        let key = key.into_inner();
+
+        if !ATTRIBUTES.contains(&key.as_str()) {
+            return Err(Error::Custom(
+                format!(
+                    "unknown attribute {key:?}, expected {}",
+                    Separators::comma_or().with(ATTRIBUTES.iter().map(|key| format!("{key:?}"))),
+                )
+                .into(),
+            ));
+        }
+
+        let value = match value {
+            Value::Str(str) => str.as_str().into(),
+            Value::Num(num) => eco_format!("{}", num.0),
+            _ => {
+                return Err(Error::TypeMismatch {
+                    expected: eco_vec![Type::Str, Type::Num],
+                    found: value.as_type(),
+                });
+            }
+        };
+
+        Ok(Value::Set(set_attr(key, value)))
+    }
+
+    /// Constructs the `attr(key, value)` test set. A test set which contains
+    /// all unit tests whose attribute with the given key has the given value,
+    /// see [`UnitTest::attribute`].
+    ///
+    /// The key must be one of [`ATTRIBUTES`].
+    ///
+    /// [`UnitTest::attribute`]: tytanic_core::test::UnitTest::attribute
+    pub fn set_attr(key: EcoString, value: EcoString) -> Set {
+        Set::new(move |project: &Project, _, test: &Test| {
+            Ok(test.as_unit_test().is_some_and(|unit| {
+                unit.attribute(&project.config().defaults, &key)
+                    .is_some_and(|attr| attr == value)
+            }))
+        })
+    }
+
+    /// The constructor function for the test set returned by [`set_annot`].
+    pub fn func_annot_ctor(ctx: &Context, args: &[Value]) -> Result<Value, Error> {
+        let (name, value) = match args {
+            [name] => (name, None),
+            [name, value] => (name, Some(value)),
+            _ => {
+                return Err(Error::InvalidArgumentCount {
+                    func: "annot".into(),
+                    expected: 2,
+                    is_min: false,
+                    found: args.len(),
+                });
+            }
+        };
+
+        let [name] = Func::expect_args_exact::<Str, 1>("annot", ctx, slice::from_ref(name))?;
+        let name = name.into_inner();
+
+        if !ANNOTATIONS.contains(&name.as_str()) {
+            return Err(Error::Custom(
+                format!(
+                    "unknown annotation {name:?}, expected {}",
+                    Separators::comma_or().with(ANNOTATIONS.iter().map(|name| format!("{name:?}"))),
+                )
+                .into(),
+            ));
+        }
+
+        let value = match value {
+            None => None,
+            Some(Value::Str(str)) => Some(str.as_str().into()),
+            Some(Value::Num(num)) => Some(eco_format!("{}", num.0)),
+            Some(value) => {
+                return Err(Error::TypeMismatch {
+                    expected: eco_vec![Type::Str, Type::Num],
+                    found: value.as_type(),
+                });
+            }
+        };
+
+        Ok(Value::Set(set_annot(name, value)))
+    }
+
+    /// Constructs the `annot(name)` and `annot(name, value)` test sets. A test
+    /// set which contains all unit tests which have an annotation with the
+    /// given name, including those inherited from directory configs, and if a
+    /// value is given, whose argument is that value, see [`Annotation::arg`].
+    ///
+    /// The name must be one of [`ANNOTATIONS`].
+    ///
+    /// [`Annotation::arg`]: tytanic_core::test::Annotation::arg
+    pub fn set_annot(name: EcoString, value: Option<EcoString>) -> Set {
+        Set::new(move |_, _, test: &Test| {
+            Ok(test.as_unit_test().is_some_and(|unit| {
+                unit.annotations().iter().any(|annot| {
+                    annot.name() == name
+                        && value
+                            .as_ref()
+                            .is_none_or(|value| annot.arg().is_some_and(|arg| &arg == value))
+                })
+            }))
+        })
+    }
+
+    /// The constructor function for the test set returned by [`set_count`].
+    pub fn func_count_ctor(ctx: &Context, args: &[Value]) -> Result<Value, Error> {
+        let (set, n) = expect_guard_args("count", ctx, args)?;
+        Ok(Value::Set(set_count(ctx, set, n)))
+    }
+
+    /// Constructs the `count(set, n)` test set. A test set which contains the
+    /// same tests as the given set, filtering fails if the given set doesn't
+    /// match exactly `n` tests.
+    pub fn set_count(ctx: &Context, set: Set, n: usize) -> Set {
+        ctx.register_guard(Guard::new("count", set.clone(), n, n));
+        set
+    }
+
+    /// The constructor function for the test set returned by [`set_at_most`].
+    pub fn func_at_most_ctor(ctx: &Context, args: &[Value]) -> Result<Value, Error> {
+        let (set, n) = expect_guard_args("at-most", ctx, args)?;
+        Ok(Value::Set(set_at_most(ctx, set, n)))
+    }
+
+    /// Constructs the `at-most(set, n)` test set. A test set which contains
+    /// the same tests as the given set, filtering fails if the given set
+    /// matches more than `n` tests.
+    pub fn set_at_most(ctx: &Context, set: Set, n: usize) -> Set {
+        ctx.register_guard(Guard::new("at-most", set.clone(), 0, n));
+        set
+    }
+
+    /// Extracts the set and number arguments of a guard function.
+    fn expect_guard_args(func: &str, ctx: &Context, args: &[Value]) -> Result<(Set, usize), Error> {
+        let [set, n] = args else {
+            return Err(Error::InvalidArgumentCount {
+                func: func.into(),
+                expected: 2,
+                is_min: false,
+                found: args.len(),
+            });
+        };
+
+        let [set] = Func::expect_args_exact::<Set, 1>(func, ctx, slice::from_ref(set))?;
+        let [Num(n)] = Func::expect_args_exact::<Num, 1>(func, ctx, slice::from_ref(n))?;
+
+        Ok((set, n))
+    }
 }
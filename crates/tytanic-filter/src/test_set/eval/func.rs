@@ -83,6 +83,27 @@ impl Func {
             .expect("we checked both min and max of the args"))
     }
 
+    /// Extract a variadic number of values with a maximum amount from the given
+    /// arguments. Validates the types of all arguments.
+    pub fn expect_args_max<V, const N: usize>(
+        func: &str,
+        _ctx: &Context,
+        args: &[Value],
+    ) -> Result<Vec<V>, Error>
+    where
+        V: TryFromValue + Debug,
+    {
+        if args.len() > N {
+            return Err(Error::TooManyArguments {
+                func: func.into(),
+                max: N,
+                found: args.len(),
+            });
+        }
+
+        args.iter().cloned().map(V::try_from_value).collect()
+    }
+
     /// Extract a variadic number of values with a minimum amount given arguments.
     /// Validates the types of all arguments.
     pub fn expect_args_min<V, const N: usize>(
@@ -185,4 +206,26 @@ mod tests {
             ([NUM, NUM], vec![]),
         );
     }
+
+    #[test]
+    fn test_expect_args_variadic_max_length() {
+        let ctx = Context::new();
+
+        assert_eq!(
+            Func::expect_args_max::<Num, 1>("f", &ctx, &[]).unwrap(),
+            vec![],
+        );
+        assert_eq!(
+            Func::expect_args_max::<Num, 1>("f", &ctx, &[VAL]).unwrap(),
+            vec![NUM],
+        );
+        assert!(matches!(
+            Func::expect_args_max::<Num, 1>("f", &ctx, &[VAL, VAL]),
+            Err(Error::TooManyArguments {
+                max: 1,
+                found: 2,
+                ..
+            }),
+        ));
+    }
 }
@@ -0,0 +1,87 @@
+use ecow::EcoString;
+
+use crate::test_set::eval::Error;
+use crate::test_set::eval::Set;
+
+/// A cardinality guard on a test set, this is registered by functions like
+/// `count(set, n)` and checked once all tests were filtered.
+///
+/// The guarded set is matched against every filtered test on its own, the
+/// count is therefore independent of the expression the guard is used in.
+#[derive(Debug, Clone)]
+pub struct Guard {
+    func: EcoString,
+    set: Set,
+    min: usize,
+    max: usize,
+}
+
+impl Guard {
+    /// Create a new guard for the given function which expects the set to
+    /// match between `min` and `max` tests, both inclusive.
+    pub fn new<S: Into<EcoString>>(func: S, set: Set, min: usize, max: usize) -> Self {
+        Self {
+            func: func.into(),
+            set,
+            min,
+            max,
+        }
+    }
+}
+
+impl Guard {
+    /// The identifier of the function which created this guard.
+    pub fn func(&self) -> &str {
+        &self.func
+    }
+
+    /// The guarded test set.
+    pub fn set(&self) -> &Set {
+        &self.set
+    }
+
+    /// The minimum number of tests the set must match.
+    pub fn min(&self) -> usize {
+        self.min
+    }
+
+    /// The maximum number of tests the set may match.
+    pub fn max(&self) -> usize {
+        self.max
+    }
+}
+
+impl Guard {
+    /// Checks the number of tests the guarded set matched.
+    pub fn check(&self, found: usize) -> Result<(), Error> {
+        if (self.min..=self.max).contains(&found) {
+            Ok(())
+        } else {
+            Err(Error::UnexpectedCount {
+                func: self.func.as_str().into(),
+                min: self.min,
+                max: self.max,
+                found,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_set::builtin::dsl::set_all;
+
+    #[test]
+    fn test_check() {
+        let exact = Guard::new("count", set_all(), 2, 2);
+        assert!(exact.check(1).is_err());
+        assert!(exact.check(2).is_ok());
+        assert!(exact.check(3).is_err());
+
+        let at_most = Guard::new("at-most", set_all(), 0, 2);
+        assert!(at_most.check(0).is_ok());
+        assert!(at_most.check(2).is_ok());
+        assert!(at_most.check(3).is_err());
+    }
+}
@@ -3,6 +3,8 @@
 use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::fmt::Display;
+use std::sync::Arc;
+use std::sync::Mutex;
 
 use ecow::EcoVec;
 use thiserror::Error;
@@ -12,10 +14,12 @@ use tytanic_utils::fmt::Term;
 use crate::test_set::ast::Id;
 
 mod func;
+mod guard;
 mod set;
 mod value;
 
 pub use crate::test_set::eval::func::Func;
+pub use crate::test_set::eval::guard::Guard;
 pub use crate::test_set::eval::set::Set;
 pub use crate::test_set::eval::value::TryFromValue;
 pub use crate::test_set::eval::value::Type;
@@ -32,6 +36,9 @@ pub trait Eval {
 pub struct Context {
     /// The bindings available for evaluation.
     bindings: BTreeMap<Id, Value>,
+
+    /// The guards registered during evaluation.
+    guards: Arc<Mutex<Vec<Guard>>>,
 }
 
 impl Context {
@@ -39,6 +46,7 @@ impl Context {
     pub fn new() -> Self {
         Self {
             bindings: BTreeMap::new(),
+            guards: Arc::new(Mutex::new(vec![])),
         }
     }
 }
@@ -75,6 +83,17 @@ impl Context {
             .cloned()
             .collect()
     }
+
+    /// Registers a guard which is checked once filtering is finished.
+    pub fn register_guard(&self, guard: Guard) {
+        tracing::trace!(func = %guard.func(), "registering guard in eval context");
+        self.guards.lock().unwrap().push(guard);
+    }
+
+    /// Takes all guards which were registered since the last call.
+    pub fn take_guards(&self) -> Vec<Guard> {
+        std::mem::take(&mut *self.guards.lock().unwrap())
+    }
 }
 
 impl Default for Context {
@@ -108,6 +127,18 @@ pub enum Error {
         found: usize,
     },
 
+    /// A function with optional arguments received too many arguments.
+    TooManyArguments {
+        /// The identifier of the function.
+        func: String,
+
+        /// The maximum number of arguments.
+        max: usize,
+
+        /// The number of arguments passed.
+        found: usize,
+    },
+
     /// An invalid type was used in an expression.
     TypeMismatch {
         /// The expected types.
@@ -117,6 +148,21 @@ pub enum Error {
         found: Type,
     },
 
+    /// A guarded test set matched an unexpected number of tests.
+    UnexpectedCount {
+        /// The identifier of the function which created the guard.
+        func: String,
+
+        /// The minimum number of expected tests.
+        min: usize,
+
+        /// The maximum number of expected tests.
+        max: usize,
+
+        /// The number of tests matched.
+        found: usize,
+    },
+
     /// A custom error type.
     Custom(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
 }
@@ -158,12 +204,44 @@ impl Display for Error {
 
                 Ok(())
             }
+            Error::TooManyArguments { func, max, found } => write!(
+                f,
+                "function {func} expects at most {max} {}, got {found}",
+                Term::simple("argument").with(*max),
+            ),
             Error::TypeMismatch { expected, found } => write!(
                 f,
                 "expected {}, found <{}>",
                 Separators::comma_or().with(expected.iter().map(|t| format!("<{}>", t.name()))),
                 found.name(),
             ),
+            Error::UnexpectedCount {
+                func,
+                min,
+                max,
+                found,
+            } => {
+                let (min, max, found) = (*min, *max, *found);
+
+                if min == max {
+                    write!(
+                        f,
+                        "function {func} expects exactly {min} {}, matched {found}",
+                        Term::simple("test").with(min),
+                    )
+                } else if min == 0 {
+                    write!(
+                        f,
+                        "function {func} expects at most {max} {}, matched {found}",
+                        Term::simple("test").with(max),
+                    )
+                } else {
+                    write!(
+                        f,
+                        "function {func} expects between {min} and {max} tests, matched {found}",
+                    )
+                }
+            }
             Error::Custom(err) => write!(f, "{err}"),
         }
     }
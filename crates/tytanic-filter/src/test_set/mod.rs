@@ -41,6 +41,7 @@ pub struct ExpressionFilter {
     all: bool,
     ctx: eval::Context,
     set: eval::Set,
+    guards: Vec<eval::Guard>,
 }
 
 impl ExpressionFilter {
@@ -54,12 +55,14 @@ impl ExpressionFilter {
             .unwrap_or((false, &input));
 
         let set = ast::parse(expr)?.eval(&ctx).and_then(Value::expect_type)?;
+        let guards = ctx.take_guards();
 
         Ok(Self {
             input,
             all,
             ctx,
             set,
+            guards,
         })
     }
 }
@@ -87,6 +90,12 @@ impl ExpressionFilter {
     pub fn set(&self) -> &eval::Set {
         &self.set
     }
+
+    /// The guards registered while evaluating the inner test set, these are
+    /// checked when the filter is finished.
+    pub fn guards(&self) -> &[eval::Guard] {
+        &self.guards
+    }
 }
 
 impl ExpressionFilter {
@@ -111,19 +120,43 @@ impl Filter for ExpressionFilter {
         Self: 'a;
 
     fn state(&self) -> Self::State<'_> {
-        ExpressionFilterState(self)
+        ExpressionFilterState {
+            filter: self,
+            counts: vec![0; self.guards.len()],
+        }
     }
 }
 
 /// Created by [`Filter::state`] for [`ExpressionFilter`].
 #[derive(Debug)]
-pub struct ExpressionFilterState<'f>(&'f ExpressionFilter);
+pub struct ExpressionFilterState<'f> {
+    filter: &'f ExpressionFilter,
+    counts: Vec<usize>,
+}
 
 impl FilterState for ExpressionFilterState<'_> {
     type Error = eval::Error;
 
     fn filter(&mut self, project: &Project, test: &Test) -> Result<bool, Self::Error> {
-        self.0.set.contains(project, &self.0.ctx, test)
+        let ExpressionFilter {
+            ctx, set, guards, ..
+        } = self.filter;
+
+        for (guard, count) in guards.iter().zip(&mut self.counts) {
+            if guard.set().contains(project, ctx, test)? {
+                *count += 1;
+            }
+        }
+
+        set.contains(project, ctx, test)
+    }
+
+    fn finish(self, _project: &Project) -> Result<(), Self::Error> {
+        for (guard, count) in self.filter.guards.iter().zip(self.counts) {
+            guard.check(count)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -10,6 +10,7 @@ pub mod fmt;
 pub mod fs;
 pub mod path;
 pub mod result;
+pub mod sync;
 #[cfg(feature = "typst-manifest-builder")]
 pub mod typst;
 #[cfg(feature = "terminal-ui")]
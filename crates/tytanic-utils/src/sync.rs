@@ -0,0 +1,152 @@
+//! Helper types for synchronization.
+
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+/// A counting semaphore with an adjustable limit.
+///
+/// A limit of `0` means that permits are never exhausted.
+///
+/// # Example
+/// ```
+/// # use tytanic_utils::sync::Semaphore;
+/// static PERMITS: Semaphore = Semaphore::unlimited();
+///
+/// PERMITS.set_limit(2);
+/// let a = PERMITS.acquire();
+/// let b = PERMITS.acquire();
+/// assert_eq!(PERMITS.in_use(), 2);
+///
+/// drop(a);
+/// assert_eq!(PERMITS.in_use(), 1);
+/// ```
+#[derive(Debug)]
+pub struct Semaphore {
+    limit: AtomicUsize,
+    in_use: Mutex<usize>,
+    released: Condvar,
+}
+
+impl Semaphore {
+    /// Creates a new semaphore without a limit.
+    pub const fn unlimited() -> Self {
+        Self::new(0)
+    }
+
+    /// Creates a new semaphore with the given limit.
+    pub const fn new(limit: usize) -> Self {
+        Self {
+            limit: AtomicUsize::new(limit),
+            in_use: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+}
+
+impl Semaphore {
+    /// The current limit, `0` if there is none.
+    pub fn limit(&self) -> usize {
+        self.limit.load(Ordering::SeqCst)
+    }
+
+    /// Sets the limit, `0` removes it.
+    ///
+    /// Permits which are already acquired are not revoked if the new limit is
+    /// lower than the number of permits in use.
+    pub fn set_limit(&self, limit: usize) {
+        self.limit.store(limit, Ordering::SeqCst);
+        self.released.notify_all();
+    }
+
+    /// The number of permits currently in use.
+    pub fn in_use(&self) -> usize {
+        *self.in_use.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Acquires a permit, blocking until one is available.
+    ///
+    /// The permit is released once the returned guard is dropped.
+    pub fn acquire(&self) -> SemaphoreGuard<'_> {
+        let mut in_use = self.in_use.lock().unwrap_or_else(PoisonError::into_inner);
+
+        loop {
+            let limit = self.limit();
+            if limit == 0 || *in_use < limit {
+                break;
+            }
+
+            in_use = self
+                .released
+                .wait(in_use)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+
+        *in_use += 1;
+
+        SemaphoreGuard { semaphore: self }
+    }
+}
+
+/// A permit acquired from a [`Semaphore`], it is released on drop.
+#[derive(Debug)]
+pub struct SemaphoreGuard<'s> {
+    semaphore: &'s Semaphore,
+}
+
+impl Drop for SemaphoreGuard<'_> {
+    fn drop(&mut self) {
+        let mut in_use = self
+            .semaphore
+            .in_use
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *in_use -= 1;
+        self.semaphore.released.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_semaphore_unlimited() {
+        let semaphore = Semaphore::unlimited();
+        let guards: Vec<_> = (0..100).map(|_| semaphore.acquire()).collect();
+        assert_eq!(semaphore.in_use(), 100);
+
+        drop(guards);
+        assert_eq!(semaphore.in_use(), 0);
+    }
+
+    #[test]
+    fn test_semaphore_limit() {
+        let semaphore = Arc::new(Semaphore::new(2));
+        let max = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let semaphore = Arc::clone(&semaphore);
+                let max = Arc::clone(&max);
+                thread::spawn(move || {
+                    let _permit = semaphore.acquire();
+                    max.fetch_max(semaphore.in_use(), Ordering::SeqCst);
+                    thread::yield_now();
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert!(max.load(Ordering::SeqCst) <= 2);
+        assert_eq!(semaphore.in_use(), 0);
+    }
+}
@@ -26,7 +26,7 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
     let mut filter = ctx.filter(&args.filter)?;
 
     if let Some(exact) = filter.exact()
-        && exact.expected().contains(&Id::template())
+        && exact.expected().iter().any(Id::is_template)
     {
         writeln!(ctx.ui.error()?, "Cannot delete template test")?;
         eyre::bail!(OperationFailure);
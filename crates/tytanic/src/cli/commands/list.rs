@@ -1,13 +1,34 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs;
+use std::fs::File;
+use std::io::BufWriter;
 use std::io::Write;
+use std::path::PathBuf;
 
+use camino::Utf8Component;
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
 use color_eyre::eyre;
+use ecow::EcoString;
+use serde_json::json;
 use termcolor::Color;
+use typst_syntax::SyntaxNode;
+use typst_syntax::ast;
+use tytanic_core::Project;
+use tytanic_core::UnitTest;
+use tytanic_core::test::Annotation;
 use tytanic_core::test::Test;
 use tytanic_core::test::unit::Kind as TestKind;
+use tytanic_utils::fmt::Term;
 
 use super::Context;
 use super::FilterOptions;
 use crate::cwrite;
+use crate::json::ManifestConfigJson;
+use crate::json::ManifestTestJson;
+use crate::json::SUITE_MANIFEST_VERSION;
+use crate::json::SuiteManifestJson;
 use crate::json::TestJson;
 use crate::ui;
 
@@ -18,6 +39,14 @@ pub struct Args {
     #[arg(long)]
     pub json: bool,
 
+    /// Write a suite manifest describing the matched tests to the given path.
+    ///
+    /// The manifest contains the kind, resolved config, reference page count
+    /// and statically resolved imports of each test. It is deterministic and
+    /// versioned such that it can be committed and reviewed.
+    #[arg(long, value_name = "PATH", conflicts_with = "json")]
+    pub manifest: Option<PathBuf>,
+
     #[command(flatten)]
     pub filter: FilterOptions,
 }
@@ -25,6 +54,7 @@ pub struct Args {
 pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
     let project = ctx.project()?;
     let suite = ctx.collect_tests_with_filter(&project, ctx.filter(&args.filter)?)?;
+    ctx.warn_collection(&project, suite.inner())?;
 
     if args.json {
         serde_json::to_writer_pretty(
@@ -39,6 +69,35 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
         return Ok(());
     }
 
+    if let Some(path) = &args.manifest {
+        let manifest = SuiteManifestJson {
+            version: SUITE_MANIFEST_VERSION,
+            tests: suite
+                .matched()
+                .tests()
+                .map(|test| manifest_test(&project, test))
+                .collect(),
+        };
+
+        let mut w = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut w, &manifest)?;
+        writeln!(w)?;
+        w.flush()?;
+
+        let len = manifest.tests.len();
+        let mut w = ctx.ui.stderr();
+        write!(w, "Wrote suite manifest for ")?;
+        cwrite!(colored(w, Color::Green), "{len}")?;
+        writeln!(
+            w,
+            " {} to {}",
+            Term::simple("test").with(len),
+            path.display()
+        )?;
+
+        return Ok(());
+    }
+
     let mut w = ctx.ui.stderr();
 
     // NOTE(tinger): Max padding of 50 should be enough for most cases.
@@ -83,3 +142,202 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
 
     Ok(())
 }
+
+/// Describes a test for the suite manifest.
+fn manifest_test<'t>(project: &Project, test: &'t Test) -> ManifestTestJson<'t> {
+    match test {
+        Test::Unit(test) => ManifestTestJson {
+            id: test.id().as_str(),
+            r#type: "unit",
+            kind: Some(test.kind().as_str()),
+            is_skip: test.is_skip(),
+            skip_reason: test.skip_reason(),
+            path: portable(&relative(project, &project.unit_test_dir(test.id()))),
+            config: resolved_config(project, test),
+            reference_pages: test
+                .kind()
+                .is_persistent()
+                .then(|| test.check_reference_document(project).ok())
+                .flatten(),
+            imports: static_imports(
+                project,
+                &relative(project, &project.unit_test_script(test.id())),
+            ),
+        },
+        Test::Template(test) => ManifestTestJson {
+            id: test.id().as_str(),
+            r#type: "template",
+            kind: None,
+            is_skip: false,
+            skip_reason: None,
+            path: portable(&relative(
+                project,
+                &project.template_root().unwrap_or_default(),
+            )),
+            config: BTreeMap::new(),
+            reference_pages: None,
+            imports: BTreeSet::new(),
+        },
+    }
+}
+
+/// Resolves the comparison and export config of a test from the project
+/// defaults and its annotations.
+fn resolved_config(
+    project: &Project,
+    test: &UnitTest,
+) -> BTreeMap<&'static str, ManifestConfigJson> {
+    let defaults = &project.config().defaults;
+
+    let mut config = BTreeMap::from([
+        ("dir", json!(defaults.direction)),
+        ("ppi", json!(defaults.ppi)),
+        ("max-delta", json!(defaults.max_delta)),
+        ("max-deviations", json!(defaults.max_deviations)),
+        ("max-failing-pages", json!(defaults.max_failing_pages)),
+    ])
+    .into_iter()
+    .map(|(member, value)| {
+        (
+            member,
+            ManifestConfigJson {
+                value,
+                source: "default",
+            },
+        )
+    })
+    .collect::<BTreeMap<_, _>>();
+
+    let own = test
+        .own_annotations()
+        .iter()
+        .map(|annot| (annot, "annotation"));
+    let inherited = test
+        .inherited_annotations()
+        .iter()
+        .map(|annot| (annot, "directory"));
+
+    for (annot, source) in own.chain(inherited) {
+        if !annot.applies_to(test.kind()) {
+            continue;
+        }
+
+        let value = match *annot {
+            Annotation::Dir(dir) => json!(dir),
+            Annotation::Ppi(ppi) => json!(ppi),
+            Annotation::MaxDelta(max_delta) => json!(max_delta),
+            Annotation::MaxDeviations(max_deviations) => json!(max_deviations),
+            Annotation::MaxFailingPages(max_failing_pages) => json!(max_failing_pages),
+            _ => continue,
+        };
+
+        config.insert(annot.name(), ManifestConfigJson { value, source });
+    }
+
+    config
+}
+
+/// Collects the files imported or included by the given script and,
+/// transitively, by the local Typst files it imports.
+///
+/// Only imports of string literals can be resolved, local files are listed
+/// relative to the project root and packages by their spec without following
+/// them.
+fn static_imports(project: &Project, script: &Utf8Path) -> BTreeSet<String> {
+    let mut imports = BTreeSet::new();
+    let mut visited = BTreeSet::new();
+    let mut queue = vec![script.to_path_buf()];
+
+    while let Some(file) = queue.pop() {
+        if !visited.insert(file.clone()) {
+            continue;
+        }
+
+        let Ok(text) = fs::read_to_string(project.root().join(&file)) else {
+            continue;
+        };
+
+        for import in import_paths(&typst_syntax::parse(&text)) {
+            if import.starts_with('@') {
+                imports.insert(import.into());
+                continue;
+            }
+
+            let path = match import.strip_prefix('/') {
+                Some(rooted) => Utf8PathBuf::from(rooted),
+                None => file.parent().unwrap_or(Utf8Path::new("")).join(&*import),
+            };
+
+            let Some(path) = normalize(&path) else {
+                imports.insert(import.into());
+                continue;
+            };
+
+            imports.insert(portable(&path));
+
+            if path.extension() == Some("typ") {
+                queue.push(path);
+            }
+        }
+    }
+
+    imports
+}
+
+/// The string literal sources of all imports and includes in the given node.
+fn import_paths(node: &SyntaxNode) -> Vec<EcoString> {
+    let source = if let Some(import) = node.cast::<ast::ModuleImport>() {
+        Some(import.source())
+    } else {
+        node.cast::<ast::ModuleInclude>()
+            .map(|include| include.source())
+    };
+
+    let mut paths = vec![];
+    if let Some(ast::Expr::Str(path)) = source {
+        paths.push(path.get());
+    }
+
+    for child in node.children() {
+        paths.extend(import_paths(child));
+    }
+
+    paths
+}
+
+/// Lexically normalizes a relative path, returns `None` if it escapes its
+/// root.
+fn normalize(path: &Utf8Path) -> Option<Utf8PathBuf> {
+    let mut normalized = Utf8PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Utf8Component::CurDir => {}
+            Utf8Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Utf8Component::Normal(part) => normalized.push(part),
+            Utf8Component::Prefix(_) | Utf8Component::RootDir => return None,
+        }
+    }
+
+    Some(normalized)
+}
+
+/// The given path relative to the project root.
+fn relative(project: &Project, path: &Utf8Path) -> Utf8PathBuf {
+    path.strip_prefix(project.root())
+        .unwrap_or(path)
+        .to_path_buf()
+}
+
+/// Renders a path with forward slashes, such that manifests are identical
+/// across platforms.
+fn portable(path: &Utf8Path) -> String {
+    path.components()
+        .map(|component| component.as_str())
+        .collect::<Vec<_>>()
+        .join("/")
+}
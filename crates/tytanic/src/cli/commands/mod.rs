@@ -1,4 +1,7 @@
+use std::collections::BTreeMap;
 use std::fmt::Display;
+use std::fs::File;
+use std::io::Write;
 use std::path::PathBuf;
 
 use camino::Utf8PathBuf;
@@ -9,12 +12,28 @@ use clap::ColorChoice;
 use clap::Parser;
 use clap::ValueEnum;
 use color_eyre::eyre;
+use color_eyre::eyre::WrapErr;
+use ecow::EcoString;
+use typst::utils::Scalar;
+use typst_syntax::package::PackageSpec;
 use tytanic_core::config::Direction;
+use tytanic_core::config::ProjectConfig;
+use tytanic_core::doc::ArtifactFormat;
+use tytanic_core::doc::OptimizeLevel;
+use tytanic_core::doc::compare::CompareMode;
+use tytanic_core::doc::compare::Prescale;
+use tytanic_core::doc::compare::PrescaleMode;
+use tytanic_core::doc::compare::Strategy;
 use tytanic_core::doc::compile::Warnings;
+use tytanic_core::doc::render::DiffColors;
+use tytanic_core::doc::render::OverviewOptions;
+use tytanic_core::suite::SuiteResult;
 use tytanic_core::test::Id;
 use tytanic_core::test::unit::Kind;
+use uuid::Uuid;
 
 use super::Context;
+use crate::report::ReportFormat;
 
 pub mod delete;
 pub mod list;
@@ -62,6 +81,26 @@ impl Display for DiagnosticFormat {
     }
 }
 
+/// Which format to use for warnings about the usage of the CLI.
+#[derive(ValueEnum, Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum CliWarningFormat {
+    /// Display human readable messages.
+    #[default]
+    Human,
+
+    /// Display one JSON object per warning and line.
+    Json,
+}
+
+impl Display for CliWarningFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliWarningFormat::Human => f.write_str("human"),
+            CliWarningFormat::Json => f.write_str("json"),
+        }
+    }
+}
+
 /// The kind of a unit test.
 #[derive(ValueEnum, Debug, Clone, Copy)]
 pub enum KindOption {
@@ -216,6 +255,21 @@ impl_switch! {
     }
 }
 
+impl_switch! {
+    /// The `--[no-]compare-cache` switch.
+    CompareCacheSwitch(true) {
+        /// Skip comparisons which passed before with the same output,
+        /// references and comparison options (default).
+        ///
+        /// Passed comparisons are cached in the `.cache` directory of the test
+        /// root.
+        compare_cache,
+
+        /// Always compare tests, ignoring the comparison cache.
+        no_compare_cache,
+    }
+}
+
 impl_switch! {
     /// The `--[no-]export-ephemeral` switch.
     ExportEphemeralSwitch(true) {
@@ -229,6 +283,19 @@ impl_switch! {
     }
 }
 
+impl_switch! {
+    /// The `--[no-]export-overview` switch.
+    ExportOverviewSwitch(false) {
+        /// Export a page overview for each compared test.
+        ///
+        /// The overview is a strip of page thumbnails marked as passed or
+        /// failed, it is saved as `overview.png` in the diff directory.
+        export_overview,
+        /// Don't export page overviews (default).
+        no_export_overview,
+    }
+}
+
 impl_switch! {
     /// The `--[no-]fail-fast` switch.
     FailFastSwitch(true) {
@@ -263,6 +330,50 @@ impl_switch! {
     }
 }
 
+impl_switch! {
+    /// The `--[no-]only-changed-refs` switch.
+    OnlyChangedRefsSwitch(true) {
+        /// Only write reference pages which differ from the stored ones
+        /// (default).
+        only_changed_refs,
+
+        /// Write all reference pages of updated tests.
+        no_only_changed_refs,
+    }
+}
+
+impl_switch! {
+    /// The `--[no-]compare-ignore-aa` switch.
+    CompareIgnoreAaSwitch(false) {
+        /// Ignore deviations which are likely anti-aliasing at the edges of
+        /// shapes.
+        ///
+        /// This is an approximate heuristic which may also ignore genuine
+        /// changes at the edges of shapes. Defaults to `false`, can be
+        /// configured in the manifest.
+        compare_ignore_aa,
+
+        /// Count anti-aliasing deviations like any other deviation.
+        no_compare_ignore_aa,
+    }
+}
+
+impl_switch! {
+    /// The `--[no-]crop` switch.
+    CropSwitch(false) {
+        /// Crop the borders of uniform color around rendered pages before
+        /// they are compared, exported or stored as references.
+        ///
+        /// Persistent references store the offsets of their cropped pages,
+        /// such that content which moved still fails comparison. Defaults to
+        /// `false`, can be configured in the manifest.
+        crop,
+
+        /// Don't crop rendered pages.
+        no_crop,
+    }
+}
+
 macro_rules! ansi {
     ($s:expr; b) => {
         concat!("\x1B[1m", $s, "\x1B[0m")
@@ -303,14 +414,30 @@ pub struct CliArguments {
     #[arg(long, short, env = "TYPST_ROOT", global = true)]
     pub root: Option<Utf8PathBuf>,
 
-    /// The number of threads to use for compilation.
+    /// The number of threads to use for running tests in parallel and
+    /// compilation.
+    ///
+    /// If none is given, one thread per logical CPU is used.
     #[arg(long, short, global = true)]
     pub jobs: Option<usize>,
 
+    /// The maximum number of files to write concurrently when exporting or
+    /// updating documents, `0` removes the limit.
+    ///
+    /// This is independent of `--jobs`. If none is given, the limit is
+    /// derived from the file descriptor limit of the process where it can be
+    /// detected.
+    #[arg(long, value_name = "N", global = true)]
+    pub io_jobs: Option<usize>,
+
     /// The version control system to use.
     #[arg(long, value_enum, default_value = "auto", global = true)]
     pub vcs: Vcs,
 
+    /// Turn warnings about annotations without effect into errors.
+    #[arg(long, global = true)]
+    pub strict_config: bool,
+
     #[command(flatten, next_help_heading = "Font Options")]
     pub font: FontOptions,
 
@@ -381,6 +508,50 @@ pub struct PackageOptions {
     /// Path to a custom CA certificate to use when making network requests.
     #[clap(long, visible_alias = "cert", env = "TYPST_CERT", global = true)]
     pub certificate: Option<PathBuf>,
+
+    /// Resolve a package to a local directory instead of the package cache.
+    ///
+    /// Expects a package spec and a directory containing a package manifest,
+    /// like `@preview/foo:1.2.3=path/to/foo`. This only affects the worlds in
+    /// which tests are compiled. Can be given multiple times, if a package is
+    /// given more than once the last directory is used.
+    #[clap(
+        long,
+        value_name = "SPEC=DIR",
+        value_parser = parse_package_override,
+        global = true
+    )]
+    pub package_override: Vec<(PackageSpec, PathBuf)>,
+}
+
+fn parse_package_override(raw: &str) -> Result<(PackageSpec, PathBuf), String> {
+    let Some((spec, dir)) = raw.split_once('=') else {
+        return Err("package override must be of the form `SPEC=DIR`".into());
+    };
+
+    let spec: PackageSpec = spec
+        .trim()
+        .parse()
+        .map_err(|err| format!("invalid package spec `{spec}` ({err})"))?;
+
+    let dir = std::path::absolute(dir.trim())
+        .map_err(|err| format!("invalid package directory `{dir}` ({err})"))?;
+
+    if !dir.is_dir() {
+        return Err(format!(
+            "package directory `{}` does not exist or is not a directory",
+            dir.display()
+        ));
+    }
+
+    if !dir.join("typst.toml").is_file() {
+        return Err(format!(
+            "package directory `{}` does not contain a `typst.toml` manifest",
+            dir.display()
+        ));
+    }
+
+    Ok((spec, dir))
 }
 
 /// Options for filtering/selecting tests.
@@ -401,11 +572,37 @@ pub struct FilterOptions {
     /// The tests to operate on.
     ///
     /// This will always ignore the `[skip]` annotation and fail if a test can't
-    /// be found.
-    #[arg(required = false, value_name = "TEST")]
+    /// be found. Identifiers may be wrapped in single or double quotes.
+    #[arg(required = false, value_name = "TEST", value_parser = parse_test_id)]
     pub tests: Vec<Id>,
 }
 
+fn parse_test_id(raw: &str) -> Result<Id, String> {
+    // Some shells and CI runners pass quotes through verbatim, so we strip one
+    // layer of matching quotes here.
+    let unquoted = ['\'', '"']
+        .into_iter()
+        .find_map(|q| raw.strip_prefix(q).and_then(|rest| rest.strip_suffix(q)))
+        .unwrap_or(raw);
+
+    Id::new(unquoted).map_err(|err| {
+        format!("{err}, use a test set expression like `--expression \"e:'{unquoted}'\"` instead")
+    })
+}
+
+fn parse_input(raw: &str) -> Result<(String, String), String> {
+    let Some((key, value)) = raw.split_once('=') else {
+        return Err("input must be of the form `KEY=VALUE`".into());
+    };
+
+    let key = key.trim();
+    if key.is_empty() {
+        return Err("input key must not be empty".into());
+    }
+
+    Ok((key.into(), value.trim().into()))
+}
+
 fn parse_source_date_epoch(raw: &str) -> Result<DateTime<Utc>, String> {
     if raw.eq_ignore_ascii_case("now") {
         return Ok(Utc::now());
@@ -442,6 +639,14 @@ pub struct CompileOptions {
     /// The diagnostic format to use.
     #[arg(long, default_value_t, value_name = "FORMAT")]
     pub diagnostic_format: DiagnosticFormat,
+
+    /// Add a key-value pair to `sys.inputs` of all tests, may be given
+    /// multiple times.
+    ///
+    /// These take precedence over the input annotations of a test and the
+    /// inputs configured in the manifest.
+    #[arg(long = "input", value_name = "KEY=VALUE", value_parser = parse_input)]
+    pub inputs: Vec<(String, String)>,
 }
 
 /// Options for document rendering and export.
@@ -471,8 +676,151 @@ pub struct ExportOptions {
     #[command(flatten)]
     pub export_ephemeral: ExportEphemeralSwitch,
 
+    /// The format in which test output is exported.
+    ///
+    /// Defaults to `png`, can be configured in the manifest.
+    #[arg(long, value_name = "FORMAT")]
+    pub export_format: Option<ExportFormatOption>,
+
+    #[command(flatten)]
+    pub crop: CropSwitch,
+
+    #[command(flatten)]
+    pub export_overview: ExportOverviewSwitch,
+
+    /// The maximum width and height of a page thumbnail in the overview.
+    ///
+    /// Defaults to `128`.
+    #[arg(long, value_name = "PX")]
+    pub overview_thumbnail_size: Option<u32>,
+
+    /// The number of thumbnails per row in the overview.
+    ///
+    /// Defaults to `0`, which places all thumbnails in a single row.
+    #[arg(long, value_name = "N")]
+    pub overview_columns: Option<usize>,
+
+    /// The color placed behind difference images, given as a hex code like
+    /// `ffffff`.
+    ///
+    /// Defaults to a transparent background.
+    #[arg(long, value_name = "COLOR", value_parser = parse_color)]
+    pub diff_bg: Option<(u8, u8, u8)>,
+
+    /// The color with which differing pixels are highlighted in difference
+    /// images, given as a hex code like `ff00ff`.
+    ///
+    /// Defaults to showing the difference of both pixels.
+    #[arg(long, value_name = "COLOR", value_parser = parse_color)]
+    pub diff_highlight: Option<(u8, u8, u8)>,
+
     #[command(flatten)]
     pub optimize_refs: OptimizeRefsSwitch,
+
+    /// How thoroughly persistent references stored as PNG are optimized.
+    ///
+    /// Defaults to `max`, can be configured in the manifest. Has no effect if
+    /// `--no-optimize-refs` is given.
+    #[arg(long, value_name = "LEVEL")]
+    pub optimize_level: Option<OptimizeLevelOption>,
+}
+
+impl ExportOptions {
+    /// The overview options, if overviews should be exported.
+    pub fn overview(&self) -> Option<OverviewOptions> {
+        if !self.export_overview.get_or_default() {
+            return None;
+        }
+
+        let default = OverviewOptions::default();
+        Some(OverviewOptions {
+            thumbnail_size: self
+                .overview_thumbnail_size
+                .unwrap_or(default.thumbnail_size),
+            columns: self.overview_columns.unwrap_or(default.columns),
+        })
+    }
+
+    /// The level with which persistent references are optimized.
+    pub fn optimize_level(&self, config: &ProjectConfig) -> OptimizeLevel {
+        if !self.optimize_refs.get_or_default() {
+            return OptimizeLevel::None;
+        }
+
+        self.optimize_level
+            .map(OptionDelegate::into_native)
+            .unwrap_or(config.optimize_refs)
+    }
+
+    /// The colors with which difference images are rendered.
+    pub fn diff_colors(&self) -> DiffColors {
+        DiffColors {
+            background: self.diff_bg,
+            highlight: self.diff_highlight,
+        }
+    }
+}
+
+fn parse_color(raw: &str) -> Result<(u8, u8, u8), String> {
+    let hex = raw.strip_prefix('#').unwrap_or(raw);
+    let channel = |idx: usize| {
+        hex.get(idx..idx + 2)
+            .filter(|channel| channel.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+    };
+
+    match (hex.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok((r, g, b)),
+        _ => Err(format!(
+            "expected a color as a six digit hex code like `ff00ff`, got {raw:?}"
+        )),
+    }
+}
+
+/// The format in which test output is exported.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExportFormatOption {
+    /// Each page is exported as a PNG image.
+    Png,
+
+    /// The whole document is exported as a single PDF.
+    Pdf,
+}
+
+impl OptionDelegate for ExportFormatOption {
+    type Native = ArtifactFormat;
+
+    fn into_native(self) -> Self::Native {
+        match self {
+            ExportFormatOption::Png => ArtifactFormat::Png,
+            ExportFormatOption::Pdf => ArtifactFormat::Pdf,
+        }
+    }
+}
+
+/// How thoroughly persistent references are optimized.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OptimizeLevelOption {
+    /// References are not optimized.
+    None,
+
+    /// References are optimized with a fast preset.
+    Fast,
+
+    /// References are optimized with maximum compression.
+    Max,
+}
+
+impl OptionDelegate for OptimizeLevelOption {
+    type Native = OptimizeLevel;
+
+    fn into_native(self) -> Self::Native {
+        match self {
+            OptimizeLevelOption::None => OptimizeLevel::None,
+            OptimizeLevelOption::Fast => OptimizeLevel::Fast,
+            OptimizeLevelOption::Max => OptimizeLevel::Max,
+        }
+    }
 }
 
 /// The reading direction of a document.
@@ -520,6 +868,238 @@ pub struct CompareOptions {
     /// Defaults to `0`, can be configured in the manifest.
     #[arg(long)]
     pub max_deviations: Option<usize>,
+
+    /// The maximum allowed pages which may fail comparison.
+    ///
+    /// If more pages of a document fail comparison than this, then the whole
+    /// comparison fails. Documents with different page counts always fail.
+    ///
+    /// Defaults to `0`, can be configured in the manifest.
+    #[arg(long, value_name = "N")]
+    pub max_failing_pages: Option<usize>,
+
+    /// The maximum translation in pixels by which small tiles of a reference
+    /// may be shifted to find a better match before deviations are counted.
+    ///
+    /// This forgives systematic small shifts like those caused by font hinting,
+    /// but makes comparisons considerably slower for failing pages.
+    ///
+    /// Defaults to `0`, can be configured in the manifest.
+    #[arg(long, value_name = "PX")]
+    pub compare_align_tolerance: Option<u32>,
+
+    /// The factor by which pages are downscaled for a fast comparison before
+    /// or instead of the full resolution comparison.
+    ///
+    /// Factors below `2` disable the downscaled comparison.
+    ///
+    /// Defaults to `0`, can be configured in the manifest.
+    #[arg(long, value_name = "FACTOR")]
+    pub compare_prescale: Option<u32>,
+
+    /// How the downscaled comparison is used.
+    ///
+    /// Defaults to `gate`, can be configured in the manifest.
+    #[arg(long, value_name = "MODE")]
+    pub compare_prescale_mode: Option<PrescaleModeOption>,
+
+    /// The factor by which output and reference pages are downscaled before
+    /// they are compared and diffed.
+    ///
+    /// Pages are downscaled like they are by `--compare-prescale`, but this
+    /// also applies to all comparison modes and to difference pages. Factors
+    /// below `2` compare pages at full resolution.
+    ///
+    /// Defaults to `0`, can be configured in the manifest.
+    #[arg(long, value_name = "FACTOR")]
+    pub compare_downscale: Option<u32>,
+
+    #[command(flatten)]
+    pub compare_ignore_aa: CompareIgnoreAaSwitch,
+
+    /// Ignore deviating pixels which match any of the 8 neighbors of their
+    /// reference pixel with at most this difference per channel.
+    ///
+    /// This forgives anti-aliasing fringes and edges shifted by a single
+    /// pixel.
+    ///
+    /// Disabled by default, can be configured in the manifest.
+    #[arg(long, value_name = "DELTA")]
+    pub compare_aa_tolerance: Option<u8>,
+
+    /// Which comparison strategy is used.
+    ///
+    /// The `ssim` mode ignores the pixel tolerances and fails pages whose
+    /// structural similarity is below `--min-ssim`, the `pdf-text` mode
+    /// ignores pixels entirely and fails pages whose text differs.
+    ///
+    /// Defaults to `simple`, can be configured in the manifest.
+    #[arg(long, value_name = "MODE")]
+    pub compare_mode: Option<CompareModeOption>,
+
+    /// The minimum structural similarity index a page must reach in the
+    /// `ssim` comparison mode, between `-1.0` and `1.0`.
+    ///
+    /// Defaults to `0.99`, can be configured in the manifest.
+    #[arg(long, value_name = "SCORE", value_parser = parse_min_ssim)]
+    pub min_ssim: Option<f64>,
+
+    #[command(flatten)]
+    pub compare_cache: CompareCacheSwitch,
+}
+
+impl CompareOptions {
+    /// The mode in which documents are compared.
+    pub fn compare_mode(&self, config: &ProjectConfig) -> CompareMode {
+        self.compare_mode
+            .map(OptionDelegate::into_native)
+            .unwrap_or(config.defaults.compare_mode)
+    }
+
+    /// The strategy with which documents are compared, `None` if comparison is
+    /// disabled.
+    ///
+    /// This doesn't include the comparison annotations of individual tests,
+    /// these are applied by the runner.
+    pub fn strategy(&self, config: &ProjectConfig) -> Option<Strategy> {
+        if !self.compare.get_or_default() {
+            return None;
+        }
+
+        let defaults = &config.defaults;
+        let max_failing_pages = self.max_failing_pages.unwrap_or(defaults.max_failing_pages);
+
+        Some(match self.compare_mode(config) {
+            CompareMode::Simple | CompareMode::PdfText => {
+                let prescale = Prescale {
+                    factor: self.compare_prescale.unwrap_or(defaults.compare_prescale),
+                    mode: self
+                        .compare_prescale_mode
+                        .map(OptionDelegate::into_native)
+                        .unwrap_or(defaults.compare_prescale_mode),
+                };
+
+                Strategy::Simple {
+                    max_delta: self.max_delta.unwrap_or(defaults.max_delta),
+                    max_deviation: self.max_deviations.unwrap_or(defaults.max_deviations),
+                    max_failing_pages,
+                    align_tolerance: self
+                        .compare_align_tolerance
+                        .unwrap_or(defaults.compare_align_tolerance),
+                    prescale: (prescale.factor > 1).then_some(prescale),
+                    ignore_aa: self
+                        .compare_ignore_aa
+                        .get()
+                        .unwrap_or(defaults.compare_ignore_aa),
+                    aa_tolerance: self.compare_aa_tolerance.or(defaults.compare_aa_tolerance),
+                }
+            }
+            CompareMode::Ssim => Strategy::Ssim {
+                min_ssim: Scalar::new(self.min_ssim.unwrap_or(defaults.min_ssim)),
+                max_failing_pages,
+            },
+        })
+    }
+
+    /// Whether documents are compared by the text of their pages instead of
+    /// their pixels.
+    pub fn compare_text(&self, config: &ProjectConfig) -> bool {
+        self.compare_mode(config) == CompareMode::PdfText
+    }
+
+    /// The factor by which pages are downscaled before they are compared.
+    pub fn compare_downscale(&self, config: &ProjectConfig) -> u32 {
+        self.compare_downscale
+            .unwrap_or(config.defaults.compare_downscale)
+    }
+}
+
+/// How a downscaled comparison is used.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrescaleModeOption {
+    /// Quickly reject grossly different pages, then compare the remaining
+    /// pages at full resolution.
+    Gate,
+
+    /// Only compare the downscaled pages.
+    Only,
+}
+
+/// How the progress and results of a test run are reported.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReporterOption {
+    /// Human readable output on stderr.
+    Human,
+
+    /// Test Anything Protocol version 14 on stdout.
+    Tap,
+
+    /// GitHub Actions workflow commands on stdout.
+    Github,
+
+    /// JSON Lines on stdout, one object per test and a final summary.
+    Jsonl,
+}
+
+impl OptionDelegate for ReporterOption {
+    type Native = ReportFormat;
+
+    fn into_native(self) -> Self::Native {
+        match self {
+            ReporterOption::Human => ReportFormat::Human,
+            ReporterOption::Tap => ReportFormat::Tap,
+            ReporterOption::Github => ReportFormat::Github,
+            ReporterOption::Jsonl => ReportFormat::Jsonl,
+        }
+    }
+}
+
+/// Which comparison strategy is used.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompareModeOption {
+    /// Count pixels whose channels differ by more than the maximum delta.
+    Simple,
+
+    /// Compute the structural similarity of the luminance of pages.
+    Ssim,
+
+    /// Compare the text of pages as it's written to PDF exports.
+    PdfText,
+}
+
+impl OptionDelegate for CompareModeOption {
+    type Native = CompareMode;
+
+    fn into_native(self) -> Self::Native {
+        match self {
+            CompareModeOption::Simple => CompareMode::Simple,
+            CompareModeOption::Ssim => CompareMode::Ssim,
+            CompareModeOption::PdfText => CompareMode::PdfText,
+        }
+    }
+}
+
+fn parse_min_ssim(raw: &str) -> Result<f64, String> {
+    let min_ssim: f64 = raw
+        .parse()
+        .map_err(|err| format!("minimum structural similarity must be a number ({err})"))?;
+
+    if !(-1.0..=1.0).contains(&min_ssim) {
+        return Err("minimum structural similarity must be between -1.0 and 1.0".into());
+    }
+
+    Ok(min_ssim)
+}
+
+impl OptionDelegate for PrescaleModeOption {
+    type Native = PrescaleMode;
+
+    fn into_native(self) -> Self::Native {
+        match self {
+            PrescaleModeOption::Gate => PrescaleMode::Gate,
+            PrescaleModeOption::Only => PrescaleMode::Only,
+        }
+    }
 }
 
 /// Options for configuring the test runner.
@@ -527,6 +1107,126 @@ pub struct CompareOptions {
 pub struct RunnerOptions {
     #[command(flatten)]
     pub fail_fast: FailFastSwitch,
+
+    /// The id of this test run, a UUID or `auto`.
+    ///
+    /// If `auto` a random id is generated, supplying a known id allows
+    /// callers to correlate the reported run id with their own records.
+    #[arg(long, value_name = "ID", default_value = "auto", value_parser = parse_run_id)]
+    pub run_id: RunId,
+
+    /// Attach a metadata entry to this test run, may be given multiple times.
+    ///
+    /// Metadata such as the branch, commit or CI job id is reported with the
+    /// run and can be used by external tooling to filter runs. Keys may only
+    /// contain ASCII alphanumerics, `-`, `_` and `.`, values may not contain
+    /// control characters. Later entries override earlier ones with the same
+    /// key.
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_run_meta)]
+    pub run_meta: Vec<(EcoString, EcoString)>,
+
+    /// Record the inputs of each unit test and write their hashes to the
+    /// given JSON file after the run.
+    ///
+    /// The hash of a test covers the files read during compilation, the
+    /// persistent references, the available fonts, the configuration and the
+    /// version of tytanic. It is stable across machines and can be used by
+    /// external tooling to skip tests whose inputs are unchanged.
+    #[arg(long, value_name = "PATH")]
+    pub record_inputs: Option<PathBuf>,
+}
+
+impl RunnerOptions {
+    /// The metadata passed by `--run-meta`.
+    pub fn metadata(&self) -> BTreeMap<EcoString, EcoString> {
+        self.run_meta.iter().cloned().collect()
+    }
+
+    /// Writes the input hashes of the given results to the path passed by
+    /// `--record-inputs`, if any.
+    ///
+    /// The file contains a JSON object mapping test ids to their hashes as
+    /// hexadecimal strings, tests without a recorded hash are omitted.
+    pub fn write_input_hashes(&self, result: &SuiteResult) -> eyre::Result<()> {
+        let Some(path) = &self.record_inputs else {
+            return Ok(());
+        };
+
+        let hashes = result
+            .results()
+            .iter()
+            .filter_map(|(id, result)| {
+                result
+                    .input_hash()
+                    .map(|hash| (id.as_str(), format!("{hash:032x}")))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let mut file = File::create(path)
+            .wrap_err_with(|| format!("creating input hash file {}", path.display()))?;
+        serde_json::to_writer_pretty(&mut file, &hashes)?;
+        writeln!(file)?;
+
+        Ok(())
+    }
+}
+
+/// The id of a test run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RunId {
+    /// Generate a random run id.
+    Auto,
+
+    /// Use the given run id.
+    Fixed(Uuid),
+}
+
+impl RunId {
+    /// The fixed run id, if one was given.
+    pub fn fixed(self) -> Option<Uuid> {
+        match self {
+            RunId::Auto => None,
+            RunId::Fixed(id) => Some(id),
+        }
+    }
+}
+
+fn parse_run_id(raw: &str) -> Result<RunId, String> {
+    if raw.eq_ignore_ascii_case("auto") {
+        return Ok(RunId::Auto);
+    }
+
+    Uuid::parse_str(raw)
+        .map(RunId::Fixed)
+        .map_err(|err| format!("run id must be a UUID or the literal string `auto` ({err})"))
+}
+
+fn parse_run_meta(raw: &str) -> Result<(EcoString, EcoString), String> {
+    let Some((key, value)) = raw.split_once('=') else {
+        return Err("run metadata must be of the form `KEY=VALUE`".into());
+    };
+
+    if key.is_empty() {
+        return Err("run metadata key must not be empty".into());
+    }
+
+    if let Some(c) = key
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+    {
+        return Err(format!(
+            "run metadata key `{key}` contains invalid character {c:?}, \
+            only ASCII alphanumerics, `-`, `_` and `.` are allowed"
+        ));
+    }
+
+    if value.chars().any(char::is_control) {
+        return Err(format!(
+            "run metadata value for `{key}` must not contain control characters"
+        ));
+    }
+
+    Ok((key.into(), value.into()))
 }
 
 /// Options for configuring the CLI output.
@@ -553,8 +1253,26 @@ pub struct OutputArgs {
     ///
     /// Logs are written to stderr, the increasing number of verbose flags
     /// corresponds to the log levels ERROR, WARN, INFO, DEBUG, TRACE.
+    /// Test runs additionally report the time spent in each stage of a test.
     #[arg(long, short, action = clap::ArgAction::Count, global = true)]
     pub verbose: u8,
+
+    /// Suppress warnings about the usage of the CLI.
+    ///
+    /// This includes notices about deprecated sub command aliases, it does
+    /// not affect test warnings or the exit code.
+    #[arg(long, short, global = true)]
+    pub quiet: bool,
+
+    /// The format to use for warnings about the usage of the CLI.
+    #[arg(
+        long,
+        default_value_t,
+        value_name = "FORMAT",
+        require_equals = true,
+        global = true
+    )]
+    pub warning_format: CliWarningFormat,
 }
 
 #[derive(clap::Subcommand, Debug, Clone)]
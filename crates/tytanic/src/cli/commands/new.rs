@@ -1,5 +1,4 @@
 use std::io::Write;
-use std::ops::Not;
 
 use color_eyre::eyre;
 use termcolor::Color;
@@ -28,7 +27,6 @@ use super::KindOption;
 use super::OptionDelegate;
 use super::Switch;
 use super::TemplateSwitch;
-use crate::DEFAULT_OPTIMIZE_OPTIONS;
 use crate::cli::OperationFailure;
 use crate::cli::commands::DiagnosticFormat as CliDiagnosticFormat;
 use crate::cwriteln;
@@ -68,7 +66,7 @@ pub struct Args {
 }
 
 pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
-    if args.test == Id::template() {
+    if args.test.is_template() {
         writeln!(ctx.ui.error()?, "Cannot create template test")?;
         eyre::bail!(OperationFailure);
     }
@@ -154,10 +152,9 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
                 doc,
                 opt: args
                     .export
-                    .optimize_refs
-                    .get_or_default()
-                    .not()
-                    .then(|| Box::new(DEFAULT_OPTIMIZE_OPTIONS.clone())),
+                    .optimize_level(project.config())
+                    .options()
+                    .map(Box::new),
             })
         }
     };
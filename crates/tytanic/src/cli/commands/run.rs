@@ -1,27 +1,69 @@
+use std::collections::BTreeSet;
+use std::convert;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
+
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
 use color_eyre::eyre;
+use color_eyre::eyre::WrapErr;
+use notify::Event;
+use notify::RecursiveMode;
+use notify::Watcher;
 use typst::utils::Scalar;
 use typst_render::RenderOptions;
-use tytanic_core::doc::compare::Strategy;
+use tytanic_core::Id;
+use tytanic_core::Project;
+use tytanic_core::UnitTest;
+use tytanic_core::config::Direction as NativeDirection;
 use tytanic_core::doc::render;
 use tytanic_core::doc::render::Origin;
+use tytanic_core::suite::FilteredSuite;
+use tytanic_core::suite::SuiteResult;
+use tytanic_core::test::Annotation;
+use tytanic_core::test::Test;
+use tytanic_core::test::TestResult;
+use tytanic_core::world_builder::file::project_file_key;
+use tytanic_filter::CombinedFilter;
+use tytanic_filter::exact::ExactFilter;
+use tytanic_filter::test_set::builtin::dsl;
+use tytanic_filter::test_set::eval;
 
 use super::CompareOptions;
 use super::CompileOptions;
 use super::Context;
-use super::Direction;
 use super::ExportOptions;
 use super::FilterOptions;
 use super::OptionDelegate;
+use super::ReporterOption;
 use super::RunnerOptions;
 use super::Switch;
 use crate::cli::CANCELLED;
+use crate::cli::OperationFailure;
 use crate::cli::TestFailure;
 use crate::cli::commands::DiagnosticFormat as CliDiagnosticFormat;
+use crate::report::ConfigDelta;
+use crate::report::ConfigSource;
+use crate::report::RepeatTally;
+use crate::report::ReportFormat;
 use crate::report::Reporter;
 use crate::runner::Action;
 use crate::runner::Runner;
 use crate::runner::RunnerConfig;
+use crate::sarif;
+use crate::ui;
+use crate::world::Providers;
 use typst_kit::diagnostics::DiagnosticFormat;
+use tytanic_utils::fmt::Term;
+
+/// How long to wait for further changes after a change before rerunning
+/// tests in watch mode, this collects the writes of a single save.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(100);
 
 #[derive(clap::Args, Debug, Clone)]
 #[group(id = "run-args")]
@@ -38,79 +80,713 @@ pub struct Args {
     #[command(flatten)]
     pub runner: RunnerOptions,
 
+    /// Explain each failed test after the run.
+    ///
+    /// This shows the stage a test reached, its duration, diagnostics,
+    /// comparison details and the paths to its artifacts. It is most useful
+    /// when running a single test.
+    #[arg(long)]
+    pub explain_failure: bool,
+
+    /// Check the references of all selected persistent tests before the run.
+    ///
+    /// This reports all missing, empty or incomplete references at once and
+    /// aborts the run instead of failing each test individually.
+    #[arg(long)]
+    pub fail_empty_references: bool,
+
+    /// Report the tests whose effective config differs from the project
+    /// defaults before the run.
+    ///
+    /// This lists each overridden member together with where it was set, e.g.
+    /// an annotation loosening the comparison of a test.
+    #[arg(long)]
+    pub audit_config: bool,
+
+    /// Don't write any test artifacts, tests are only compared in memory.
+    ///
+    /// This takes precedence over `--export-ephemeral` and
+    /// `--export-overview`, it is useful if only the outcome of a run is of
+    /// interest.
+    #[arg(long)]
+    pub no_artifacts: bool,
+
+    /// Export difference documents of tests which passed comparison too and
+    /// report their deviations.
+    ///
+    /// By default difference documents are only exported for failed
+    /// comparisons, this is useful when tuning comparison thresholds.
+    #[arg(long)]
+    pub export_diff_always: bool,
+
+    /// Update the references of persistent tests which fail comparison.
+    ///
+    /// Unlike `tt update`, tests are run as usual and only the references of
+    /// tests which failed comparison are written, these are reported as
+    /// updated instead of failed.
+    #[arg(long)]
+    pub update_on_fail: bool,
+
+    /// Exclude template tests from the run.
+    ///
+    /// Equivalent to wrapping the test set expression in `(...) ~ template()`,
+    /// this composes with `--expression` and `--[no-]skip`.
+    #[arg(long)]
+    pub no_template: bool,
+
+    /// Run the matched tests the given number of times and report how often
+    /// each test passed.
+    ///
+    /// All runs are completed even if tests fail, this can be used to find
+    /// tests which fail intermittently.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..),
+    )]
+    pub repeat: u32,
+
+    /// Run each failing test again up to the given number of times before
+    /// reporting it as failed.
+    ///
+    /// A test which passes on a retry is reported as flaky, this is
+    /// overridden by the retries annotation of a test.
+    #[arg(long, value_name = "N")]
+    pub retries: Option<usize>,
+
+    /// Watch the project for changes and rerun the affected tests.
+    ///
+    /// After the initial run, a test is rerun whenever a file it read during
+    /// its last run or a file in its test directory changes. Rapid successive
+    /// changes are collected into a single rerun, press ctrl-c to stop.
+    #[arg(long, conflicts_with = "repeat")]
+    pub watch: bool,
+
+    /// How the progress and results of the run are reported.
+    ///
+    /// The `tap` reporter writes Test Anything Protocol version 14 to stdout,
+    /// failures are described in YAML diagnostic blocks.
+    ///
+    /// The `github` reporter writes GitHub Actions workflow commands to
+    /// stdout, such that failures show up as annotations, only the summary is
+    /// written to stderr. It is used by default if `GITHUB_ACTIONS=true`,
+    /// otherwise `human` is used.
+    ///
+    /// The `jsonl` reporter writes one JSON object per test result and a
+    /// final summary object to stdout, each on its own line, diagnostics are
+    /// written to stderr.
+    #[arg(long, value_name = "REPORTER", conflicts_with = "repeat")]
+    pub reporter: Option<ReporterOption>,
+
+    /// Write a Chrome trace event profile of the run to the given path.
+    ///
+    /// The profile contains the duration of each stage of each test on the
+    /// thread it ran on, it can be loaded in `chrome://tracing` or Perfetto
+    /// to investigate where the time of a run goes.
+    #[arg(long, value_name = "PATH")]
+    pub profile: Option<PathBuf>,
+
+    /// Write the failures of the run as a SARIF 2.1.0 log to the given path.
+    ///
+    /// Each compilation diagnostic is a result located at its span, each
+    /// failed comparison is a result located at the test script, this can be
+    /// uploaded to code scanning dashboards.
+    #[arg(long, value_name = "PATH")]
+    pub sarif_out: Option<PathBuf>,
+
     #[command(flatten)]
     pub filter: FilterOptions,
 }
 
 pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
     let project = ctx.project()?;
-    let suite = ctx.collect_tests_with_filter(&project, ctx.filter(&args.filter)?)?;
-    let providers = ctx.providers(&project, &ctx.args.package, &ctx.args.font, &args.compile)?;
+    let suite = ctx.collect_tests_with_filter(&project, filter(ctx, args)?)?;
+    let skipped = ctx.skipped_tests(&project, &suite, &args.filter, convert::identity)?;
 
-    let origin = match args
-        .export
-        .dir
-        .map(OptionDelegate::into_native)
-        .unwrap_or(project.config().defaults.direction)
-    {
-        Direction::Ltr => Origin::TopLeft,
-        Direction::Rtl => Origin::TopRight,
-    };
+    if args.fail_empty_references {
+        let invalid = suite
+            .matched()
+            .unit_tests()
+            .filter(|test| test.kind().is_persistent())
+            .filter_map(|test| {
+                test.check_reference_document(&project)
+                    .err()
+                    .map(|err| (test, err))
+            })
+            .collect::<Vec<_>>();
+
+        if !invalid.is_empty() {
+            let mut w = ctx.ui.error()?;
+            writeln!(
+                w,
+                "Found invalid references for {} {}:",
+                invalid.len(),
+                Term::simple("test").with(invalid.len()),
+            )?;
+            for (test, err) in invalid {
+                ui::write_test_id(&mut w, test.id())?;
+                writeln!(w, ": {err}")?;
+            }
+
+            writeln!(
+                ctx.ui.hint()?,
+                "Use `tt update` to create the references or omit `--fail-empty-references`"
+            )?;
+
+            eyre::bail!(OperationFailure);
+        }
+    }
+
+    let origin = Origin::from(
+        args.export
+            .dir
+            .map(OptionDelegate::into_native)
+            .unwrap_or(project.config().defaults.direction),
+    );
 
     let pixel_per_pt = render::ppi_to_ppp(args.export.ppi.unwrap_or(project.config().defaults.ppi));
 
-    let max_delta = args
-        .compare
-        .max_delta
-        .unwrap_or(project.config().defaults.max_delta);
+    let mut providers =
+        ctx.providers(&project, &ctx.args.package, &ctx.args.font, &args.compile)?;
+    if args.audit_config {
+        let audit = suite
+            .matched()
+            .unit_tests()
+            .map(|test| (test, audit_config(&project, test, args)))
+            .filter(|(_, deltas)| !deltas.is_empty())
+            .collect::<Vec<_>>();
 
-    let max_deviation = args
-        .compare
-        .max_deviations
-        .unwrap_or(project.config().defaults.max_deviations);
-
-    let runner = Runner::new(
-        &project,
-        &suite,
-        &providers,
-        RunnerConfig {
-            warnings: args.compile.warnings.into_native(),
-            optimize: args.export.optimize_refs.get_or_default(),
-            fail_fast: args.runner.fail_fast.get_or_default(),
-            // TODO: Respect bleed option.
-            render_options: RenderOptions {
-                pixel_per_pt: Scalar::new(pixel_per_pt),
-                render_bleed: false,
-            },
-            strategy: args
-                .compare
-                .compare
-                .get_or_default()
-                .then_some(Strategy::Simple {
-                    max_delta,
-                    max_deviation,
-                }),
-            export_ephemeral: args.export.export_ephemeral.get_or_default(),
-            origin,
-            action: Action::Run,
-            cancellation: &CANCELLED,
+        reporter(ctx, args, &providers).report_config_audit(&audit)?;
+    }
+
+    let config = RunnerConfig {
+        warnings: args.compile.warnings.into_native(),
+        optimize: args.export.optimize_level(project.config()).options(),
+        fail_fast: args.runner.fail_fast.get_or_default(),
+        retries: args.retries.unwrap_or(project.config().defaults.retries),
+        // TODO: Respect bleed option.
+        render_options: RenderOptions {
+            pixel_per_pt: Scalar::new(pixel_per_pt),
+            render_bleed: false,
         },
-    );
+        strategy: args.compare.strategy(project.config()),
+        compare_text: args.compare.compare_text(project.config()),
+        compare_downscale: args.compare.compare_downscale(project.config()),
+        export_ephemeral: !args.no_artifacts && args.export.export_ephemeral.get_or_default(),
+        export_diff_always: args.export_diff_always,
+        update_on_fail: args.update_on_fail,
+        export_format: args
+            .export
+            .export_format
+            .map(OptionDelegate::into_native)
+            .unwrap_or(project.config().export_format),
+        crop: args
+            .export
+            .crop
+            .get()
+            .unwrap_or(project.config().defaults.crop),
+        overview: args.export.overview().filter(|_| !args.no_artifacts),
+        origin,
+        diff_colors: args.export.diff_colors(),
+        compare_cache: !args.no_artifacts && args.compare.compare_cache.get_or_default(),
+        run_id: args.runner.run_id.fixed(),
+        metadata: args.runner.metadata(),
+        record_inputs: args.runner.record_inputs.is_some() || args.watch,
+        inputs: args.compile.inputs.iter().cloned().collect(),
+        skipped,
+        events: None,
+        action: Action::Run,
+        cancellation: &CANCELLED,
+    };
+
+    let mut tally = RepeatTally::default();
+    let mut complete_pass = true;
+    let mut last_result = None;
+
+    for iteration in 0..args.repeat {
+        if iteration != 0 {
+            if CANCELLED.load(Ordering::SeqCst) {
+                break;
+            }
+
+            // Each run gets fresh providers such that no cached files or
+            // fonts are reused between runs.
+            providers =
+                ctx.providers(&project, &ctx.args.package, &ctx.args.font, &args.compile)?;
+        }
+
+        let runner = Runner::new(&project, &suite, &providers, config.clone());
+
+        let reporter = reporter(ctx, args, &providers);
+
+        let result = runner.run(&reporter)?;
+        args.runner.write_input_hashes(&result)?;
+
+        if args.explain_failure {
+            explain_failures(&reporter, &project, &suite, &result)?;
+        }
+
+        complete_pass &= result.is_complete_pass();
+        tally.add(&result);
+        last_result = Some(result);
+    }
+
+    if let Some(path) = &args.sarif_out
+        && let Some(result) = &last_result
+    {
+        sarif::write(path, &project, &providers, &suite, result)
+            .wrap_err_with(|| format!("couldn't write SARIF log to {}", path.display()))?;
+    }
+
+    if args.watch
+        && let Some(result) = last_result
+    {
+        return watch(ctx, args, &project, &providers, &config, &result);
+    }
+
+    if args.repeat > 1 {
+        reporter(ctx, args, &providers).report_repeat(&tally)?;
+    }
+
+    if !complete_pass {
+        eyre::bail!(TestFailure);
+    }
+
+    Ok(())
+}
+
+/// Creates the filter for the tests to run.
+fn filter(ctx: &Context, args: &Args) -> eyre::Result<CombinedFilter> {
+    let mut filter = ctx.filter(&args.filter)?;
+
+    if args.no_template {
+        if let Some(exact) = filter.exact()
+            && exact.expected().iter().any(Id::is_template)
+        {
+            writeln!(
+                ctx.ui.error()?,
+                "Cannot run template test with --no-template"
+            )?;
+            eyre::bail!(OperationFailure);
+        }
+
+        filter.map_test_set(|set| eval::Set::expr_diff(set, dsl::set_template()));
+    }
+
+    Ok(filter)
+}
+
+/// Explains each failed test of the given run.
+fn explain_failures<F>(
+    reporter: &Reporter,
+    project: &Project,
+    suite: &FilteredSuite<F>,
+    result: &SuiteResult,
+) -> eyre::Result<()> {
+    for (id, test_result) in result.results() {
+        if !test_result.is_fail() {
+            continue;
+        }
+
+        if let Some(test) = suite.matched().get(id) {
+            reporter.report_explanation(project, test, test_result)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Watches the project for changes and reruns the tests affected by them
+/// until the run is cancelled.
+///
+/// The providers are reused between runs, such that unchanged files and
+/// compilations stay cached.
+fn watch(
+    ctx: &Context,
+    args: &Args,
+    project: &Project,
+    providers: &Providers,
+    config: &RunnerConfig<'_>,
+    result: &SuiteResult,
+) -> eyre::Result<()> {
+    let mut results = result.results().clone();
+
+    // NOTE(tinger): Events report canonical paths, these are rebased onto the
+    // project root, such that they match project paths even if the root is
+    // reached through a symlink.
+    let canonical_root = project
+        .root()
+        .canonicalize_utf8()
+        .wrap_err_with(|| format!("couldn't canonicalize project root {}", project.root()))?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(canonical_root.as_std_path(), RecursiveMode::Recursive)?;
+
+    writeln!(
+        ctx.ui.stderr(),
+        "Watching for changes, press ctrl-c to stop"
+    )?;
+
+    while let Some(changed) = wait_for_changes(&rx, project, &canonical_root)? {
+        providers.reset_files();
+
+        let suite = ctx
+            .collect_tests(project)?
+            .filter(project, filter(ctx, args)?)?;
+
+        let affected = suite
+            .matched()
+            .tests()
+            .filter(|test| is_affected(project, test, results.get(test.id()), &changed))
+            .map(|test| test.id().clone())
+            .collect::<Vec<_>>();
+
+        if affected.is_empty() {
+            continue;
+        }
+
+        let suite = suite
+            .matched()
+            .clone()
+            .filter(project, ExactFilter::new(affected))?;
 
-    let reporter = Reporter::new(
+        let runner = Runner::new(project, &suite, providers, config.clone());
+        let reporter = reporter(ctx, args, providers);
+        let result = runner.run(&reporter)?;
+
+        if args.explain_failure {
+            explain_failures(&reporter, project, &suite, &result)?;
+        }
+
+        results.extend(
+            result
+                .results()
+                .iter()
+                .filter(|(id, _)| suite.matched().get(id).is_some())
+                .map(|(id, result)| (id.clone(), result.clone())),
+        );
+    }
+
+    Ok(())
+}
+
+/// Waits for changes to files which may affect tests and returns their paths
+/// relative to the project root, changes in quick succession are collected
+/// together.
+///
+/// Returns `None` if the run was cancelled while waiting.
+fn wait_for_changes(
+    rx: &Receiver<notify::Result<Event>>,
+    project: &Project,
+    canonical_root: &Utf8Path,
+) -> eyre::Result<Option<BTreeSet<Utf8PathBuf>>> {
+    let mut changed = BTreeSet::new();
+
+    loop {
+        if CANCELLED.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+
+        match rx.recv_timeout(WATCH_DEBOUNCE) {
+            Ok(event) => {
+                let event = event?;
+                if event.kind.is_access() {
+                    continue;
+                }
+
+                changed.extend(
+                    event
+                        .paths
+                        .into_iter()
+                        .filter_map(|path| Utf8PathBuf::from_path_buf(path).ok())
+                        .filter_map(|path| {
+                            let path = path.strip_prefix(canonical_root).ok()?;
+                            Some(project.root().join(path))
+                        })
+                        .filter(|path| !is_generated(project, path)),
+                );
+            }
+            Err(RecvTimeoutError::Timeout) if !changed.is_empty() => return Ok(Some(changed)),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(None),
+        }
+    }
+}
+
+/// Whether the given path is written by Tytanic or the VCS itself, changes to
+/// these paths never cause a rerun.
+fn is_generated(project: &Project, path: &Utf8Path) -> bool {
+    if path.starts_with(project.cache_root()) {
+        return true;
+    }
+
+    if path
+        .ancestors()
+        .any(|ancestor| matches!(ancestor.file_name(), Some(".git" | ".jj" | ".sl")))
+    {
+        return true;
+    }
+
+    let tests_root = project.unit_tests_root();
+    path.ancestors().any(|ancestor| {
+        let Some(id) = ancestor
+            .parent()
+            .and_then(|parent| parent.strip_prefix(&tests_root).ok())
+            .and_then(|dir| Id::new_from_path(dir).ok())
+        else {
+            return false;
+        };
+
+        ancestor == project.unit_test_out_dir(&id)
+            || ancestor == project.unit_test_diff_dir(&id)
+            // Ephemeral references are compiled into the reference directory.
+            || (ancestor == project.unit_test_ref_dir(&id)
+                && project.unit_test_ref_script(&id).exists())
+    })
+}
+
+/// Whether a test is affected by the given changed files, i.e. whether it
+/// read one of them during its last run or one of them is within its test
+/// directory.
+///
+/// Tests without recorded inputs, like newly added tests, are affected by any
+/// change.
+fn is_affected(
+    project: &Project,
+    test: &Test,
+    last: Option<&TestResult>,
+    changed: &BTreeSet<Utf8PathBuf>,
+) -> bool {
+    let Some(inputs) = last
+        .map(TestResult::inputs)
+        .filter(|inputs| !inputs.is_empty())
+    else {
+        return true;
+    };
+
+    if let Some(test) = test.as_unit_test() {
+        let dir = project.unit_test_dir(test.id());
+        if changed.iter().any(|path| path.starts_with(&dir)) {
+            return true;
+        }
+    }
+
+    changed
+        .iter()
+        .filter_map(|path| project_file_key(project.root(), path))
+        .any(|key| inputs.contains(&key))
+}
+
+/// Creates the reporter for a single test run.
+///
+/// Without an explicit reporter, the GitHub reporter is used on GitHub
+/// Actions unless the run is repeated.
+fn reporter<'a>(ctx: &Context<'a>, args: &Args, providers: &'a Providers) -> Reporter<'a, 'a> {
+    let report_format = match args.reporter {
+        Some(reporter) => reporter.into_native(),
+        None if args.repeat == 1 && ReportFormat::is_github_actions() => ReportFormat::Github,
+        None => ReportFormat::Human,
+    };
+
+    Reporter::new(
         ctx.ui,
-        &providers,
+        providers,
         ctx.ui.can_live_report() && ctx.args.output.verbose == 0,
         match args.compile.diagnostic_format {
             CliDiagnosticFormat::Human => DiagnosticFormat::Human,
             CliDiagnosticFormat::Short => DiagnosticFormat::Short,
         },
-    );
-    let result = runner.run(&reporter)?;
+    )
+    .with_report_format(report_format)
+    .with_timings(ctx.args.output.verbose > 0)
+}
 
-    if !result.is_complete_pass() {
-        eyre::bail!(TestFailure);
+/// Computes how the effective config of a test differs from the project
+/// defaults.
+fn audit_config(project: &Project, test: &UnitTest, args: &Args) -> Vec<ConfigDelta> {
+    let defaults = &project.config().defaults;
+
+    let dir_str = |dir| match dir {
+        NativeDirection::Ltr => "ltr",
+        NativeDirection::Rtl => "rtl",
+    };
+
+    let mut dir = args
+        .export
+        .dir
+        .map(|dir| (dir.into_native(), ConfigSource::CommandLine));
+    let mut ppi = args.export.ppi.map(|ppi| (ppi, ConfigSource::CommandLine));
+    let mut max_delta = args
+        .compare
+        .max_delta
+        .map(|max_delta| (max_delta, ConfigSource::CommandLine));
+    let mut max_deviations = args
+        .compare
+        .max_deviations
+        .map(|max_deviations| (max_deviations, ConfigSource::CommandLine));
+    let mut max_failing_pages = args
+        .compare
+        .max_failing_pages
+        .map(|max_failing_pages| (max_failing_pages, ConfigSource::CommandLine));
+
+    // Annotations take precedence over the command line, but only if they
+    // have any effect on the test.
+    let own = test
+        .own_annotations()
+        .iter()
+        .map(|annot| (annot, ConfigSource::Annotation));
+    let inherited = test
+        .inherited_annotations()
+        .iter()
+        .map(|annot| (annot, ConfigSource::Directory));
+
+    for (annot, source) in own.chain(inherited) {
+        if !annot.applies_to(test.kind()) {
+            continue;
+        }
+
+        match *annot {
+            Annotation::Dir(set) => dir = Some((set, source)),
+            Annotation::Ppi(set) => ppi = Some((set, source)),
+            Annotation::MaxDelta(set) => max_delta = Some((set, source)),
+            Annotation::MaxDeviations(set) => max_deviations = Some((set, source)),
+            Annotation::MaxFailingPages(set) => max_failing_pages = Some((set, source)),
+            _ => {}
+        }
     }
 
-    Ok(())
+    let mut deltas = vec![];
+
+    if let Some((dir, source)) = dir
+        && dir != defaults.direction
+    {
+        deltas.push(ConfigDelta {
+            member: "dir",
+            default: dir_str(defaults.direction).to_owned(),
+            value: dir_str(dir).to_owned(),
+            source,
+        });
+    }
+
+    if let Some((ppi, source)) = ppi
+        && ppi != defaults.ppi
+    {
+        deltas.push(ConfigDelta {
+            member: "ppi",
+            default: defaults.ppi.to_string(),
+            value: ppi.to_string(),
+            source,
+        });
+    }
+
+    if let Some((max_delta, source)) = max_delta
+        && max_delta != defaults.max_delta
+    {
+        deltas.push(ConfigDelta {
+            member: "max-delta",
+            default: defaults.max_delta.to_string(),
+            value: max_delta.to_string(),
+            source,
+        });
+    }
+
+    if let Some((max_deviations, source)) = max_deviations
+        && max_deviations != defaults.max_deviations
+    {
+        deltas.push(ConfigDelta {
+            member: "max-deviations",
+            default: defaults.max_deviations.to_string(),
+            value: max_deviations.to_string(),
+            source,
+        });
+    }
+
+    if let Some((max_failing_pages, source)) = max_failing_pages
+        && max_failing_pages != defaults.max_failing_pages
+    {
+        deltas.push(ConfigDelta {
+            member: "max-failing-pages",
+            default: defaults.max_failing_pages.to_string(),
+            value: max_failing_pages.to_string(),
+            source,
+        });
+    }
+
+    deltas
+}
+
+#[cfg(test)]
+mod tests {
+    use tytanic_core::test::unit::Kind;
+    use tytanic_utils::fs::TempTestEnv;
+
+    use super::*;
+
+    #[test]
+    fn test_is_generated() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("tests/ephemeral/test.typ", "Hello World")
+                    .setup_file("tests/ephemeral/ref.typ", "Hello World")
+                    .setup_file("tests/persistent/test.typ", "Hello World")
+            },
+            |root| {
+                let project = Project::new(root);
+                let path = |path: &str| project.root().join(path);
+
+                assert!(is_generated(&project, &path("tests/.cache/foo")));
+                assert!(is_generated(&project, &path(".git/index")));
+                assert!(is_generated(&project, &path("tests/persistent/out/1.png")));
+                assert!(is_generated(&project, &path("tests/persistent/diff/1.png")));
+                assert!(is_generated(&project, &path("tests/ephemeral/ref/1.png")));
+
+                // Output of tests deleted while watching.
+                assert!(is_generated(&project, &path("tests/deleted/out/1.png")));
+
+                assert!(!is_generated(&project, &path("tests/persistent/ref/1.png")));
+                assert!(!is_generated(&project, &path("tests/persistent/test.typ")));
+                assert!(!is_generated(&project, &path("src/out/lib.typ")));
+            },
+        );
+    }
+
+    #[test]
+    fn test_is_affected() {
+        let project = Project::new("/project");
+        let test = Test::Unit(UnitTest::new(Id::new("foo").unwrap(), Kind::Persistent));
+        let changed = |path: &str| BTreeSet::from([project.root().join(path)]);
+
+        // Tests without recorded inputs are affected by any change.
+        assert!(is_affected(&project, &test, None, &changed("src/lib.typ")));
+
+        let mut last = TestResult::skipped();
+        last.set_inputs(["/src/lib.typ".into()]);
+
+        assert!(is_affected(
+            &project,
+            &test,
+            Some(&last),
+            &changed("src/lib.typ")
+        ));
+        assert!(is_affected(
+            &project,
+            &test,
+            Some(&last),
+            &changed("tests/foo/data.csv")
+        ));
+        assert!(!is_affected(
+            &project,
+            &test,
+            Some(&last),
+            &changed("src/other.typ")
+        ));
+        assert!(!is_affected(
+            &project,
+            &test,
+            Some(&last),
+            &changed("tests/bar/test.typ")
+        ));
+    }
 }
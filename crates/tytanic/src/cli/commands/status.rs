@@ -19,6 +19,7 @@ pub struct Args {
 pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
     let project = ctx.project()?;
     let suite = ctx.collect_tests(&project)?;
+    ctx.warn_collection(&project, &suite)?;
 
     let delim_open = " ┌ ";
     let delim_middle = " ├ ";
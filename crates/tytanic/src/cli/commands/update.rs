@@ -1,22 +1,28 @@
 use std::io::Write;
+use std::sync::atomic::Ordering;
 
 use color_eyre::eyre;
+use color_eyre::eyre::WrapErr;
 use typst::utils::Scalar;
 use typst_kit::diagnostics::DiagnosticFormat;
 use typst_render::RenderOptions;
+use tytanic_core::FilteredSuite;
 use tytanic_core::Id;
-use tytanic_core::doc::compare::Strategy;
+use tytanic_core::Project;
 use tytanic_core::doc::render;
 use tytanic_core::doc::render::Origin;
+use tytanic_core::project::vcs;
+use tytanic_core::suite::SuiteResult;
+use tytanic_core::test::Stage;
 use tytanic_filter::test_set::builtin::dsl;
 use tytanic_filter::test_set::eval;
 
 use super::CompareOptions;
 use super::CompileOptions;
 use super::Context;
-use super::Direction;
 use super::ExportOptions;
 use super::FilterOptions;
+use super::OnlyChangedRefsSwitch;
 use super::OptionDelegate;
 use super::RunnerOptions;
 use super::Switch;
@@ -29,6 +35,8 @@ use crate::runner::Action;
 use crate::runner::Runner;
 use crate::runner::RunnerConfig;
 use crate::ui;
+use crate::ui::Ui;
+use crate::world::Providers;
 
 #[derive(clap::Args, Debug, Clone)]
 #[group(id = "update-args")]
@@ -48,9 +56,50 @@ pub struct Args {
     #[command(flatten)]
     pub filter: FilterOptions,
 
+    #[command(flatten)]
+    pub only_changed_refs: OnlyChangedRefsSwitch,
+
     /// Update all included tests, even if they didn't fail.
     #[arg(long)]
     pub force: bool,
+
+    /// Update only the given 1-based pages of the references, e.g. `3` or
+    /// `2,5`.
+    ///
+    /// All other reference pages are left untouched. This fails if the
+    /// number of pages of a test differs from its stored reference.
+    #[arg(long, value_name = "PAGES", value_delimiter = ',')]
+    pub pages: Vec<usize>,
+
+    /// Update only tests affected by the files which changed since the given
+    /// revision of the project's VCS.
+    ///
+    /// A test is affected if a file in its directory changed or it reads a
+    /// changed file while compiling, like a package source file. All other
+    /// tests are left untouched, this keeps updates after a dependency bump
+    /// limited to the tests it affects. The revision must not start with a
+    /// `-`.
+    #[arg(long, value_name = "REV", value_parser = parse_revision)]
+    pub since: Option<String>,
+
+    /// Ask for confirmation before updating the references of each test.
+    ///
+    /// Tests are run one at a time, the comparison failure of each test is
+    /// shown before asking whether to accept its new references. Updates
+    /// without confirmation if no terminal is attached or `--yes` is given.
+    #[arg(long)]
+    pub interactive: bool,
+
+    /// Accept all updates without asking for confirmation.
+    #[arg(long)]
+    pub yes: bool,
+
+    /// Report which tests would be updated without writing any references.
+    ///
+    /// Tests are compared as usual, the number of pages which would be
+    /// written is reported for each test which would be updated.
+    #[arg(long, conflicts_with = "interactive")]
+    pub dry_run: bool,
 }
 
 pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
@@ -59,7 +108,7 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
     let mut filter = ctx.filter(&args.filter)?;
 
     if let Some(exact) = filter.exact()
-        && exact.expected().contains(&Id::template())
+        && exact.expected().iter().any(Id::is_template)
     {
         writeln!(ctx.ui.error()?, "Cannot update template test")?;
         eyre::bail!(OperationFailure);
@@ -68,6 +117,9 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
     filter.map_test_set(|set| eval::Set::expr_inter(set, dsl::set_persistent(), []));
 
     let suite = ctx.collect_tests_with_filter(&project, filter)?;
+    let skipped = ctx.skipped_tests(&project, &suite, &args.filter, |set| {
+        eval::Set::expr_inter(set, dsl::set_persistent(), [])
+    })?;
 
     let mut illegal_tests = vec![];
     for test in suite.matched() {
@@ -89,68 +141,100 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
         eyre::bail!(OperationFailure);
     }
 
-    let providers = ctx.providers(&project, &ctx.args.package, &ctx.args.font, &args.compile)?;
+    let since = match &args.since {
+        Some(rev) => {
+            let Some(vcs) = project.vcs() else {
+                writeln!(ctx.ui.error()?, "Cannot use --since without a VCS")?;
+                eyre::bail!(OperationFailure);
+            };
 
-    let origin = match args
-        .export
-        .dir
-        .map(OptionDelegate::into_native)
-        .unwrap_or(project.config().defaults.direction)
-    {
-        Direction::Ltr => Origin::TopLeft,
-        Direction::Rtl => Origin::TopRight,
+            Some(
+                vcs.changed_files(Some(rev))
+                    .wrap_err_with(|| format!("couldn't query files changed since {rev}"))?,
+            )
+        }
+        None => None,
     };
 
-    let pixel_per_pt = render::ppi_to_ppp(args.export.ppi.unwrap_or(project.config().defaults.ppi));
+    let providers = ctx.providers(&project, &ctx.args.package, &ctx.args.font, &args.compile)?;
 
-    let max_delta = args
-        .compare
-        .max_delta
-        .unwrap_or(project.config().defaults.max_delta);
+    let origin = Origin::from(
+        args.export
+            .dir
+            .map(OptionDelegate::into_native)
+            .unwrap_or(project.config().defaults.direction),
+    );
 
-    let max_deviation = args
-        .compare
-        .max_deviations
-        .unwrap_or(project.config().defaults.max_deviations);
+    let pixel_per_pt = render::ppi_to_ppp(args.export.ppi.unwrap_or(project.config().defaults.ppi));
 
-    let runner = Runner::new(
-        &project,
-        &suite,
-        &providers,
-        RunnerConfig {
-            warnings: args.compile.warnings.into_native(),
-            optimize: args.export.optimize_refs.get_or_default(),
-            fail_fast: args.runner.fail_fast.get_or_default(),
-            // TODO: Respect bleed option.
-            render_options: RenderOptions {
-                pixel_per_pt: Scalar::new(pixel_per_pt),
-                render_bleed: false,
-            },
-            strategy: args
-                .compare
-                .compare
-                .get_or_default()
-                .then_some(Strategy::Simple {
-                    max_delta,
-                    max_deviation,
-                }),
-            export_ephemeral: args.export.export_ephemeral.get_or_default(),
-            origin,
-            action: Action::Update { force: args.force },
-            cancellation: &CANCELLED,
+    let config = RunnerConfig {
+        warnings: args.compile.warnings.into_native(),
+        optimize: args.export.optimize_level(project.config()).options(),
+        fail_fast: args.runner.fail_fast.get_or_default(),
+        retries: 0,
+        // TODO: Respect bleed option.
+        render_options: RenderOptions {
+            pixel_per_pt: Scalar::new(pixel_per_pt),
+            render_bleed: false,
         },
-    );
+        strategy: args.compare.strategy(project.config()),
+        compare_text: args.compare.compare_text(project.config()),
+        compare_downscale: args.compare.compare_downscale(project.config()),
+        export_ephemeral: args.export.export_ephemeral.get_or_default(),
+        export_diff_always: false,
+        update_on_fail: false,
+        export_format: args
+            .export
+            .export_format
+            .map(OptionDelegate::into_native)
+            .unwrap_or(project.config().export_format),
+        crop: args
+            .export
+            .crop
+            .get()
+            .unwrap_or(project.config().defaults.crop),
+        overview: args.export.overview(),
+        origin,
+        diff_colors: args.export.diff_colors(),
+        compare_cache: args.compare.compare_cache.get_or_default(),
+        run_id: args.runner.run_id.fixed(),
+        metadata: args.runner.metadata(),
+        // The inputs decide which tests are affected by changed files.
+        record_inputs: args.runner.record_inputs.is_some() || since.is_some(),
+        inputs: args.compile.inputs.iter().cloned().collect(),
+        skipped,
+        events: None,
+        action: Action::Update {
+            force: args.force,
+            only_changed: args.only_changed_refs.get_or_default(),
+            pages: (!args.pages.is_empty()).then(|| args.pages.iter().copied().collect()),
+            since,
+            dry_run: args.dry_run,
+        },
+        cancellation: &CANCELLED,
+    };
+
+    let interactive = args.interactive && !args.yes && ctx.ui.can_prompt();
 
     let reporter = Reporter::new(
         ctx.ui,
         &providers,
-        ctx.ui.can_live_report() && ctx.args.output.verbose == 0,
+        !interactive && ctx.ui.can_live_report() && ctx.args.output.verbose == 0,
         match args.compile.diagnostic_format {
             CliDiagnosticFormat::Human => DiagnosticFormat::Human,
             CliDiagnosticFormat::Short => DiagnosticFormat::Short,
         },
-    );
-    let result = runner.run(&reporter)?;
+    )
+    .with_timings(ctx.args.output.verbose > 0)
+    .with_dry_run(args.dry_run);
+    let result = if interactive {
+        run_interactive(&project, &suite, &providers, config, &reporter, |id| {
+            confirm_update(ctx.ui, id)
+        })?
+    } else {
+        Runner::new(&project, &suite, &providers, config).run(&reporter)?
+    };
+    args.runner.write_input_hashes(&result)?;
 
     if !result.is_complete_pass() {
         eyre::bail!(TestFailure);
@@ -158,3 +242,259 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
 
     Ok(())
 }
+
+/// The answer to the confirmation of a single update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Confirmation {
+    /// Update the references of this test.
+    Yes,
+
+    /// Leave the references of this test untouched.
+    No,
+
+    /// Update the references of this and all following tests.
+    All,
+
+    /// Leave the references of this and all following tests untouched.
+    Quit,
+}
+
+/// Asks whether the references of the given test should be updated until a
+/// valid answer is given.
+fn confirm_update(ui: &Ui, id: &Id) -> eyre::Result<Confirmation> {
+    loop {
+        let answer = ui.prompt_with(|w| {
+            write!(w, "Update references of ")?;
+            ui::write_test_id(w, id)?;
+            write!(w, "? [y]es, [n]o, [a]ll, [q]uit: ")
+        })?;
+
+        match &answer.to_ascii_lowercase()[..] {
+            "y" | "yes" => return Ok(Confirmation::Yes),
+            "n" | "no" => return Ok(Confirmation::No),
+            "a" | "all" => return Ok(Confirmation::All),
+            "q" | "quit" => return Ok(Confirmation::Quit),
+            _ => writeln!(
+                ui.warn()?,
+                "Expected [y]es, [n]o, [a]ll or [q]uit, got: {answer:?}"
+            )?,
+        }
+    }
+}
+
+/// Updates the matched tests one at a time, asking for confirmation before the
+/// references of a test are written.
+///
+/// Only tests which fail comparison are offered for an update, or all tests if
+/// the update is forced, their comparison is explained before asking. Declined
+/// tests are reported as failed, tests after quitting are not run. The run is
+/// reported and recorded like a non-interactive update, see [`Runner::run`].
+fn run_interactive<F>(
+    project: &Project,
+    suite: &FilteredSuite<F>,
+    providers: &Providers,
+    config: RunnerConfig<'_>,
+    reporter: &Reporter,
+    mut confirm: impl FnMut(&Id) -> eyre::Result<Confirmation>,
+) -> eyre::Result<SuiteResult> {
+    let force = matches!(config.action, Action::Update { force: true, .. });
+
+    // The comparison decides which tests are offered, confirmed updates are
+    // forced since they are known to be wanted.
+    let mut check_config = config.clone();
+    check_config.action = Action::Run;
+    check_config.strategy = Some(config.strategy.unwrap_or_default());
+
+    let mut update_config = config;
+    if let Action::Update { force, .. } = &mut update_config.action {
+        *force = true;
+    }
+
+    let checker = Runner::new(project, suite, providers, check_config);
+    let updater = Runner::new(project, suite, providers, update_config);
+
+    updater.run_with(reporter, |updater, reporter| {
+        let mut accept_all = false;
+        for test in suite.matched().tests() {
+            if updater.config.cancellation.load(Ordering::SeqCst) {
+                break;
+            }
+
+            let Some(unit_test) = test.as_unit_test() else {
+                continue;
+            };
+
+            let checked = checker.unit_test(unit_test).run()?;
+            let offered = force || matches!(checked.stage(), Stage::FailedComparison(_));
+
+            let result = if !offered {
+                checked
+            } else if accept_all {
+                updater.unit_test(unit_test).run()?
+            } else {
+                reporter.report_explanation(project, test, &checked)?;
+
+                match confirm(test.id())? {
+                    Confirmation::Yes => updater.unit_test(unit_test).run()?,
+                    Confirmation::All => {
+                        accept_all = true;
+                        updater.unit_test(unit_test).run()?
+                    }
+                    Confirmation::No => checked,
+                    Confirmation::Quit => break,
+                }
+            };
+
+            reporter.report_test_result(project, test, &result)?;
+            updater.result.set_test_result(test.id().clone(), result);
+        }
+
+        Ok(())
+    })
+}
+
+fn parse_revision(raw: &str) -> Result<String, String> {
+    vcs::validate_base(raw).map_err(|err| err.to_string())?;
+    Ok(raw.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::collections::BTreeSet;
+    use std::fs;
+
+    use clap::Parser;
+    use termcolor::ColorChoice;
+    use tytanic_core::doc::compare::Strategy;
+    use tytanic_core::doc::compile::Warnings;
+    use tytanic_core::doc::render::DiffColors;
+    use tytanic_core::suite::LastRun;
+    use tytanic_core::suite::LastStatus;
+    use tytanic_core::suite::Suite;
+    use tytanic_filter::CombinedFilter;
+    use tytanic_filter::exact::ExactFilter;
+    use tytanic_utils::fs::TempTestEnv;
+
+    use super::*;
+    use crate::cli::commands::CliArguments;
+    use crate::cli::commands::Command;
+
+    fn config(project: &Project, action: Action) -> RunnerConfig<'static> {
+        RunnerConfig {
+            warnings: Warnings::Emit,
+            optimize: None,
+            fail_fast: false,
+            retries: 0,
+            render_options: RenderOptions {
+                pixel_per_pt: Scalar::new(render::ppi_to_ppp(project.config().defaults.ppi)),
+                render_bleed: false,
+            },
+            strategy: Some(Strategy::default()),
+            compare_text: false,
+            compare_downscale: 1,
+            export_ephemeral: false,
+            export_diff_always: false,
+            update_on_fail: false,
+            export_format: project.config().export_format,
+            crop: false,
+            overview: None,
+            origin: Origin::default(),
+            diff_colors: DiffColors::default(),
+            compare_cache: false,
+            run_id: None,
+            metadata: BTreeMap::new(),
+            record_inputs: false,
+            inputs: BTreeMap::new(),
+            skipped: BTreeSet::new(),
+            events: None,
+            action,
+            cancellation: &CANCELLED,
+        }
+    }
+
+    fn update(force: bool) -> Action {
+        Action::Update {
+            force,
+            only_changed: false,
+            pages: None,
+            since: None,
+            dry_run: false,
+        }
+    }
+
+    #[test]
+    fn test_run_interactive_records_last_run() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("tests/accepted/test.typ", "Hello")
+                    .setup_dir("tests/accepted/ref")
+                    .setup_file("tests/declined/test.typ", "Hello")
+                    .setup_dir("tests/declined/ref")
+                    .setup_file("tests/passing/test.typ", "World")
+                    .setup_dir("tests/passing/ref")
+            },
+            |root| {
+                let project = Project::new(root);
+                let cli = CliArguments::try_parse_from(["tt", "update"]).unwrap();
+                let Command::Update(args) = &cli.cmd else {
+                    unreachable!()
+                };
+                let providers =
+                    Providers::new(&project, &cli.package, &cli.font, &args.compile).unwrap();
+                let exact = ExactFilter::new(
+                    ["accepted", "declined", "passing"].map(|id| Id::new(id).unwrap()),
+                );
+                let suite = Suite::collect(&project)
+                    .unwrap()
+                    .filter(&project, CombinedFilter::new(None, Some(exact)))
+                    .unwrap();
+                let ui = Ui::new(ColorChoice::Never, ColorChoice::Never);
+                let reporter = Reporter::new(&ui, &providers, false, DiagnosticFormat::Human);
+
+                Runner::new(&project, &suite, &providers, config(&project, update(true)))
+                    .run(&reporter)
+                    .unwrap();
+
+                // Make the references of both tests outdated.
+                for test in ["accepted", "declined"] {
+                    fs::copy(
+                        project.root().join("tests/passing/ref/1.png"),
+                        project.root().join(format!("tests/{test}/ref/1.png")),
+                    )
+                    .unwrap();
+                }
+
+                let mut offered = vec![];
+                let result = run_interactive(
+                    &project,
+                    &suite,
+                    &providers,
+                    config(&project, update(false)),
+                    &reporter,
+                    |id| {
+                        offered.push(id.clone());
+                        Ok(if id.as_str() == "accepted" {
+                            Confirmation::Yes
+                        } else {
+                            Confirmation::No
+                        })
+                    },
+                )
+                .unwrap();
+
+                assert_eq!(
+                    offered,
+                    [Id::new("accepted").unwrap(), Id::new("declined").unwrap()]
+                );
+                assert!(!result.is_complete_pass());
+
+                let last_run = LastRun::read(&project).unwrap().unwrap();
+                let status = |id: &str| last_run.status(&Id::new(id).unwrap());
+                assert_eq!(status("accepted"), Some(LastStatus::Passed));
+                assert_eq!(status("declined"), Some(LastStatus::Failed));
+                assert_eq!(status("passing"), Some(LastStatus::Passed));
+            },
+        );
+    }
+}
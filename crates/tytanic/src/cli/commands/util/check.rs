@@ -0,0 +1,84 @@
+use std::io::Write;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use tytanic_utils::fmt::Term;
+
+use super::Context;
+use crate::cli::OperationFailure;
+use crate::cli::commands::FilterOptions;
+use crate::cwrite;
+use crate::ui;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-check-args")]
+pub struct Args {
+    /// Delete orphaned references after confirmation.
+    #[arg(long)]
+    pub fix: bool,
+
+    #[command(flatten)]
+    pub filter: FilterOptions,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let suite = ctx.collect_tests_with_filter(&project, ctx.filter(&args.filter)?)?;
+
+    let mut orphaned = vec![];
+    for test in suite.matched().unit_tests() {
+        if test.has_orphaned_references(&project)? {
+            orphaned.push(test);
+        }
+    }
+
+    let len = orphaned.len();
+    if len == 0 {
+        let mut w = ctx.ui.stderr();
+        writeln!(w, "Found no orphaned references")?;
+        return Ok(());
+    }
+
+    {
+        let mut w = ctx.ui.warn()?;
+        write!(w, "Found orphaned references for ")?;
+        cwrite!(colored(w, Color::Yellow), "{len}")?;
+        writeln!(w, " {}", Term::simple("test").with(len))?;
+
+        for test in &orphaned {
+            write!(w, "- ")?;
+            ui::write_test_id(&mut w, test.id())?;
+            writeln!(w)?;
+        }
+    }
+
+    if !args.fix {
+        let mut w = ctx.ui.hint()?;
+        write!(w, "You can run ")?;
+        cwrite!(colored(w, Color::Cyan), "tt util check --fix")?;
+        writeln!(w, " to delete them")?;
+
+        eyre::bail!(OperationFailure);
+    }
+
+    if !ctx.ui.prompt_yes_no(
+        format!(
+            "Delete orphaned references of {len} {}",
+            Term::simple("test").with(len)
+        ),
+        false,
+    )? {
+        eyre::bail!(OperationFailure);
+    }
+
+    for test in &orphaned {
+        test.delete_reference_document(&project)?;
+    }
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Removed orphaned references for ")?;
+    cwrite!(colored(w, Color::Green), "{len}")?;
+    writeln!(w, " {}", Term::simple("test").with(len))?;
+
+    Ok(())
+}
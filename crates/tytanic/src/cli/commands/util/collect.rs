@@ -0,0 +1,72 @@
+use std::io::Write;
+use std::time::Instant;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use tytanic_core::Suite;
+use tytanic_utils::fmt::Term;
+
+use super::Context;
+use crate::cwrite;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-collect-args")]
+pub struct Args {
+    /// Report how long the test collection took.
+    #[arg(long)]
+    pub bench: bool,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+
+    // NOTE(tinger): We deliberately don't use `Context::collect_tests` here,
+    // nested tests should not abort collection when only measuring it.
+    let start = Instant::now();
+    let suite = Suite::collect(&project)?;
+    let duration = start.elapsed();
+
+    let mut w = ctx.ui.stderr();
+
+    let tests = suite.len();
+    write!(w, "Collected ")?;
+    cwrite!(colored(w, Color::Green), "{tests}")?;
+    writeln!(w, " {}", Term::simple("test").with(tests))?;
+
+    let nested = suite.nested().len();
+    if nested != 0 {
+        write!(w, "Found ")?;
+        cwrite!(colored(w, Color::Yellow), "{nested}")?;
+        writeln!(w, " nested {}", Term::simple("test").with(nested))?;
+    }
+
+    let invalid = suite.invalid().len();
+    if invalid != 0 {
+        write!(w, "Ignored ")?;
+        cwrite!(colored(w, Color::Red), "{invalid}")?;
+        writeln!(
+            w,
+            " {} with invalid identifiers",
+            Term::new("directory", "directories").with(invalid),
+        )?;
+    }
+
+    let skipped = suite.skipped().len();
+    if skipped != 0 {
+        write!(w, "Skipped ")?;
+        cwrite!(colored(w, Color::Yellow), "{skipped}")?;
+        writeln!(
+            w,
+            " {} without tests",
+            Term::new("directory", "directories").with(skipped),
+        )?;
+    }
+
+    if args.bench {
+        write!(w, "Collection took ")?;
+        cwrite!(bold(w), "{}ms", duration.as_millis())?;
+        writeln!(w)?;
+    }
+
+    Ok(())
+}
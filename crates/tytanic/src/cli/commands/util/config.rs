@@ -0,0 +1,201 @@
+use std::io::Write;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use tytanic_core::config::Direction;
+use tytanic_core::doc::OptimizeLevel;
+use tytanic_core::doc::compare::CompareMode;
+use tytanic_core::doc::compare::PrescaleMode;
+
+use super::Context;
+use crate::cwrite;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-config-args")]
+pub struct Args {
+    /// Print the effective config as a TOML document to stdout.
+    ///
+    /// The output can be pasted into the project manifest.
+    #[arg(long)]
+    pub dump: bool,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let config = project.config();
+
+    if args.dump {
+        write!(ctx.ui.stdout(), "{}", config.to_manifest_toml()?)?;
+        return Ok(());
+    }
+
+    let delim_open = " ┌ ";
+    let delim_middle = " ├ ";
+    let delim_close = " └ ";
+
+    let mut w = ctx.ui.stderr();
+
+    let align = ["Max deviations", "Max failing pages", "Align tolerance"]
+        .map(str::len)
+        .into_iter()
+        .max()
+        .unwrap();
+
+    write!(w, "{:>align$}{}", "Tests", delim_open)?;
+    cwrite!(bold_colored(w, Color::Cyan), "{}", config.unit_tests_root)?;
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "Ref format", delim_middle)?;
+    cwrite!(
+        bold_colored(w, Color::Cyan),
+        "{}",
+        config.ref_format.extension()
+    )?;
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "Ref pattern", delim_middle)?;
+    cwrite!(bold_colored(w, Color::Cyan), "{}", config.ref_pattern)?;
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "Ref meta", delim_middle)?;
+    cwrite!(
+        bold_colored(w, Color::Cyan),
+        "{}",
+        if config.ref_meta { "on" } else { "off" }
+    )?;
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "Ref variants", delim_middle)?;
+    cwrite!(
+        bold_colored(w, Color::Cyan),
+        "{}",
+        if config.ref_variants { "on" } else { "off" }
+    )?;
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "Optimize refs", delim_middle)?;
+    let optimize = match config.optimize_refs {
+        OptimizeLevel::None => "none",
+        OptimizeLevel::Fast => "fast",
+        OptimizeLevel::Max => "max",
+    };
+    cwrite!(bold_colored(w, Color::Cyan), "{optimize}")?;
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "Direction", delim_middle)?;
+    let dir = match config.defaults.direction {
+        Direction::Ltr => "ltr",
+        Direction::Rtl => "rtl",
+    };
+    cwrite!(bold_colored(w, Color::Cyan), "{dir}")?;
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "PPI", delim_middle)?;
+    cwrite!(bold_colored(w, Color::Cyan), "{}", config.defaults.ppi)?;
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "Crop", delim_middle)?;
+    cwrite!(
+        bold_colored(w, Color::Cyan),
+        "{}",
+        if config.defaults.crop { "on" } else { "off" }
+    )?;
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "Max delta", delim_middle)?;
+    cwrite!(
+        bold_colored(w, Color::Cyan),
+        "{}",
+        config.defaults.max_delta
+    )?;
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "Max deviations", delim_middle)?;
+    cwrite!(
+        bold_colored(w, Color::Cyan),
+        "{}",
+        config.defaults.max_deviations
+    )?;
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "Max failing pages", delim_middle)?;
+    cwrite!(
+        bold_colored(w, Color::Cyan),
+        "{}",
+        config.defaults.max_failing_pages
+    )?;
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "Align tolerance", delim_middle)?;
+    cwrite!(
+        bold_colored(w, Color::Cyan),
+        "{}",
+        config.defaults.compare_align_tolerance
+    )?;
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "Ignore AA", delim_middle)?;
+    cwrite!(
+        bold_colored(w, Color::Cyan),
+        "{}",
+        if config.defaults.compare_ignore_aa {
+            "on"
+        } else {
+            "off"
+        }
+    )?;
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "AA tolerance", delim_middle)?;
+    match config.defaults.compare_aa_tolerance {
+        Some(tolerance) => cwrite!(bold_colored(w, Color::Cyan), "{tolerance}")?,
+        None => cwrite!(bold_colored(w, Color::Cyan), "off")?,
+    }
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "Prescale", delim_middle)?;
+    if config.defaults.compare_prescale > 1 {
+        let mode = match config.defaults.compare_prescale_mode {
+            PrescaleMode::Gate => "gate",
+            PrescaleMode::Only => "only",
+        };
+        cwrite!(
+            bold_colored(w, Color::Cyan),
+            "{}",
+            config.defaults.compare_prescale
+        )?;
+        write!(w, " ({mode})")?;
+    } else {
+        cwrite!(bold_colored(w, Color::Cyan), "off")?;
+    }
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "Downscale", delim_middle)?;
+    if config.defaults.compare_downscale > 1 {
+        cwrite!(
+            bold_colored(w, Color::Cyan),
+            "{}",
+            config.defaults.compare_downscale
+        )?;
+    } else {
+        cwrite!(bold_colored(w, Color::Cyan), "off")?;
+    }
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "Retries", delim_middle)?;
+    cwrite!(bold_colored(w, Color::Cyan), "{}", config.defaults.retries)?;
+    writeln!(w)?;
+
+    write!(w, "{:>align$}{}", "Compare mode", delim_close)?;
+    match config.defaults.compare_mode {
+        CompareMode::Simple => cwrite!(bold_colored(w, Color::Cyan), "simple")?,
+        CompareMode::Ssim => {
+            cwrite!(bold_colored(w, Color::Cyan), "ssim")?;
+            write!(w, " (min {})", config.defaults.min_ssim)?;
+        }
+        CompareMode::PdfText => cwrite!(bold_colored(w, Color::Cyan), "pdf-text")?,
+    }
+    writeln!(w)?;
+
+    Ok(())
+}
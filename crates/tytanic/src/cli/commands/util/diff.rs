@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use typst::utils::Scalar;
+use typst_render::RenderOptions;
+use tytanic_core::doc::Document;
+use tytanic_core::doc::render::DiffColors;
+use tytanic_core::doc::render::Origin;
+use tytanic_core::doc::render::ppi_to_ppp;
+use tytanic_core::test::Id;
+use tytanic_core::test::Stage;
+use tytanic_core::test::unit::Kind;
+use tytanic_filter::CombinedFilter;
+use tytanic_filter::exact::ExactFilter;
+use tytanic_utils::fmt::Term;
+
+use super::Context;
+use crate::cli::CANCELLED;
+use crate::cli::OperationFailure;
+use crate::cli::TestFailure;
+use crate::cli::commands::CompareOptions;
+use crate::cli::commands::CompileOptions;
+use crate::cli::commands::OptionDelegate;
+use crate::cli::commands::parse_test_id;
+use crate::cwrite;
+use crate::runner::Action;
+use crate::runner::Runner;
+use crate::runner::RunnerConfig;
+use crate::runner::UnitTestRunner;
+use crate::ui;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-diff-args")]
+pub struct Args {
+    /// Write the difference pages to the given directory instead of the diff
+    /// directory of the test.
+    #[arg(long, short, value_name = "DIR")]
+    pub output: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub compile: CompileOptions,
+
+    #[command(flatten)]
+    pub compare: CompareOptions,
+
+    /// The test to render the difference document of.
+    #[arg(value_name = "TEST", value_parser = parse_test_id)]
+    pub test: Id,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let suite = ctx.collect_tests(&project)?;
+
+    match suite.get(&args.test).and_then(|test| test.as_unit_test()) {
+        Some(test) if test.kind().is_compile_only() => {
+            writeln!(
+                ctx.ui.error()?,
+                "Cannot render difference document of compile-only test"
+            )?;
+            eyre::bail!(OperationFailure);
+        }
+        Some(_) => {}
+        None => {
+            let mut w = ctx.ui.error()?;
+            write!(w, "Unit test ")?;
+            ui::write_test_id(&mut w, &args.test)?;
+            writeln!(w, " does not exist")?;
+            eyre::bail!(OperationFailure);
+        }
+    }
+
+    let suite = suite.filter(
+        &project,
+        CombinedFilter::new(None, Some(ExactFilter::new([args.test.clone()]))),
+    )?;
+    let test = suite
+        .matched()
+        .get(&args.test)
+        .and_then(|test| test.as_unit_test())
+        .expect("checked above");
+
+    let providers = ctx.providers(&project, &ctx.args.package, &ctx.args.font, &args.compile)?;
+
+    let defaults = &project.config().defaults;
+    let strategy = args.compare.strategy(project.config());
+    let origin = Origin::from(defaults.direction);
+
+    let config = RunnerConfig {
+        warnings: args.compile.warnings.into_native(),
+        optimize: None,
+        fail_fast: false,
+        retries: 0,
+        render_options: RenderOptions {
+            pixel_per_pt: Scalar::new(ppi_to_ppp(defaults.ppi)),
+            render_bleed: false,
+        },
+        strategy,
+        compare_text: args.compare.compare_text(project.config()),
+        compare_downscale: args.compare.compare_downscale(project.config()),
+        // Only used to prepare the temporary directories of the test.
+        export_ephemeral: args.output.is_none(),
+        export_diff_always: false,
+        update_on_fail: false,
+        export_format: project.config().export_format,
+        crop: defaults.crop,
+        overview: None,
+        origin,
+        diff_colors: DiffColors::default(),
+        compare_cache: false,
+        run_id: None,
+        metadata: BTreeMap::new(),
+        record_inputs: false,
+        inputs: args.compile.inputs.iter().cloned().collect(),
+        skipped: BTreeSet::new(),
+        events: None,
+        action: Action::Run,
+        cancellation: &CANCELLED,
+    };
+
+    let runner = Runner::new(&project, &suite, &providers, config);
+    let mut test_runner = runner.unit_test(test);
+    test_runner.prepare()?;
+
+    let (output, reference) = match documents(&mut test_runner, test.kind()) {
+        Ok(documents) => documents,
+        Err(err) if err.chain().any(|s| s.is::<TestFailure>()) => {
+            let mut w = ctx.ui.error()?;
+            write!(w, "Test ")?;
+            ui::write_test_id(&mut w, test.id())?;
+            writeln!(w, " failed to compile")?;
+            eyre::bail!(OperationFailure);
+        }
+        Err(err) => return Err(err),
+    };
+
+    let diff = test_runner.render_diff_doc(&output, &reference, origin)?;
+    let dir = match &args.output {
+        Some(dir) => {
+            fs::create_dir_all(dir)?;
+            diff.save(dir, None)?;
+            dir.clone()
+        }
+        None => {
+            test_runner.export_diff_doc(&diff)?;
+            project.unit_test_diff_dir(test.id()).into()
+        }
+    };
+
+    if let Some(strategy) = strategy
+        && let Err(err) = test_runner.compare(&output, &reference, strategy)
+        && !err.chain().any(|s| s.is::<TestFailure>())
+    {
+        return Err(err);
+    }
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Wrote difference document of ")?;
+    ui::write_test_id(&mut w, test.id())?;
+    writeln!(w, " to {}", dir.display())?;
+
+    match test_runner.result().stage() {
+        Stage::FailedComparison(error) if error.output != error.reference => {
+            write!(w, "Comparison ")?;
+            cwrite!(colored(w, Color::Red), "failed")?;
+            writeln!(w, ": {error}")?;
+        }
+        Stage::FailedComparison(error) => {
+            let deviations = error
+                .details
+                .iter()
+                .map(|(_, diff)| diff.deviations)
+                .sum::<usize>();
+
+            write!(w, "Comparison ")?;
+            cwrite!(colored(w, Color::Red), "failed")?;
+            writeln!(
+                w,
+                " with {deviations} {} on {} {}",
+                Term::simple("deviation").with(deviations),
+                error.pages.len(),
+                Term::simple("page").with(error.pages.len()),
+            )?;
+
+            for (page, error) in &error.pages {
+                writeln!(w, "- Page {}: {error}", page + 1)?;
+            }
+        }
+        Stage::PassedComparison => {
+            write!(w, "Comparison ")?;
+            cwrite!(colored(w, Color::Green), "passed")?;
+            writeln!(w)?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Renders the output and reference documents of a test, ephemeral references
+/// are compiled while persistent references are loaded.
+fn documents<F>(
+    runner: &mut UnitTestRunner<'_, '_, '_, F>,
+    kind: Kind,
+) -> eyre::Result<(Document, Document)> {
+    let output = runner.compile_out_doc()?;
+    let output = runner.render_out_doc(output)?;
+
+    let reference = if kind.is_persistent() {
+        runner.load_ref_doc()?
+    } else {
+        let reference = runner.compile_ref_doc()?;
+        runner.render_ref_doc(reference)?
+    };
+
+    Ok((output, reference))
+}
@@ -0,0 +1,336 @@
+use std::error::Error;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+use color_eyre::eyre;
+use serde::Serialize;
+use termcolor::Color;
+use typst_kit::packages::FsPackages;
+use tytanic_core::project::Project;
+use tytanic_core::project::ShallowProject;
+use tytanic_core::suite::Suite;
+use tytanic_utils::fmt::Term;
+
+use crate::cli::Context;
+use crate::cli::OperationFailure;
+use crate::cwrite;
+use crate::ui;
+use crate::world;
+
+/// The padding to use for check status annotations.
+const STATUS_PADDING: usize = 4;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-doctor-args")]
+pub struct Args {
+    /// Print a JSON describing the checks to stdout.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// The outcome of a single check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Status {
+    /// The check passed.
+    Pass,
+
+    /// The check found something which may cause problems.
+    Warn,
+
+    /// The check found something which will cause problems.
+    Fail,
+}
+
+/// A single environment check.
+#[derive(Debug, Serialize)]
+struct Check {
+    /// The name of the check.
+    name: &'static str,
+
+    /// The outcome of the check.
+    status: Status,
+
+    /// A short description of the outcome.
+    message: String,
+
+    /// A hint on how to resolve a warning or failure.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Pass,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(name: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Warn,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn fail(name: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Fail,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn with_hint(self, hint: impl Into<String>) -> Self {
+        Self {
+            hint: Some(hint.into()),
+            ..self
+        }
+    }
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let mut checks = vec![];
+
+    let project = check_project(ctx, &mut checks)?;
+    if let Some(project) = &project {
+        check_vcs(project, &mut checks);
+        check_tests(project, &mut checks);
+    }
+
+    check_fonts(ctx, project.as_ref(), &mut checks);
+    check_packages(ctx, &mut checks);
+
+    let failed = checks.iter().any(|c| c.status == Status::Fail);
+
+    if args.json {
+        serde_json::to_writer_pretty(ctx.ui.stdout(), &checks)?;
+    } else {
+        for check in &checks {
+            let (annot, color) = match check.status {
+                Status::Pass => ("pass", Color::Green),
+                Status::Warn => ("warn", Color::Yellow),
+                Status::Fail => ("fail", Color::Red),
+            };
+
+            let mut w = ui::annotated(ctx.ui.stderr(), annot, color, STATUS_PADDING)?;
+            cwrite!(bold(w), "{}", check.name)?;
+            writeln!(w, ": {}", check.message)?;
+
+            if let Some(hint) = &check.hint {
+                cwrite!(colored(w, Color::Cyan), "hint:")?;
+                writeln!(w, " {hint}")?;
+            }
+        }
+    }
+
+    if failed {
+        eyre::bail!(OperationFailure);
+    }
+
+    Ok(())
+}
+
+/// Checks that a project can be found and its manifest and config are valid.
+fn check_project(ctx: &Context, checks: &mut Vec<Check>) -> eyre::Result<Option<Project>> {
+    let root = ctx.root()?;
+
+    let Some(project) = ShallowProject::discover(root, ctx.args.root.is_some())? else {
+        checks.push(
+            Check::fail("Project", "no typst project found")
+                .with_hint("pass the project root using `--root <path>`"),
+        );
+        return Ok(None);
+    };
+
+    let manifest = project.manifest_file();
+    let project = match project.load() {
+        Ok(project) => project,
+        Err(err) => {
+            checks.push(Check::fail("Manifest", error_chain(&err)));
+            return Ok(None);
+        }
+    };
+
+    match project.manifest() {
+        Some(manifest) => checks.push(Check::pass(
+            "Manifest",
+            format!(
+                "found package {}:{}",
+                manifest.package.name, manifest.package.version
+            ),
+        )),
+        None => checks.push(Check::warn(
+            "Manifest",
+            format!("no manifest found at {manifest}"),
+        )),
+    }
+
+    checks.push(Check::pass("Config", "project config is valid"));
+
+    Ok(Some(project))
+}
+
+/// Checks whether a VCS was detected.
+fn check_vcs(project: &Project, checks: &mut Vec<Check>) {
+    checks.push(match project.vcs() {
+        Some(vcs) => Check::pass("VCS", format!("detected {}", vcs.kind())),
+        None => Check::warn("VCS", "no VCS detected")
+            .with_hint("temporary test artifacts will not be ignored automatically"),
+    });
+}
+
+/// Checks the test suite structure and annotations.
+fn check_tests(project: &Project, checks: &mut Vec<Check>) {
+    let root = project.unit_tests_root();
+    if !root.try_exists().unwrap_or(false) {
+        checks.push(
+            Check::warn("Tests", format!("test root {root} does not exist"))
+                .with_hint("create a test using `tt new <name>`"),
+        );
+        return;
+    }
+
+    let suite = match Suite::collect(project) {
+        Ok(suite) => suite,
+        Err(err) => {
+            checks.push(Check::fail("Tests", error_chain(&err)));
+            return;
+        }
+    };
+
+    let len = suite.len();
+    checks.push(Check::pass(
+        "Tests",
+        format!("collected {len} {}", Term::simple("test").with(len)),
+    ));
+
+    let nested = suite.nested().len();
+    if nested != 0 {
+        checks.push(
+            Check::warn(
+                "Structure",
+                format!(
+                    "found {nested} nested {}",
+                    Term::simple("test").with(nested)
+                ),
+            )
+            .with_hint("run `tt util migrate` to move them"),
+        );
+    } else {
+        checks.push(Check::pass("Structure", "no tests need migration"));
+    }
+
+    let invalid = suite.invalid().len();
+    if invalid != 0 {
+        checks.push(Check::warn(
+            "Test ids",
+            format!(
+                "ignored {invalid} {} with invalid test ids",
+                Term::new("directory", "directories").with(invalid)
+            ),
+        ));
+    }
+
+    let inapplicable = suite
+        .unit_tests()
+        .map(|test| test.inapplicable_annotations().count())
+        .sum::<usize>();
+    if inapplicable != 0 {
+        checks.push(
+            Check::warn(
+                "Annotations",
+                format!(
+                    "found {inapplicable} {} without effect",
+                    Term::simple("annotation").with(inapplicable)
+                ),
+            )
+            .with_hint("run `tt util collect` to see them"),
+        );
+    } else {
+        checks.push(Check::pass("Annotations", "all annotations have an effect"));
+    }
+}
+
+/// Checks that fonts are available.
+fn check_fonts(ctx: &Context, project: Option<&Project>, checks: &mut Vec<Check>) {
+    let fonts = world::font_provider(project, &ctx.args.font);
+    let families = fonts.provide_font_book().families().count();
+
+    checks.push(if families == 0 {
+        Check::fail("Fonts", "no fonts found")
+            .with_hint("enable embedded or system fonts, or pass `--font-path <dir>`")
+    } else {
+        Check::pass(
+            "Fonts",
+            format!(
+                "found {families} font {}",
+                Term::new("family", "families").with(families)
+            ),
+        )
+    });
+}
+
+/// Checks that the package directories are usable.
+fn check_packages(ctx: &Context, checks: &mut Vec<Check>) {
+    let cache = match &ctx.args.package.package_cache_path {
+        Some(path) => Some(path.clone()),
+        None => FsPackages::system_cache().map(|p| p.path().to_owned()),
+    };
+
+    checks.push(match cache {
+        Some(path) => check_dir("Package cache", &path, "will be created on first download"),
+        None => Check::fail(
+            "Package cache",
+            "no package cache directory could be determined",
+        )
+        .with_hint("pass `--package-cache-path <dir>`"),
+    });
+
+    let data = match &ctx.args.package.package_path {
+        Some(path) => Some(path.clone()),
+        None => FsPackages::system_data().map(|p| p.path().to_owned()),
+    };
+
+    if let Some(path) = data {
+        checks.push(check_dir(
+            "Local packages",
+            &path,
+            "no local packages installed",
+        ));
+    }
+}
+
+/// Checks that the given path is a directory if it exists, `missing` is used
+/// as the message if it doesn't.
+fn check_dir(name: &'static str, path: &Path, missing: &str) -> Check {
+    match path.metadata() {
+        Ok(meta) if meta.is_dir() => Check::pass(name, format!("{}", path.display())),
+        Ok(_) => Check::fail(name, format!("{} is not a directory", path.display())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            Check::pass(name, format!("{}, {missing}", path.display()))
+        }
+        Err(err) => Check::fail(name, format!("{}: {err}", path.display())),
+    }
+}
+
+/// Formats an error and its sources on a single line.
+pub(super) fn error_chain(err: &dyn Error) -> String {
+    let mut message = err.to_string();
+
+    let mut source = err.source();
+    while let Some(err) = source {
+        message.push_str(": ");
+        message.push_str(&err.to_string());
+        source = err.source();
+    }
+
+    message
+}
@@ -3,6 +3,7 @@ use std::io::Write;
 use color_eyre::eyre;
 use termcolor::Color;
 use typst::text::FontStyle;
+use tytanic_core::project::ShallowProject;
 
 use crate::cli::Context;
 use crate::cwrite;
@@ -28,7 +29,12 @@ pub struct Args {
 }
 
 pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
-    let fonts = world::font_provider(&ctx.args.font);
+    // Fonts can also be listed outside of a project, the font paths of the
+    // project config are only included if one is found.
+    let project = ShallowProject::discover(ctx.root()?, ctx.args.root.is_some())?
+        .and_then(|project| project.load().ok());
+
+    let fonts = world::font_provider(project.as_ref(), &ctx.args.font);
     let book = fonts.provide_font_book();
 
     let fonts = book
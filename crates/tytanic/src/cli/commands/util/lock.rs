@@ -0,0 +1,121 @@
+use std::io::Write;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use typst::diag::Warned;
+use tytanic_core::doc::compile;
+use tytanic_core::world_builder::lock::LOCKFILE_NAME;
+use tytanic_core::world_builder::lock::LockingFileProvider;
+use tytanic_utils::fmt::Term;
+
+use super::Context;
+use crate::cli::OperationFailure;
+use crate::cli::commands::CompileOptions;
+use crate::cli::commands::OptionDelegate;
+use crate::cwrite;
+use crate::ui;
+use crate::world::Providers;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-lock-args")]
+pub struct Args {
+    /// Write the lockfile even if some tests failed to compile.
+    ///
+    /// The packages imported by these tests may not be locked.
+    #[arg(long)]
+    pub force: bool,
+
+    #[command(flatten)]
+    pub compile: CompileOptions,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let suite = ctx.collect_tests(&project)?;
+
+    // The existing lockfile must not be used, otherwise packages which aren't
+    // locked yet could never be locked.
+    let providers = Providers::with_lockfile(
+        &project,
+        &ctx.args.package,
+        &ctx.args.font,
+        &args.compile,
+        None,
+    )?;
+
+    let files = LockingFileProvider::with_exempt(
+        providers.project_files(),
+        ctx.args
+            .package
+            .package_override
+            .iter()
+            .map(|(spec, _)| spec.clone()),
+    );
+
+    let warnings = args.compile.warnings.into_native();
+
+    let mut failed = vec![];
+    for test in suite.unit_tests() {
+        let scripts: &[bool] = if test.kind().is_ephemeral() {
+            &[false, true]
+        } else {
+            &[false]
+        };
+
+        for &is_ref in scripts {
+            let world = providers.unit_world(&project, test, is_ref, None, Some(&files));
+            let Warned { output, .. } = compile::compile(&world, warnings);
+            if output.is_err() {
+                failed.push(test);
+                break;
+            }
+        }
+    }
+
+    if !failed.is_empty() {
+        let len = failed.len();
+
+        {
+            let mut w = if args.force {
+                ui::warn(ctx.ui.stderr())?
+            } else {
+                ui::error(ctx.ui.stderr())?
+            };
+            writeln!(
+                w,
+                "Failed to compile {len} {}, their packages may not be locked",
+                Term::simple("test").with(len)
+            )?;
+
+            for test in &failed {
+                write!(w, "- ")?;
+                ui::write_test_id(&mut w, test.id())?;
+                writeln!(w)?;
+            }
+        }
+
+        if !args.force {
+            let mut w = ctx.ui.hint()?;
+            write!(w, "You can pass ")?;
+            cwrite!(colored(w, Color::Cyan), "--force")?;
+            writeln!(w, " to write the lockfile anyway")?;
+
+            eyre::bail!(OperationFailure);
+        }
+    }
+
+    let lockfile = files.into_lockfile();
+    lockfile.write(project.lockfile())?;
+
+    let packages = lockfile.packages.len();
+    let mut w = ctx.ui.stderr();
+    write!(w, "Locked ")?;
+    cwrite!(colored(w, Color::Green), "{packages}")?;
+    writeln!(
+        w,
+        " {} in {LOCKFILE_NAME}",
+        Term::simple("package").with(packages)
+    )?;
+
+    Ok(())
+}
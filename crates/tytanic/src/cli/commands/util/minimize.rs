@@ -0,0 +1,333 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use typst::diag::Warned;
+use typst::utils::Scalar;
+use typst_render::RenderOptions;
+use typst_syntax::Source;
+use tytanic_core::doc::compile;
+use tytanic_core::doc::render::DiffColors;
+use tytanic_core::doc::render::Origin;
+use tytanic_core::doc::render::ppi_to_ppp;
+use tytanic_core::test::Id;
+use tytanic_core::test::Stage;
+use tytanic_filter::CombinedFilter;
+use tytanic_filter::exact::ExactFilter;
+use tytanic_utils::fmt::Term;
+
+use super::Context;
+use crate::cli::CANCELLED;
+use crate::cli::OperationFailure;
+use crate::cli::TestFailure;
+use crate::cli::commands::CompareOptions;
+use crate::cli::commands::CompileOptions;
+use crate::cli::commands::OptionDelegate;
+use crate::cli::commands::parse_test_id;
+use crate::cwrite;
+use crate::runner::Action;
+use crate::runner::Runner;
+use crate::runner::RunnerConfig;
+use crate::ui;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-minimize-args")]
+pub struct Args {
+    /// Which failure must be preserved while minimizing.
+    #[arg(long, default_value = "compile-error")]
+    pub predicate: Predicate,
+
+    /// The maximum number of candidates to compile before giving up.
+    ///
+    /// The smallest failing source found so far is written if this limit is
+    /// reached.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1000,
+        value_parser = clap::value_parser!(u32).range(1..),
+    )]
+    pub max_iterations: u32,
+
+    /// Write the minimized source to the given path instead of stdout.
+    #[arg(long, short, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub compile: CompileOptions,
+
+    #[command(flatten)]
+    pub compare: CompareOptions,
+
+    /// The test to minimize.
+    #[arg(value_name = "TEST", value_parser = parse_test_id)]
+    pub test: Id,
+}
+
+/// The failure which must be preserved while minimizing.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Predicate {
+    /// The test must fail compilation with the same first error.
+    CompileError,
+
+    /// The test must compile, but fail comparison against its reference.
+    Comparison,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let suite = ctx.collect_tests(&project)?;
+
+    let Some(test) = suite.get(&args.test).and_then(|test| test.as_unit_test()) else {
+        let mut w = ctx.ui.error()?;
+        write!(w, "Unit test ")?;
+        ui::write_test_id(&mut w, &args.test)?;
+        writeln!(w, " does not exist")?;
+        eyre::bail!(OperationFailure);
+    };
+
+    if args.predicate == Predicate::Comparison && test.kind().is_compile_only() {
+        writeln!(
+            ctx.ui.error()?,
+            "Cannot minimize comparison failures of compile-only test"
+        )?;
+        eyre::bail!(OperationFailure);
+    }
+
+    let strategy = match (args.predicate, args.compare.strategy(project.config())) {
+        (Predicate::Comparison, None) => {
+            writeln!(
+                ctx.ui.error()?,
+                "Cannot minimize comparison failures with comparison disabled"
+            )?;
+            eyre::bail!(OperationFailure);
+        }
+        (_, strategy) => strategy,
+    };
+
+    let suite = suite.filter(
+        &project,
+        CombinedFilter::new(None, Some(ExactFilter::new([args.test.clone()]))),
+    )?;
+    let test = suite
+        .matched()
+        .get(&args.test)
+        .and_then(|test| test.as_unit_test())
+        .expect("checked above");
+
+    let providers = ctx.providers(&project, &ctx.args.package, &ctx.args.font, &args.compile)?;
+    let warnings = args.compile.warnings.into_native();
+    let defaults = &project.config().defaults;
+
+    // Candidates are rendered and compared like they are when running the
+    // test, such that the predicate agrees with `tt run`.
+    let config = RunnerConfig {
+        warnings,
+        optimize: None,
+        fail_fast: false,
+        retries: 0,
+        render_options: RenderOptions {
+            pixel_per_pt: Scalar::new(ppi_to_ppp(defaults.ppi)),
+            render_bleed: false,
+        },
+        strategy,
+        compare_text: args.compare.compare_text(project.config()),
+        compare_downscale: args.compare.compare_downscale(project.config()),
+        export_ephemeral: false,
+        export_diff_always: false,
+        update_on_fail: false,
+        export_format: project.config().export_format,
+        crop: defaults.crop,
+        overview: None,
+        origin: Origin::from(defaults.direction),
+        diff_colors: DiffColors::default(),
+        compare_cache: false,
+        run_id: None,
+        metadata: BTreeMap::new(),
+        record_inputs: false,
+        inputs: args.compile.inputs.iter().cloned().collect(),
+        skipped: BTreeSet::new(),
+        events: None,
+        action: Action::Run,
+        cancellation: &CANCELLED,
+    };
+    let runner = Runner::new(&project, &suite, &providers, config);
+
+    let reference = match (args.predicate, strategy) {
+        (Predicate::Comparison, Some(strategy)) => {
+            let mut test_runner = runner.unit_test(test);
+            let reference = if test.kind().is_persistent() {
+                test_runner.load_ref_doc()
+            } else {
+                test_runner
+                    .compile_ref_doc()
+                    .and_then(|doc| test_runner.render_ref_doc(doc))
+            };
+
+            match reference {
+                Ok(reference) => Some((reference, strategy)),
+                Err(err) if err.chain().any(|s| s.is::<TestFailure>()) => {
+                    writeln!(
+                        ctx.ui.error()?,
+                        "Cannot minimize comparison failures, the reference failed to compile"
+                    )?;
+                    eyre::bail!(OperationFailure);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        _ => None,
+    };
+
+    let source = test.load_source(&project)?;
+
+    // The failure of a candidate, `None` if it doesn't fail in the selected
+    // way.
+    let failure = |text: &str| {
+        let world = providers.system_world(Source::new(source.id(), text.into()));
+        let Warned { output, .. } = compile::compile(&world, warnings);
+
+        match (args.predicate, output) {
+            (Predicate::CompileError, Err(err)) => {
+                Some(err.0.first().map(|diag| diag.message.clone()))
+            }
+            (Predicate::Comparison, Ok(output)) => {
+                let (reference, strategy) =
+                    reference.as_ref().expect("loaded for comparison predicate");
+                let mut test_runner = runner.unit_test(test);
+                let output = test_runner.render_out_doc(output).ok()?;
+                let _ = test_runner.compare(&output, reference, *strategy);
+
+                matches!(test_runner.result().stage(), Stage::FailedComparison(_)).then_some(None)
+            }
+            _ => None,
+        }
+    };
+
+    let Some(expected) = failure(source.text()) else {
+        let mut w = ctx.ui.error()?;
+        write!(w, "Test ")?;
+        ui::write_test_id(&mut w, test.id())?;
+        writeln!(
+            w,
+            " does not fail {}",
+            match args.predicate {
+                Predicate::CompileError => "compilation",
+                Predicate::Comparison => "comparison",
+            }
+        )?;
+        eyre::bail!(OperationFailure);
+    };
+
+    let minimized = minimize(source.text(), args.max_iterations as usize, |text| {
+        failure(text).as_ref() == Some(&expected)
+    });
+
+    match &args.output {
+        Some(path) => fs::write(path, &minimized.source)?,
+        None => write!(ctx.ui.stdout(), "{}", minimized.source)?,
+    }
+
+    if minimized.exhausted {
+        let mut w = ctx.ui.warn()?;
+        writeln!(
+            w,
+            "Reached the limit of {} {}, the source may not be minimal",
+            minimized.iterations,
+            Term::simple("iteration").with(minimized.iterations)
+        )?;
+    }
+
+    let before = source.text().lines().count();
+    let after = minimized.source.lines().count();
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Minimized ")?;
+    ui::write_test_id(&mut w, test.id())?;
+    write!(w, " from {before} to ")?;
+    cwrite!(colored(w, Color::Green), "{after}")?;
+    writeln!(
+        w,
+        " {} in {} {}",
+        Term::simple("line").with(after),
+        minimized.iterations,
+        Term::simple("iteration").with(minimized.iterations)
+    )?;
+
+    Ok(())
+}
+
+/// The result of [`minimize`].
+struct Minimized {
+    /// The smallest source found for which the predicate holds.
+    source: String,
+
+    /// The number of times the predicate was checked.
+    iterations: usize,
+
+    /// Whether the iteration limit was reached before the source was minimal.
+    exhausted: bool,
+}
+
+/// Removes chunks of lines from the given source as long as the predicate
+/// holds, starting with chunks of half the source and halving their size
+/// whenever no chunk can be removed.
+///
+/// The predicate is checked at most `max_iterations` times.
+fn minimize(source: &str, max_iterations: usize, mut holds: impl FnMut(&str) -> bool) -> Minimized {
+    let join = |lines: &[&str]| {
+        let mut text = lines.join("\n");
+        if source.ends_with('\n') && !lines.is_empty() {
+            text.push('\n');
+        }
+        text
+    };
+
+    let mut lines = source.lines().collect::<Vec<_>>();
+    let mut chunk = lines.len().div_ceil(2).max(1);
+    let mut iterations = 0;
+
+    loop {
+        let mut removed = false;
+        let mut start = 0;
+
+        while start < lines.len() {
+            if iterations >= max_iterations {
+                return Minimized {
+                    source: join(&lines),
+                    iterations,
+                    exhausted: true,
+                };
+            }
+
+            let end = Ord::min(start + chunk, lines.len());
+            let candidate = [&lines[..start], &lines[end..]].concat();
+
+            iterations += 1;
+            if holds(&join(&candidate)) {
+                lines = candidate;
+                removed = true;
+            } else {
+                start = end;
+            }
+        }
+
+        if !removed {
+            if chunk == 1 {
+                break;
+            }
+
+            chunk = chunk.div_ceil(2);
+        }
+    }
+
+    Minimized {
+        source: join(&lines),
+        iterations,
+        exhausted: false,
+    }
+}
@@ -3,12 +3,21 @@ use color_eyre::eyre;
 use super::Context;
 
 pub mod about;
+pub mod check;
 pub mod clean;
+pub mod collect;
 pub mod completion;
+pub mod config;
+pub mod diff;
+pub mod doctor;
 pub mod fonts;
+pub mod lock;
 pub mod manpage;
 pub mod migrate;
+pub mod minimize;
+pub mod reencode;
 pub mod vcs;
+pub mod verify_refs;
 
 #[derive(clap::Args, Debug, Clone)]
 #[group(id = "util-args")]
@@ -24,14 +33,38 @@ pub enum Command {
     #[command()]
     About(about::Args),
 
+    /// Check tests for orphaned references.
+    #[command()]
+    Check(check::Args),
+
     /// Remove test output artifacts.
     #[command()]
     Clean(clean::Args),
 
+    /// Collect tests without running them.
+    #[command()]
+    Collect(collect::Args),
+
     /// Generate completions.
     #[command()]
     Completion(completion::Args),
 
+    /// Show the effective project config.
+    #[command()]
+    Config(config::Args),
+
+    /// Render the difference document of a single test.
+    #[command()]
+    Diff(diff::Args),
+
+    /// Check the environment for common problems.
+    #[command()]
+    Doctor(doctor::Args),
+
+    /// Lock the package files imported by tests.
+    #[command()]
+    Lock(lock::Args),
+
     /// Generate a man page for Tytanic.
     #[command()]
     Manpage(manpage::Args),
@@ -44,21 +77,42 @@ pub enum Command {
     #[command()]
     Migrate(migrate::Args),
 
+    /// Shrink the source of a failing test while preserving its failure.
+    #[command()]
+    Minimize(minimize::Args),
+
+    /// Re-encode persistent references in the configured format.
+    #[command()]
+    Reencode(reencode::Args),
+
     /// Vcs related commands.
     #[command()]
     Vcs(vcs::Args),
+
+    /// Check that all persistent references can be decoded.
+    #[command()]
+    VerifyRefs(verify_refs::Args),
 }
 
 impl Command {
     pub fn run(&self, ctx: &mut Context) -> eyre::Result<()> {
         match self {
             Command::About(args) => about::run(ctx, args),
+            Command::Check(args) => check::run(ctx, args),
             Command::Clean(args) => clean::run(ctx, args),
+            Command::Collect(args) => collect::run(ctx, args),
             Command::Completion(args) => completion::run(ctx, args),
+            Command::Config(args) => config::run(ctx, args),
+            Command::Diff(args) => diff::run(ctx, args),
+            Command::Doctor(args) => doctor::run(ctx, args),
+            Command::Lock(args) => lock::run(ctx, args),
             Command::Manpage(args) => manpage::run(ctx, args),
             Command::Fonts(args) => fonts::run(ctx, args),
             Command::Migrate(args) => migrate::run(ctx, args),
+            Command::Minimize(args) => minimize::run(ctx, args),
+            Command::Reencode(args) => reencode::run(ctx, args),
             Command::Vcs(args) => args.cmd.run(ctx),
+            Command::VerifyRefs(args) => verify_refs::run(ctx, args),
         }
     }
 }
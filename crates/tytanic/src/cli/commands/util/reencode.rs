@@ -0,0 +1,69 @@
+use std::io::Write;
+
+use color_eyre::eyre;
+use color_eyre::eyre::WrapErr;
+use termcolor::Color;
+use tytanic_core::doc::OptimizeLevel;
+use tytanic_utils::fmt::Term;
+
+use super::Context;
+use crate::cli::commands::FilterOptions;
+use crate::cli::commands::OptimizeLevelOption;
+use crate::cli::commands::OptimizeRefsSwitch;
+use crate::cli::commands::OptionDelegate;
+use crate::cli::commands::Switch;
+use crate::cwrite;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-reencode-args")]
+pub struct Args {
+    #[command(flatten)]
+    pub optimize_refs: OptimizeRefsSwitch,
+
+    /// How thoroughly persistent references stored as PNG are optimized.
+    ///
+    /// Defaults to `max`, can be configured in the manifest. Has no effect if
+    /// `--no-optimize-refs` is given.
+    #[arg(long, value_name = "LEVEL")]
+    pub optimize_level: Option<OptimizeLevelOption>,
+
+    #[command(flatten)]
+    pub filter: FilterOptions,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let suite = ctx.collect_tests_with_filter(&project, ctx.filter(&args.filter)?)?;
+
+    let optimize_level = if args.optimize_refs.get_or_default() {
+        args.optimize_level
+            .map(OptionDelegate::into_native)
+            .unwrap_or(project.config().optimize_refs)
+    } else {
+        OptimizeLevel::None
+    };
+    let optimize_options = optimize_level.options();
+
+    let mut count = 0;
+    for test in suite.matched().unit_tests() {
+        if !test.kind().is_persistent() {
+            continue;
+        }
+
+        let reference = test
+            .load_reference_document(&project)
+            .wrap_err_with(|| format!("couldn't load reference document for test {}", test.id()))?;
+
+        test.create_reference_document(&project, &reference, optimize_options.as_ref())?;
+        count += 1;
+    }
+
+    let format = project.config().ref_format.extension();
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Re-encoded persistent references for ")?;
+    cwrite!(colored(w, Color::Green), "{count}")?;
+    writeln!(w, " {} as {format}", Term::simple("test").with(count))?;
+
+    Ok(())
+}
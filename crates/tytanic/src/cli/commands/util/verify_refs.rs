@@ -0,0 +1,68 @@
+use std::io::Write;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use tytanic_utils::fmt::Term;
+
+use super::Context;
+use super::doctor::error_chain;
+use crate::cli::OperationFailure;
+use crate::cli::commands::FilterOptions;
+use crate::cwrite;
+use crate::ui;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-verify-refs-args")]
+pub struct Args {
+    #[command(flatten)]
+    pub filter: FilterOptions,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let suite = ctx.collect_tests_with_filter(&project, ctx.filter(&args.filter)?)?;
+
+    let mut verified = 0;
+    let mut corrupt = vec![];
+    for test in suite.matched().unit_tests() {
+        if !test.kind().is_persistent() {
+            continue;
+        }
+
+        let failures = test.verify_reference_document(&project)?;
+        if !failures.is_empty() {
+            corrupt.push((test, failures));
+        }
+
+        verified += 1;
+    }
+
+    let len = corrupt.len();
+    if len == 0 {
+        let mut w = ctx.ui.stderr();
+        write!(w, "Verified persistent references for ")?;
+        cwrite!(colored(w, Color::Green), "{verified}")?;
+        writeln!(w, " {}", Term::simple("test").with(verified))?;
+        return Ok(());
+    }
+
+    let mut w = ctx.ui.error()?;
+    write!(w, "Found corrupt references for ")?;
+    cwrite!(colored(w, Color::Red), "{len}")?;
+    writeln!(w, " {}", Term::simple("test").with(len))?;
+
+    for (test, failures) in &corrupt {
+        write!(w, "- ")?;
+        ui::write_test_id(&mut w, test.id())?;
+        writeln!(w)?;
+
+        for (path, err) in failures {
+            let path = path.strip_prefix(project.root()).unwrap_or(path);
+            write!(w, "  ")?;
+            cwrite!(colored(w, Color::Cyan), "{}", path.display())?;
+            writeln!(w, ": {}", error_chain(err))?;
+        }
+    }
+
+    eyre::bail!(OperationFailure);
+}
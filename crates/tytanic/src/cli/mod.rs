@@ -1,8 +1,10 @@
+use std::collections::BTreeSet;
 use std::env;
 use std::io;
 use std::io::Write;
 use std::sync::atomic::AtomicBool;
 
+use camino::Utf8Path;
 use camino::Utf8PathBuf;
 use color_eyre::eyre;
 use color_eyre::eyre::WrapErr;
@@ -10,8 +12,11 @@ use commands::CompileOptions;
 use commands::FontOptions;
 use commands::PackageOptions;
 use termcolor::Color;
+use termcolor::WriteColor;
 use thiserror::Error;
 use tytanic_core::doc;
+use tytanic_core::filter::Filter;
+use tytanic_core::filter::FilterState;
 use tytanic_core::project::ConfigError;
 use tytanic_core::project::ManifestError;
 use tytanic_core::project::Project;
@@ -21,7 +26,9 @@ use tytanic_core::project::VcsKind;
 use tytanic_core::suite::FilteredSuite;
 use tytanic_core::suite::Suite;
 use tytanic_core::test;
+use tytanic_core::test::Id;
 use tytanic_core::test::ParseIdError;
+use tytanic_core::world_builder::lock::LockfileError;
 use tytanic_filter::CombinedFilter;
 use tytanic_filter::Error as FilterError;
 use tytanic_filter::exact::ExactFilter;
@@ -30,6 +37,7 @@ use tytanic_filter::test_set::ast;
 use tytanic_filter::test_set::builtin;
 use tytanic_filter::test_set::builtin::dsl;
 use tytanic_filter::test_set::eval;
+use tytanic_utils::fmt::Term;
 
 use self::commands::CliArguments;
 use self::commands::FilterOptions;
@@ -156,11 +164,7 @@ impl Context<'_> {
     pub fn filter(&self, filter: &FilterOptions) -> eyre::Result<CombinedFilter> {
         let exact = ExactFilter::new(filter.tests.iter().cloned());
 
-        let test_set = if let Some(expression) = filter
-            .expression
-            .as_deref()
-            .or_else(|| filter.tests.is_empty().then_some("all()"))
-        {
+        let test_set = if let Some(expression) = test_set_expression(filter) {
             let ctx = builtin::context();
             let mut test_set = ExpressionFilter::new(ctx, expression)?;
             if filter.skip.get_or_default() {
@@ -174,6 +178,44 @@ impl Context<'_> {
         Ok(CombinedFilter::new(test_set, Some(exact)))
     }
 
+    /// Collects the ids of those filtered tests of the suite which were only
+    /// removed by the given filter because of their `skip` annotation.
+    ///
+    /// The test set is mapped like the one of the filter used to create the
+    /// suite, such that tests which would be filtered out regardless are not
+    /// included.
+    #[tracing::instrument(skip_all)]
+    pub fn skipped_tests<F>(
+        &self,
+        project: &Project,
+        suite: &FilteredSuite<F>,
+        filter: &FilterOptions,
+        map: impl FnOnce(eval::Set) -> eval::Set,
+    ) -> eyre::Result<BTreeSet<Id>> {
+        let mut skipped = BTreeSet::new();
+
+        if !filter.skip.get_or_default() {
+            return Ok(skipped);
+        }
+
+        let Some(expression) = test_set_expression(filter) else {
+            return Ok(skipped);
+        };
+
+        let test_set = ExpressionFilter::new(builtin::context(), expression)?
+            .map(|set| map(eval::Set::expr_inter(set, dsl::set_skip(), [])));
+
+        let mut state = test_set.state();
+        for test in suite.filtered().tests() {
+            if state.filter(project, test)? {
+                skipped.insert(test.id().clone());
+            }
+        }
+        state.finish(project)?;
+
+        Ok(skipped)
+    }
+
     /// Collect and filter tests for the given project.
     #[tracing::instrument(skip_all)]
     pub fn collect_tests_with_filter(
@@ -219,9 +261,78 @@ impl Context<'_> {
             eyre::bail!(OperationFailure);
         }
 
+        let mut inapplicable = false;
+        for test in suite.unit_tests() {
+            for annot in test.inapplicable_annotations() {
+                inapplicable = true;
+
+                let write = |mut w: &mut dyn WriteColor| -> io::Result<()> {
+                    write!(w, "Annotation ")?;
+                    cwrite!(colored(w, Color::Cyan), "{}", annot.name())?;
+                    write!(w, " has no effect on {} test ", test.kind().as_str())?;
+                    ui::write_test_id(w, test.id())?;
+                    writeln!(w)
+                };
+
+                if self.args.strict_config {
+                    write(&mut self.ui.error()?)?;
+                } else {
+                    write(&mut self.ui.warn()?)?;
+                }
+            }
+        }
+
+        if inapplicable && self.args.strict_config {
+            eyre::bail!(OperationFailure);
+        }
+
         Ok(suite)
     }
 
+    /// Warn about directories which were skipped or ignored while collecting
+    /// the given suite.
+    pub fn warn_collection(&self, project: &Project, suite: &Suite) -> eyre::Result<()> {
+        let skipped = suite.skipped().len();
+        let invalid = suite.invalid().len();
+
+        if skipped == 0 && invalid == 0 {
+            return Ok(());
+        }
+
+        let mut summary = vec![];
+        if skipped != 0 {
+            summary.push(format!(
+                "{skipped} {} skipped",
+                Term::new("directory", "directories").with(skipped)
+            ));
+        }
+        if invalid != 0 {
+            summary.push(format!(
+                "{invalid} invalid {}",
+                Term::simple("identifier").with(invalid)
+            ));
+        }
+
+        let path = |dir: &Utf8Path| {
+            let path = project.unit_tests_root().join(dir);
+            path.strip_prefix(project.root())
+                .map(Utf8Path::to_path_buf)
+                .unwrap_or(path)
+        };
+
+        let mut w = self.ui.warn()?;
+        writeln!(w, "{}", summary.join(", "))?;
+
+        for dir in suite.invalid() {
+            writeln!(w, "- {}: invalid identifier", path(dir))?;
+        }
+        for dir in suite.skipped() {
+            writeln!(w, "- {}: no test script", path(dir))?;
+        }
+
+        Ok(())
+    }
+
     /// Create a `Providers` from the given args.
     #[tracing::instrument(skip_all)]
     pub fn providers(
@@ -313,6 +424,11 @@ impl Context<'_> {
                 }
             }
 
+            if let Some(LockfileError::Parse(error)) = error.downcast_ref() {
+                writeln!(self.ui.error()?, "Failed to parse lockfile:\n{error}")?;
+                eyre::bail!(OperationFailure);
+            }
+
             if let Some(error) = error.downcast_ref::<ast::Error>() {
                 writeln!(self.ui.error()?, "Couldn't parse test set:\n{error}")?;
                 eyre::bail!(OperationFailure);
@@ -341,3 +457,12 @@ impl Context<'_> {
         eyre::bail!(error);
     }
 }
+
+/// The test set expression of the given filter options, this is `all()` if
+/// neither an expression nor exact tests were given.
+fn test_set_expression(filter: &FilterOptions) -> Option<&str> {
+    filter
+        .expression
+        .as_deref()
+        .or_else(|| filter.tests.is_empty().then_some("all()"))
+}
@@ -1,5 +1,8 @@
 //! Common report PODs for stable JSON representation of internal entities.
 
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
 use camino::Utf8PathBuf;
 use serde::Serialize;
 use typst_syntax::package::PackageManifest;
@@ -8,7 +11,14 @@ use tytanic_core::TemplateTest;
 use tytanic_core::UnitTest;
 use tytanic_core::project::Project;
 use tytanic_core::suite::Suite;
+use tytanic_core::suite::SuiteResult;
+use tytanic_core::test::Stage;
 use tytanic_core::test::Test;
+use tytanic_core::test::TestResult;
+use tytanic_core::test::unit::ATTRIBUTES;
+use tytanic_utils::fmt::Term;
+
+use crate::ui::CliWarning;
 
 #[derive(Debug, Serialize)]
 pub struct ProjectJson<'m, 's> {
@@ -16,6 +26,7 @@ pub struct ProjectJson<'m, 's> {
     pub vcs: Option<String>,
     pub tests: Vec<UnitTestJson<'s>>,
     pub template_test: Option<TemplateTestJson<'s>>,
+    pub template_tests: Vec<TemplateTestJson<'s>>,
 }
 
 impl<'m, 's> ProjectJson<'m, 's> {
@@ -33,6 +44,10 @@ impl<'m, 's> ProjectJson<'m, 's> {
             template_test: suite
                 .template_test()
                 .map(|test| TemplateTestJson::new(project, test)),
+            template_tests: suite
+                .template_tests()
+                .map(|test| TemplateTestJson::new(project, test))
+                .collect(),
         }
     }
 }
@@ -67,16 +82,31 @@ pub struct UnitTestJson<'t> {
     pub id: &'t str,
     pub kind: &'static str,
     pub is_skip: bool,
+    pub skip_reason: Option<&'t str>,
     pub path: Utf8PathBuf,
+    pub config: BTreeMap<&'static str, String>,
+    pub has_references: bool,
 }
 
 impl<'t> UnitTestJson<'t> {
     pub fn new(project: &Project, test: &'t UnitTest) -> Self {
+        let defaults = &project.config().defaults;
+
         Self {
             id: test.id().as_str(),
             kind: test.kind().as_str(),
             is_skip: test.is_skip(),
+            skip_reason: test.skip_reason(),
             path: project.unit_test_dir(test.id()),
+            // NOTE(tinger): The kind and skip attributes are already included
+            // as dedicated fields.
+            config: ATTRIBUTES
+                .iter()
+                .filter(|&&key| key != "kind" && key != "skip")
+                .filter_map(|&key| Some((key, test.attribute(defaults, key)?.into())))
+                .collect(),
+            has_references: test.kind().is_persistent()
+                && project.unit_test_ref_dir(test.id()).is_dir(),
         }
     }
 }
@@ -85,6 +115,7 @@ impl<'t> UnitTestJson<'t> {
 pub struct TemplateTestJson<'t> {
     pub id: &'t str,
     pub path: Utf8PathBuf,
+    pub entrypoint: &'t str,
 }
 
 impl<'t> TemplateTestJson<'t> {
@@ -92,6 +123,7 @@ impl<'t> TemplateTestJson<'t> {
         Self {
             id: test.id().as_str(),
             path: project.template_root().unwrap(),
+            entrypoint: test.entrypoint(),
         }
     }
 }
@@ -108,3 +140,173 @@ pub struct FontJson<'f> {
     pub name: &'f str,
     pub variants: Vec<FontVariantJson>,
 }
+
+#[derive(Debug, Serialize)]
+pub struct CliWarningJson {
+    pub r#type: &'static str,
+    pub code: &'static str,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl CliWarningJson {
+    pub fn new(warning: &CliWarning) -> Self {
+        Self {
+            r#type: "warning",
+            code: warning.code,
+            message: warning.message.as_str().replace('`', ""),
+            hint: warning
+                .hint
+                .as_ref()
+                .map(|hint| hint.as_str().replace('`', "")),
+        }
+    }
+}
+
+/// The version of the suite manifest schema, this is incremented whenever the
+/// schema changes.
+pub const SUITE_MANIFEST_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+pub struct SuiteManifestJson<'t> {
+    pub version: u32,
+    pub tests: Vec<ManifestTestJson<'t>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ManifestTestJson<'t> {
+    pub id: &'t str,
+    pub r#type: &'static str,
+    pub kind: Option<&'static str>,
+    pub is_skip: bool,
+    pub skip_reason: Option<&'t str>,
+    pub path: String,
+    pub config: BTreeMap<&'static str, ManifestConfigJson>,
+    pub reference_pages: Option<usize>,
+    pub imports: BTreeSet<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ManifestConfigJson {
+    pub value: serde_json::Value,
+    pub source: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TestResultLineJson<'t> {
+    pub r#type: &'static str,
+    pub id: &'t str,
+    pub kind: &'static str,
+    pub status: &'static str,
+    pub duration_ms: i64,
+    pub peak_memory: Option<usize>,
+    pub warnings: usize,
+    pub failure: Option<TestFailureJson>,
+}
+
+impl<'t> TestResultLineJson<'t> {
+    pub fn new(test: &'t Test, result: &TestResult) -> Self {
+        let status = match result.stage() {
+            Stage::Skipped => "skipped",
+            Stage::Cancelled => "cancelled",
+            Stage::Filtered => "filtered",
+            Stage::FailedCompilation { .. } | Stage::FailedComparison(_) => "failed",
+            Stage::PassedCompilation | Stage::PassedComparison if result.is_flaky() => "flaky",
+            Stage::PassedCompilation | Stage::PassedComparison => "passed",
+            Stage::Updated { dry_run: false, .. } => "updated",
+            Stage::Updated { dry_run: true, .. } => "would-update",
+        };
+
+        let failure = match result.stage() {
+            Stage::FailedCompilation { error, reference } => Some(TestFailureJson {
+                stage: if *reference {
+                    "reference-compilation"
+                } else {
+                    "compilation"
+                },
+                messages: error
+                    .0
+                    .iter()
+                    .map(|diag| diag.message.to_string())
+                    .collect(),
+            }),
+            Stage::FailedComparison(error) => Some(TestFailureJson {
+                stage: "comparison",
+                messages: if error.output != error.reference {
+                    vec![format!(
+                        "expected {} {}, got {}",
+                        error.reference,
+                        Term::simple("page").with(error.reference),
+                        error.output,
+                    )]
+                } else {
+                    error
+                        .pages
+                        .iter()
+                        .map(|(p, e)| format!("page {}: {e}", p + 1))
+                        .collect()
+                },
+            }),
+            _ => None,
+        };
+
+        Self {
+            r#type: "test",
+            id: test.id().as_str(),
+            kind: match test {
+                Test::Unit(test) => test.kind().as_str(),
+                Test::Template(_) => "template",
+            },
+            status,
+            duration_ms: result.duration().num_milliseconds(),
+            peak_memory: result.peak_memory(),
+            warnings: result.warnings().len(),
+            failure,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TestFailureJson {
+    pub stage: &'static str,
+    pub messages: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SummaryLineJson {
+    pub r#type: &'static str,
+    pub run_id: String,
+    pub total: usize,
+    pub filtered: usize,
+    pub skipped: usize,
+    pub cancelled: usize,
+    pub run: usize,
+    pub passed: usize,
+    pub flaky: usize,
+    pub updated: usize,
+    pub failed: usize,
+    pub warned: usize,
+    pub warnings: usize,
+    pub duration_ms: i64,
+}
+
+impl SummaryLineJson {
+    pub fn new(result: &SuiteResult) -> Self {
+        Self {
+            r#type: "summary",
+            run_id: result.id().to_string(),
+            total: result.total(),
+            filtered: result.filtered(),
+            skipped: result.skipped(),
+            cancelled: result.cancelled(),
+            run: result.run(),
+            passed: result.passed(),
+            flaky: result.flaky(),
+            updated: result.updated(),
+            failed: result.failed(),
+            warned: result.warned(),
+            warnings: result.warnings(),
+            duration_ms: result.duration().num_milliseconds(),
+        }
+    }
+}
@@ -8,11 +8,10 @@ use std::sync::atomic::Ordering;
 
 use clap::Parser;
 use color_eyre::eyre;
-use once_cell::sync::Lazy;
-use termcolor::Color;
 use termcolor::StandardStream;
 use termcolor::WriteColor;
 use tracing::level_filters::LevelFilter;
+use tracing_subscriber::Layer;
 use tracing_subscriber::filter::Targets;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
@@ -22,18 +21,28 @@ use crate::cli::Context;
 use crate::cli::OperationFailure;
 use crate::cli::TestFailure;
 use crate::cli::commands::CliArguments;
+use crate::cli::commands::CliWarningFormat;
+use crate::cli::commands::Command;
+use crate::ui::CliWarning;
 use crate::ui::Ui;
+use crate::ui::WarningFormat;
 
 mod cli;
 mod json;
+mod memory;
+mod profile;
 mod report;
 mod runner;
+mod sarif;
 mod ui;
 mod world;
 
-/// The default optimization options to use.
-pub static DEFAULT_OPTIMIZE_OPTIONS: Lazy<oxipng::Options> =
-    Lazy::new(oxipng::Options::max_compression);
+/// The maximum number of files written concurrently if no `--io-jobs` is
+/// given.
+const MAX_DEFAULT_IO_JOBS: usize = 64;
+
+#[global_allocator]
+static ALLOCATOR: memory::TrackingAllocator = memory::TrackingAllocator;
 
 fn main() -> ExitCode {
     match main_impl() {
@@ -45,6 +54,30 @@ fn main() -> ExitCode {
     }
 }
 
+/// The default number of files written concurrently.
+///
+/// Where the file descriptor limit of the process can be detected this leaves
+/// most descriptors to compilation, which reads sources, fonts and packages.
+fn default_io_jobs() -> usize {
+    #[cfg(unix)]
+    {
+        let mut limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+
+        // SAFETY: `limit` is a valid pointer to an `rlimit` struct.
+        if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } == 0
+            && limit.rlim_cur != libc::RLIM_INFINITY
+        {
+            let quarter = usize::try_from(limit.rlim_cur / 4).unwrap_or(usize::MAX);
+            return quarter.clamp(1, MAX_DEFAULT_IO_JOBS);
+        }
+    }
+
+    MAX_DEFAULT_IO_JOBS
+}
+
 fn main_impl() -> eyre::Result<ExitCode> {
     let args = CliArguments::parse();
 
@@ -56,38 +89,32 @@ fn main_impl() -> eyre::Result<ExitCode> {
         clap::ColorChoice::Never => termcolor::ColorChoice::Never,
     };
 
-    let ui = Ui::new(cc, cc);
+    let warning_format = match (args.output.quiet, args.output.warning_format) {
+        (true, _) => WarningFormat::Quiet,
+        (false, CliWarningFormat::Human) => WarningFormat::Human,
+        (false, CliWarningFormat::Json) => WarningFormat::Json,
+    };
+
+    let ui = Ui::new(cc, cc).with_warning_format(warning_format);
 
     // Emit deprecation warning for aliases
     if let Some(arg) = env::args().skip(1).find(|arg| !arg.starts_with('-')) {
-        match &arg[..] {
-            "remove" | "rm" => {
-                let mut w = ui.warn()?;
-                write!(w, "Sub command alias ")?;
-                cwrite!(colored(w, Color::Cyan), "remove")?;
-                write!(w, "|")?;
-                cwrite!(colored(w, Color::Cyan), "rm")?;
-                writeln!(w, " is deprecated")?;
-                drop(w);
-
-                let mut w = ui.hint()?;
-                write!(w, "Use ")?;
-                cwrite!(colored(w, Color::Cyan), "delete")?;
-                writeln!(w, " instead")?;
-            }
-            "add" => {
-                let mut w = ui.warn()?;
-                write!(w, "Sub command alias ")?;
-                cwrite!(colored(w, Color::Cyan), "add")?;
-                writeln!(w, " is deprecated")?;
-                drop(w);
-
-                let mut w = ui.hint()?;
-                write!(w, "Use ")?;
-                cwrite!(colored(w, Color::Cyan), "new")?;
-                writeln!(w, " instead")?;
-            }
-            _ => {}
+        let warning = match &arg[..] {
+            "remove" | "rm" => Some(CliWarning {
+                code: "deprecated-alias",
+                message: "Sub command alias `remove`|`rm` is deprecated".into(),
+                hint: Some("Use `delete` instead".into()),
+            }),
+            "add" => Some(CliWarning {
+                code: "deprecated-alias",
+                message: "Sub command alias `add` is deprecated".into(),
+                hint: Some("Use `new` instead".into()),
+            }),
+            _ => None,
+        };
+
+        if let Some(warning) = warning {
+            ui.cli_warning(&warning)?;
         }
     }
 
@@ -96,25 +123,30 @@ fn main_impl() -> eyre::Result<ExitCode> {
     // capable without constructing a stream and asking for it.
     let tracing_ansi = StandardStream::stderr(cc).supports_color();
 
+    let level = match args.output.verbose {
+        0 => LevelFilter::OFF,
+        1 => LevelFilter::ERROR,
+        2 => LevelFilter::WARN,
+        3 => LevelFilter::INFO,
+        4 => LevelFilter::DEBUG,
+        5.. => LevelFilter::TRACE,
+    };
+
+    let (profile_layer, profile) = match &args.cmd {
+        Command::Run(run) => run.profile.clone().map(profile::profile).unzip(),
+        _ => (None, None),
+    };
+
     tracing_subscriber::registry()
         .with(
             // We set with_ansi to true, because UI handles the usage of color
             // through termcolor::StandardStream.
             HierarchicalLayer::new(4)
                 .with_targets(true)
-                .with_ansi(tracing_ansi),
+                .with_ansi(tracing_ansi)
+                .with_filter(targets(level)),
         )
-        .with(Targets::new().with_target(
-            tytanic_core::TOOL_NAME,
-            match args.output.verbose {
-                0 => LevelFilter::OFF,
-                1 => LevelFilter::ERROR,
-                2 => LevelFilter::WARN,
-                3 => LevelFilter::INFO,
-                4 => LevelFilter::DEBUG,
-                5.. => LevelFilter::TRACE,
-            },
-        ))
+        .with(profile_layer.map(|layer| layer.with_filter(targets(LevelFilter::INFO))))
         .init();
 
     if let Err(err) = ctrlc::set_handler(|| {
@@ -134,6 +166,8 @@ fn main_impl() -> eyre::Result<ExitCode> {
             .ok();
     }
 
+    tytanic_core::doc::set_max_concurrent_writes(args.io_jobs.unwrap_or_else(default_io_jobs));
+
     let mut ctx = Context::new(&args, &ui);
 
     let exit_code = match ctx.run() {
@@ -177,7 +211,21 @@ fn main_impl() -> eyre::Result<ExitCode> {
         }
     };
 
+    if let Some(profile) = profile {
+        profile.write()?;
+    }
+
     ctx.ui.flush()?;
 
     Ok(ExitCode::from(exit_code))
 }
+
+/// The tracing targets of tytanic at the given level.
+fn targets(level: LevelFilter) -> Targets {
+    Targets::new()
+        .with_target(tytanic_core::TOOL_NAME, level)
+        // The runner lives in this binary, whose module
+        // targets are not prefixed by the tool name. The separator
+        // ensures we don't match other crates starting with `tt`.
+        .with_target(concat!(env!("CARGO_BIN_NAME"), "::"), level)
+}
@@ -0,0 +1,121 @@
+//! Measurement of the memory allocated while compiling tests.
+//!
+//! The global allocator of the binary counts the bytes allocated on each
+//! thread while a measurement is active on it, see [`measure`].
+//!
+//! Tests are compiled in parallel on the rayon pool, a worker may run jobs of
+//! other tests while it waits and these would be counted too. Budgets are
+//! therefore enforced with [`measure_detached`].
+//!
+//! The measurement is approximate, memoized results are shared between tests,
+//! so the count depends on which tests were compiled before or alongside the
+//! measured one.
+
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::cell::Cell;
+use std::panic;
+use std::thread;
+
+thread_local! {
+    static MEASURING: Cell<bool> = const { Cell::new(false) };
+    static CURRENT: Cell<isize> = const { Cell::new(0) };
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+/// The global allocator, this forwards to the system allocator and counts
+/// allocations on threads which are currently measured.
+pub struct TrackingAllocator;
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            track(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            track(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        track(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new.is_null() {
+            track(new_size as isize - layout.size() as isize);
+        }
+        new
+    }
+}
+
+/// Adds the given number of bytes to the counter of the current thread if it
+/// is measured.
+fn track(bytes: isize) {
+    // NOTE(tinger): The thread locals are const initialized and don't
+    // allocate, `try_with` only fails while the thread is torn down.
+    let measuring = MEASURING.try_with(Cell::get).unwrap_or(false);
+    if !measuring {
+        return;
+    }
+
+    let _ = CURRENT.try_with(|current| {
+        let now = current.get() + bytes;
+        current.set(now);
+        let _ = PEAK.try_with(|peak| peak.set(peak.get().max(now)));
+    });
+}
+
+/// Runs the given closure and returns its result together with the peak
+/// number of bytes allocated on the current thread while it ran.
+///
+/// Memory allocated on other threads, like that of work spawned by the
+/// closure, is not counted. Memory freed by the closure which was allocated
+/// before it was called reduces the count.
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let outer = MEASURING.with(|measuring| measuring.replace(true));
+    let outer_current = CURRENT.with(|current| current.replace(0));
+    let outer_peak = PEAK.with(|peak| peak.replace(0));
+
+    let res = f();
+
+    let current = CURRENT.with(|current| current.replace(outer_current));
+    let peak = PEAK.with(|peak| peak.replace(outer_peak));
+    MEASURING.with(|measuring| measuring.set(outer));
+
+    // Propagate the usage to an enclosing measurement.
+    if outer {
+        CURRENT.with(|outer| {
+            outer.set(outer.get() + current);
+            PEAK.with(|outer_peak| {
+                outer_peak.set(outer_peak.get().max(outer.get() - current + peak))
+            });
+        });
+    }
+
+    (res, peak.max(0) as usize)
+}
+
+/// Like [`measure`], but runs the closure on a dedicated thread outside of the
+/// rayon pool.
+///
+/// The measured thread never runs jobs of other tests. Memory allocated on
+/// other threads, like that of parallel layout, is still not counted.
+pub fn measure_detached<T: Send>(f: impl FnOnce() -> T + Send) -> (T, usize) {
+    thread::scope(|scope| {
+        scope
+            .spawn(|| measure(f))
+            .join()
+            .unwrap_or_else(|payload| panic::resume_unwind(payload))
+    })
+}
@@ -0,0 +1,222 @@
+//! Recording of tracing spans as a Chrome trace event profile.
+//!
+//! The resulting file can be loaded in `chrome://tracing`, Perfetto or other
+//! tools which understand the [trace event format].
+//!
+//! [trace event format]: https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU
+
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use serde::Serialize;
+use serde_json::Map;
+use serde_json::Value;
+use tracing::Subscriber;
+use tracing::field::Field;
+use tracing::field::Visit;
+use tracing::span::Attributes;
+use tracing::span::Id;
+use tracing::span::Record;
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+/// Creates a layer which records spans and the profile which writes them to
+/// the given path once the run is over.
+pub fn profile(path: PathBuf) -> (ProfileLayer, Profile) {
+    let shared = Arc::new(Shared {
+        start: Instant::now(),
+        events: Mutex::new(vec![]),
+    });
+
+    (
+        ProfileLayer {
+            shared: Arc::clone(&shared),
+        },
+        Profile { path, shared },
+    )
+}
+
+#[derive(Debug)]
+struct Shared {
+    start: Instant,
+    events: Mutex<Vec<TraceEvent>>,
+}
+
+/// A single complete event in the trace event format.
+#[derive(Debug, Serialize)]
+struct TraceEvent {
+    name: String,
+    cat: &'static str,
+    ph: &'static str,
+    ts: f64,
+    dur: f64,
+    pid: u32,
+    tid: u64,
+    args: Map<String, Value>,
+}
+
+/// The profile of a run, see [`profile`].
+#[derive(Debug)]
+pub struct Profile {
+    path: PathBuf,
+    shared: Arc<Shared>,
+}
+
+impl Profile {
+    /// Writes all events recorded so far to the profile path.
+    pub fn write(self) -> io::Result<()> {
+        let events = self.shared.events.lock().unwrap();
+
+        let mut w = BufWriter::new(File::create(&self.path)?);
+        serde_json::to_writer(
+            &mut w,
+            &serde_json::json!({
+                "traceEvents": &*events,
+                "displayTimeUnit": "ms",
+            }),
+        )?;
+        writeln!(w)?;
+        w.flush()
+    }
+}
+
+/// A layer recording each entered span as a complete event, see [`profile`].
+#[derive(Debug)]
+pub struct ProfileLayer {
+    shared: Arc<Shared>,
+}
+
+/// The fields of a span, stored in its extensions.
+#[derive(Debug, Default)]
+struct Fields(Map<String, Value>);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().into(), Value::String(format!("{value:?}")));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+}
+
+/// The instants at which a span was entered and not yet exited, stored in its
+/// extensions.
+#[derive(Debug, Default)]
+struct Entered(Vec<Instant>);
+
+impl<S> Layer<S> for ProfileLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        span.extensions_mut().insert(fields);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        if let Some(fields) = span.extensions_mut().get_mut::<Fields>() {
+            values.record(fields);
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut extensions = span.extensions_mut();
+        match extensions.get_mut::<Entered>() {
+            Some(entered) => entered.0.push(Instant::now()),
+            None => extensions.insert(Entered(vec![Instant::now()])),
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let end = Instant::now();
+
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut extensions = span.extensions_mut();
+        let Some(start) = extensions
+            .get_mut::<Entered>()
+            .and_then(|entered| entered.0.pop())
+        else {
+            return;
+        };
+
+        let args = extensions
+            .get_mut::<Fields>()
+            .map(|fields| fields.0.clone())
+            .unwrap_or_default();
+
+        // NOTE(tinger): Stage spans share the same name, naming the event
+        // after the stage makes the timeline readable at a glance.
+        let name = args
+            .get("stage")
+            .and_then(Value::as_str)
+            .unwrap_or(span.name())
+            .to_owned();
+
+        let event = TraceEvent {
+            name,
+            cat: span.metadata().target(),
+            ph: "X",
+            ts: micros(start.duration_since(self.shared.start)),
+            dur: micros(end.duration_since(start)),
+            pid: std::process::id(),
+            tid: thread_id(),
+            args,
+        };
+
+        self.shared.events.lock().unwrap().push(event);
+    }
+}
+
+fn micros(duration: std::time::Duration) -> f64 {
+    duration.as_secs_f64() * 1_000_000.0
+}
+
+/// A small unique id of the current thread, used as the track of its events.
+fn thread_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+
+    thread_local! {
+        static ID: u64 = NEXT.fetch_add(1, Ordering::Relaxed);
+    }
+
+    ID.with(|id| *id)
+}
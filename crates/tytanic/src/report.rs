@@ -1,24 +1,38 @@
 //! Live reporting of test progress.
 
+use std::collections::BTreeMap;
+use std::env;
 use std::io;
 use std::io::Write;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 
 use chrono::TimeDelta;
 use chrono::Utc;
 use color_eyre::eyre;
+use serde::Serialize;
 use termcolor::Color;
+use typst::World;
+use typst::WorldExt;
+use typst::diag::SourceDiagnostic;
+use typst::syntax::VirtualRoot;
 use typst_kit::diagnostics;
 use typst_kit::diagnostics::DiagnosticFormat;
 use tytanic_core::Project;
+use tytanic_core::UnitTest;
 use tytanic_core::doc::compare;
 use tytanic_core::doc::compare::PageError;
 use tytanic_core::suite::SuiteResult;
+use tytanic_core::test::Id;
 use tytanic_core::test::Stage;
+use tytanic_core::test::StageTimings;
 use tytanic_core::test::Test;
 use tytanic_core::test::TestResult;
 use tytanic_utils::fmt::Term;
 
 use crate::cwrite;
+use crate::json::SummaryLineJson;
+use crate::json::TestResultLineJson;
 use crate::ui;
 use crate::ui::CWrite;
 use crate::ui::Ui;
@@ -27,6 +41,129 @@ use crate::world::Providers;
 /// The padding to use for annotations while test run reporting.
 const RUN_ANNOT_PADDING: usize = 10;
 
+/// Where the effective value of a config member was set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// The value was set on the command line.
+    CommandLine,
+
+    /// The value was set by a test annotation.
+    Annotation,
+
+    /// The value was set by a directory config.
+    Directory,
+}
+
+impl ConfigSource {
+    /// A human readable description of this source.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConfigSource::CommandLine => "command line",
+            ConfigSource::Annotation => "annotation",
+            ConfigSource::Directory => "directory config",
+        }
+    }
+}
+
+/// A config member of a test which differs from the project defaults.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigDelta {
+    /// The name of the member as it is written in an annotation.
+    pub member: &'static str,
+
+    /// The project default.
+    pub default: String,
+
+    /// The effective value for the test.
+    pub value: String,
+
+    /// Where the effective value was set.
+    pub source: ConfigSource,
+}
+
+/// The aggregated outcomes of each test across repeated test runs.
+#[derive(Debug, Clone, Default)]
+pub struct RepeatTally {
+    iterations: usize,
+    counts: BTreeMap<Id, RepeatCount>,
+}
+
+/// How often a single test passed across repeated test runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RepeatCount {
+    /// The number of runs in which the test passed.
+    pub passed: usize,
+
+    /// The number of runs in which the test was run.
+    pub runs: usize,
+}
+
+impl RepeatTally {
+    /// Adds the results of a test run, tests which weren't run are ignored.
+    pub fn add(&mut self, result: &SuiteResult) {
+        self.iterations += 1;
+
+        for (id, result) in result.results() {
+            if result.is_skipped() || result.is_filtered() {
+                continue;
+            }
+
+            let count = self.counts.entry(id.clone()).or_default();
+            count.runs += 1;
+            if result.is_pass() {
+                count.passed += 1;
+            }
+        }
+    }
+
+    /// The number of test runs added to this tally.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// The counts of each test which was run at least once.
+    pub fn counts(&self) -> &BTreeMap<Id, RepeatCount> {
+        &self.counts
+    }
+
+    /// Whether any test failed in any of the runs.
+    pub fn any_failed(&self) -> bool {
+        self.counts.values().any(|count| count.passed != count.runs)
+    }
+}
+
+/// How the progress and results of a test run are reported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// Human readable output on stderr.
+    #[default]
+    Human,
+
+    /// [Test Anything Protocol] version 14 on stdout.
+    ///
+    /// [Test Anything Protocol]: https://testanything.org/tap-version-14-specification.html
+    Tap,
+
+    /// GitHub Actions [workflow commands] on stdout, which surface failures
+    /// as annotations, only the summary is reported on stderr.
+    ///
+    /// [workflow commands]: https://docs.github.com/en/actions/reference/workflow-commands-for-github-actions
+    Github,
+
+    /// [JSON Lines] on stdout, one object per test result and a final
+    /// summary, diagnostics are reported on stderr.
+    ///
+    /// [JSON Lines]: https://jsonlines.org/
+    Jsonl,
+}
+
+impl ReportFormat {
+    /// Whether the current process runs on GitHub Actions.
+    pub fn is_github_actions() -> bool {
+        env::var("GITHUB_ACTIONS").is_ok_and(|value| value == "true")
+    }
+}
+
 /// A reporter for test output and test run status reporting.
 pub struct Reporter<'ui, 'p> {
     ui: &'ui Ui,
@@ -34,6 +171,17 @@ pub struct Reporter<'ui, 'p> {
 
     live: bool,
     format: DiagnosticFormat,
+    report_format: ReportFormat,
+
+    /// Whether to report the time spent in each stage of a test.
+    timings: bool,
+
+    /// Whether updates are only reported as those which would be written.
+    dry_run: bool,
+
+    /// The number of test results reported in the TAP format so far, used as
+    /// the test point numbers.
+    tap_points: AtomicUsize,
 }
 
 impl<'ui, 'p> Reporter<'ui, 'p> {
@@ -48,13 +196,55 @@ impl<'ui, 'p> Reporter<'ui, 'p> {
             providers,
             live,
             format,
+            report_format: ReportFormat::Human,
+            timings: false,
+            dry_run: false,
+            tap_points: AtomicUsize::new(0),
         }
     }
+
+    /// Sets how the progress and results of test runs are reported, live
+    /// status reporting is only used for human readable output.
+    pub fn with_report_format(mut self, report_format: ReportFormat) -> Self {
+        self.report_format = report_format;
+        self.live &= report_format == ReportFormat::Human;
+        self
+    }
+
+    /// Sets whether the time spent in each stage of a test is reported for
+    /// human readable output.
+    pub fn with_timings(mut self, timings: bool) -> Self {
+        self.timings = timings;
+        self
+    }
+
+    /// Sets whether updated tests are reported as those which would be
+    /// updated, this is used for dry runs of `tt update`.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
 }
 
 impl Reporter<'_, '_> {
     /// Reports the start of a test run.
     pub fn report_start(&self, result: &SuiteResult) -> io::Result<()> {
+        if self.report_format == ReportFormat::Tap {
+            self.tap_points.store(0, Ordering::SeqCst);
+
+            let mut w = self.ui.stdout();
+            writeln!(w, "TAP version 14")?;
+            writeln!(w, "1..{}", result.expected())?;
+            return Ok(());
+        }
+
+        if matches!(
+            self.report_format,
+            ReportFormat::Github | ReportFormat::Jsonl
+        ) {
+            return Ok(());
+        }
+
         let mut w = ui::annotated(
             self.ui.stderr(),
             "Starting",
@@ -76,11 +266,53 @@ impl Reporter<'_, '_> {
         cwrite!(bold(w), "{}", result.id())?;
         writeln!(w, ")")?;
 
+        for (key, value) in result.metadata() {
+            cwrite!(bold(w), "{key}")?;
+            writeln!(w, " = {value}")?;
+        }
+
+        Ok(())
+    }
+
+    /// Reports a test which was filtered out because of its `skip`
+    /// annotation, together with the reason given on it.
+    pub fn report_test_skipped(&self, test: &UnitTest) -> io::Result<()> {
+        if self.report_format != ReportFormat::Human {
+            return Ok(());
+        }
+
+        let mut w = ui::annotated(self.ui.stderr(), "skip", Color::Yellow, RUN_ANNOT_PADDING)?;
+
+        ui::write_test_id(&mut w, test.id())?;
+        if let Some(reason) = test.skip_reason() {
+            write!(w, " (reason: {reason})")?;
+        }
+        writeln!(w)?;
+
         Ok(())
     }
 
     /// Reports the end of a test run.
     pub fn report_end(&self, result: &SuiteResult) -> io::Result<()> {
+        if self.report_format == ReportFormat::Tap {
+            let reported = self.tap_points.load(Ordering::SeqCst);
+            if reported < result.expected() {
+                writeln!(
+                    self.ui.stdout(),
+                    "Bail out! {} of {} {} were not run",
+                    result.expected() - reported,
+                    result.expected(),
+                    Term::simple("test").with(result.expected()),
+                )?;
+            }
+
+            return Ok(());
+        }
+
+        if self.report_format == ReportFormat::Jsonl {
+            return self.write_json_line(&SummaryLineJson::new(result));
+        }
+
         let mut w = self.ui.stderr();
 
         let color = if result.failed() == 0 {
@@ -135,6 +367,28 @@ impl Reporter<'_, '_> {
             cwrite!(colored(w, Color::Red), "failed")?;
         }
 
+        if result.flaky() != 0 {
+            write!(w, ", ")?;
+            cwrite!(bold(w), "{}", result.flaky())?;
+            write!(w, " ")?;
+            cwrite!(colored(w, Color::Yellow), "flaky")?;
+        }
+
+        if result.updated() != 0 {
+            write!(w, ", ")?;
+            cwrite!(bold(w), "{}", result.updated())?;
+            write!(w, " ")?;
+            cwrite!(
+                colored(w, Color::Green),
+                "{}",
+                if self.dry_run {
+                    "would update"
+                } else {
+                    "updated"
+                }
+            )?;
+        }
+
         if result.filtered() != 0 {
             write!(w, ", ")?;
             cwrite!(bold(w), "{}", result.filtered())?;
@@ -149,13 +403,207 @@ impl Reporter<'_, '_> {
             cwrite!(colored(w, Color::Yellow), "skipped")?;
         }
 
+        if result.cancelled() != 0 {
+            write!(w, ", ")?;
+            cwrite!(bold(w), "{}", result.cancelled())?;
+            write!(w, " ")?;
+            cwrite!(colored(w, Color::Yellow), "cancelled")?;
+        }
+
         writeln!(w)?;
 
+        if result.cancelled() != 0 {
+            write!(w, "Run ")?;
+            cwrite!(colored(w, Color::Yellow), "cancelled")?;
+            writeln!(w, ": {}/{} completed", result.run(), result.expected())?;
+        }
+
+        if result.warnings() != 0 {
+            cwrite!(bold(w), "{}", result.warned())?;
+            write!(
+                w,
+                " {} produced ",
+                Term::simple("test").with(result.warned())
+            )?;
+            cwrite!(bold(w), "{}", result.warnings())?;
+            write!(w, " ")?;
+            cwrite!(
+                colored(w, Color::Yellow),
+                "{}",
+                Term::simple("warning").with(result.warnings())
+            )?;
+            writeln!(w)?;
+        }
+
         // TODO(tinger): Report failures, mean, and average time.
 
         Ok(())
     }
 
+    /// Reports a test result as a TAP test point, failures are described in
+    /// a YAML diagnostic block.
+    fn report_tap_test_result(&self, test: &Test, result: &TestResult) -> io::Result<()> {
+        let point = self.tap_points.fetch_add(1, Ordering::SeqCst) + 1;
+        let id = test.id();
+
+        let mut w = self.ui.stdout();
+
+        match result.stage() {
+            Stage::Skipped | Stage::Cancelled | Stage::Filtered => {
+                writeln!(w, "ok {point} - {id} # SKIP")?;
+            }
+            Stage::FailedCompilation { error, reference } => {
+                writeln!(w, "not ok {point} - {id}")?;
+                writeln!(w, "  ---")?;
+                writeln!(w, "  stage: failed compilation")?;
+                writeln!(w, "  reference: {reference}")?;
+                writeln!(w, "  errors:")?;
+                for diag in &error.0 {
+                    writeln!(w, "    - {}", yaml_str(&diag.message))?;
+                }
+                writeln!(w, "  ...")?;
+            }
+            Stage::FailedComparison(compare::Error {
+                output,
+                reference,
+                pages,
+                max_failing_pages,
+                ..
+            }) => {
+                writeln!(w, "not ok {point} - {id}")?;
+                writeln!(w, "  ---")?;
+                writeln!(w, "  stage: failed comparison")?;
+                writeln!(w, "  output-pages: {output}")?;
+                writeln!(w, "  reference-pages: {reference}")?;
+                writeln!(w, "  max-failing-pages: {max_failing_pages}")?;
+                writeln!(w, "  pages:")?;
+                for (p, e) in pages {
+                    writeln!(w, "    - page: {}", p + 1)?;
+                    writeln!(w, "      error: {}", yaml_str(&e.to_string()))?;
+                }
+                writeln!(w, "  ...")?;
+            }
+            Stage::PassedCompilation | Stage::PassedComparison | Stage::Updated { .. } => {
+                writeln!(w, "ok {point} - {id}")?;
+                if result.is_flaky() {
+                    writeln!(w, "  ---")?;
+                    writeln!(w, "  retries: {}", result.retries())?;
+                    writeln!(w, "  ...")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reports a test result as a JSON line, its diagnostics are emitted on
+    /// stderr.
+    fn report_jsonl_test_result(
+        &self,
+        project: &Project,
+        test: &Test,
+        result: &TestResult,
+    ) -> eyre::Result<()> {
+        self.write_json_line(&TestResultLineJson::new(test, result))?;
+
+        let world = match test {
+            Test::Unit(test) => self.providers.unit_world(project, test, false, None, None),
+            Test::Template(test) => self.providers.template_world(project, test),
+        };
+
+        let mut w = self.ui.stderr();
+        diagnostics::emit(&mut w, &world, result.warnings(), self.format)?;
+        diagnostics::emit(
+            &mut w,
+            &world,
+            result.errors().unwrap_or_default(),
+            self.format,
+        )?;
+
+        Ok(())
+    }
+
+    /// Writes a single JSON object on its own line to stdout and flushes it,
+    /// such that watching processes see each line as soon as it's reported.
+    fn write_json_line(&self, value: &impl Serialize) -> io::Result<()> {
+        let mut w = self.ui.stdout();
+        serde_json::to_writer(&mut w, value)?;
+        writeln!(w)?;
+        w.flush()
+    }
+
+    /// Reports a failed test result as GitHub Actions error commands, one per
+    /// compilation error or one for a failed comparison.
+    fn report_github_test_result(
+        &self,
+        project: &Project,
+        test: &Test,
+        result: &TestResult,
+    ) -> io::Result<()> {
+        let id = test.id();
+
+        let mut w = self.ui.stdout();
+
+        match result.stage() {
+            Stage::FailedCompilation { error, reference } => {
+                let world = match test {
+                    Test::Unit(test) => self
+                        .providers
+                        .unit_world(project, test, *reference, None, None),
+                    Test::Template(test) => self.providers.template_world(project, test),
+                };
+
+                for diag in &error.0 {
+                    write!(w, "::error ")?;
+                    if let Some((file, line)) = github_location(project, &world, diag) {
+                        write!(w, "file={},line={line},", github_escape_property(&file))?;
+                    }
+                    writeln!(
+                        w,
+                        "title={}::{}",
+                        github_escape_property(id.as_str()),
+                        github_escape_data(&format!(
+                            "Compilation of {} failed: {}",
+                            if *reference { "reference" } else { "test" },
+                            diag.message,
+                        )),
+                    )?;
+                }
+            }
+            Stage::FailedComparison(error) => {
+                let mut message = format!("Comparison of {id} failed");
+                if error.output != error.reference {
+                    message.push_str(&format!(
+                        ": expected {} {}, got {} {}",
+                        error.reference,
+                        Term::simple("page").with(error.reference),
+                        error.output,
+                        Term::simple("page").with(error.output),
+                    ));
+                } else {
+                    for (p, e) in &error.pages {
+                        message.push_str(&format!("\npage {}: {e}", p + 1));
+                    }
+                }
+
+                writeln!(
+                    w,
+                    "::error title={}::{}",
+                    github_escape_property(id.as_str()),
+                    github_escape_data(&message),
+                )?;
+            }
+            Stage::Skipped
+            | Stage::Cancelled
+            | Stage::Filtered
+            | Stage::PassedCompilation
+            | Stage::PassedComparison
+            | Stage::Updated { .. } => {}
+        }
+
+        Ok(())
+    }
+
     /// Clears the last line, i.e the status output.
     pub fn clear_status(&self) -> io::Result<()> {
         if !self.live {
@@ -233,10 +681,23 @@ impl Reporter<'_, '_> {
         test: &Test,
         result: &TestResult,
     ) -> eyre::Result<()> {
+        match self.report_format {
+            ReportFormat::Human => {}
+            ReportFormat::Tap => return Ok(self.report_tap_test_result(test, result)?),
+            ReportFormat::Github => {
+                return Ok(self.report_github_test_result(project, test, result)?);
+            }
+            ReportFormat::Jsonl => {
+                return self.report_jsonl_test_result(project, test, result);
+            }
+        }
+
         let (annot, color) = match result.stage() {
             Stage::Skipped => ("skip", Color::Yellow),
+            Stage::Cancelled => ("cancel", Color::Yellow),
             Stage::Filtered => ("filter", Color::Yellow),
             Stage::FailedCompilation { .. } | Stage::FailedComparison(_) => ("fail", Color::Red),
+            _ if result.is_flaky() => ("flaky", Color::Yellow),
             Stage::PassedCompilation => ("compile", Color::Green),
             Stage::PassedComparison => ("pass", Color::Green),
             Stage::Updated { .. } => ("update", Color::Green),
@@ -254,8 +715,16 @@ impl Reporter<'_, '_> {
         ui::write_test_id(&mut w, test.id())?;
         writeln!(w)?;
 
+        if self.timings {
+            write_timings(&mut w, result.timings())?;
+        }
+
+        if let Some(peak) = result.peak_memory() {
+            writeln!(w, "Peak memory: {peak} bytes")?;
+        }
+
         let world = match test {
-            Test::Unit(test) => self.providers.unit_world(project, test, false, None),
+            Test::Unit(test) => self.providers.unit_world(project, test, false, None, None),
             Test::Template(test) => self.providers.template_world(project, test),
         };
 
@@ -268,7 +737,23 @@ impl Reporter<'_, '_> {
         )?;
 
         match result.stage() {
-            Stage::PassedCompilation | Stage::PassedComparison => {}
+            Stage::PassedCompilation => {}
+            Stage::PassedComparison => {
+                if let Some(deviations) = result.deviations() {
+                    writeln!(
+                        w,
+                        "Passed with {deviations} {}",
+                        Term::simple("deviation").with(deviations),
+                    )?;
+                }
+
+                if result.has_stale_references() {
+                    writeln!(
+                        w,
+                        "References may be stale, the test or config changed since they were last updated",
+                    )?;
+                }
+            }
             Stage::FailedCompilation { reference, .. } => {
                 writeln!(
                     w,
@@ -280,6 +765,8 @@ impl Reporter<'_, '_> {
                 output,
                 reference,
                 pages,
+                max_failing_pages,
+                details,
             }) => {
                 if output != reference {
                     writeln!(
@@ -299,18 +786,91 @@ impl Reporter<'_, '_> {
                                     writeln!(w, "Reference: {reference}")
                                 })?;
                             }
-                            PageError::SimpleDeviations { deviations } => {
+                            PageError::SimpleDeviations {
+                                deviations,
+                                max_delta,
+                                max_deviation,
+                            } => {
                                 writeln!(
                                     w,
-                                    "Page {p} had {deviations} {}",
+                                    "Page {p} had {deviations} {}, at most {max_deviation} \
+                                    allowed with a max delta of {max_delta}",
                                     Term::simple("deviation").with(*deviations),
                                 )?;
+
+                                if let Some((_, diff)) =
+                                    details.iter().find(|(idx, _)| *idx + 1 == p)
+                                    && let Some(region) = diff.region
+                                {
+                                    w.write_with(2, |w| {
+                                        writeln!(w, "Region: {region}")?;
+                                        writeln!(w, "Largest delta: {}", diff.largest_delta)
+                                    })?;
+                                }
+                            }
+                            PageError::CropOffset { output, reference } => {
+                                writeln!(w, "Page {p} had its content at a different position")?;
+                                w.write_with(2, |w| {
+                                    writeln!(w, "Output: {}, {}", output.x, output.y)?;
+                                    writeln!(w, "Reference: {}, {}", reference.x, reference.y)
+                                })?;
+                            }
+                            PageError::Ppi { output, reference } => {
+                                writeln!(
+                                    w,
+                                    "Page {p} was rendered at {output} PPI, but its reference at \
+                                    {reference} PPI, the reference needs to be updated",
+                                )?;
+                            }
+                            PageError::Ssim { score, min_ssim } => {
+                                writeln!(
+                                    w,
+                                    "Page {p} had a structural similarity of {score:.4}, \
+                                    at least {min_ssim} required",
+                                )?;
+                            }
+                            PageError::Text {
+                                line,
+                                output,
+                                reference,
+                            } => {
+                                writeln!(w, "Page {p} had different text on line {line}")?;
+                                w.write_with(2, |w| {
+                                    writeln!(w, "Output: {output:?}")?;
+                                    writeln!(w, "Reference: {reference:?}")
+                                })?;
                             }
                         }
                     }
+
+                    if *max_failing_pages != 0 {
+                        writeln!(
+                            w,
+                            "{} {} failed, at most {max_failing_pages} allowed",
+                            pages.len(),
+                            Term::simple("page").with(pages.len()),
+                        )?;
+                    }
                 }
             }
-            Stage::Updated { .. } => {}
+            Stage::Updated { pages, dry_run, .. } => {
+                if let Some(deviations) = result.deviations() {
+                    writeln!(
+                        w,
+                        "Failed with {deviations} {}",
+                        Term::simple("deviation").with(deviations),
+                    )?;
+                }
+
+                writeln!(
+                    w,
+                    "{} {} {}, {} unchanged",
+                    if *dry_run { "Would update" } else { "Updated" },
+                    pages.updated,
+                    Term::simple("page").with(pages.updated),
+                    pages.unchanged,
+                )?;
+            }
             _ => unreachable!(),
         }
 
@@ -318,6 +878,234 @@ impl Reporter<'_, '_> {
     }
 }
 
+impl Reporter<'_, '_> {
+    /// Report the pass rate of each test across repeated test runs.
+    pub fn report_repeat(&self, tally: &RepeatTally) -> io::Result<()> {
+        writeln!(self.ui.stderr(), "{:─>RUN_ANNOT_PADDING$}", "")?;
+
+        for (id, count) in tally.counts() {
+            let (annot, color) = if count.passed == count.runs {
+                ("pass", Color::Green)
+            } else if count.passed == 0 {
+                ("fail", Color::Red)
+            } else {
+                ("flaky", Color::Yellow)
+            };
+
+            let mut w = ui::annotated(self.ui.stderr(), annot, color, RUN_ANNOT_PADDING)?;
+            ui::write_test_id(&mut w, id)?;
+            write!(w, ": ")?;
+            cwrite!(bold_colored(w, color), "{}/{}", count.passed, count.runs)?;
+            writeln!(w, " passed")?;
+        }
+
+        let flaky = tally
+            .counts()
+            .values()
+            .filter(|count| count.passed != 0 && count.passed != count.runs)
+            .count();
+
+        let mut w = ui::annotated(
+            self.ui.stderr(),
+            "Repeated",
+            if tally.any_failed() {
+                Color::Yellow
+            } else {
+                Color::Green
+            },
+            RUN_ANNOT_PADDING,
+        )?;
+        cwrite!(bold(w), "{}", tally.iterations())?;
+        write!(w, " {}, ", Term::simple("run").with(tally.iterations()))?;
+        cwrite!(bold(w), "{flaky}")?;
+        writeln!(w, " flaky {}", Term::simple("test").with(flaky))?;
+
+        Ok(())
+    }
+
+    /// Report the tests whose effective config differs from the project
+    /// defaults.
+    pub fn report_config_audit(&self, audit: &[(&UnitTest, Vec<ConfigDelta>)]) -> io::Result<()> {
+        for (test, deltas) in audit {
+            let mut w = ui::annotated(self.ui.stderr(), "Audit", Color::Yellow, RUN_ANNOT_PADDING)?;
+            ui::write_test_id(&mut w, test.id())?;
+            writeln!(w)?;
+
+            for delta in deltas {
+                cwrite!(bold(w), "{}", delta.member)?;
+                write!(w, ": {} -> ", delta.default)?;
+                cwrite!(colored(w, Color::Yellow), "{}", delta.value)?;
+                writeln!(w, " ({})", delta.source.as_str())?;
+            }
+        }
+
+        let mut w = ui::annotated(self.ui.stderr(), "Audit", Color::Yellow, RUN_ANNOT_PADDING)?;
+        if audit.is_empty() {
+            writeln!(w, "no tests override the project defaults")?;
+        } else {
+            writeln!(
+                w,
+                "{} {} the project defaults",
+                audit.len(),
+                Term::new("test overrides", "tests override").with(audit.len()),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Report a detailed breakdown of a test result, this includes the stage
+    /// the test reached, its diagnostics, the comparison details and the paths
+    /// to its artifacts.
+    pub fn report_explanation(
+        &self,
+        project: &Project,
+        test: &Test,
+        result: &TestResult,
+    ) -> eyre::Result<()> {
+        writeln!(self.ui.stderr(), "{:─>RUN_ANNOT_PADDING$}", "")?;
+
+        let mut w = ui::annotated(self.ui.stderr(), "Explain", Color::Cyan, RUN_ANNOT_PADDING)?;
+        ui::write_test_id(&mut w, test.id())?;
+        writeln!(w)?;
+
+        let kind = match test {
+            Test::Unit(test) => test.kind().as_str(),
+            Test::Template(_) => "template",
+        };
+        write!(w, "Kind: ")?;
+        cwrite!(bold(w), "{kind}")?;
+        writeln!(w)?;
+
+        let stage = match result.stage() {
+            Stage::Skipped => "skipped",
+            Stage::Cancelled => "cancelled",
+            Stage::Filtered => "filtered",
+            Stage::FailedCompilation {
+                reference: true, ..
+            } => "failed reference compilation",
+            Stage::FailedCompilation {
+                reference: false, ..
+            } => "failed test compilation",
+            Stage::FailedComparison(_) => "failed comparison",
+            Stage::PassedCompilation => "passed compilation",
+            Stage::PassedComparison => "passed comparison",
+            Stage::Updated { dry_run: false, .. } => "updated",
+            Stage::Updated { dry_run: true, .. } => "would update",
+        };
+        write!(w, "Stage: ")?;
+        cwrite!(
+            colored(
+                w,
+                if result.is_fail() {
+                    Color::Red
+                } else {
+                    Color::Green
+                }
+            ),
+            "{stage}"
+        )?;
+        writeln!(w)?;
+
+        write!(w, "Duration: ")?;
+        write_duration(&mut w, result.duration())?;
+        writeln!(w)?;
+
+        if let Test::Unit(test) = test
+            && !test.annotations().is_empty()
+        {
+            write!(w, "Annotations: ")?;
+            for (idx, annot) in test.annotations().iter().enumerate() {
+                if idx != 0 {
+                    write!(w, ", ")?;
+                }
+                cwrite!(bold(w), "{}", annot.name())?;
+            }
+            writeln!(w)?;
+        }
+
+        let errors = result.errors().unwrap_or_default();
+        writeln!(
+            w,
+            "Diagnostics: {} {}, {} {}",
+            errors.len(),
+            Term::simple("error").with(errors.len()),
+            result.warnings().len(),
+            Term::simple("warning").with(result.warnings().len()),
+        )?;
+
+        if !errors.is_empty() || !result.warnings().is_empty() {
+            let world = match test {
+                Test::Unit(test) => self.providers.unit_world(project, test, false, None, None),
+                Test::Template(test) => self.providers.template_world(project, test),
+            };
+
+            w.write_with(2, |w| {
+                diagnostics::emit(&mut *w, &world, result.warnings(), self.format)?;
+                diagnostics::emit(&mut *w, &world, errors, self.format)
+            })?;
+        }
+
+        if let Stage::FailedComparison(compare::Error {
+            output,
+            reference,
+            pages,
+            max_failing_pages,
+            ..
+        }) = result.stage()
+        {
+            writeln!(w, "Comparison:")?;
+            w.write_with(2, |w| {
+                writeln!(w, "Output: {output} {}", Term::simple("page").with(*output))?;
+                writeln!(
+                    w,
+                    "Reference: {reference} {}",
+                    Term::simple("page").with(*reference),
+                )?;
+
+                for (p, e) in pages {
+                    writeln!(w, "Page {}: {e}", p + 1)?;
+                }
+
+                if *max_failing_pages != 0 {
+                    writeln!(w, "Allowed failing pages: {max_failing_pages}")?;
+                }
+
+                io::Result::Ok(())
+            })?;
+        }
+
+        if let Test::Unit(test) = test {
+            let id = test.id();
+            let artifacts = [
+                ("Test script", project.unit_test_script(id)),
+                ("Reference script", project.unit_test_ref_script(id)),
+                ("References", project.unit_test_persistent_ref_dir(id)),
+                ("Output", project.unit_test_out_dir(id)),
+                ("Difference", project.unit_test_diff_dir(id)),
+            ];
+
+            writeln!(w, "Artifacts:")?;
+            w.write_with(2, |w| {
+                for (name, path) in artifacts {
+                    if !path.exists() {
+                        continue;
+                    }
+
+                    let path = path.strip_prefix(project.root()).unwrap_or(&path);
+                    write!(w, "{name}: ")?;
+                    cwrite!(colored(*w, Color::Cyan), "{path}")?;
+                    writeln!(w)?;
+                }
+
+                io::Result::Ok(())
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
 /// Writes a padded duration in human readable form
 fn write_duration(w: &mut dyn Write, duration: TimeDelta) -> io::Result<()> {
     let s = duration.num_seconds();
@@ -334,6 +1122,21 @@ fn write_duration(w: &mut dyn Write, duration: TimeDelta) -> io::Result<()> {
     Ok(())
 }
 
+/// Writes the time spent in each stage of a test which ran.
+fn write_timings(w: &mut dyn Write, timings: &StageTimings) -> io::Result<()> {
+    let stages = timings
+        .iter()
+        .filter(|(_, duration)| !duration.is_zero())
+        .map(|(stage, duration)| format!("{} {}ms", stage.name(), duration.num_milliseconds()))
+        .collect::<Vec<_>>();
+
+    if stages.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(w, "Stages: {}", stages.join(", "))
+}
+
 /// Returns the color to use for a test's duration.
 fn duration_color(duration: TimeDelta) -> Color {
     match duration.num_seconds() {
@@ -343,3 +1146,52 @@ fn duration_color(duration: TimeDelta) -> Color {
         _ => Color::Red,
     }
 }
+
+/// Resolves the file and 1-based line of a diagnostic for a GitHub Actions
+/// annotation.
+///
+/// The file is relative to the GitHub workspace if it lies within it, files
+/// outside of the project, like those in packages, are not resolved.
+fn github_location(
+    project: &Project,
+    world: &dyn World,
+    diag: &SourceDiagnostic,
+) -> Option<(String, usize)> {
+    let id = diag.span.id()?;
+    if !matches!(id.root(), VirtualRoot::Project) {
+        return None;
+    }
+
+    let source = world.source(id).ok()?;
+    let range = world.range(diag.span)?;
+    let line = source.lines().byte_to_line(range.start)? + 1;
+
+    let path = project
+        .root()
+        .as_std_path()
+        .join(id.vpath().get_without_slash());
+    let path = env::var_os("GITHUB_WORKSPACE")
+        .and_then(|workspace| path.strip_prefix(workspace).ok().map(ToOwned::to_owned))
+        .unwrap_or(path);
+
+    Some((path.to_string_lossy().replace('\\', "/"), line))
+}
+
+/// Escapes the message of a GitHub Actions workflow command.
+fn github_escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a property value of a GitHub Actions workflow command.
+fn github_escape_property(s: &str) -> String {
+    github_escape_data(s)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+/// Quotes a string for use as a YAML scalar, JSON strings are valid YAML.
+fn yaml_str(s: &str) -> String {
+    serde_json::to_string(s).expect("strings are always serializable")
+}
@@ -1,35 +1,75 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::fmt::Debug;
+use std::fs;
+use std::iter;
+use std::sync::Mutex;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
+use std::sync::mpsc::Sender;
 
+use camino::Utf8PathBuf;
+use chrono::Utc;
 use color_eyre::eyre;
 use color_eyre::eyre::WrapErr;
+use ecow::EcoString;
+use ecow::eco_format;
+use ecow::eco_vec;
+use rayon::iter::IntoParallelIterator;
+use rayon::iter::ParallelIterator;
+use typst::diag::SourceDiagnostic;
 use typst::diag::Warned;
 use typst::foundations::Dict;
 use typst::foundations::Str;
 use typst::foundations::Value;
+use typst::syntax::Span;
 use typst::utils::Scalar;
+use typst::utils::hash128;
 use typst_layout::PagedDocument;
+use typst_pdf::PdfOptions;
 use typst_render::RenderOptions;
+use tytanic_core::Id;
 use tytanic_core::TemplateTest;
 use tytanic_core::UnitTest;
-use tytanic_core::config::Direction;
+use tytanic_core::doc::ArtifactFormat;
 use tytanic_core::doc::Document;
+use tytanic_core::doc::PAGE_EXTENSION;
+use tytanic_core::doc::SaveSummary;
+use tytanic_core::doc::acquire_write_permit;
+use tytanic_core::doc::compare;
+use tytanic_core::doc::compare::CompareCache;
+use tytanic_core::doc::compare::PageCompare;
+use tytanic_core::doc::compare::PageTolerance;
 use tytanic_core::doc::compare::Strategy;
 use tytanic_core::doc::compile;
 use tytanic_core::doc::compile::Warnings;
 use tytanic_core::doc::render;
+use tytanic_core::doc::render::DiffColors;
+use tytanic_core::doc::render::Mask;
 use tytanic_core::doc::render::Origin;
+use tytanic_core::doc::render::OverviewOptions;
+use tytanic_core::event::ResultKind;
+use tytanic_core::event::RunEvent;
+use tytanic_core::event::RunStage;
 use tytanic_core::project::Project;
+use tytanic_core::project::REFERENCE_PLATFORM;
 use tytanic_core::suite::FilteredSuite;
+use tytanic_core::suite::LastRun;
 use tytanic_core::suite::SuiteResult;
 use tytanic_core::test::Annotation;
+use tytanic_core::test::Stage;
 use tytanic_core::test::Test;
 use tytanic_core::test::TestResult;
 use tytanic_core::test::unit::Kind;
+use tytanic_core::world_builder::ProvideFile;
+use tytanic_core::world_builder::file::DataRootFileProvider;
+use tytanic_core::world_builder::file::RecordingFileProvider;
+use tytanic_core::world_builder::file::project_file_key;
+use uuid::Uuid;
 
-use crate::DEFAULT_OPTIMIZE_OPTIONS;
 use crate::cli::TestFailure;
+use crate::memory;
 use crate::report::Reporter;
 use crate::world::Providers;
 use crate::world::augmented_library_provider_with_inputs;
@@ -43,32 +83,116 @@ pub enum Action {
     Update {
         /// Whether to update passing tests.
         force: bool,
+
+        /// Whether to only write reference pages which changed.
+        only_changed: bool,
+
+        /// The 1-based reference pages to write, all other pages are left
+        /// untouched, if this is `None` all pages are written.
+        pages: Option<BTreeSet<usize>>,
+
+        /// The files which changed since a given revision, only tests which
+        /// read one of them or contain one in their directory are updated. If
+        /// this is `None` all tests are updated.
+        since: Option<BTreeSet<Utf8PathBuf>>,
+
+        /// Whether to only record which references would be updated without
+        /// writing anything.
+        dry_run: bool,
     },
 }
 
+impl Action {
+    /// Whether this is an update which doesn't write anything.
+    pub fn is_dry_run(&self) -> bool {
+        matches!(self, Action::Update { dry_run: true, .. })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RunnerConfig<'c> {
     /// How to handle warnings.
     pub warnings: Warnings,
 
-    /// Whether to optimize reference documents.
-    pub optimize: bool,
+    /// The options with which to optimize reference documents, `None` if they
+    /// should not be optimized.
+    pub optimize: Option<oxipng::Options>,
 
     /// Whether to stop after the first failure.
     pub fail_fast: bool,
 
+    /// How often a failing unit test is run again before it is reported as
+    /// failed, this is overridden by the retries annotation of a test.
+    pub retries: usize,
+
     /// The render options to use when rendering documents.
     pub render_options: RenderOptions,
 
     /// The strategy to use when comparing documents.
     pub strategy: Option<Strategy>,
 
+    /// Whether documents are compared by the text of their pages instead of
+    /// their pixels, the strategy is then only used for its maximum number of
+    /// failing pages.
+    pub compare_text: bool,
+
+    /// The factor by which output and reference pages are downscaled before
+    /// they are compared and diffed, factors below `2` compare them at full
+    /// resolution.
+    pub compare_downscale: u32,
+
     /// Whether to export ephemeral output.
     pub export_ephemeral: bool,
 
+    /// Whether to export difference documents and record the deviations of
+    /// tests which passed comparison, difference documents are otherwise only
+    /// exported for failed comparisons.
+    pub export_diff_always: bool,
+
+    /// Whether to update the references of persistent tests which fail
+    /// comparison while running them.
+    pub update_on_fail: bool,
+
+    /// The format in which test output is exported.
+    pub export_format: ArtifactFormat,
+
+    /// Whether to crop the borders of uniform color around rendered pages.
+    pub crop: bool,
+
+    /// The options used to export a page overview after comparison, if one
+    /// should be exported.
+    pub overview: Option<OverviewOptions>,
+
     /// The origin at which to render diff images of different dimensions.
     pub origin: Origin,
 
+    /// The colors with which to render diff images.
+    pub diff_colors: DiffColors,
+
+    /// Whether to skip comparisons which passed before with the same output,
+    /// reference and strategy.
+    pub compare_cache: bool,
+
+    /// The id of the test run, a random id is used if this is `None`.
+    pub run_id: Option<Uuid>,
+
+    /// The user supplied metadata to attach to the run.
+    pub metadata: BTreeMap<EcoString, EcoString>,
+
+    /// Whether to record the inputs of each test and compute an input hash.
+    pub record_inputs: bool,
+
+    /// The key-value pairs exposed in `sys.inputs` of all tests, these take
+    /// precedence over the project defaults and test annotations.
+    pub inputs: BTreeMap<String, String>,
+
+    /// The filtered tests which were only removed because of their `skip`
+    /// annotation, these are reported as skipped at the start of the run.
+    pub skipped: BTreeSet<Id>,
+
+    /// A channel on which progress events are sent during the run, if any.
+    pub events: Option<Sender<RunEvent>>,
+
     /// The action to take for the test.
     pub action: Action,
 
@@ -92,9 +216,15 @@ impl<'c, 'p, F> Runner<'c, 'p, F> {
         providers: &'p Providers,
         config: RunnerConfig<'c>,
     ) -> Self {
+        let mut result = match config.run_id {
+            Some(id) => SuiteResult::with_id(suite, id),
+            None => SuiteResult::new(suite),
+        };
+        result.set_metadata(config.metadata.clone());
+
         Self {
             project,
-            result: SuiteResult::new(suite),
+            result,
             suite,
             providers,
             config,
@@ -106,7 +236,42 @@ impl<'c, 'p, F> Runner<'c, 'p, F> {
             project_runner: self,
             test,
             result: TestResult::skipped(),
+            inputs: self.config.record_inputs.then(BTreeMap::new),
+        }
+    }
+
+    /// Runs a unit test and, if it fails while running, runs it again up to
+    /// its number of retries.
+    pub fn run_unit_test(&self, test: &'p UnitTest) -> eyre::Result<TestResult> {
+        let retries = match self.config.action {
+            Action::Run => test
+                .annotations()
+                .iter()
+                .find_map(|annot| match annot {
+                    Annotation::Retries(retries) => Some(*retries),
+                    _ => None,
+                })
+                .unwrap_or(self.config.retries),
+            Action::Update { .. } => 0,
+        };
+
+        let mut result = self.unit_test(test).run()?;
+        let mut retried = 0;
+
+        while result.is_fail()
+            && retried < retries
+            && !self.config.cancellation.load(Ordering::SeqCst)
+        {
+            // NOTE(tinger): The providers are shared with the tests running
+            // in parallel, resetting their files would make those tests read
+            // files again mid compilation. Only the test's own state is fresh.
+            retried += 1;
+            result = self.unit_test(test).run()?;
         }
+
+        result.set_retries(retried);
+
+        Ok(result)
     }
 
     pub fn template_test<'s>(
@@ -120,73 +285,229 @@ impl<'c, 'p, F> Runner<'c, 'p, F> {
         }
     }
 
-    pub fn run_inner(&mut self, reporter: &Reporter) -> eyre::Result<()> {
+    /// Sends an event on the event channel, if there is one.
+    pub fn emit(&self, event: RunEvent) {
+        if let Some(events) = &self.config.events {
+            // NOTE(tinger): The receiver hanging up must not abort the run.
+            _ = events.send(event);
+        }
+    }
+
+    /// Runs a stage of a test within a tracing span and emits an event once it
+    /// finished.
+    pub fn timed<T, E>(
+        &self,
+        id: &Id,
+        stage: RunStage,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let _span = tracing::info_span!("stage", test = %id, stage = stage.name()).entered();
+
+        if self.config.events.is_none() {
+            return f();
+        }
+
+        let start = Utc::now();
+        let res = f();
+
+        self.emit(RunEvent::StageFinished {
+            id: id.clone(),
+            stage,
+            passed: res.is_ok(),
+            duration: Utc::now().signed_duration_since(start),
+        });
+
+        res
+    }
+
+    pub fn run_inner(&mut self, reporter: &Reporter) -> eyre::Result<()>
+    where
+        F: Sync,
+    {
         reporter.report_status(&self.result)?;
 
-        for test in self.suite.matched() {
-            if self.config.cancellation.load(Ordering::SeqCst) {
-                return Ok(());
-            }
+        // NOTE(tinger): Tests run in parallel on the global thread pool, whose
+        // size is configured by `--jobs`. Each test builds its own world, the
+        // suite result is only touched while reporting such that the reports
+        // of concurrently finishing tests are not interleaved.
+        let result = Mutex::new(self.result.clone());
+        let failed = AtomicBool::new(false);
+
+        let this = &*self;
+        let tests = this.suite.matched().tests().collect::<Vec<_>>();
+        let res = tests
+            .into_par_iter()
+            .try_for_each(|test| -> eyre::Result<()> {
+                if this.config.cancellation.load(Ordering::SeqCst)
+                    || (this.config.fail_fast && failed.load(Ordering::SeqCst))
+                {
+                    return Ok(());
+                }
 
-            let result = match test {
-                Test::Unit(test) => self.unit_test(test).run()?,
-                Test::Template(test) => self.template_test(test).run()?,
-            };
+                this.emit(RunEvent::TestStarted {
+                    id: test.id().clone(),
+                });
 
-            reporter.clear_status()?;
+                let test_result = match test {
+                    Test::Unit(test) => this.run_unit_test(test)?,
+                    Test::Template(test) => this.template_test(test).run()?,
+                };
 
-            // TODO(tinger): Retrieve export var from action.
-            reporter.report_test_result(self.project, test, &result)?;
+                this.emit(RunEvent::TestFinished {
+                    id: test.id().clone(),
+                    kind: ResultKind::from(test_result.stage()),
+                    duration: test_result.duration(),
+                });
 
-            if result.is_fail() && self.config.fail_fast {
-                self.result.set_test_result(test.id().clone(), result);
-                return Ok(());
-            }
+                let mut result = result.lock().unwrap();
 
-            reporter.report_status(&self.result)?;
+                reporter.clear_status()?;
 
-            self.result.set_test_result(test.id().clone(), result);
-        }
+                // TODO(tinger): Retrieve export var from action.
+                reporter.report_test_result(this.project, test, &test_result)?;
+
+                if test_result.is_fail() {
+                    failed.store(true, Ordering::SeqCst);
+                }
+
+                if !(test_result.is_fail() && this.config.fail_fast) {
+                    reporter.report_status(&result)?;
+                }
+
+                result.set_test_result(test.id().clone(), test_result);
+
+                Ok(())
+            });
+
+        self.result = result.into_inner().unwrap();
+        res?;
 
         reporter.clear_status()?;
 
         Ok(())
     }
 
-    #[tracing::instrument(skip_all)]
-    pub fn run(mut self, reporter: &Reporter) -> eyre::Result<SuiteResult> {
+    /// Creates the comparison cache directory and ignores it in the VCS, if
+    /// the comparison cache is used.
+    pub fn prepare_compare_cache(&self) -> eyre::Result<()> {
+        if !self.config.compare_cache {
+            return Ok(());
+        }
+
+        let dir = self.project.compare_cache_dir();
+        tytanic_utils::fs::create_dir(&dir, true)
+            .wrap_err_with(|| format!("creating comparison cache directory {dir}"))?;
+
+        if let Some(vcs) = self.project.vcs() {
+            vcs.ignore_directory(self.project.cache_root())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn run(self, reporter: &Reporter) -> eyre::Result<SuiteResult>
+    where
+        F: Sync,
+    {
+        self.run_with(reporter, Self::run_inner)
+    }
+
+    /// Runs the tests of the suite using the given function instead of
+    /// [`Runner::run_inner`], the run is reported and recorded like it is by
+    /// [`Runner::run`].
+    ///
+    /// The function must report and set the result of each test it runs.
+    #[tracing::instrument(skip_all, fields(run_id = %self.result.id()))]
+    pub fn run_with(
+        mut self,
+        reporter: &Reporter,
+        f: impl FnOnce(&mut Self, &Reporter) -> eyre::Result<()>,
+    ) -> eyre::Result<SuiteResult> {
+        self.prepare_compare_cache()?;
         self.result.start();
         reporter.report_start(&self.result)?;
-        let res = self.run_inner(reporter);
+        for test in self.suite.filtered().unit_tests() {
+            if self.config.skipped.contains(test.id()) {
+                reporter.report_test_skipped(test)?;
+            }
+        }
+        let res = f(&mut self, reporter);
+        if self.config.cancellation.load(Ordering::SeqCst) {
+            self.result.set_cancelled();
+        }
         self.result.end();
         reporter.report_end(&self.result)?;
 
+        self.emit(RunEvent::SuiteFinished {
+            run_id: self.result.id(),
+            passed: self.result.passed(),
+            failed: self.result.failed(),
+            skipped: self.result.skipped(),
+            duration: self.result.duration(),
+        });
+
         res?;
 
+        // NOTE(tinger): A dry run didn't update anything, recording its
+        // results would mark the tests it would have updated as passed.
+        if !self.config.action.is_dry_run() {
+            self.record_last_run()?;
+        }
+
         Ok(self.result)
     }
+
+    /// Records the outcome of the tests of this run for test sets like
+    /// `failed()`, tests which were not run keep their previous status.
+    fn record_last_run(&self) -> eyre::Result<()> {
+        let mut last_run = LastRun::read(self.project)
+            .wrap_err("couldn't read the last run")?
+            .unwrap_or_default();
+        last_run.record(&self.result);
+        last_run
+            .write(self.project)
+            .wrap_err("couldn't record the last run")?;
+
+        if let Some(vcs) = self.project.vcs() {
+            vcs.ignore_directory(self.project.cache_root())?;
+        }
+
+        Ok(())
+    }
 }
 
 pub struct UnitTestRunner<'c, 's, 'p, F> {
     project_runner: &'s Runner<'c, 'p, F>,
     test: &'p UnitTest,
     result: TestResult,
+
+    /// The content hashes of the inputs read so far, if they are recorded.
+    inputs: Option<BTreeMap<EcoString, u128>>,
 }
 
 impl<F> UnitTestRunner<'_, '_, '_, F> {
     fn run_inner(&mut self) -> eyre::Result<()> {
         let export = self.project_runner.config.export_ephemeral;
+        let diff_always = self.project_runner.config.export_diff_always;
         let strategy = self.project_runner.config.strategy;
         let origin = self.project_runner.config.origin;
+        let overview = self.project_runner.config.overview;
 
         // TODO(tinger): Don't exit early if there are still exports possible.
 
         match self.project_runner.config.action {
             Action::Run => {
                 let output = self.compile_out_doc()?;
+                let export_pdf =
+                    export && self.project_runner.config.export_format == ArtifactFormat::Pdf;
+
+                if export_pdf {
+                    self.export_out_pdf(&output)?;
+                }
+
                 let output = self.render_out_doc(output)?;
 
-                if export {
+                if export && !export_pdf {
                     self.export_out_doc(&output)?;
                 }
 
@@ -197,43 +518,96 @@ impl<F> UnitTestRunner<'_, '_, '_, F> {
 
                         if export {
                             self.export_ref_doc(&reference)?;
-
-                            let diff = self.render_diff_doc(&output, &reference, origin)?;
-                            self.export_diff_doc(&diff)?;
                         }
 
-                        if let Some(strategy) = strategy
-                            && let Err(err) = self.compare(&output, &reference, strategy)
-                        {
-                            eyre::bail!(err);
+                        if let Some(strategy) = strategy {
+                            let res = self.compare(&output, &reference, strategy);
+
+                            if res.is_ok() && diff_always {
+                                self.record_deviations(&output, &reference, strategy);
+                            }
+
+                            if export {
+                                if res.is_err() || diff_always {
+                                    let diff = self.render_diff_doc(&output, &reference, origin)?;
+                                    self.export_diff_doc(&diff)?;
+                                }
+
+                                if let Some(options) = overview {
+                                    self.export_overview(&output, options)?;
+                                }
+
+                                self.export_aa_doc(&output, &reference, strategy)?;
+                            }
+
+                            res?;
                         }
                     }
                     Kind::Persistent => {
                         let reference = self.load_ref_doc()?;
 
-                        // TODO(tinger): Don't unconditionally export this
-                        // perhaps? On the other hand without comparison we
-                        // don't know whether this is meaningful or not.
-                        if export {
-                            let diff = self.render_diff_doc(&output, &reference, origin)?;
-                            self.export_diff_doc(&diff)?;
-                        }
+                        if let Some(strategy) = strategy {
+                            if let Err(err) =
+                                self.check_reference_ppi(&output, &reference, strategy)
+                            {
+                                return self.update_on_fail(&output, Err(err));
+                            }
+
+                            let res = self.compare(&output, &reference, strategy);
+
+                            if res.is_ok() && diff_always {
+                                self.record_deviations(&output, &reference, strategy);
+                            }
+
+                            if export {
+                                if res.is_err() || diff_always {
+                                    let diff = self.render_diff_doc(&output, &reference, origin)?;
+                                    self.export_diff_doc(&diff)?;
+                                }
 
-                        if let Some(strategy) = strategy
-                            && let Err(err) = self.compare(&output, &reference, strategy)
-                        {
-                            eyre::bail!(err);
+                                if let Some(options) = overview {
+                                    self.export_overview(&output, options)?;
+                                }
+
+                                self.export_aa_doc(&output, &reference, strategy)?;
+                            }
+
+                            self.update_on_fail(&output, res)?;
+                            self.check_reference_freshness()?;
                         }
                     }
                     Kind::CompileOnly => {}
                 }
             }
-            Action::Update { force } => match self.test.kind() {
+            Action::Update {
+                force,
+                only_changed,
+                ref pages,
+                ref since,
+                dry_run,
+            } => match self.test.kind() {
                 Kind::Ephemeral => eyre::bail!("attempted to update ephemeral test"),
                 Kind::Persistent => {
                     let output = self.compile_out_doc()?;
+
+                    if let Some(changed) = since
+                        && !self.is_affected(changed)
+                    {
+                        tracing::debug!(test = ?self.test.id(), "leaving unaffected test untouched");
+                        return Ok(());
+                    }
+
                     let output = self.render_out_doc(output)?;
 
+                    if dry_run {
+                        return self.record_dry_run_update(
+                            &output,
+                            force,
+                            only_changed,
+                            pages.as_ref(),
+                        );
+                    }
+
                     let needs_update = force || {
                         let reference = self.load_ref_doc()?;
                         let strategy = strategy.unwrap_or_default();
@@ -241,18 +615,11 @@ impl<F> UnitTestRunner<'_, '_, '_, F> {
                     };
 
                     if needs_update {
-                        self.test.create_reference_document(
-                            self.project_runner.project,
-                            &output,
-                            self.project_runner
-                                .config
-                                .optimize
-                                .then_some(&*DEFAULT_OPTIMIZE_OPTIONS),
-                        )?;
-
-                        self.result.set_updated(self.project_runner.config.optimize);
+                        self.update_references(&output, only_changed, pages.as_ref())?;
                     }
 
+                    self.write_reference_meta()?;
+
                     if export {
                         let reference = self.load_ref_doc()?;
                         self.export_out_doc(&reference)?;
@@ -268,14 +635,185 @@ impl<F> UnitTestRunner<'_, '_, '_, F> {
         Ok(())
     }
 
-    #[tracing::instrument(skip_all)]
+    /// Whether one of the given changed files lies within the directory of
+    /// this test or was read while compiling it so far, the latter requires
+    /// inputs to be recorded.
+    fn is_affected(&self, changed: &BTreeSet<Utf8PathBuf>) -> bool {
+        let project = self.project_runner.project;
+        let dir = project.unit_test_dir(self.test.id());
+
+        changed.iter().any(|path| {
+            path.starts_with(&dir)
+                || project_file_key(project.root(), path).is_some_and(|key| {
+                    self.inputs
+                        .as_ref()
+                        .is_some_and(|inputs| inputs.contains_key(&key))
+                })
+        })
+    }
+
+    /// Writes the given output as the references of this persistent test,
+    /// either only the given pages, only the changed pages or all of them.
+    fn update_references(
+        &mut self,
+        output: &Document,
+        only_changed: bool,
+        pages: Option<&BTreeSet<usize>>,
+    ) -> eyre::Result<()> {
+        let project_runner = self.project_runner;
+        let project = project_runner.project;
+        let optimize_options = project_runner.config.optimize.as_ref();
+
+        let summary = self.timed(RunStage::Update, |this| -> eyre::Result<_> {
+            Ok(match pages {
+                Some(pages) => this
+                    .test
+                    .update_reference_pages(project, output, pages, optimize_options)
+                    .wrap_err_with(|| {
+                        format!("couldn't update pages of test {}", this.test.id())
+                    })?,
+                None if only_changed => {
+                    this.test
+                        .update_reference_document(project, output, optimize_options)?
+                }
+                None => {
+                    this.test
+                        .create_reference_document(project, output, optimize_options)?;
+
+                    SaveSummary {
+                        updated: output.buffers().len(),
+                        unchanged: 0,
+                    }
+                }
+            })
+        })?;
+
+        self.test
+            .write_reference_ppi(project, self.ppi())
+            .wrap_err_with(|| format!("couldn't write reference PPI of test {}", self.test.id()))?;
+
+        if project_runner.config.compare_text
+            && let Some(text) = output.text()
+        {
+            let text = self.select_text(text);
+            self.test
+                .write_reference_text(project, &text)
+                .wrap_err_with(|| {
+                    format!("couldn't write reference text of test {}", self.test.id())
+                })?;
+        }
+
+        self.result.set_updated(optimize_options.is_some(), summary);
+
+        Ok(())
+    }
+
+    /// Records how many reference pages of this persistent test an update
+    /// would write without writing them, tests which pass comparison are
+    /// left as they are unless the update is forced.
+    ///
+    /// The deviations of failed comparisons are recorded with it.
+    fn record_dry_run_update(
+        &mut self,
+        output: &Document,
+        force: bool,
+        only_changed: bool,
+        pages: Option<&BTreeSet<usize>>,
+    ) -> eyre::Result<()> {
+        // NOTE(tinger): References which can't be loaded, like those of new
+        // tests, would be written entirely.
+        let reference = self.load_ref_doc().ok();
+
+        let failed = match &reference {
+            Some(reference) => {
+                let strategy = self.project_runner.config.strategy.unwrap_or_default();
+                self.compare(output, reference, strategy).is_err()
+            }
+            None => true,
+        };
+
+        if let Stage::FailedComparison(error) = self.result.stage() {
+            let deviations = error.details.iter().map(|(_, diff)| diff.deviations).sum();
+            self.result.set_deviations(deviations);
+        }
+
+        if !failed && !force {
+            return Ok(());
+        }
+
+        let total = output.buffers().len();
+        let updated = match (pages, &reference) {
+            (Some(pages), _) => pages
+                .iter()
+                .filter(|&&page| (1..=total).contains(&page))
+                .count(),
+            (None, Some(reference)) if only_changed => output
+                .buffers()
+                .iter()
+                .enumerate()
+                .filter(|(idx, page)| reference.buffers().get(*idx) != Some(*page))
+                .count(),
+            (None, _) => total,
+        };
+
+        self.result.set_would_update(SaveSummary {
+            updated,
+            unchanged: total - updated,
+        });
+
+        Ok(())
+    }
+
+    /// Writes the reference metadata of this persistent test if it's enabled
+    /// for the project.
+    fn write_reference_meta(&mut self) -> eyre::Result<()> {
+        let project = self.project_runner.project;
+        if project.config().ref_meta {
+            self.test.write_reference_meta(project).wrap_err_with(|| {
+                format!(
+                    "couldn't write reference metadata for test {}",
+                    self.test.id()
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Updates the changed references of this persistent test if it failed
+    /// comparison and the runner is configured to update failing tests,
+    /// otherwise returns the result unchanged.
+    fn update_on_fail(&mut self, output: &Document, res: eyre::Result<()>) -> eyre::Result<()> {
+        if res.is_ok()
+            || !self.project_runner.config.update_on_fail
+            || !matches!(self.result.stage(), Stage::FailedComparison(_))
+        {
+            return res;
+        }
+
+        tracing::debug!(test = ?self.test.id(), "updating references of failed test");
+        self.update_references(output, true, None)?;
+        self.write_reference_meta()
+    }
+
+    /// The result of the stages which ran so far.
+    pub fn result(&self) -> &TestResult {
+        &self.result
+    }
+
+    #[tracing::instrument(skip_all, fields(test = %self.test.id()))]
     pub fn run(mut self) -> eyre::Result<TestResult> {
         self.result.start();
-        self.prepare()?;
+        self.timed(RunStage::Prepare, Self::prepare)?;
         let res = self.run_inner();
         self.cleanup()?;
         self.result.end();
 
+        if let Some(inputs) = &self.inputs {
+            self.result.set_input_hash(self.input_hash(inputs));
+            self.result.set_inputs(inputs.keys().cloned());
+        }
+
         if let Err(err) = res
             && !err.chain().any(|s| s.is::<TestFailure>())
         {
@@ -285,6 +823,37 @@ impl<F> UnitTestRunner<'_, '_, '_, F> {
         Ok(self.result)
     }
 
+    /// Combines the recorded inputs with everything else which affects the
+    /// outcome of this test into a single hash.
+    fn input_hash(&self, inputs: &BTreeMap<EcoString, u128>) -> u128 {
+        let config = &self.project_runner.config;
+
+        // NOTE(tinger): The debug representation of these types is stable
+        // enough for this purpose, it only needs to be consistent across
+        // machines for the same version of tytanic.
+        let config = format!(
+            "{:?}",
+            (
+                self.test.kind(),
+                config.warnings,
+                config.render_options.pixel_per_pt,
+                config.strategy,
+                config.compare_text,
+                config.compare_downscale,
+                config.origin,
+                &config.action,
+                &config.inputs,
+            )
+        );
+
+        hash128(&(
+            env!("CARGO_PKG_VERSION"),
+            &**self.project_runner.providers.fonts().provide_font_book(),
+            config,
+            inputs,
+        ))
+    }
+
     pub fn prepare(&mut self) -> eyre::Result<()> {
         tracing::trace!(test = ?self.test.id(), "clearing temporary directories");
 
@@ -300,6 +869,47 @@ impl<F> UnitTestRunner<'_, '_, '_, F> {
         Ok(())
     }
 
+    /// Runs a stage of this test using [`Runner::timed`] and adds its
+    /// duration to the stage timings of the result.
+    fn timed<T, E>(
+        &mut self,
+        stage: RunStage,
+        f: impl FnOnce(&mut Self) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let project_runner = self.project_runner;
+        let test = self.test;
+        let start = Utc::now();
+        let res = project_runner.timed(test.id(), stage, || f(self));
+        self.result
+            .add_stage_duration(stage, Utc::now().signed_duration_since(start));
+        res
+    }
+
+    /// Marks the references of this test as stale if reference metadata is
+    /// enabled and the stored freshness hash differs from the current one.
+    pub fn check_reference_freshness(&mut self) -> eyre::Result<()> {
+        let project = self.project_runner.project;
+
+        if !project.config().ref_meta {
+            return Ok(());
+        }
+
+        let stale = self.test.are_references_stale(project).wrap_err_with(|| {
+            format!(
+                "couldn't read reference metadata for test {}",
+                self.test.id()
+            )
+        })?;
+
+        // NOTE(tinger): References without metadata were last updated before
+        // it was enabled, we can't tell whether they are stale.
+        if stale == Some(true) {
+            self.result.set_stale_references();
+        }
+
+        Ok(())
+    }
+
     pub fn load_ref_doc(&mut self) -> eyre::Result<Document> {
         tracing::trace!(test = ?self.test.id(), "loading reference document");
 
@@ -307,29 +917,68 @@ impl<F> UnitTestRunner<'_, '_, '_, F> {
             eyre::bail!("attempted to load reference source for non-persistent test");
         }
 
-        self.test
+        let mut reference = self
+            .test
             .load_reference_document(self.project_runner.project)
             .wrap_err_with(|| {
-                format!(
-                    "couldn't load reference document for test {}",
-                    self.test.id()
-                )
-            })
+                let project = self.project_runner.project;
+                if project.config().ref_variants
+                    && !project.unit_test_ref_variant_dir(self.test.id()).exists()
+                {
+                    format!(
+                        "couldn't load reference document for test {}, it has neither references for platform {} nor default references",
+                        self.test.id(),
+                        REFERENCE_PLATFORM,
+                    )
+                } else {
+                    format!(
+                        "couldn't load reference document for test {}",
+                        self.test.id()
+                    )
+                }
+            })?;
+
+        // References are stored cropped to the focus rectangle, they are
+        // masked too in case they were stored before a mask was added. Masks
+        // can't be placed on references which were cropped to their content,
+        // these were already masked when they were stored.
+        let masks = self.focused_masks();
+        if !masks.is_empty() && reference.crops().is_empty() {
+            reference.mask(&masks, self.render_options().pixel_per_pt.get());
+        }
+
+        // Persistent references are not read through the world, so they must
+        // be recorded separately.
+        if let Some(inputs) = &mut self.inputs {
+            for (idx, page) in reference.buffers().iter().enumerate() {
+                inputs.insert(
+                    eco_format!("{}/ref/{}.{PAGE_EXTENSION}", self.test.id(), idx + 1),
+                    hash128(page.data()),
+                );
+            }
+        }
+
+        Ok(reference)
     }
 
+    #[tracing::instrument(skip_all, fields(test = %self.test.id(), stage = "render"))]
     pub fn render_out_doc(&mut self, doc: PagedDocument) -> eyre::Result<Document> {
         tracing::trace!(test = ?self.test.id(), "rendering output document");
 
-        let mut render_options = self.project_runner.config.render_options.clone();
-        for annot in self.test.annotations().iter() {
-            if let Annotation::Ppi(ppi) = annot {
-                render_options.pixel_per_pt = Scalar::new(render::ppi_to_ppp(*ppi));
-            }
+        let render_options = self.render_options();
+
+        let mut doc = self.render_doc(doc, &render_options)?;
+        self.mask_doc(&mut doc, render_options.pixel_per_pt.get());
+        self.focus_doc(&mut doc, render_options.pixel_per_pt.get())?;
+        doc.process(&self.project_runner.project.config().defaults.post_process[..]);
+        if self.project_runner.config.crop {
+            doc.crop();
         }
 
-        Ok(Document::render(doc, &render_options))
+        Ok(doc)
     }
 
+    #[tracing::instrument(skip_all, fields(test = %self.test.id(), stage = "render"))]
     pub fn render_ref_doc(&mut self, doc: PagedDocument) -> eyre::Result<Document> {
         tracing::trace!(test = ?self.test.id(), "rendering reference document");
 
@@ -337,6 +986,64 @@ impl<F> UnitTestRunner<'_, '_, '_, F> {
             eyre::bail!("attempted to render reference for non-ephemeral test");
         }
 
+        let render_options = self.render_options();
+
+        let mut doc = self.render_doc(doc, &render_options)?;
+        self.mask_doc(&mut doc, render_options.pixel_per_pt.get());
+        self.focus_doc(&mut doc, render_options.pixel_per_pt.get())?;
+        doc.process(&self.project_runner.project.config().defaults.post_process[..]);
+        if self.project_runner.config.crop {
+            doc.crop();
+        }
+
+        Ok(doc)
+    }
+
+    /// Renders the selected pages of a compiled document of this test, or all
+    /// of them if it has no page selection.
+    fn render_doc(
+        &self,
+        doc: PagedDocument,
+        render_options: &RenderOptions,
+    ) -> eyre::Result<Document> {
+        match self.selected_pages() {
+            Some(pages) => Document::render_pages(doc, render_options, pages)
+                .wrap_err_with(|| format!("invalid pages for test {}", self.test.id())),
+            None => Ok(Document::render(doc, render_options)),
+        }
+    }
+
+    /// The sorted 1-based page numbers to which rendering and comparison of
+    /// this test are restricted, if any.
+    fn selected_pages(&self) -> Option<&[usize]> {
+        if self.test.kind().is_compile_only() {
+            return None;
+        }
+
+        self.test
+            .annotations()
+            .iter()
+            .find_map(|annot| match annot {
+                Annotation::Pages(pages) => Some(&pages[..]),
+                _ => None,
+            })
+    }
+
+    /// Restricts the text of each page of a document to the selected pages of
+    /// this test.
+    fn select_text(&self, text: Vec<String>) -> Vec<String> {
+        match self.selected_pages() {
+            Some(pages) => pages
+                .iter()
+                .filter_map(|page| text.get(page - 1).cloned())
+                .collect(),
+            None => text,
+        }
+    }
+
+    /// The render options of this test, the pixel per inch annotation takes
+    /// precedence over the configured render options.
+    fn render_options(&self) -> RenderOptions {
         let mut render_options = self.project_runner.config.render_options.clone();
         for annot in self.test.annotations().iter() {
             if let Annotation::Ppi(ppi) = annot {
@@ -344,9 +1051,101 @@ impl<F> UnitTestRunner<'_, '_, '_, F> {
             }
         }
 
-        Ok(Document::render(doc, &render_options))
+        render_options
+    }
+
+    /// The PPI at which the pages of this test are rendered.
+    fn ppi(&self) -> f64 {
+        self.test
+            .annotations()
+            .iter()
+            .rev()
+            .find_map(|annot| match annot {
+                Annotation::Ppi(ppi) => Some(*ppi),
+                _ => None,
+            })
+            .unwrap_or_else(|| {
+                // NOTE(tinger): The configured PPI is only stored as a
+                // pixel-per-pt ratio, rounding undoes the error of converting
+                // it back and forth.
+                let ppi = render::ppp_to_ppi(
+                    self.project_runner.config.render_options.pixel_per_pt.get(),
+                );
+                (ppi * 1e6).round() / 1e6
+            })
+    }
+
+    /// The mask rectangles of this test.
+    fn masks(&self) -> Vec<Mask> {
+        self.test
+            .annotations()
+            .iter()
+            .filter_map(|annot| match annot {
+                Annotation::Mask(mask) => Some(*mask),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The mask rectangles of this test relative to its focus rectangle, i.e.
+    /// on pages which were already cropped to it.
+    fn focused_masks(&self) -> Vec<Mask> {
+        let focus = self
+            .test
+            .annotations()
+            .iter()
+            .find_map(|annot| match annot {
+                Annotation::Focus(focus) => Some(*focus),
+                _ => None,
+            });
+
+        self.masks()
+            .into_iter()
+            .map(|mask| match focus {
+                Some(focus) => mask.relative_to(focus),
+                None => mask,
+            })
+            .collect()
+    }
+
+    /// Clears the regions of the pages of a rendered document which are
+    /// masked by this test, this happens before cropping them to the focus
+    /// rectangle, such that masks are relative to the whole page.
+    fn mask_doc(&self, doc: &mut Document, pixel_per_pt: f64) {
+        if self.test.kind().is_compile_only() {
+            return;
+        }
+
+        let masks = self.masks();
+        if !masks.is_empty() {
+            doc.mask(&masks, pixel_per_pt);
+        }
+    }
+
+    /// Crops the pages of a rendered document to the focus rectangle of this
+    /// test, if it has one.
+    fn focus_doc(&self, doc: &mut Document, pixel_per_pt: f64) -> eyre::Result<()> {
+        if self.test.kind().is_compile_only() {
+            return Ok(());
+        }
+
+        let Some(focus) = self
+            .test
+            .annotations()
+            .iter()
+            .find_map(|annot| match annot {
+                Annotation::Focus(focus) => Some(*focus),
+                _ => None,
+            })
+        else {
+            return Ok(());
+        };
+
+        doc.focus(focus, pixel_per_pt)
+            .wrap_err_with(|| format!("invalid focus for test {}", self.test.id()))
     }
 
+    #[tracing::instrument(skip_all, fields(test = %self.test.id(), stage = "render"))]
     pub fn render_diff_doc(
         &mut self,
         output: &Document,
@@ -359,21 +1158,42 @@ impl<F> UnitTestRunner<'_, '_, '_, F> {
             eyre::bail!("attempted to render difference document for compile-only test");
         }
 
+        let mut diff_origin = None;
         for annot in self.test.annotations().iter() {
             match annot {
-                Annotation::Dir(Direction::Ltr) => origin = Origin::TopLeft,
-                Annotation::Dir(Direction::Rtl) => origin = Origin::TopRight,
+                Annotation::Dir(dir) => origin = Origin::from(*dir),
+                Annotation::DiffOrigin(set) => diff_origin = Some(*set),
                 _ => {}
             }
         }
 
-        Ok(Document::render_diff(reference, output, origin))
+        let output = self.downscaled(output);
+        let reference = self.downscaled(reference);
+        let mut diff = Document::render_diff(
+            &reference,
+            &output,
+            diff_origin.unwrap_or(origin),
+            self.project_runner.config.diff_colors,
+        );
+
+        // Show which regions were excluded from the comparison, the diff has
+        // the resolution of the downscaled pages.
+        let masks = self.focused_masks();
+        if !masks.is_empty() {
+            let pixel_per_pt = self.render_options().pixel_per_pt.get()
+                / self.project_runner.config.compare_downscale.max(1) as f64;
+            diff.highlight_masks(&masks, pixel_per_pt);
+        }
+
+        Ok(diff)
     }
 
     pub fn compile_out_doc(&mut self) -> eyre::Result<PagedDocument> {
         tracing::trace!(test = ?self.test.id(), "compiling output document");
 
-        self.compile_inner(false)
+        self.timed(RunStage::Compilation { reference: false }, |this| {
+            this.compile_inner(false)
+        })
     }
 
     pub fn compile_ref_doc(&mut self) -> eyre::Result<PagedDocument> {
@@ -383,37 +1203,122 @@ impl<F> UnitTestRunner<'_, '_, '_, F> {
             eyre::bail!("attempted to compile reference for compile-only test");
         }
 
-        self.compile_inner(true)
+        self.timed(RunStage::Compilation { reference: true }, |this| {
+            this.compile_inner(true)
+        })
     }
 
     fn compile_inner(&mut self, is_reference: bool) -> eyre::Result<PagedDocument> {
-        // Assemble additional inputs based on test annotations.
-        let inputs = self
+        // Assemble additional inputs from the project defaults, the test
+        // annotations and the command line, in increasing precedence. These
+        // are sorted by key such that the library hash is stable across runs.
+        let mut inputs = self
+            .project_runner
+            .project
+            .config()
+            .defaults
+            .inputs
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect::<BTreeMap<_, _>>();
+        for annot in self.test.annotations() {
+            if let Annotation::Input { key, value } = annot {
+                inputs.insert(key, value);
+            }
+        }
+        for (key, value) in &self.project_runner.config.inputs {
+            inputs.insert(key, value);
+        }
+
+        let library = augmented_library_provider_with_inputs(
+            inputs
+                .into_iter()
+                .map(|(key, value)| (Str::from(key), Value::Str(Str::from(value))))
+                .collect::<Dict>(),
+        );
+
+        // Remap data file reads if the test has a data root.
+        let data_root_files = self
+            .test
+            .data_root(self.project_runner.project)?
+            .map(|root| {
+                DataRootFileProvider::new(self.project_runner.providers.project_files(), root)
+            });
+
+        let files = match &data_root_files {
+            Some(files) => files as &dyn ProvideFile,
+            None => self.project_runner.providers.project_files(),
+        };
+
+        // Record the files read during compilation if requested.
+        let recording_files = self
+            .inputs
+            .is_some()
+            .then(|| RecordingFileProvider::new(files));
+
+        let files = match &recording_files {
+            Some(files) => files as &dyn ProvideFile,
+            None => files,
+        };
+
+        let world = self.project_runner.providers.unit_world(
+            self.project_runner.project,
+            self.test,
+            is_reference,
+            Some(&*library),
+            Some(files),
+        );
+
+        // Only the test document is measured, the budget doesn't apply to the
+        // reference document.
+        let max_memory = self
             .test
             .annotations()
             .iter()
-            .filter_map(|kind| match kind {
-                Annotation::Input { key, value } => Some((
-                    Str::from(key.as_str()),
-                    Value::Str(Str::from(value.as_str())),
-                )),
+            .find_map(|annot| match annot {
+                Annotation::MaxMemory(max) => Some(*max),
                 _ => None,
             })
-            .collect::<Dict>();
-        let library = augmented_library_provider_with_inputs(inputs);
+            .filter(|_| !is_reference);
+
+        let warnings = self.project_runner.config.warnings;
+        let (Warned { output, warnings }, peak_memory) = match max_memory {
+            Some(_) => {
+                let (warned, peak) =
+                    memory::measure_detached(|| compile::compile(&world, warnings));
+                (warned, Some(peak))
+            }
+            None => (compile::compile(&world, warnings), None),
+        };
 
-        let Warned { output, warnings } = compile::compile(
-            &self.project_runner.providers.unit_world(
-                self.project_runner.project,
-                self.test,
-                is_reference,
-                Some(&*library),
-            ),
-            self.project_runner.config.warnings,
-        );
+        if let Some(recording_files) = recording_files
+            && let Some(inputs) = &mut self.inputs
+        {
+            inputs.extend(recording_files.into_files());
+        }
 
         self.result.set_warnings(warnings);
 
+        if let Some(peak) = peak_memory {
+            self.result.set_peak_memory(peak);
+        }
+
+        // A test exceeding its memory budget is reported like a compilation
+        // failure, the document is discarded.
+        let output = match (output, max_memory, peak_memory) {
+            (Ok(_), Some(max), Some(peak)) if peak > max => Err(compile::Error(eco_vec![
+                SourceDiagnostic::error(
+                    Span::detached(),
+                    eco_format!(
+                        "compilation allocated up to {peak} bytes, \
+                        but at most {max} bytes are allowed",
+                    ),
+                )
+                .with_hint("the budget is set by the max-memory annotation")
+            ])),
+            (output, _, _) => output,
+        };
+
         let doc = match output {
             Ok(doc) => {
                 self.result.set_passed_compilation();
@@ -462,6 +1367,35 @@ impl<F> UnitTestRunner<'_, '_, '_, F> {
         Ok(())
     }
 
+    /// Exports the laid out output document as a single PDF into the output
+    /// directory.
+    pub fn export_out_pdf(&mut self, output: &PagedDocument) -> eyre::Result<()> {
+        tracing::trace!(test = ?self.test.id(), "saving output document as pdf");
+
+        let buffer = typst_pdf::pdf(output, &PdfOptions::default()).map_err(|errors| {
+            eyre::eyre!(
+                "failed to export output document as pdf: {}",
+                errors
+                    .iter()
+                    .map(|diag| diag.message.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })?;
+
+        let _permit = acquire_write_permit();
+        fs::write(
+            self.project_runner
+                .project
+                .unit_test_out_dir(self.test.id())
+                .join("output")
+                .with_extension("pdf"),
+            buffer,
+        )?;
+
+        Ok(())
+    }
+
     pub fn export_diff_doc(&mut self, doc: &Document) -> eyre::Result<()> {
         tracing::trace!(test = ?self.test.id(), "saving difference document");
 
@@ -479,39 +1413,252 @@ impl<F> UnitTestRunner<'_, '_, '_, F> {
         Ok(())
     }
 
-    pub fn compare(
+    /// Exports which deviating pixels were classified as anti-aliasing into
+    /// the `aa` directory within the diff directory, if the strategy ignores
+    /// anti-aliasing.
+    pub fn export_aa_doc(
         &mut self,
         output: &Document,
         reference: &Document,
         strategy: Strategy,
     ) -> eyre::Result<()> {
-        tracing::trace!(test = ?self.test.id(), "comparing");
+        let Strategy::Simple {
+            max_delta,
+            ignore_aa: true,
+            ..
+        } = self.test_strategy(strategy)
+        else {
+            return Ok(());
+        };
+
+        tracing::trace!(test = ?self.test.id(), "saving anti-aliasing document");
 
         if self.test.kind().is_compile_only() {
-            eyre::bail!("attempted to compare compile-only test");
+            eyre::bail!("attempted to save anti-aliasing document for compile-only test");
         }
 
-        let Strategy::Simple {
-            mut max_delta,
-            mut max_deviation,
-        } = strategy;
+        let dir = self
+            .project_runner
+            .project
+            .unit_test_diff_dir(self.test.id())
+            .join("aa");
+        tytanic_utils::fs::create_dir(&dir, true)?;
+
+        let output = self.downscaled(output);
+        let reference = self.downscaled(reference);
+        Document::render_antialiasing(&output, &reference, max_delta).save(dir, None)?;
+
+        Ok(())
+    }
 
+    pub fn export_overview(
+        &mut self,
+        output: &Document,
+        options: OverviewOptions,
+    ) -> eyre::Result<()> {
+        tracing::trace!(test = ?self.test.id(), "saving page overview");
+
+        if self.test.kind().is_compile_only() {
+            eyre::bail!("attempted to save page overview for compile-only test");
+        }
+
+        let (failed, reference_len) = match self.result.stage() {
+            Stage::FailedComparison(err) => (
+                err.pages.iter().map(|(idx, _)| *idx).collect(),
+                err.reference,
+            ),
+            _ => (vec![], output.buffers().len()),
+        };
+
+        // NOTE(tinger): Failed pages are reported by their index in the whole
+        // document, the output only contains the selected pages.
+        let selected = self.selected_pages();
+        let pages = output.buffers().iter().enumerate().map(|(idx, page)| {
+            let page_idx = selected
+                .and_then(|pages| pages.get(idx))
+                .map_or(idx, |page| page - 1);
+            (page, idx < reference_len && !failed.contains(&page_idx))
+        });
+
+        let Some(overview) = render::page_overview(pages, options) else {
+            return Ok(());
+        };
+
+        let _permit = acquire_write_permit();
+        overview.save_png(
+            self.project_runner
+                .project
+                .unit_test_diff_dir(self.test.id())
+                .join("overview")
+                .with_extension(PAGE_EXTENSION),
+        )?;
+
+        Ok(())
+    }
+
+    /// Downscales a rendered document for comparison if the runner is
+    /// configured to, see [`RunnerConfig::compare_downscale`].
+    fn downscaled<'d>(&self, doc: &'d Document) -> Cow<'d, Document> {
+        match self.project_runner.config.compare_downscale {
+            factor @ 2.. => Cow::Owned(doc.downscale(factor)),
+            _ => Cow::Borrowed(doc),
+        }
+    }
+
+    /// Applies the comparison annotations of this test to the given strategy.
+    ///
+    /// The pixel tolerances only apply to [`Strategy::Simple`].
+    fn test_strategy(&self, mut strategy: Strategy) -> Strategy {
         for annot in self.test.annotations().iter() {
+            match (annot, &mut strategy) {
+                (Annotation::MaxDelta(set), Strategy::Simple { max_delta, .. }) => {
+                    *max_delta = *set;
+                }
+                (Annotation::MaxDeviations(set), Strategy::Simple { max_deviation, .. }) => {
+                    *max_deviation = *set;
+                }
+                (Annotation::MaxFailingPages(set), strategy) => {
+                    *strategy = strategy.with_max_failing_pages(*set);
+                }
+                _ => {}
+            }
+        }
+
+        strategy
+    }
+
+    /// Applies the tolerance and per-page annotations of this test to the
+    /// per-page tolerances of the project, such that the annotations of the
+    /// test take precedence over them.
+    fn test_page_compare(&self) -> PageCompare {
+        let mut tolerance = PageTolerance::default();
+        for annot in self.test.annotations() {
             match annot {
-                Annotation::MaxDelta(set) => max_delta = *set,
-                Annotation::MaxDeviations(set) => max_deviation = *set,
+                Annotation::MaxDelta(max_delta) => tolerance.max_delta = Some(*max_delta),
+                Annotation::MaxDeviations(max_deviations) => {
+                    tolerance.max_deviations = Some(*max_deviations)
+                }
                 _ => {}
             }
         }
 
-        if let Err(error) = Document::compare(
-            output,
-            reference,
-            Strategy::Simple {
-                max_delta,
-                max_deviation,
-            },
-        ) {
+        let mut page_compare = self
+            .project_runner
+            .project
+            .config()
+            .defaults
+            .page_compare
+            .clone()
+            .override_all(tolerance);
+
+        for annot in self.test.annotations().iter() {
+            if let Annotation::Page { page, tolerance } = annot {
+                page_compare = page_compare.override_page(*page, *tolerance);
+            }
+        }
+
+        match self.selected_pages() {
+            Some(pages) => page_compare.select(pages),
+            None => page_compare,
+        }
+    }
+
+    pub fn compare(
+        &mut self,
+        output: &Document,
+        reference: &Document,
+        strategy: Strategy,
+    ) -> eyre::Result<()> {
+        tracing::trace!(test = ?self.test.id(), "comparing");
+
+        if self.test.kind().is_compile_only() {
+            eyre::bail!("attempted to compare compile-only test");
+        }
+
+        let strategy = self.test_strategy(strategy);
+
+        if self.project_runner.config.compare_text {
+            return self.compare_text(output, reference, strategy);
+        }
+
+        let page_compare = &self.test_page_compare();
+        let output = self.downscaled(output);
+        let reference = self.downscaled(reference);
+
+        // NOTE(tinger): The cache key only covers the pixels of the pages, a
+        // cached comparison must not hide that their content moved.
+        let cache = (self.project_runner.config.compare_cache
+            && output.crops() == reference.crops())
+        .then(|| {
+            (
+                CompareCache::new(self.project_runner.project.compare_cache_dir()),
+                CompareCache::key(
+                    output.buffers(),
+                    reference.buffers(),
+                    strategy,
+                    page_compare,
+                ),
+            )
+        });
+
+        if let Some((cache, key)) = &cache
+            && cache.contains(*key)?
+        {
+            tracing::trace!(test = ?self.test.id(), "found cached comparison");
+            self.result.set_passed_comparison();
+            return Ok(());
+        }
+
+        if let Err(error) = self.timed(RunStage::Comparison, |_| {
+            Document::compare_pages(&output, &reference, strategy, page_compare)
+        }) {
+            let error = self.select_error(error);
+            self.result.set_failed_comparison(error);
+            eyre::bail!(TestFailure);
+        }
+
+        if let Some((cache, key)) = &cache {
+            cache.insert(*key)?;
+        }
+
+        self.result.set_passed_comparison();
+
+        Ok(())
+    }
+
+    /// Compares the text of the output and reference pages, the reference
+    /// text of persistent tests is read from their reference directory.
+    fn compare_text(
+        &mut self,
+        output: &Document,
+        reference: &Document,
+        strategy: Strategy,
+    ) -> eyre::Result<()> {
+        let outputs = output
+            .text()
+            .map(|text| self.select_text(text))
+            .ok_or_else(|| eyre::eyre!("output of test {} was not compiled", self.test.id()))?;
+
+        let references = match reference.text() {
+            Some(text) => self.select_text(text),
+            None => self
+                .test
+                .read_reference_text(self.project_runner.project)
+                .wrap_err_with(|| {
+                    format!("couldn't read reference text of test {}", self.test.id())
+                })?
+                .ok_or_else(|| {
+                    eyre::eyre!(
+                        "test {} has no reference text, update it to compare its text",
+                        self.test.id()
+                    )
+                })?,
+        };
+
+        if let Err(error) = self.timed(RunStage::Comparison, |_| {
+            compare::text(&outputs, &references, strategy.max_failing_pages())
+        }) {
+            let error = self.select_error(error);
             self.result.set_failed_comparison(error);
             eyre::bail!(TestFailure);
         }
@@ -520,6 +1667,92 @@ impl<F> UnitTestRunner<'_, '_, '_, F> {
 
         Ok(())
     }
+
+    /// Maps the failed pages of a comparison of the selected pages of this
+    /// test back to their indices in the whole document.
+    fn select_error(&self, error: compare::Error) -> compare::Error {
+        match self.selected_pages() {
+            Some(pages) => error.select(pages),
+            None => error,
+        }
+    }
+
+    /// Fails the comparison if the persistent references of this test were
+    /// rendered at a different PPI than its output, their pages can't be
+    /// meaningfully compared.
+    pub fn check_reference_ppi(
+        &mut self,
+        output: &Document,
+        reference: &Document,
+        strategy: Strategy,
+    ) -> eyre::Result<()> {
+        // NOTE(tinger): The text of pages doesn't depend on the resolution
+        // they were rendered at.
+        if self.project_runner.config.compare_text {
+            return Ok(());
+        }
+
+        let output_ppi = self.ppi();
+        let reference_ppi = self
+            .test
+            .read_reference_ppi(self.project_runner.project)
+            .wrap_err_with(|| format!("couldn't read reference PPI of test {}", self.test.id()))?;
+
+        if output_ppi == reference_ppi {
+            return Ok(());
+        }
+
+        let (outputs, references) = (output.buffers().len(), reference.buffers().len());
+        self.result.set_failed_comparison(compare::Error {
+            output: outputs,
+            reference: references,
+            pages: (0..Ord::min(outputs, references))
+                .map(|idx| {
+                    (
+                        idx,
+                        compare::PageError::Ppi {
+                            output: output_ppi,
+                            reference: reference_ppi,
+                        },
+                    )
+                })
+                .collect(),
+            max_failing_pages: self.test_strategy(strategy).max_failing_pages(),
+            details: vec![],
+        });
+
+        eyre::bail!(TestFailure);
+    }
+
+    /// Records the number of deviating pixels of all pages of equal size in
+    /// the result, this is used to show how close a passing test is to
+    /// failing.
+    pub fn record_deviations(
+        &mut self,
+        output: &Document,
+        reference: &Document,
+        strategy: Strategy,
+    ) {
+        let strategy = self.test_strategy(strategy);
+        let page_compare = self.test_page_compare();
+        let output = self.downscaled(output);
+        let reference = self.downscaled(reference);
+
+        let deviations = iter::zip(output.buffers(), reference.buffers())
+            .enumerate()
+            .filter_map(|(idx, (a, b))| {
+                let max_delta = match page_compare.resolve(idx, strategy) {
+                    Strategy::Simple { max_delta, .. } => max_delta,
+                    Strategy::Ssim { .. } => 0,
+                };
+
+                compare::page_detailed(a, b, max_delta).ok()
+            })
+            .map(|diff| diff.deviations)
+            .sum();
+
+        self.result.set_deviations(deviations);
+    }
 }
 
 pub struct TemplateTestRunner<'c, 's, 'p, F> {
@@ -546,6 +1779,7 @@ impl<F> TemplateTestRunner<'_, '_, '_, F> {
         Ok(())
     }
 
+    #[tracing::instrument(skip_all, fields(test = %self.test.id()))]
     pub fn run(mut self) -> eyre::Result<TestResult> {
         self.result.start();
         self.prepare()?;
@@ -571,6 +1805,18 @@ impl<F> TemplateTestRunner<'_, '_, '_, F> {
     }
 
     pub fn compile_template(&mut self) -> eyre::Result<PagedDocument> {
+        let project_runner = self.project_runner;
+        let test = self.test;
+        let stage = RunStage::Compilation { reference: false };
+
+        let start = Utc::now();
+        let res = project_runner.timed(test.id(), stage, || self.compile_template_inner());
+        self.result
+            .add_stage_duration(stage, Utc::now().signed_duration_since(start));
+        res
+    }
+
+    fn compile_template_inner(&mut self) -> eyre::Result<PagedDocument> {
         let Warned { output, warnings } = compile::compile(
             &self
                 .project_runner
@@ -0,0 +1,323 @@
+//! Serialization of test results as a [SARIF 2.1.0] log.
+//!
+//! Each compilation diagnostic of a failed test becomes a result located at
+//! its span, each failed comparison becomes a result located at the test
+//! script with the number of deviations as a property.
+//!
+//! [SARIF 2.1.0]: https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+
+use camino::Utf8Path;
+use serde::Serialize;
+use typst::World;
+use typst::WorldExt;
+use typst::diag::SourceDiagnostic;
+use typst::syntax::VirtualRoot;
+use tytanic_core::Project;
+use tytanic_core::suite::FilteredSuite;
+use tytanic_core::suite::SuiteResult;
+use tytanic_core::test::Stage;
+use tytanic_core::test::Test;
+use tytanic_core::test::TestResult;
+use tytanic_utils::fmt::Term;
+
+use crate::world::Providers;
+
+/// The JSON schema of SARIF 2.1.0 logs.
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// The id of the base URI which artifact locations are relative to, this is
+/// the project root.
+const URI_BASE_ID: &str = "PROJECTROOT";
+
+/// The rule of results for compilation diagnostics.
+const RULE_COMPILATION: &str = "compilation-failure";
+
+/// The rule of results for failed comparisons.
+const RULE_COMPARISON: &str = "comparison-failure";
+
+/// Writes the failures of the given run as a SARIF log to the given path.
+pub fn write<F>(
+    path: &Path,
+    project: &Project,
+    providers: &Providers,
+    suite: &FilteredSuite<F>,
+    result: &SuiteResult,
+) -> io::Result<()> {
+    let mut results = vec![];
+
+    for (id, test_result) in result.results() {
+        if let Some(test) = suite.matched().get(id) {
+            test_results(project, providers, test, test_result, &mut results);
+        }
+    }
+
+    let log = Log {
+        schema: SCHEMA,
+        version: "2.1.0",
+        runs: vec![Run {
+            tool: Tool {
+                driver: Driver {
+                    name: "tytanic",
+                    version: env!("CARGO_PKG_VERSION"),
+                    information_uri: env!("CARGO_PKG_REPOSITORY"),
+                    rules: vec![
+                        Rule {
+                            id: RULE_COMPILATION,
+                            short_description: Message {
+                                text: "A test or its reference failed to compile".into(),
+                            },
+                        },
+                        Rule {
+                            id: RULE_COMPARISON,
+                            short_description: Message {
+                                text: "A test did not match its reference".into(),
+                            },
+                        },
+                    ],
+                },
+            },
+            original_uri_base_ids: [(
+                URI_BASE_ID,
+                ArtifactLocation {
+                    uri: format!("file://{}/", project.root()),
+                    uri_base_id: None,
+                },
+            )]
+            .into_iter()
+            .collect(),
+            results,
+        }],
+    };
+
+    let mut w = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut w, &log)?;
+    writeln!(w)?;
+    w.flush()
+}
+
+/// Appends the SARIF results of a single failed test.
+fn test_results(
+    project: &Project,
+    providers: &Providers,
+    test: &Test,
+    result: &TestResult,
+    results: &mut Vec<SarifResult>,
+) {
+    let id = test.id().as_str();
+
+    match result.stage() {
+        Stage::FailedCompilation { error, reference } => {
+            let world = match test {
+                Test::Unit(test) => providers.unit_world(project, test, *reference, None, None),
+                Test::Template(test) => providers.template_world(project, test),
+            };
+
+            for diag in &error.0 {
+                results.push(SarifResult {
+                    rule_id: RULE_COMPILATION,
+                    level: "error",
+                    message: Message {
+                        text: format!(
+                            "Compilation of {} failed: {}",
+                            if *reference { "reference" } else { "test" },
+                            diag.message,
+                        ),
+                    },
+                    locations: diagnostic_location(&world, diag).into_iter().collect(),
+                    properties: Properties {
+                        test_id: id.into(),
+                        deviations: None,
+                        failed_pages: None,
+                    },
+                });
+            }
+        }
+        Stage::FailedComparison(error) => {
+            let mut text = format!("Comparison of {id} failed");
+            if error.output != error.reference {
+                text.push_str(&format!(
+                    ": expected {} {}, got {} {}",
+                    error.reference,
+                    Term::simple("page").with(error.reference),
+                    error.output,
+                    Term::simple("page").with(error.output),
+                ));
+            } else {
+                for (p, e) in &error.pages {
+                    text.push_str(&format!("\npage {}: {e}", p + 1));
+                }
+            }
+
+            let script = match test {
+                Test::Unit(test) => Some(project.unit_test_script(test.id())),
+                Test::Template(_) => project.template_entrypoint(),
+            };
+
+            results.push(SarifResult {
+                rule_id: RULE_COMPARISON,
+                level: "error",
+                message: Message { text },
+                locations: script
+                    .and_then(|script| {
+                        Some(Location {
+                            physical_location: PhysicalLocation {
+                                artifact_location: artifact_location(
+                                    script.strip_prefix(project.root()).ok()?,
+                                ),
+                                region: None,
+                            },
+                        })
+                    })
+                    .into_iter()
+                    .collect(),
+                properties: Properties {
+                    test_id: id.into(),
+                    deviations: Some(error.details.iter().map(|(_, diff)| diff.deviations).sum()),
+                    failed_pages: Some(error.pages.len()),
+                },
+            });
+        }
+        Stage::Skipped
+        | Stage::Cancelled
+        | Stage::Filtered
+        | Stage::PassedCompilation
+        | Stage::PassedComparison
+        | Stage::Updated { .. } => {}
+    }
+}
+
+/// Resolves the location of a diagnostic, diagnostics in files outside of the
+/// project, like those in packages, are not located.
+fn diagnostic_location(world: &dyn World, diag: &SourceDiagnostic) -> Option<Location> {
+    let id = diag.span.id()?;
+    if !matches!(id.root(), VirtualRoot::Project) {
+        return None;
+    }
+
+    let source = world.source(id).ok()?;
+    let range = world.range(diag.span)?;
+    let lines = source.lines();
+
+    Some(Location {
+        physical_location: PhysicalLocation {
+            artifact_location: artifact_location(Utf8Path::new(id.vpath().get_without_slash())),
+            region: Some(Region {
+                start_line: lines.byte_to_line(range.start)? + 1,
+                start_column: lines.byte_to_column(range.start)? + 1,
+            }),
+        },
+    })
+}
+
+/// The location of a file relative to the project root.
+fn artifact_location(path: &Utf8Path) -> ArtifactLocation {
+    ArtifactLocation {
+        uri: path
+            .components()
+            .map(|component| component.as_str())
+            .collect::<Vec<_>>()
+            .join("/"),
+        uri_base_id: Some(URI_BASE_ID),
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Log {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<Run>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Run {
+    tool: Tool,
+    original_uri_base_ids: BTreeMap<&'static str, ArtifactLocation>,
+    results: Vec<SarifResult>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Driver {
+    name: &'static str,
+    version: &'static str,
+    information_uri: &'static str,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+    id: &'static str,
+    short_description: Message,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: &'static str,
+    level: &'static str,
+    message: Message,
+    locations: Vec<Location>,
+    properties: Properties,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Properties {
+    test_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deviations: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    failed_pages: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Message {
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<Region>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactLocation {
+    uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uri_base_id: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: usize,
+    start_column: usize,
+}
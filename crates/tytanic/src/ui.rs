@@ -11,6 +11,7 @@ use std::io::StdinLock;
 use std::io::Write;
 
 use color_eyre::eyre;
+use ecow::EcoString;
 use termcolor::Color;
 use termcolor::ColorChoice;
 use termcolor::ColorSpec;
@@ -20,6 +21,8 @@ use termcolor::StandardStreamLock;
 use termcolor::WriteColor;
 use tytanic_core::test::Id;
 
+use crate::json::CliWarningJson;
+
 #[macro_export]
 macro_rules! cwrite {
     ($ctor:ident($dst:expr $(, $($arg1:tt)*)?), $($arg2:tt)*) => {{
@@ -66,6 +69,41 @@ pub struct Ui {
 
     /// The unlocked stderr stream.
     stderr: StandardStream,
+
+    /// How warnings about the usage of the CLI are emitted.
+    warning_format: WarningFormat,
+}
+
+/// How warnings about the usage of the CLI are emitted, see
+/// [`Ui::cli_warning`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WarningFormat {
+    /// Write human readable warnings and hints.
+    #[default]
+    Human,
+
+    /// Write one JSON object per warning and line.
+    Json,
+
+    /// Don't write warnings at all.
+    Quiet,
+}
+
+/// A warning about the usage of the CLI, such as the use of a deprecated sub
+/// command alias.
+///
+/// Text within backticks in the message and hint is highlighted in human
+/// readable output and written without the backticks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliWarning {
+    /// A stable identifier of the kind of this warning.
+    pub code: &'static str,
+
+    /// The warning message.
+    pub message: EcoString,
+
+    /// An optional hint on how to avoid this warning.
+    pub hint: Option<EcoString>,
 }
 
 /// Returns whether or not a given output stream is connected to a terminal.
@@ -86,8 +124,15 @@ impl Ui {
             stdin: io::stdin(),
             stdout: StandardStream::stdout(check_terminal(io::stdout(), out)),
             stderr: StandardStream::stderr(check_terminal(io::stderr(), err)),
+            warning_format: WarningFormat::Human,
         }
     }
+
+    /// Sets the format used for warnings about the usage of the CLI.
+    pub fn with_warning_format(mut self, format: WarningFormat) -> Self {
+        self.warning_format = format;
+        self
+    }
 }
 
 impl Ui {
@@ -134,6 +179,32 @@ impl Ui {
         hint(self.stderr())
     }
 
+    /// Emits a warning about the usage of the CLI in the configured format.
+    pub fn cli_warning(&self, warning: &CliWarning) -> io::Result<()> {
+        match self.warning_format {
+            WarningFormat::Human => {
+                let mut w = self.warn()?;
+                write_highlighted(&mut w, &warning.message)?;
+                writeln!(w)?;
+                drop(w);
+
+                if let Some(hint) = &warning.hint {
+                    let mut w = self.hint()?;
+                    write_highlighted(&mut w, hint)?;
+                    writeln!(w)?;
+                }
+            }
+            WarningFormat::Json => {
+                let mut w = self.stderr();
+                serde_json::to_writer(&mut w, &CliWarningJson::new(warning))?;
+                writeln!(w)?;
+            }
+            WarningFormat::Quiet => {}
+        }
+
+        Ok(())
+    }
+
     /// Prompts the user for input with the given prompt on stderr.
     pub fn prompt_with(
         &self,
@@ -322,6 +393,19 @@ pub fn hint<W: WriteColor>(w: W) -> io::Result<Indented<W>> {
     annotated(w, "hint:", Color::Cyan, None)
 }
 
+/// Writes the given text, highlighting the parts within backticks.
+fn write_highlighted<W: WriteColor>(mut w: W, text: &str) -> io::Result<()> {
+    for (idx, part) in text.split('`').enumerate() {
+        if idx % 2 == 1 {
+            cwrite!(colored(w, Color::Cyan), "{part}")?;
+        } else {
+            write!(w, "{part}")?;
+        }
+    }
+
+    Ok(())
+}
+
 /// Write a test id.
 pub fn write_test_id(mut w: &mut dyn WriteColor, id: &Id) -> io::Result<()> {
     if !id.module().is_empty() {
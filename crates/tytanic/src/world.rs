@@ -5,6 +5,7 @@
 
 // TODO(tinger): Upstream this to typst-kit.
 
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 use chrono::Datelike;
@@ -18,6 +19,8 @@ use typst::foundations::Bytes;
 use typst::foundations::Datetime;
 use typst::foundations::Dict;
 use typst::foundations::Duration;
+use typst::foundations::Str;
+use typst::foundations::Value;
 use typst::syntax::FileId;
 use typst::text::Font;
 use typst::text::FontBook;
@@ -36,10 +39,13 @@ use tytanic_core::TemplateTest;
 use tytanic_core::UnitTest;
 use tytanic_core::library::augmented_default_library;
 use tytanic_core::library::augmented_library;
+use tytanic_core::test::Annotation;
 use tytanic_core::world_builder::ComposedWorld;
 use tytanic_core::world_builder::ProvideFile;
 use tytanic_core::world_builder::ProvideFont;
 use tytanic_core::world_builder::file::FilesystemFileProvider;
+use tytanic_core::world_builder::lock::LockedFileProvider;
+use tytanic_core::world_builder::lock::Lockfile;
 
 use crate::cli::commands::CompileOptions;
 use crate::cli::commands::FontOptions;
@@ -58,17 +64,41 @@ fn package_storage(package_opts: &PackageOptions) -> SystemPackages {
     SystemPackages::new(downloader)
 }
 
+/// The package overrides given on the command line, later overrides of the
+/// same package take precedence.
+fn package_overrides(package_opts: &PackageOptions) -> impl Iterator<Item = (PackageSpec, FsRoot)> {
+    package_opts
+        .package_override
+        .iter()
+        .map(|(spec, dir)| (spec.clone(), FsRoot::new(dir.clone())))
+}
+
 /// A file provider which is rooted at a project's root and provides access to
 /// all files in that project as well as access to packages on demand.
-#[tracing::instrument(skip(project))]
+///
+/// Packages which are overridden on the command line are resolved to their
+/// local directories instead. If a lockfile is given, all other package files
+/// are verified against it.
+#[tracing::instrument(skip(project, lockfile))]
 pub fn project_file_provider(
     project: &Project,
     package_opts: &PackageOptions,
+    lockfile: Option<Lockfile>,
 ) -> Box<dyn ProvideFile> {
-    Box::new(FilesystemFileProvider::new(
+    let files = FilesystemFileProvider::with_overrides(
         project.root(),
+        package_overrides(package_opts),
         Some(package_storage(package_opts)),
-    )) as _
+    );
+
+    match lockfile {
+        Some(lockfile) => Box::new(LockedFileProvider::with_exempt(
+            files,
+            lockfile,
+            package_overrides(package_opts).map(|(spec, _)| spec),
+        )),
+        None => Box::new(files),
+    }
 }
 
 /// Provides access as if in a freshly created template from the given template
@@ -96,17 +126,18 @@ pub fn template_file_provider(
 
     Box::new(FilesystemFileProvider::with_overrides(
         project.template_root().unwrap(),
-        [(
+        package_overrides(package_opts).chain([(
             spec,
             FsRoot::new(project.root().as_std_path().to_path_buf()),
-        )],
+        )]),
         Some(package_storage(package_opts)),
     ))
 }
 
-/// A font provider that provides embedded and system fonts.
-#[tracing::instrument]
-pub fn font_provider(font_opts: &FontOptions) -> Box<dyn ProvideFont> {
+/// A font provider that provides embedded and system fonts, as well as those
+/// found in the font paths of the command line and the project config.
+#[tracing::instrument(skip(project))]
+pub fn font_provider(project: Option<&Project>, font_opts: &FontOptions) -> Box<dyn ProvideFont> {
     let mut store = FontStore::new();
 
     #[cfg(feature = "embedded-fonts")]
@@ -126,6 +157,22 @@ pub fn font_provider(font_opts: &FontOptions) -> Box<dyn ProvideFont> {
             .flat_map(fonts::scan),
     );
 
+    if let Some(project) = project {
+        let font_paths = project
+            .config()
+            .font_paths
+            .iter()
+            .map(|path| project.root().join(path))
+            .collect::<Vec<_>>();
+
+        store.extend(
+            font_paths
+                .iter()
+                .map(|path| path.as_std_path())
+                .flat_map(fonts::scan),
+        );
+    }
+
     tracing::debug!(fonts = ?store.book().families().count(), "collected font families");
     Box::new(store)
 }
@@ -174,10 +221,32 @@ pub fn datetime_provider(compile_opts: &CompileOptions) -> eyre::Result<Box<Time
     ))
 }
 
-/// Provides the augmented library.
-#[tracing::instrument]
-pub fn augmented_library_provider() -> Box<LazyHash<Library>> {
-    Box::new(LazyHash::new(augmented_default_library()))
+/// Provides the augmented library with the inputs of the project defaults
+/// and the command line, the latter take precedence.
+#[tracing::instrument(skip_all)]
+pub fn augmented_library_provider(
+    project: &Project,
+    compile_opts: &CompileOptions,
+) -> Box<LazyHash<Library>> {
+    let defaults = &project.config().defaults.inputs;
+    if defaults.is_empty() && compile_opts.inputs.is_empty() {
+        return Box::new(LazyHash::new(augmented_default_library()));
+    }
+
+    let mut inputs = defaults
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect::<BTreeMap<_, _>>();
+    for (key, value) in &compile_opts.inputs {
+        inputs.insert(key, value);
+    }
+
+    augmented_library_provider_with_inputs(
+        inputs
+            .into_iter()
+            .map(|(key, value)| (Str::from(key), Value::Str(Str::from(value))))
+            .collect(),
+    )
 }
 
 /// Provides the augmented library with additional inputs.
@@ -209,27 +278,68 @@ pub struct Providers {
 }
 
 impl Providers {
+    /// Creates the providers for the given project, package files of the
+    /// project files are verified against the project's lockfile if it exists.
     pub fn new(
         project: &Project,
         package_opts: &PackageOptions,
         font_opts: &FontOptions,
         compile_opts: &CompileOptions,
+    ) -> eyre::Result<Providers> {
+        let lockfile = Lockfile::read(project.lockfile())?;
+        if lockfile.is_some() {
+            tracing::debug!("verifying package files against lockfile");
+        }
+
+        Self::with_lockfile(project, package_opts, font_opts, compile_opts, lockfile)
+    }
+
+    /// Creates the providers for the given project, package files of the
+    /// project files are verified against the given lockfile if any.
+    pub fn with_lockfile(
+        project: &Project,
+        package_opts: &PackageOptions,
+        font_opts: &FontOptions,
+        compile_opts: &CompileOptions,
+        lockfile: Option<Lockfile>,
     ) -> eyre::Result<Providers> {
         Ok(Self {
-            augmented_library: augmented_library_provider(),
+            augmented_library: augmented_library_provider(project, compile_opts),
             default_library: default_library_provider(),
-            project_files: project_file_provider(project, package_opts),
+            project_files: project_file_provider(project, package_opts, lockfile),
             template_files: project.manifest().and_then(|m| {
                 m.template
                     .is_some()
                     .then(|| template_file_provider(project, package_opts))
             }),
-            fonts: font_provider(font_opts),
+            fonts: font_provider(Some(project), font_opts),
             datetime: datetime_provider(compile_opts)?,
         })
     }
 }
 
+impl Providers {
+    /// The file provider for project files.
+    pub fn project_files(&self) -> &dyn ProvideFile {
+        &*self.project_files
+    }
+
+    /// The font provider.
+    pub fn fonts(&self) -> &dyn ProvideFont {
+        &*self.fonts
+    }
+
+    /// Marks all cached project and template files as not yet accessed, such
+    /// that changed files are read again for the next compilation, unchanged
+    /// files keep their incrementally parsed sources.
+    pub fn reset_files(&self) {
+        self.project_files.reset_all();
+        if let Some(files) = &self.template_files {
+            files.reset_all();
+        }
+    }
+}
+
 impl Providers {
     /// Constructs a world for unit test creation.
     pub fn system_world(&self, source: Source) -> NewTestWorld<'_> {
@@ -247,13 +357,14 @@ impl Providers {
     /// Constructs a world for unit tests.
     ///
     /// The `alternative_library` argument can be assembled by test code to e.g. provide additional
-    /// system inputs.
+    /// system inputs, likewise `alternative_files` can be used to e.g. remap data files.
     pub fn unit_world<'w>(
         &'w self,
         project: &Project,
         test: &'w UnitTest,
         is_ref: bool,
         alternative_library: Option<&'w LazyHash<Library>>,
+        alternative_files: Option<&'w dyn ProvideFile>,
     ) -> ComposedWorld<'w> {
         let id = if is_ref {
             project.unit_test_ref_script_id(test.id(), project)
@@ -267,12 +378,22 @@ impl Providers {
             &*self.augmented_library
         };
 
-        ComposedWorld::builder()
+        let files = alternative_files.unwrap_or(&*self.project_files);
+
+        let mut builder = ComposedWorld::builder()
             .library_provider(library)
-            .file_provider(&*self.project_files)
+            .file_provider(files)
             .font_provider(&*self.fonts)
-            .datetime_provider(&self.datetime)
-            .build(id)
+            .datetime_provider(&self.datetime);
+
+        if let Some(date) = test.annotations().iter().find_map(|annot| match annot {
+            Annotation::Date(date) => Some(*date),
+            _ => None,
+        }) {
+            builder = builder.fixed_date(date);
+        }
+
+        builder.build(id)
     }
 
     /// Constructs a world for template tests.
@@ -280,19 +401,20 @@ impl Providers {
     /// Panics if the project has no manifest.
     pub fn template_world<'w>(
         &'w self,
-        project: &Project,
-        _test: &'w TemplateTest,
+        _project: &Project,
+        test: &'w TemplateTest,
     ) -> ComposedWorld<'w> {
-        let id = project
-            .template_entrypoint_id()
+        let files = self
+            .template_files
+            .as_ref()
             .expect("Providers::template_world must not be called without template test");
 
         ComposedWorld::builder()
             .library_provider(&self.default_library)
-            .file_provider(&**self.template_files.as_ref().unwrap())
+            .file_provider(&**files)
             .font_provider(&*self.fonts)
             .datetime_provider(&self.datetime)
-            .build(id)
+            .build(test.entrypoint_id())
     }
 }
 
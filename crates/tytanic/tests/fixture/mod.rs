@@ -73,6 +73,10 @@ impl Environment {
         let mut cmd = Command::new(cargo_bin!("tt"));
         cmd.current_dir(self.root());
 
+        // Don't pick up the GitHub reporter when the tests themselves run on
+        // GitHub Actions.
+        cmd.env_remove("GITHUB_ACTIONS");
+
         f(&mut cmd);
 
         let output = cmd.output().unwrap();
@@ -49,3 +49,36 @@ fn test_new_delete_alias() {
     --- END
     ");
 }
+
+#[test]
+fn test_new_delete_alias_quiet() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["--quiet", "remove", "failing/compile"]);
+
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 0
+    --- STDOUT:
+
+    --- STDERR:
+    Deleted 1 test
+
+    --- END
+    ");
+}
+
+#[test]
+fn test_new_delete_alias_json_warning() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["--warning-format=json", "remove", "failing/compile"]);
+
+    insta::assert_snapshot!(res.output(), @r#"
+    --- CODE: 0
+    --- STDOUT:
+
+    --- STDERR:
+    {"type":"warning","code":"deprecated-alias","message":"Sub command alias remove|rm is deprecated","hint":"Use delete instead"}
+    Deleted 1 test
+
+    --- END
+    "#);
+}
@@ -1,3 +1,5 @@
+use std::fs;
+
 mod fixture;
 
 #[test]
@@ -24,3 +26,226 @@ fn test_status() {
     --- END
     ");
 }
+
+#[test]
+fn test_list_quoted_id() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["list", "'passing/compile'"]);
+
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 0
+    --- STDOUT:
+
+    --- STDERR:
+    passing/compile compile-only
+
+    --- END
+    ");
+}
+
+#[test]
+fn test_list_invalid_id() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["list", "passing/compile.typ"]);
+
+    insta::assert_snapshot!(res.output(), @r#"
+    --- CODE: 2
+    --- STDOUT:
+
+    --- STDERR:
+    error: invalid value 'passing/compile.typ' for '[TEST]...': id contained an invalid fragment, fragments must start with an ASCII letter and contain only ASCII letters, digits, `-` or `_`, use a test set expression like `--expression "e:'passing/compile.typ'"` instead
+
+    For more information, try '--help'.
+
+    --- END
+    "#);
+}
+
+#[test]
+fn test_list_attr() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["list", "--expression", "attr('kind', 'persistent')"]);
+
+    assert!(res.output().status().success());
+    let stderr = res.output().stderr();
+    assert!(stderr.contains("passing/persistent"));
+    assert!(stderr.contains("failing/persistent-empty"));
+    assert!(!stderr.contains("passing/ephemeral"));
+    assert!(!stderr.contains("@template"));
+
+    let res = env.run_tytanic([
+        "list",
+        "--expression",
+        "attr('max-delta', 1) & e:'passing/compile'",
+    ]);
+    assert!(res.output().stderr().contains("passing/compile"));
+}
+
+#[test]
+fn test_list_attr_unknown_key() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["list", "--expression", "attr('warnings', 'promote')"]);
+
+    assert_eq!(res.output().status().code(), Some(2));
+    assert!(res.output().stderr().contains(
+        "unknown attribute \"warnings\", expected \"kind\", \"skip\", \"dir\", \"ppi\", \"max-delta\", \"max-deviations\" or \"max-failing-pages\""
+    ));
+}
+
+#[test]
+fn test_list_annot() {
+    let env = fixture::Environment::default_package();
+
+    let test = env.root().join("tests/annotated");
+    fs::create_dir_all(&test).unwrap();
+    fs::write(
+        test.join("test.typ"),
+        "/// [retries: 2]\n/// [input: tag=slow]\n\nHello\n",
+    )
+    .unwrap();
+
+    for expr in [
+        "annot('retries')",
+        "annot('retries', 2)",
+        "annot('input', 'tag=slow')",
+    ] {
+        let res = env.run_tytanic(["list", "--expression", expr]);
+        assert!(res.output().status().success(), "{expr}");
+        let stderr = res.output().stderr();
+        assert!(stderr.contains("annotated"), "{expr}");
+        assert!(!stderr.contains("passing/compile"), "{expr}");
+    }
+
+    let res = env.run_tytanic(["list", "--expression", "annot('input', 'tag=fast')"]);
+    assert!(!res.output().stderr().contains("annotated"));
+
+    let res = env.run_tytanic(["list", "--expression", "annot('slow')"]);
+    assert_eq!(res.output().status().code(), Some(2));
+    assert!(
+        res.output()
+            .stderr()
+            .contains("unknown annotation \"slow\", expected \"skip\"")
+    );
+}
+
+#[test]
+fn test_list_count() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["list", "--expression", "count(e:'passing/compile', 1)"]);
+
+    assert!(res.output().status().success());
+    assert!(res.output().stderr().contains("passing/compile"));
+
+    let res = env.run_tytanic([
+        "list",
+        "--expression",
+        "at-most(e:'passing/compile' | e:'passing/ephemeral', 1)",
+    ]);
+    assert_eq!(res.output().status().code(), Some(2));
+    assert!(
+        res.output()
+            .stderr()
+            .contains("function at-most expects at most 1 test, matched 2")
+    );
+}
+
+#[test]
+fn test_list_collection_warnings() {
+    let env = fixture::Environment::default_package();
+    fs::create_dir_all(env.root().join("tests/typo")).unwrap();
+    fs::write(env.root().join("tests/typo/tset.typ"), "Hello World").unwrap();
+    fs::create_dir_all(env.root().join("tests/invalid!")).unwrap();
+    fs::write(env.root().join("tests/invalid!/test.typ"), "Hello World").unwrap();
+
+    let res = env.run_tytanic(["list"]);
+    assert!(res.output().status().success());
+
+    let stderr = res.output().stderr();
+    assert!(stderr.contains("1 directory skipped, 1 invalid identifier"));
+    assert!(stderr.contains("- tests/invalid!: invalid identifier"));
+    assert!(stderr.contains("- tests/typo: no test script"));
+}
+
+#[test]
+fn test_list_json() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["list", "--json"]);
+
+    assert!(res.output().status().success());
+
+    let tests: serde_json::Value = serde_json::from_str(res.output().stdout()).unwrap();
+    let tests = tests.as_array().unwrap();
+    let test = |id: &str| tests.iter().find(|test| test["test"]["id"] == id).unwrap();
+
+    let persistent = test("passing/persistent");
+    assert_eq!(persistent["type"], "unit");
+    assert_eq!(persistent["test"]["kind"], "persistent");
+    assert_eq!(persistent["test"]["has_references"], true);
+    assert_eq!(persistent["test"]["config"]["dir"], "ltr");
+
+    let ephemeral = test("passing/ephemeral");
+    assert_eq!(ephemeral["test"]["has_references"], false);
+
+    assert_eq!(test("@template")["type"], "template");
+}
+
+#[test]
+fn test_list_manifest() {
+    let env = fixture::Environment::default_package();
+    let manifest = env.root().join("suite.json");
+    let res = env.run_tytanic(["list", "--manifest", manifest.to_str().unwrap()]);
+
+    assert!(res.output().status().success());
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(manifest).unwrap()).unwrap();
+    assert_eq!(manifest["version"], 1);
+
+    let tests = manifest["tests"].as_array().unwrap();
+    let test = |id: &str| tests.iter().find(|test| test["id"] == id).unwrap();
+
+    let persistent = test("passing/persistent");
+    assert_eq!(persistent["kind"], "persistent");
+    assert_eq!(persistent["path"], "tests/passing/persistent");
+    assert_eq!(persistent["reference_pages"], 1);
+    assert_eq!(persistent["config"]["ppi"]["source"], "default");
+    assert_eq!(
+        persistent["imports"],
+        serde_json::json!(["src/internal.typ", "src/lib.typ"])
+    );
+
+    let compile = test("failing/compile");
+    assert_eq!(compile["reference_pages"], serde_json::Value::Null);
+
+    assert_eq!(test("@template")["type"], "template");
+}
+
+#[test]
+fn test_list_last_status() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic(["list", "--expression", "failed()"]);
+    assert!(res.output().status().success());
+    assert!(!res.output().stderr().contains("passing/compile"));
+
+    let res = env.run_tytanic([
+        "run",
+        "--no-fail-fast",
+        "passing/compile",
+        "failing/compile",
+    ]);
+    assert_eq!(res.output().status().code(), Some(1));
+
+    let res = env.run_tytanic(["list", "--expression", "failed()"]);
+    assert!(res.output().status().success());
+    assert!(res.output().stderr().contains("failing/compile"));
+    assert!(!res.output().stderr().contains("passing/compile"));
+
+    let res = env.run_tytanic(["list", "--expression", "passed()"]);
+    assert!(res.output().stderr().contains("passing/compile"));
+    assert!(!res.output().stderr().contains("failing/compile"));
+
+    let res = env.run_tytanic(["list", "--expression", "new()"]);
+    assert!(res.output().stderr().contains("passing/ephemeral"));
+    assert!(!res.output().stderr().contains("passing/compile"));
+}
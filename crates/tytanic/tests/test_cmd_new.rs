@@ -1,3 +1,5 @@
+use std::fs;
+
 mod fixture;
 
 #[test]
@@ -49,3 +51,50 @@ fn test_new_add_alias() {
     --- END
     ");
 }
+
+#[test]
+fn test_new_kinds_pass() {
+    let env = fixture::Environment::default_package();
+
+    for (kind, id) in [
+        ("--persistent", "new/persistent"),
+        ("--ephemeral", "new/ephemeral"),
+        ("--compile-only", "new/compile-only"),
+    ] {
+        let res = env.run_tytanic(["new", kind, id]);
+        assert!(res.output().status().success(), "{kind}");
+
+        let res = env.run_tytanic(["run", id]);
+        assert!(res.output().status().success(), "{kind}");
+    }
+
+    let tests = env.root().join("tests/new");
+    assert!(tests.join("persistent/ref/1.png").is_file());
+    assert!(!tests.join("persistent/ref.typ").exists());
+    assert_eq!(
+        fs::read_to_string(tests.join("ephemeral/ref.typ")).unwrap(),
+        fs::read_to_string(env.root().join("tests/template.typ")).unwrap(),
+    );
+    assert!(!tests.join("compile-only/ref").exists());
+    assert!(!tests.join("compile-only/ref.typ").exists());
+}
+
+#[test]
+fn test_new_no_template() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["new", "--ephemeral", "--no-template", "foo"]);
+    assert!(res.output().status().success());
+
+    let test = env.root().join("tests/foo");
+    assert_eq!(
+        fs::read_to_string(test.join("test.typ")).unwrap(),
+        fs::read_to_string(test.join("ref.typ")).unwrap(),
+    );
+    assert_ne!(
+        fs::read_to_string(test.join("test.typ")).unwrap(),
+        fs::read_to_string(env.root().join("tests/template.typ")).unwrap(),
+    );
+
+    let res = env.run_tytanic(["run", "foo"]);
+    assert!(res.output().status().success());
+}
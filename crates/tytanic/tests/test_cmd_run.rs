@@ -0,0 +1,1103 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+mod fixture;
+
+#[test]
+fn test_run_export_overview() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic([
+        "run",
+        "--export-overview",
+        "--no-fail-fast",
+        "passing/ephemeral",
+        "failing/persistent-compare-failure",
+    ]);
+
+    assert_eq!(res.output().status().code(), Some(1));
+
+    let tests = env.root().join("tests");
+    assert!(tests.join("passing/ephemeral/diff/overview.png").is_file());
+    assert!(
+        tests
+            .join("failing/persistent-compare-failure/diff/overview.png")
+            .is_file()
+    );
+}
+
+#[test]
+fn test_run_explain_failure() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic([
+        "run",
+        "--explain-failure",
+        "--no-fail-fast",
+        "passing/ephemeral",
+        "failing/persistent-compare-failure",
+    ]);
+
+    assert_eq!(res.output().status().code(), Some(1));
+
+    let stderr = res.output().stderr();
+    assert!(!stderr.contains("Explain passing/ephemeral"));
+    assert!(stderr.contains("Explain failing/persistent-compare-failure"));
+    assert!(stderr.contains("Stage: failed comparison"));
+    assert!(stderr.contains("Difference: tests/failing/persistent-compare-failure/diff"));
+}
+
+#[test]
+fn test_run_fixed_run_id() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic([
+        "run",
+        "--run-id",
+        "67e55044-10b1-426f-9247-bb680e5fe0c8",
+        "passing/ephemeral",
+    ]);
+
+    assert!(res.output().status().success());
+    assert!(
+        res.output()
+            .stderr()
+            .contains("(run ID: 67e55044-10b1-426f-9247-bb680e5fe0c8)")
+    );
+}
+
+#[test]
+fn test_run_invalid_run_id() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["run", "--run-id", "not-a-uuid", "passing/ephemeral"]);
+
+    assert_eq!(res.output().status().code(), Some(2));
+    assert!(
+        res.output()
+            .stderr()
+            .contains("run id must be a UUID or the literal string `auto`")
+    );
+}
+
+#[test]
+fn test_run_data_root() {
+    let env = fixture::Environment::default_package();
+
+    let test = env.root().join("tests/data-root");
+    fs::create_dir_all(test.join("data")).unwrap();
+    fs::write(env.root().join("value.txt"), "project").unwrap();
+    fs::write(test.join("data/value.txt"), "test").unwrap();
+    fs::write(env.root().join("fallback.txt"), "fallback").unwrap();
+    fs::write(
+        test.join("test.typ"),
+        "/// [data-root: data]\n\n\
+        #assert.eq(read(\"/value.txt\"), \"test\")\n\
+        #assert.eq(read(\"/fallback.txt\"), \"fallback\")\n",
+    )
+    .unwrap();
+
+    let res = env.run_tytanic(["run", "data-root"]);
+    assert!(res.output().status().success());
+}
+
+#[test]
+fn test_run_no_artifacts() {
+    let env = fixture::Environment::default_package();
+
+    let test = env.root().join("tests/failing/persistent-compare-failure");
+    fs::remove_dir_all(test.join("out")).unwrap();
+    fs::remove_dir_all(test.join("diff")).unwrap();
+
+    let res = env.run_tytanic([
+        "run",
+        "--no-artifacts",
+        "--export-overview",
+        "failing/persistent-compare-failure",
+    ]);
+
+    assert_eq!(res.output().status().code(), Some(1));
+    assert!(!test.join("out").exists());
+    assert!(!test.join("diff").exists());
+}
+
+#[test]
+fn test_run_repeat() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic([
+        "run",
+        "--repeat",
+        "2",
+        "--no-fail-fast",
+        "passing/ephemeral",
+        "failing/persistent-compare-failure",
+    ]);
+
+    assert_eq!(res.output().status().code(), Some(1));
+
+    let stderr = res.output().stderr();
+    assert!(stderr.contains("passing/ephemeral: 2/2 passed"));
+    assert!(stderr.contains("failing/persistent-compare-failure: 0/2 passed"));
+    assert!(stderr.contains("2 runs, 0 flaky tests"));
+}
+
+#[test]
+fn test_run_repeat_zero() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["run", "--repeat", "0"]);
+    assert_eq!(res.output().status().code(), Some(2));
+}
+
+#[test]
+fn test_run_focus() {
+    let env = fixture::Environment::default_package();
+
+    let page = "#set page(width: 100pt, height: 100pt)\n\
+        #place(top + left, rect(width: 10pt, height: 10pt, fill: black))\n";
+
+    let test = env.root().join("tests/focus");
+    fs::create_dir_all(&test).unwrap();
+    fs::write(
+        test.join("test.typ"),
+        format!(
+            "/// [focus: 0pt, 0pt, 50pt, 50pt]\n\n{page}\
+            #place(bottom + right, rect(width: 10pt, height: 10pt, fill: black))\n"
+        ),
+    )
+    .unwrap();
+    fs::write(test.join("ref.typ"), page).unwrap();
+
+    let res = env.run_tytanic(["run", "focus"]);
+    assert!(res.output().status().success());
+
+    fs::write(
+        test.join("test.typ"),
+        format!("/// [focus: 80pt, 80pt, 50pt, 50pt]\n\n{page}"),
+    )
+    .unwrap();
+
+    let res = env.run_tytanic(["run", "focus"]);
+    assert!(!res.output().status().success());
+    assert!(
+        res.output()
+            .stderr()
+            .contains("does not lie within page 1 (100pt x 100pt)")
+    );
+}
+
+#[test]
+fn test_run_mask() {
+    let env = fixture::Environment::default_package();
+
+    let page = "#set page(width: 100pt, height: 100pt)\n\
+        #place(top + left, rect(width: 10pt, height: 10pt, fill: black))\n";
+    let dynamic = "#place(bottom + right, rect(width: 10pt, height: 10pt, fill: black))\n";
+
+    let test = env.root().join("tests/mask");
+    fs::create_dir_all(&test).unwrap();
+    fs::write(
+        test.join("test.typ"),
+        format!("/// [mask: 70pt, 70pt, 30pt, 30pt]\n\n{page}{dynamic}"),
+    )
+    .unwrap();
+    fs::write(test.join("ref.typ"), page).unwrap();
+
+    let res = env.run_tytanic(["run", "mask"]);
+    assert!(res.output().status().success());
+
+    fs::write(test.join("test.typ"), format!("{page}{dynamic}")).unwrap();
+
+    let res = env.run_tytanic(["run", "mask"]);
+    assert_eq!(res.output().status().code(), Some(1));
+}
+
+#[test]
+fn test_run_page_tolerance() {
+    let env = fixture::Environment::default_package();
+
+    let test = env.root().join("tests/noisy");
+    fs::create_dir_all(&test).unwrap();
+    fs::write(
+        test.join("test.typ"),
+        "/// [page: 2, max-deviations=100000]\n\nContent\n#pagebreak()\nNoise\n",
+    )
+    .unwrap();
+    fs::write(test.join("ref.typ"), "Content\n#pagebreak()\nOther\n").unwrap();
+
+    let res = env.run_tytanic(["run", "noisy"]);
+    assert!(res.output().status().success());
+
+    fs::write(
+        test.join("test.typ"),
+        "/// [page: 1, max-deviations=100000]\n\nContent\n#pagebreak()\nNoise\n",
+    )
+    .unwrap();
+
+    let res = env.run_tytanic(["run", "noisy"]);
+    assert_eq!(res.output().status().code(), Some(1));
+}
+
+#[test]
+fn test_run_pages() {
+    let env = fixture::Environment::default_package();
+
+    let test = env.root().join("tests/pages");
+    fs::create_dir_all(&test).unwrap();
+    fs::write(
+        test.join("test.typ"),
+        "/// [pages: 1, 3]\n\nA\n#pagebreak()\nNoise\n#pagebreak()\nC\n",
+    )
+    .unwrap();
+    fs::write(
+        test.join("ref.typ"),
+        "A\n#pagebreak()\nB\n#pagebreak()\nC\n",
+    )
+    .unwrap();
+
+    let res = env.run_tytanic(["run", "pages"]);
+    assert!(res.output().status().success());
+
+    fs::write(
+        test.join("test.typ"),
+        "/// [pages: 2-3]\n\nA\n#pagebreak()\nNoise\n#pagebreak()\nC\n",
+    )
+    .unwrap();
+
+    let res = env.run_tytanic(["run", "pages"]);
+    assert_eq!(res.output().status().code(), Some(1));
+
+    fs::write(test.join("test.typ"), "/// [pages: 1, 4]\n\nA\n").unwrap();
+
+    let res = env.run_tytanic(["run", "pages"]);
+    assert!(!res.output().status().success());
+    assert!(
+        res.output()
+            .stderr()
+            .contains("page 4 was selected, but the document only has 1 page")
+    );
+}
+
+#[test]
+fn test_run_compare_downscale() {
+    let env = fixture::Environment::default_package();
+
+    let page = "#set page(width: 100pt, height: 100pt)\n";
+
+    let test = env.root().join("tests/downscale");
+    fs::create_dir_all(&test).unwrap();
+    fs::write(
+        test.join("test.typ"),
+        format!("{page}#place(rect(width: 1pt, height: 1pt, fill: black))\n"),
+    )
+    .unwrap();
+    fs::write(test.join("ref.typ"), page).unwrap();
+
+    let res = env.run_tytanic(["run", "downscale", "--max-delta", "10"]);
+    assert_eq!(res.output().status().code(), Some(1));
+
+    // A 2x2 pixel dot is averaged out in a block of 16x16 pixels.
+    let res = env.run_tytanic([
+        "run",
+        "downscale",
+        "--max-delta",
+        "10",
+        "--compare-downscale",
+        "16",
+    ]);
+    assert!(res.output().status().success());
+}
+
+#[test]
+fn test_run_crop() {
+    let env = fixture::Environment::default_package();
+
+    let square = |dx| {
+        format!(
+            "#set page(width: 100pt, height: 100pt)\n\
+            #place(dx: {dx}pt, dy: 10pt, rect(width: 10pt, height: 10pt, fill: black))\n"
+        )
+    };
+
+    let test = env.root().join("tests/cropped");
+    fs::create_dir_all(&test).unwrap();
+    fs::write(test.join("test.typ"), square(10)).unwrap();
+    fs::write(test.join("ref.typ"), square(10)).unwrap();
+
+    let res = env.run_tytanic(["run", "--crop", "cropped"]);
+    assert!(res.output().status().success());
+    assert!(test.join("out/1.png").exists());
+
+    fs::write(test.join("ref.typ"), square(20)).unwrap();
+
+    let res = env.run_tytanic(["run", "--crop", "cropped"]);
+    assert_eq!(res.output().status().code(), Some(1));
+    assert!(
+        res.output()
+            .stderr()
+            .contains("Page 1 had its content at a different position")
+    );
+}
+
+#[test]
+fn test_run_max_failing_pages() {
+    let env = fixture::Environment::default_package();
+
+    let test = env.root().join("tests/volatile");
+    fs::create_dir_all(&test).unwrap();
+    fs::write(
+        test.join("test.typ"),
+        "/// [max-failing-pages: 1]\n\nCover\n#pagebreak()\nContent\n",
+    )
+    .unwrap();
+    fs::write(test.join("ref.typ"), "Other cover\n#pagebreak()\nContent\n").unwrap();
+
+    let res = env.run_tytanic(["run", "volatile"]);
+    assert!(res.output().status().success());
+
+    let res = env.run_tytanic(["run", "--max-failing-pages", "0", "volatile"]);
+    assert!(res.output().status().success());
+
+    fs::write(test.join("test.typ"), "Cover\n#pagebreak()\nContent\n").unwrap();
+
+    let res = env.run_tytanic(["run", "--max-failing-pages", "0", "volatile"]);
+    assert_eq!(res.output().status().code(), Some(1));
+}
+
+#[test]
+fn test_run_date_annotation() {
+    let env = fixture::Environment::default_package();
+
+    let test = env.root().join("tests/dated");
+    fs::create_dir_all(&test).unwrap();
+    fs::write(
+        test.join("test.typ"),
+        "/// [date: 2024-01-01]\n\n\
+        #assert.eq(datetime.today(), datetime(year: 2024, month: 1, day: 1))\n",
+    )
+    .unwrap();
+
+    let res = env.run_tytanic(["run", "dated"]);
+    assert!(res.output().status().success());
+}
+
+#[test]
+fn test_run_retries() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic([
+        "run",
+        "--no-fail-fast",
+        "--retries",
+        "2",
+        "--reporter",
+        "jsonl",
+        "passing/compile",
+        "failing/compile",
+    ]);
+    assert_eq!(res.output().status().code(), Some(1));
+
+    let lines = res
+        .output()
+        .stdout()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+
+    let test = |id: &str| lines.iter().find(|line| line["id"] == id).unwrap();
+    assert_eq!(test("passing/compile")["status"], "passed");
+    assert_eq!(test("failing/compile")["status"], "failed");
+
+    let summary = &lines[2];
+    assert_eq!(summary["passed"], 1);
+    assert_eq!(summary["flaky"], 0);
+    assert_eq!(summary["failed"], 1);
+}
+
+#[test]
+fn test_run_template_entrypoints() {
+    let env = fixture::Environment::default_package();
+
+    let manifest = env.root().join("typst.toml");
+    let mut contents = fs::read_to_string(&manifest).unwrap();
+    contents.push_str("\n[tool.tytanic]\ntemplate-entrypoints = [\"article.typ\"]\n");
+    fs::write(&manifest, contents).unwrap();
+    fs::write(
+        env.root().join("template/article.typ"),
+        "#import \"@preview/template:0.1.0\": template\n\
+        #show: template(title: \"Article\")\n",
+    )
+    .unwrap();
+
+    let res = env.run_tytanic(["run", "@template:article"]);
+    assert!(res.output().status().success());
+    assert!(res.output().stderr().contains("@template:article"));
+
+    fs::write(env.root().join("template/article.typ"), "#panic()").unwrap();
+
+    let res = env.run_tytanic(["run", "@template:article"]);
+    assert_eq!(res.output().status().code(), Some(1));
+}
+
+#[test]
+fn test_run_audit_config() {
+    let env = fixture::Environment::default_package();
+
+    let test = env.root().join("tests/audited");
+    fs::create_dir_all(&test).unwrap();
+    fs::write(test.join("test.typ"), "/// [max-delta: 10]\n\nHello").unwrap();
+    fs::write(test.join("ref.typ"), "Hello").unwrap();
+
+    let res = env.run_tytanic([
+        "run",
+        "--audit-config",
+        "--max-deviations",
+        "0",
+        "audited",
+        "passing/ephemeral",
+    ]);
+    assert!(res.output().status().success());
+
+    let stderr = res.output().stderr();
+    assert!(stderr.contains("max-delta: 1 -> 10 (annotation)"));
+    assert!(!stderr.contains("max-deviations"));
+    assert!(stderr.contains("1 test overrides the project defaults"));
+}
+
+#[test]
+fn test_run_record_inputs() {
+    let env = fixture::Environment::default_package();
+
+    let run = || {
+        let res = env.run_tytanic([
+            "run",
+            "--record-inputs",
+            "inputs.json",
+            "passing/compile",
+            "passing/persistent",
+        ]);
+        assert!(res.output().status().success());
+
+        let inputs = fs::read_to_string(env.root().join("inputs.json")).unwrap();
+        serde_json::from_str::<BTreeMap<String, String>>(&inputs).unwrap()
+    };
+
+    let first = run();
+    assert_eq!(
+        first.keys().collect::<Vec<_>>(),
+        ["passing/compile", "passing/persistent"],
+    );
+    assert_eq!(run(), first);
+
+    let script = env.root().join("tests/passing/compile/test.typ");
+    let source = fs::read_to_string(&script).unwrap();
+    fs::write(&script, format!("{source}\n// changed\n")).unwrap();
+
+    let second = run();
+    assert_ne!(second["passing/compile"], first["passing/compile"]);
+    assert_eq!(second["passing/persistent"], first["passing/persistent"]);
+}
+
+#[test]
+fn test_run_fail_empty_references() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic([
+        "run",
+        "--fail-empty-references",
+        "--no-fail-fast",
+        "passing/persistent",
+        "failing/persistent-empty",
+        "failing/persistent-compare-failure",
+    ]);
+
+    assert_eq!(res.output().status().code(), Some(2));
+    insta::assert_snapshot!(res.output().stderr(), @r"
+    error: Found invalid references for 1 test:
+           failing/persistent-empty: the document contains no pages
+    hint: Use `tt update` to create the references or omit `--fail-empty-references`
+    ");
+}
+
+#[test]
+fn test_run_meta() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic([
+        "run",
+        "--run-meta",
+        "branch=main",
+        "--run-meta",
+        "ci.job-id=1234",
+        "passing/ephemeral",
+    ]);
+
+    assert!(res.output().status().success());
+    let stderr = res.output().stderr();
+    assert!(stderr.contains("branch = main"));
+    assert!(stderr.contains("ci.job-id = 1234"));
+}
+
+#[test]
+fn test_run_invalid_meta() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["run", "--run-meta", "no value", "passing/ephemeral"]);
+
+    assert_eq!(res.output().status().code(), Some(2));
+    assert!(
+        res.output()
+            .stderr()
+            .contains("run metadata must be of the form `KEY=VALUE`")
+    );
+}
+
+#[test]
+fn test_run_compare_cache() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["run", "passing/ephemeral"]);
+
+    assert!(res.output().status().success());
+
+    let cache = env.root().join("tests/.cache/compare");
+    assert_eq!(fs::read_dir(&cache).unwrap().count(), 1);
+
+    let res = env.run_tytanic(["run", "passing/ephemeral"]);
+
+    assert!(res.output().status().success());
+    assert_eq!(fs::read_dir(&cache).unwrap().count(), 1);
+}
+
+#[test]
+fn test_run_no_compare_cache() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["run", "--no-compare-cache", "passing/ephemeral"]);
+
+    assert!(res.output().status().success());
+    assert!(!env.root().join("tests/.cache/compare").exists());
+}
+
+#[test]
+fn test_run_profile() {
+    let env = fixture::Environment::default_package();
+    let profile = env.root().join("profile.json");
+    let res = env.run_tytanic([
+        "run",
+        "--no-compare-cache",
+        "--profile",
+        profile.to_str().unwrap(),
+        "passing/ephemeral",
+    ]);
+
+    assert!(res.output().status().success());
+
+    let profile: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(profile).unwrap()).unwrap();
+    let events = profile["traceEvents"].as_array().unwrap();
+
+    let comparison = events
+        .iter()
+        .find(|event| event["name"] == "comparison")
+        .unwrap();
+    assert_eq!(comparison["ph"], "X");
+    assert_eq!(comparison["args"]["test"], "passing/ephemeral");
+}
+
+#[test]
+fn test_run_package_override() {
+    let env = fixture::Environment::default_package();
+
+    let package = env.root().join("mock");
+    fs::create_dir_all(&package).unwrap();
+    fs::write(
+        package.join("typst.toml"),
+        "[package]\nname = \"mock\"\nversion = \"1.2.3\"\nentrypoint = \"lib.typ\"\n",
+    )
+    .unwrap();
+    fs::write(package.join("lib.typ"), "#let value = \"mocked\"\n").unwrap();
+
+    let test = env.root().join("tests/package-override");
+    fs::create_dir_all(&test).unwrap();
+    fs::write(
+        test.join("test.typ"),
+        "#import \"@preview/mock:1.2.3\": value\n\
+        #assert.eq(value, \"mocked\")\n",
+    )
+    .unwrap();
+
+    let package_override = format!("@preview/mock:1.2.3={}", package.display());
+    let res = env.run_tytanic([
+        "run",
+        "--package-override",
+        package_override.as_str(),
+        "package-override",
+    ]);
+    assert!(res.output().status().success());
+}
+
+#[test]
+fn test_run_invalid_package_override() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic(["run", "--package-override", "@preview/mock:1.2.3=missing"]);
+    assert_eq!(res.output().status().code(), Some(2));
+    assert!(
+        res.output()
+            .stderr()
+            .contains("does not exist or is not a directory")
+    );
+
+    let res = env.run_tytanic(["run", "--package-override", "mock=."]);
+    assert_eq!(res.output().status().code(), Some(2));
+    assert!(
+        res.output()
+            .stderr()
+            .contains("invalid package spec `mock`")
+    );
+}
+
+#[test]
+fn test_run_no_template() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic(["run", "--expression", "template()"]);
+    assert!(res.output().stderr().contains("@template"));
+
+    let res = env.run_tytanic(["run", "--no-template", "--expression", "template()"]);
+    assert!(!res.output().stderr().contains("@template"));
+    assert!(res.output().stderr().contains("Test set matched no tests"));
+
+    let res = env.run_tytanic(["run", "--no-template", "@template"]);
+    assert_eq!(res.output().status().code(), Some(2));
+    assert!(
+        res.output()
+            .stderr()
+            .contains("Cannot run template test with --no-template")
+    );
+}
+
+#[test]
+fn test_run_compare_mode_ssim() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["run", "--compare-mode", "ssim", "passing/persistent"]);
+    assert!(res.output().status().success());
+
+    let res = env.run_tytanic([
+        "run",
+        "--compare-mode",
+        "ssim",
+        "--min-ssim",
+        "0.999",
+        "--explain-failure",
+        "failing/persistent-compare-failure",
+    ]);
+    assert_eq!(res.output().status().code(), Some(1));
+    assert!(res.output().stderr().contains("structural similarity"));
+}
+
+#[test]
+fn test_run_compare_mode_pdf_text() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["run", "--compare-mode", "pdf-text", "passing/persistent"]);
+    assert_eq!(res.output().status().code(), Some(2));
+    assert!(res.output().stderr().contains("has no reference text"));
+
+    let res = env.run_tytanic(["update", "--compare-mode", "pdf-text", "passing/persistent"]);
+    assert!(res.output().status().success());
+    assert!(
+        env.root()
+            .join("tests/passing/persistent/ref/.text.toml")
+            .exists()
+    );
+
+    let res = env.run_tytanic(["run", "--compare-mode", "pdf-text", "passing/persistent"]);
+    assert!(res.output().status().success());
+}
+
+#[test]
+fn test_run_invalid_min_ssim() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["run", "--min-ssim", "1.5"]);
+    assert_eq!(res.output().status().code(), Some(2));
+}
+
+#[test]
+fn test_run_inputs_precedence() {
+    let env = fixture::Environment::default_package();
+
+    let manifest = env.root().join("typst.toml");
+    let mut contents = fs::read_to_string(&manifest).unwrap();
+    contents.push_str("\n[tool.tytanic.default]\ninputs = { a = \"config\", b = \"config\" }\n");
+    fs::write(&manifest, contents).unwrap();
+
+    let test = env.root().join("tests/inputs");
+    fs::create_dir_all(&test).unwrap();
+    fs::write(
+        test.join("test.typ"),
+        "/// [input: b=annotation]\n\
+        /// [input: c=annotation]\n\n\
+        #assert.eq(sys.inputs, (a: \"config\", b: \"annotation\", c: \"cli\"))\n",
+    )
+    .unwrap();
+
+    let res = env.run_tytanic(["run", "--input", "c=cli", "inputs"]);
+    assert!(res.output().status().success());
+
+    let res = env.run_tytanic(["run", "inputs"]);
+    assert_eq!(res.output().status().code(), Some(1));
+}
+
+#[test]
+fn test_run_skip_reason() {
+    let env = fixture::Environment::default_package();
+
+    let test = env.root().join("tests/skipped");
+    fs::create_dir_all(&test).unwrap();
+    fs::write(
+        test.join("test.typ"),
+        "/// [skip: broken upstream]\n\n#panic()\n",
+    )
+    .unwrap();
+
+    let res = env.run_tytanic(["run", "--expression", "e:skipped | e:'passing/compile'"]);
+    assert!(res.output().status().success());
+    assert!(res.output().stderr().contains("(reason: broken upstream)"));
+
+    let res = env.run_tytanic(["run", "--expression", "e:'passing/compile'"]);
+    assert!(res.output().status().success());
+    assert!(!res.output().stderr().contains("reason"));
+}
+
+#[test]
+fn test_run_verbose_timings() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic(["run", "passing/ephemeral"]);
+    assert!(res.output().status().success());
+    assert!(!res.output().stderr().contains("Stages:"));
+
+    let res = env.run_tytanic(["run", "--verbose", "passing/ephemeral"]);
+    assert!(res.output().status().success());
+    assert!(res.output().stderr().contains("Stages: "));
+    assert!(res.output().stderr().contains("reference-compilation"));
+}
+
+#[test]
+fn test_run_diff_colors() {
+    let env = fixture::Environment::default_package();
+
+    let test = env.root().join("tests/failing/ephemeral-compare-failure");
+    fs::remove_dir_all(test.join("diff")).ok();
+
+    let res = env.run_tytanic([
+        "run",
+        "--diff-bg",
+        "000000",
+        "--diff-highlight",
+        "#ff00ff",
+        "failing/ephemeral-compare-failure",
+    ]);
+    assert_eq!(res.output().status().code(), Some(1));
+    assert!(test.join("diff/1.png").is_file());
+
+    let res = env.run_tytanic(["run", "--diff-bg", "black", "passing/ephemeral"]);
+    assert_eq!(res.output().status().code(), Some(2));
+    assert!(res.output().stderr().contains("six digit hex code"));
+}
+
+#[test]
+fn test_run_reporter_tap() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic([
+        "run",
+        "--reporter",
+        "tap",
+        "--no-fail-fast",
+        "passing/compile",
+        "failing/compile",
+    ]);
+
+    assert_eq!(res.output().status().code(), Some(1));
+
+    let stdout = res.output().stdout();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines[..2], ["TAP version 14", "1..2"]);
+    assert!(
+        lines
+            .iter()
+            .any(|line| line.starts_with("ok ") && line.ends_with(" - passing/compile"))
+    );
+    assert!(
+        lines
+            .iter()
+            .any(|line| line.starts_with("not ok ") && line.ends_with(" - failing/compile"))
+    );
+    assert!(lines.contains(&"  stage: failed compilation"));
+}
+
+#[test]
+fn test_run_sarif_out() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic([
+        "run",
+        "--sarif-out",
+        "results.sarif",
+        "--no-fail-fast",
+        "passing/compile",
+        "failing/compile",
+        "failing/persistent-compare-failure",
+    ]);
+
+    assert_eq!(res.output().status().code(), Some(1));
+
+    let log = fs::read_to_string(env.root().join("results.sarif")).unwrap();
+    let log = serde_json::from_str::<serde_json::Value>(&log).unwrap();
+    assert_eq!(log["version"], "2.1.0");
+
+    let results = log["runs"][0]["results"].as_array().unwrap();
+    let rule = |id: &str| {
+        results
+            .iter()
+            .filter(|result| result["ruleId"] == id)
+            .collect::<Vec<_>>()
+    };
+
+    let compilation = rule("compilation-failure");
+    assert!(!compilation.is_empty());
+    assert!(
+        compilation
+            .iter()
+            .all(|result| result["properties"]["testId"] == "failing/compile")
+    );
+
+    let comparison = rule("comparison-failure");
+    assert_eq!(comparison.len(), 1);
+    assert!(comparison[0]["properties"]["deviations"].is_u64());
+    assert_eq!(
+        comparison[0]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+        "tests/failing/persistent-compare-failure/test.typ"
+    );
+}
+
+#[test]
+fn test_run_reporter_jsonl() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic([
+        "run",
+        "--reporter",
+        "jsonl",
+        "--no-fail-fast",
+        "passing/compile",
+        "failing/compile",
+    ]);
+
+    assert_eq!(res.output().status().code(), Some(1));
+
+    let lines = res
+        .output()
+        .stdout()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+
+    let test = |id: &str| lines.iter().find(|line| line["id"] == id).unwrap();
+
+    let passing = test("passing/compile");
+    assert_eq!(passing["type"], "test");
+    assert_eq!(passing["kind"], "compile-only");
+    assert_eq!(passing["status"], "passed");
+    assert!(passing["duration_ms"].is_i64());
+    assert!(passing["failure"].is_null());
+
+    let failing = test("failing/compile");
+    assert_eq!(failing["status"], "failed");
+    assert_eq!(failing["failure"]["stage"], "compilation");
+    assert!(
+        !failing["failure"]["messages"]
+            .as_array()
+            .unwrap()
+            .is_empty()
+    );
+
+    let summary = &lines[2];
+    assert_eq!(summary["type"], "summary");
+    assert_eq!(summary["passed"], 1);
+    assert_eq!(summary["failed"], 1);
+
+    let stderr = res.output().stderr();
+    assert!(stderr.contains("error:"));
+    assert!(!stderr.contains("Summary"));
+}
+
+#[test]
+fn test_run_reporter_github() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic([
+        "run",
+        "--reporter",
+        "github",
+        "--no-fail-fast",
+        "passing/compile",
+        "failing/compile",
+        "failing/ephemeral-compare-failure",
+    ]);
+
+    assert_eq!(res.output().status().code(), Some(1));
+
+    let stdout = res.output().stdout();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert!(lines.iter().all(|line| line.starts_with("::error ")));
+    assert!(lines.iter().any(|line| {
+        line.contains("file=")
+            && line.contains(",line=")
+            && line.contains("title=failing/compile::")
+    }));
+    assert!(lines.iter().any(|line| {
+        line.starts_with("::error title=failing/ephemeral-compare-failure::Comparison")
+    }));
+    assert!(!stdout.contains("passing/compile"));
+
+    let stderr = res.output().stderr();
+    assert!(!stderr.contains("Starting"));
+    assert!(stderr.contains("Summary"));
+}
+
+#[test]
+fn test_run_export_format_pdf() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["run", "--export-format", "pdf", "passing/persistent"]);
+    assert!(res.output().status().success());
+
+    let out = env.root().join("tests/passing/persistent/out");
+    assert!(
+        fs::read(out.join("output.pdf"))
+            .unwrap()
+            .starts_with(b"%PDF")
+    );
+    assert!(!out.join("1.png").exists());
+}
+
+#[test]
+fn test_run_watch_conflicts_with_repeat() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["run", "--watch", "--repeat", "2"]);
+    assert_eq!(res.output().status().code(), Some(2));
+}
+
+#[test]
+fn test_run_export_diff_always() {
+    let env = fixture::Environment::default_package();
+
+    let test = env.root().join("tests/passing/ephemeral");
+    fs::remove_dir_all(test.join("diff")).ok();
+
+    let res = env.run_tytanic(["run", "passing/ephemeral"]);
+    assert!(res.output().status().success());
+    assert!(!test.join("diff/1.png").exists());
+    assert!(!res.output().stderr().contains("Passed with"));
+
+    let res = env.run_tytanic(["run", "--export-diff-always", "passing/ephemeral"]);
+    assert!(res.output().status().success());
+    assert!(test.join("diff/1.png").is_file());
+    assert!(res.output().stderr().contains("Passed with 0 deviations"));
+}
+
+#[test]
+fn test_run_reference_ppi_mismatch() {
+    let env = fixture::Environment::default_package();
+
+    let test = env.root().join("tests/passing/persistent");
+    let script = fs::read_to_string(test.join("test.typ")).unwrap();
+    fs::write(test.join("test.typ"), format!("/// [ppi: 72]\n{script}")).unwrap();
+
+    let res = env.run_tytanic(["run", "passing/persistent"]);
+    assert_eq!(res.output().status().code(), Some(1));
+    assert!(
+        res.output()
+            .stderr()
+            .contains("Page 1 was rendered at 72 PPI, but its reference at 144 PPI")
+    );
+
+    let res = env.run_tytanic(["update", "passing/persistent"]);
+    assert!(res.output().status().success());
+    assert!(test.join("ref/.ppi.toml").is_file());
+
+    let res = env.run_tytanic(["run", "passing/persistent"]);
+    assert!(res.output().status().success());
+
+    fs::write(test.join("test.typ"), script).unwrap();
+
+    let res = env.run_tytanic(["update", "passing/persistent"]);
+    assert!(res.output().status().success());
+    assert!(
+        fs::read_to_string(test.join("ref/.ppi.toml"))
+            .unwrap()
+            .contains("144")
+    );
+}
+
+#[test]
+fn test_run_max_memory() {
+    let env = fixture::Environment::default_package();
+
+    let test = env.root().join("tests/passing/compile");
+    let script = fs::read_to_string(test.join("test.typ")).unwrap();
+
+    fs::write(
+        test.join("test.typ"),
+        format!("/// [max-memory: 1]\n{script}"),
+    )
+    .unwrap();
+    let res = env.run_tytanic(["run", "passing/compile"]);
+    assert_eq!(res.output().status().code(), Some(1));
+    assert!(
+        res.output()
+            .stderr()
+            .contains("but at most 1 bytes are allowed")
+    );
+
+    fs::write(
+        test.join("test.typ"),
+        format!("/// [max-memory: 4GiB]\n{script}"),
+    )
+    .unwrap();
+    let res = env.run_tytanic(["run", "passing/compile"]);
+    assert!(res.output().status().success());
+    assert!(res.output().stderr().contains("Peak memory:"));
+}
+
+#[test]
+fn test_run_warnings_summary() {
+    let env = fixture::Environment::default_package();
+
+    let test = env.root().join("tests/passing/compile");
+    let script = fs::read_to_string(test.join("test.typ")).unwrap();
+    fs::write(
+        test.join("test.typ"),
+        format!("{script}\n#text(font: \"not-a-font\")[Hello]\n"),
+    )
+    .unwrap();
+
+    let res = env.run_tytanic(["run", "passing/compile"]);
+    assert!(res.output().status().success());
+    assert!(res.output().stderr().contains("1 test produced 1 warning"));
+
+    let res = env.run_tytanic(["run", "--reporter", "jsonl", "passing/compile"]);
+    assert!(res.output().status().success());
+    assert!(res.output().stdout().contains(r#""warnings":1"#));
+    assert!(res.output().stdout().contains(r#""warned":1"#));
+}
+
+#[test]
+fn test_run_update_on_fail() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic([
+        "run",
+        "--update-on-fail",
+        "--no-fail-fast",
+        "failing/persistent-compare-failure",
+        "failing/persistent-compile-failure",
+        "passing/persistent",
+    ]);
+    assert_eq!(res.output().status().code(), Some(1));
+
+    let stderr = res.output().stderr();
+    assert!(stderr.contains("2 passed, 1 failed, 1 updated"));
+
+    let res = env.run_tytanic(["run", "failing/persistent-compare-failure"]);
+    assert!(res.output().status().success());
+}
@@ -1,3 +1,5 @@
+use std::fs;
+
 mod fixture;
 
 #[test]
@@ -9,3 +11,175 @@ fn test_update_ephemeral_to_persistent_238() {
     // reproducible test runs yet.
     assert!(res.output().status().success());
 }
+
+#[test]
+fn test_update_optimize_level() {
+    let env = fixture::Environment::default_package();
+
+    for level in ["none", "fast", "max"] {
+        let res = env.run_tytanic([
+            "update",
+            "--force",
+            "--no-only-changed-refs",
+            "--optimize-level",
+            level,
+            "passing/persistent",
+        ]);
+        assert!(res.output().status().success());
+    }
+
+    let res = env.run_tytanic(["update", "--optimize-level", "best", "passing/persistent"]);
+    assert_eq!(res.output().status().code(), Some(2));
+}
+
+#[test]
+fn test_update_only_changed_refs() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic(["update", "--force", "passing/persistent"]);
+    assert!(res.output().status().success());
+    assert!(
+        res.output()
+            .stderr()
+            .contains("Updated 0 pages, 1 unchanged")
+    );
+
+    let res = env.run_tytanic([
+        "update",
+        "--force",
+        "--no-only-changed-refs",
+        "passing/persistent",
+    ]);
+    assert!(res.output().status().success());
+    assert!(
+        res.output()
+            .stderr()
+            .contains("Updated 1 page, 0 unchanged")
+    );
+}
+
+#[test]
+fn test_update_pages() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic(["update", "--force", "--pages", "1", "passing/persistent"]);
+    assert!(res.output().status().success());
+    assert!(
+        res.output()
+            .stderr()
+            .contains("Updated 1 page, 0 unchanged")
+    );
+
+    let res = env.run_tytanic(["update", "--force", "--pages", "2", "passing/persistent"]);
+    assert!(!res.output().status().success());
+    assert!(
+        res.output()
+            .stderr()
+            .contains("page 2 is not part of the document, it has 1 page")
+    );
+}
+
+#[test]
+fn test_update_crop() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic(["update", "--force", "--crop", "passing/persistent"]);
+    assert!(res.output().status().success());
+    assert!(
+        env.root()
+            .join("tests/passing/persistent/ref/.crops.toml")
+            .exists()
+    );
+
+    let res = env.run_tytanic(["run", "--crop", "passing/persistent"]);
+    assert!(res.output().status().success());
+
+    let res = env.run_tytanic(["run", "passing/persistent"]);
+    assert_eq!(res.output().status().code(), Some(1));
+
+    let res = env.run_tytanic(["update", "--force", "passing/persistent"]);
+    assert!(res.output().status().success());
+    assert!(
+        !env.root()
+            .join("tests/passing/persistent/ref/.crops.toml")
+            .exists()
+    );
+}
+
+#[test]
+fn test_update_interactive_without_terminal() {
+    let env = fixture::Environment::default_package();
+
+    // Without a terminal there is nobody to ask, so all updates are accepted.
+    let res = env.run_tytanic([
+        "update",
+        "--interactive",
+        "failing/persistent-compare-failure",
+    ]);
+    assert!(res.output().status().success());
+
+    let res = env.run_tytanic(["run", "failing/persistent-compare-failure"]);
+    assert!(res.output().status().success());
+}
+
+#[test]
+fn test_update_since_without_vcs() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic([
+        "update",
+        "--since",
+        "HEAD",
+        "failing/persistent-compare-failure",
+    ]);
+    assert_eq!(res.output().status().code(), Some(2));
+    assert!(
+        res.output()
+            .stderr()
+            .contains("Cannot use --since without a VCS")
+    );
+}
+
+#[test]
+fn test_update_since_dash_revision() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic([
+        "update",
+        "--since=--output=out.txt",
+        "failing/persistent-compare-failure",
+    ]);
+    assert_eq!(res.output().status().code(), Some(2));
+    assert!(
+        res.output()
+            .stderr()
+            .contains("the base revision \"--output=out.txt\" must not start with a `-`")
+    );
+    assert!(!env.root().join("out.txt").exists());
+}
+
+#[test]
+fn test_update_dry_run() {
+    let env = fixture::Environment::default_package();
+    let reference = env
+        .root()
+        .join("tests/failing/persistent-compare-failure/ref/1.png");
+    let before = fs::read(&reference).unwrap();
+
+    let res = env.run_tytanic([
+        "update",
+        "--dry-run",
+        "--no-only-changed-refs",
+        "failing/persistent-compare-failure",
+    ]);
+    assert!(res.output().status().success());
+    assert!(
+        res.output()
+            .stderr()
+            .contains("Would update 1 page, 0 unchanged")
+    );
+    assert_eq!(fs::read(&reference).unwrap(), before);
+
+    let res = env.run_tytanic(["update", "--dry-run", "--interactive", "passing/persistent"]);
+    assert_eq!(res.output().status().code(), Some(2));
+}
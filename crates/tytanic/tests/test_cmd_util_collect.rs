@@ -0,0 +1,17 @@
+mod fixture;
+
+#[test]
+fn test_util_collect() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["util", "collect"]);
+
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 0
+    --- STDOUT:
+
+    --- STDERR:
+    Collected 10 tests
+
+    --- END
+    ");
+}
@@ -0,0 +1,77 @@
+mod fixture;
+
+#[test]
+fn test_util_config() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["util", "config"]);
+
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 0
+    --- STDOUT:
+
+    --- STDERR:
+                Tests ┌ tests
+           Ref format ├ png
+          Ref pattern ├ {page}
+             Ref meta ├ off
+         Ref variants ├ off
+        Optimize refs ├ max
+            Direction ├ ltr
+                  PPI ├ 144
+                 Crop ├ off
+            Max delta ├ 1
+       Max deviations ├ 0
+    Max failing pages ├ 0
+      Align tolerance ├ 0
+            Ignore AA ├ off
+         AA tolerance ├ off
+             Prescale ├ off
+            Downscale ├ off
+              Retries ├ 0
+         Compare mode └ simple
+
+    --- END
+    ");
+}
+
+#[test]
+fn test_util_config_dump() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["util", "config", "--dump"]);
+
+    insta::assert_snapshot!(res.output(), @r#"
+    --- CODE: 0
+    --- STDOUT:
+    [tool.tytanic]
+    tests = "tests"
+    ref-format = "png"
+    ref-pattern = "{page}"
+    ref-meta = false
+    ref-variants = false
+    optimize-refs = "max"
+    template-entrypoints = []
+    export-format = "png"
+    font-paths = []
+
+    [tool.tytanic.default]
+    dir = "ltr"
+    ppi = 144.0
+    crop = false
+    max-delta = 1
+    max-deviations = 0
+    max-failing-pages = 0
+    compare-align-tolerance = 0
+    compare-prescale = 0
+    compare-prescale-mode = "gate"
+    compare-downscale = 0
+    compare-ignore-aa = false
+    compare-mode = "simple"
+    min-ssim = 0.99
+    retries = 0
+    post-process = []
+
+    --- STDERR:
+
+    --- END
+    "#);
+}
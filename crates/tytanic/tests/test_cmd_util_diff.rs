@@ -0,0 +1,61 @@
+use std::fs;
+
+mod fixture;
+
+#[test]
+fn test_util_diff_passing() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic(["util", "diff", "passing/persistent"]);
+    assert!(res.output().status().success());
+
+    let stderr = res.output().stderr();
+    assert!(stderr.contains("Wrote difference document"));
+    assert!(stderr.contains("Comparison passed"));
+    assert!(
+        env.root()
+            .join("tests/passing/persistent/diff/1.png")
+            .exists()
+    );
+}
+
+#[test]
+fn test_util_diff_failing() {
+    let env = fixture::Environment::default_package();
+
+    let output = env.root().join("diff");
+    let res = env.run_tytanic([
+        "util",
+        "diff",
+        "--output",
+        output.to_str().unwrap(),
+        "failing/ephemeral-compare-failure",
+    ]);
+    assert!(res.output().status().success());
+    assert!(res.output().stderr().contains("Comparison failed"));
+    assert!(fs::read_dir(output).unwrap().next().is_some());
+}
+
+#[test]
+fn test_util_diff_compile_only() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic(["util", "diff", "passing/compile"]);
+    assert_eq!(res.output().status().code(), Some(2));
+    assert!(res.output().stderr().contains("compile-only"));
+}
+
+#[test]
+fn test_util_diff_compare_options() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic([
+        "util",
+        "diff",
+        "--max-delta",
+        "255",
+        "failing/ephemeral-compare-failure",
+    ]);
+    assert!(res.output().status().success());
+    assert!(res.output().stderr().contains("Comparison passed"));
+}
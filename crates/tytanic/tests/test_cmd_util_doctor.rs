@@ -0,0 +1,60 @@
+mod fixture;
+
+#[test]
+fn test_util_doctor() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic([
+        "util",
+        "doctor",
+        "--package-cache-path",
+        "cache",
+        "--package-path",
+        "packages",
+    ]);
+
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 0
+    --- STDOUT:
+
+    --- STDERR:
+    pass Manifest: found package template:0.1.0
+    pass Config: project config is valid
+    warn VCS: no VCS detected
+         hint: temporary test artifacts will not be ignored automatically
+    pass Tests: collected 10 tests
+    pass Structure: no tests need migration
+    pass Annotations: all annotations have an effect
+    pass Fonts: found 4 font families
+    pass Package cache: cache, will be created on first download
+    pass Local packages: packages, no local packages installed
+
+    --- END
+    ");
+}
+
+#[test]
+fn test_util_doctor_no_project() {
+    let env = fixture::Environment::new();
+    let res = env.run_tytanic([
+        "util",
+        "doctor",
+        "--package-cache-path",
+        "cache",
+        "--package-path",
+        "packages",
+    ]);
+
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 2
+    --- STDOUT:
+
+    --- STDERR:
+    fail Project: no typst project found
+         hint: pass the project root using `--root <path>`
+    pass Fonts: found 4 font families
+    pass Package cache: cache, will be created on first download
+    pass Local packages: packages, no local packages installed
+
+    --- END
+    ");
+}
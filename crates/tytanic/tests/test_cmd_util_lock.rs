@@ -0,0 +1,45 @@
+use std::fs;
+
+mod fixture;
+
+#[test]
+fn test_util_lock_compile_failure() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic(["util", "lock"]);
+    assert_eq!(res.output().status().code(), Some(2));
+
+    let stderr = res.output().stderr();
+    assert!(stderr.contains("their packages may not be locked"));
+    assert!(stderr.contains("failing/compile"));
+    assert!(stderr.contains("failing/persistent-compile-failure"));
+    assert!(stderr.contains("--force"));
+    assert!(!env.root().join("tytanic.lock").exists());
+}
+
+#[test]
+fn test_util_lock_force() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic(["util", "lock", "--force"]);
+    assert_eq!(res.output().status().code(), Some(0));
+    let stderr = res.output().stderr();
+    assert!(stderr.contains("Locked 0 packages in tytanic.lock"));
+
+    let lockfile = fs::read_to_string(env.root().join("tytanic.lock")).unwrap();
+    assert!(lockfile.starts_with("# This file is generated by `tt util lock`"));
+
+    let res = env.run_tytanic(["run", "passing/persistent"]);
+    assert_eq!(res.output().status().code(), Some(0));
+}
+
+#[test]
+fn test_util_lock_invalid_lockfile() {
+    let env = fixture::Environment::default_package();
+
+    fs::write(env.root().join("tytanic.lock"), "packages = 1").unwrap();
+
+    let res = env.run_tytanic(["run", "passing/persistent"]);
+    assert_eq!(res.output().status().code(), Some(2));
+    assert!(res.output().stderr().contains("Failed to parse lockfile"));
+}
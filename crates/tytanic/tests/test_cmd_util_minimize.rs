@@ -0,0 +1,93 @@
+use std::fs;
+
+mod fixture;
+
+#[test]
+fn test_util_minimize_compile_error() {
+    let env = fixture::Environment::default_package();
+
+    let test = env.root().join("tests/minimize");
+    fs::create_dir_all(&test).unwrap();
+    fs::write(
+        test.join("test.typ"),
+        "#let a = 1\n\
+        #let b = 2\n\
+        Hello\n\
+        #panic(\"boom\")\n\
+        World\n",
+    )
+    .unwrap();
+
+    let res = env.run_tytanic(["util", "minimize", "minimize"]);
+    assert!(res.output().status().success());
+    assert_eq!(res.output().stdout(), "#panic(\"boom\")\n");
+    assert!(res.output().stderr().contains("from 5 to 1 line"));
+}
+
+#[test]
+fn test_util_minimize_output() {
+    let env = fixture::Environment::default_package();
+
+    let output = env.root().join("minimized.typ");
+    let res = env.run_tytanic([
+        "util",
+        "minimize",
+        "--output",
+        output.to_str().unwrap(),
+        "failing/compile",
+    ]);
+
+    assert!(res.output().status().success());
+    assert!(res.output().stdout().is_empty());
+    assert!(!fs::read_to_string(output).unwrap().is_empty());
+}
+
+#[test]
+fn test_util_minimize_not_failing() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic(["util", "minimize", "passing/compile"]);
+    assert_eq!(res.output().status().code(), Some(2));
+    assert!(res.output().stderr().contains("does not fail compilation"));
+}
+
+#[test]
+fn test_util_minimize_comparison() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic([
+        "util",
+        "minimize",
+        "--predicate",
+        "comparison",
+        "failing/ephemeral-compare-failure",
+    ]);
+    assert!(res.output().status().success());
+    assert!(res.output().stderr().contains("from 3 to"));
+}
+
+#[test]
+fn test_util_minimize_comparison_annotations() {
+    let env = fixture::Environment::default_package();
+
+    let test = env.root().join("tests/failing/ephemeral-compare-failure");
+    let script = fs::read_to_string(test.join("test.typ")).unwrap();
+    fs::write(
+        test.join("test.typ"),
+        format!("/// [max-failing-pages: 1]\n{script}"),
+    )
+    .unwrap();
+
+    let res = env.run_tytanic(["run", "failing/ephemeral-compare-failure"]);
+    assert!(res.output().status().success());
+
+    let res = env.run_tytanic([
+        "util",
+        "minimize",
+        "--predicate",
+        "comparison",
+        "failing/ephemeral-compare-failure",
+    ]);
+    assert_eq!(res.output().status().code(), Some(2));
+    assert!(res.output().stderr().contains("does not fail comparison"));
+}
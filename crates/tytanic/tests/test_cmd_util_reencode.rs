@@ -0,0 +1,40 @@
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+mod fixture;
+
+#[test]
+fn test_util_reencode_webp() {
+    let env = fixture::Environment::default_package();
+
+    let mut manifest = OpenOptions::new()
+        .append(true)
+        .open(env.root().join("typst.toml"))
+        .unwrap();
+    writeln!(manifest, "\n[tool.tytanic]\nref-format = \"webp\"").unwrap();
+
+    let res = env.run_tytanic(["util", "reencode", "passing/persistent"]);
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 0
+    --- STDOUT:
+
+    --- STDERR:
+    Re-encoded persistent references for 1 test as webp
+
+    --- END
+    ");
+
+    let refs = env.root().join("tests/passing/persistent/ref");
+    assert!(refs.join("1.webp").is_file());
+    assert!(!refs.join("1.png").exists());
+
+    let res = env.run_tytanic(["run", "passing/persistent"]);
+    assert!(res.output().status().success());
+
+    let res = env.run_tytanic(["update", "--force", "passing/persistent"]);
+    assert!(res.output().status().success());
+    assert_eq!(fs::read_dir(&refs).unwrap().count(), 2);
+    assert!(refs.join("1.webp").is_file());
+    assert!(refs.join(".ppi.toml").is_file());
+}
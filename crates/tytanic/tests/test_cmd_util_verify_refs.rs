@@ -0,0 +1,38 @@
+use std::fs;
+
+mod fixture;
+
+#[test]
+fn test_util_verify_refs() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic(["util", "verify-refs"]);
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 0
+    --- STDOUT:
+
+    --- STDERR:
+    Verified persistent references for 4 tests
+
+    --- END
+    ");
+}
+
+#[test]
+fn test_util_verify_refs_corrupt() {
+    let env = fixture::Environment::default_package();
+
+    fs::write(
+        env.root().join("tests/passing/persistent/ref/1.png"),
+        b"not a png",
+    )
+    .unwrap();
+
+    let res = env.run_tytanic(["util", "verify-refs"]);
+    assert_eq!(res.output().status().code(), Some(2));
+
+    let stderr = res.output().stderr();
+    assert!(stderr.contains("Found corrupt references for 1 test"));
+    assert!(stderr.contains("passing/persistent"));
+    assert!(stderr.contains("tests/passing/persistent/ref/1.png"));
+}
@@ -18,3 +18,59 @@ fn test_manifest_invalid() {
     --- END
     "#);
 }
+
+#[test]
+fn test_annotation_inapplicable() {
+    let env = fixture::Environment::default_package();
+    std::fs::write(
+        env.root().join("tests/passing/compile/test.typ"),
+        "/// [max-delta: 3]\nHello World",
+    )
+    .unwrap();
+
+    let res = env.run_tytanic(["list", "passing/compile"]);
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 0
+    --- STDOUT:
+
+    --- STDERR:
+    warning: Annotation max-delta has no effect on compile-only test passing/compile
+    passing/compile compile-only
+
+    --- END
+    ");
+
+    let res = env.run_tytanic(["--strict-config", "list", "passing/compile"]);
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 2
+    --- STDOUT:
+
+    --- STDERR:
+    error: Annotation max-delta has no effect on compile-only test passing/compile
+
+    --- END
+    ");
+}
+
+#[test]
+fn test_config_font_path_invalid() {
+    let env = fixture::Environment::default_package();
+
+    let manifest = env.root().join("typst.toml");
+    let mut contents = std::fs::read_to_string(&manifest).unwrap();
+    contents.push_str("\n[tool.tytanic]\nfont-paths = [\"fonts\"]\n");
+    std::fs::write(&manifest, contents).unwrap();
+
+    let res = env.run_tytanic(["status"]);
+    assert_eq!(res.output().status().code(), Some(2));
+    assert!(
+        res.output()
+            .stderr()
+            .contains("`font-paths[0]`: the path did not exist: \"fonts\"")
+    );
+
+    std::fs::create_dir(env.root().join("fonts")).unwrap();
+
+    let res = env.run_tytanic(["status"]);
+    assert_eq!(res.output().status().code(), Some(0));
+}